@@ -1,8 +1,9 @@
 use cranelift_entity::EntityList;
 use unnamed_common::{Span, Spanned, StrId};
 use unnamed_derive::Spanned;
+use unnamed_lexer::{FloatSuffix, IntSuffix};
 
-use crate::{Block, ExprEntity, FieldEntity, TypeEntity};
+use crate::{ArmEntity, Block, ExprEntity, FieldEntity, PatEntity, PathSegment, TypeEntity};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BinOp {
@@ -27,6 +28,51 @@ pub enum BinOp {
     BitShl,
 }
 
+impl BinOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Eq => "==",
+            BinOp::Neq => "!=",
+            BinOp::Lt => "<",
+            BinOp::LtEq => "<=",
+            BinOp::Gt => ">",
+            BinOp::GtEq => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::BitOr => "|",
+            BinOp::BitAnd => "&",
+            BinOp::BitXor => "^",
+            BinOp::BitShr => ">>",
+            BinOp::BitShl => "<<",
+        }
+    }
+
+    /// How tightly the operator binds, higher binding tighter. All binary
+    /// operators are left-associative, except comparisons which cannot be
+    /// chained at all.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Neq | BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq => 3,
+            BinOp::BitOr => 4,
+            BinOp::BitXor => 5,
+            BinOp::BitAnd => 6,
+            BinOp::BitShl | BinOp::BitShr => 7,
+            BinOp::Add | BinOp::Sub => 8,
+            BinOp::Mul | BinOp::Div => 9,
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        self.precedence() == 3
+    }
+}
+
 #[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BinExpr {
     pub lhs: ExprEntity,
@@ -42,6 +88,15 @@ pub enum UnaryOp {
     Sub,
 }
 
+impl UnaryOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UnaryOp::Add => "+",
+            UnaryOp::Sub => "-",
+        }
+    }
+}
+
 #[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct UnaryExpr {
     pub op: UnaryOp,
@@ -70,6 +125,7 @@ pub struct WhileExpr {
 #[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MethodCallExpr {
     pub receiver: ExprEntity,
+    pub method: StrId,
     pub ty_args: EntityList<TypeEntity>,
     pub args: EntityList<ExprEntity>,
     #[span]
@@ -94,9 +150,20 @@ pub enum ArrayExprKind {
     },
 }
 
+/// A variant of an enum, as in `Shape::Circle`.
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathExpr {
+    pub ty: PathSegment,
+    pub variant: PathSegment,
+    #[span]
+    pub span: Span,
+}
+
 #[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct StructExpr {
     pub name: StrId,
+    /// The variant in `Enum::Variant { .. }`, `None` for structs.
+    pub variant: Option<PathSegment>,
     pub fields: EntityList<FieldEntity>,
     #[span]
     pub span: Span,
@@ -131,6 +198,24 @@ pub enum ElseExprKind {
     Else(Block),
 }
 
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchExpr {
+    pub scrutinee: ExprEntity,
+    pub arms: EntityList<ArmEntity>,
+    #[span]
+    pub span: Span,
+}
+
+/// `pat if guard => body`, where the guard is optional.
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchArm {
+    pub pat: PatEntity,
+    pub guard: Option<ExprEntity>,
+    pub body: ExprEntity,
+    #[span]
+    pub span: Span,
+}
+
 #[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct FieldExpr {
     pub base: ExprEntity,
@@ -155,6 +240,16 @@ pub struct AssignExpr {
     pub span: Span,
 }
 
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LetExpr {
+    pub name: StrId,
+    pub name_span: Span,
+    pub ty: Option<TypeEntity>,
+    pub value: Option<ExprEntity>,
+    #[span]
+    pub span: Span,
+}
+
 #[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ReturnExpr {
     pub value: Option<ExprEntity>,
@@ -162,15 +257,19 @@ pub struct ReturnExpr {
     pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Expr {
     Ident(StrId, Span),
     Str(StrId, Span),
-    Int(u64, Span),
+    Int(u64, Option<IntSuffix>, Span),
+    Float(f64, Option<FloatSuffix>, Span),
+    Char(char, Span),
     Bool(bool, Span),
     Unit(Span),
+    Path(PathExpr),
 
     Block(Block),
+    Let(LetExpr),
     Assign(AssignExpr),
     Bin(BinExpr),
     Unary(UnaryExpr),
@@ -178,11 +277,14 @@ pub enum Expr {
     MethodCall(MethodCallExpr),
     If(IfExpr),
     While(WhileExpr),
+    Match(MatchExpr),
     Array(ArrayExpr),
     Struct(StructExpr),
     Field(FieldExpr),
     Index(IndexExpr),
     Return(ReturnExpr),
+    /// Placeholder for an expression that failed to parse.
+    Error(Span),
 }
 
 impl Spanned for Expr {
@@ -190,10 +292,14 @@ impl Spanned for Expr {
         match self {
             Expr::Ident(_, span) => *span,
             Expr::Str(_, span) => *span,
-            Expr::Int(_, span) => *span,
+            Expr::Int(.., span) => *span,
+            Expr::Float(.., span) => *span,
+            Expr::Char(_, span) => *span,
             Expr::Bool(_, span) => *span,
             Expr::Unit(span) => *span,
+            Expr::Path(path_expr) => path_expr.span(),
             Expr::Block(block) => block.span(),
+            Expr::Let(let_expr) => let_expr.span(),
             Expr::Assign(assign) => assign.span(),
             Expr::Bin(bin_expr) => bin_expr.span(),
             Expr::Unary(unary_expr) => unary_expr.span(),
@@ -201,11 +307,13 @@ impl Spanned for Expr {
             Expr::MethodCall(method_call_expr) => method_call_expr.span(),
             Expr::If(if_expr) => if_expr.span(),
             Expr::While(while_expr) => while_expr.span(),
+            Expr::Match(match_expr) => match_expr.span(),
             Expr::Array(array_expr) => array_expr.span(),
             Expr::Struct(struct_expr) => struct_expr.span(),
             Expr::Field(field_expr) => field_expr.span(),
             Expr::Index(index_expr) => index_expr.span(),
             Expr::Return(return_expr) => return_expr.span(),
+            Expr::Error(span) => *span,
         }
     }
 }
@@ -0,0 +1,147 @@
+use cranelift_entity::EntityList;
+use unnamed_common::{Span, Spanned, StrId};
+use unnamed_derive::Spanned;
+
+use crate::{
+    Block, FieldDefEntity, GenericParamEntity, ItemEntity, ParamEntity, TypeEntity, VariantEntity,
+};
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Module {
+    /// Text of the `//!` comments at the start of the file.
+    pub docs: Option<StrId>,
+    pub items: EntityList<ItemEntity>,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Param {
+    pub name: StrId,
+    /// `None` only for a bare `self` receiver.
+    pub ty: Option<TypeEntity>,
+    #[span]
+    pub span: Span,
+}
+
+/// A type parameter in the `<T, U>` list of an item.
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericParam {
+    pub name: StrId,
+    /// The traits after `T:`, separated by `+`.
+    pub bounds: EntityList<TypeEntity>,
+    /// The span of the name only.
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuncItem {
+    /// Text of the `///` comments before the item.
+    pub docs: Option<StrId>,
+    pub name: StrId,
+    pub name_span: Span,
+    pub generics: EntityList<GenericParamEntity>,
+    pub params: EntityList<ParamEntity>,
+    pub ret: Option<TypeEntity>,
+    /// `None` for signatures without a body inside trait declarations.
+    pub body: Option<Block>,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldDef {
+    pub name: StrId,
+    pub ty: TypeEntity,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StructItem {
+    /// Text of the `///` comments before the item.
+    pub docs: Option<StrId>,
+    pub name: StrId,
+    pub name_span: Span,
+    pub generics: EntityList<GenericParamEntity>,
+    pub fields: EntityList<FieldDefEntity>,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnumItem {
+    /// Text of the `///` comments before the item.
+    pub docs: Option<StrId>,
+    pub name: StrId,
+    pub name_span: Span,
+    pub generics: EntityList<GenericParamEntity>,
+    pub variants: EntityList<VariantEntity>,
+    #[span]
+    pub span: Span,
+}
+
+/// A variant of an enum. Its name starts the span.
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Variant {
+    pub name: StrId,
+    pub kind: VariantKind,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantKind {
+    /// `Name`, without any payload.
+    Unit,
+    /// `Name(A, B)`, with unnamed fields.
+    Tuple(EntityList<TypeEntity>),
+    /// `Name { a: A, b: B }`, with named fields.
+    Struct(EntityList<FieldDefEntity>),
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraitItem {
+    /// Text of the `///` comments before the item.
+    pub docs: Option<StrId>,
+    pub name: StrId,
+    pub name_span: Span,
+    pub items: EntityList<ItemEntity>,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImplItem {
+    /// Text of the `///` comments before the item.
+    pub docs: Option<StrId>,
+    pub generics: EntityList<GenericParamEntity>,
+    /// The trait in `impl Trait for Type`, `None` for inherent impls.
+    pub trait_ref: Option<TypeEntity>,
+    pub ty: TypeEntity,
+    pub items: EntityList<ItemEntity>,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Item {
+    Func(FuncItem),
+    Struct(StructItem),
+    Enum(EnumItem),
+    Trait(TraitItem),
+    Impl(ImplItem),
+}
+
+impl Spanned for Item {
+    fn span(&self) -> Span {
+        match self {
+            Item::Func(func_item) => func_item.span(),
+            Item::Struct(struct_item) => struct_item.span(),
+            Item::Enum(enum_item) => enum_item.span(),
+            Item::Trait(trait_item) => trait_item.span(),
+            Item::Impl(impl_item) => impl_item.span(),
+        }
+    }
+}
@@ -1,10 +1,15 @@
 pub mod expr;
+pub mod item;
+pub mod pat;
+pub mod printer;
 pub mod ty;
 
 use cranelift_entity::{EntityList, entity_impl};
-use unnamed_common::{EntityArena, Span};
+use unnamed_common::{EntityArena, Span, StrId};
 
 pub use expr::{BinExpr, BinOp, Expr, StructFieldExpr};
+pub use item::{FieldDef, GenericParam, Item, Module, Param, Variant};
+pub use pat::Pat;
 pub use ty::Type;
 use unnamed_derive::Spanned;
 
@@ -20,11 +25,51 @@ entity_impl!(TypeEntity);
 pub struct FieldEntity(u32);
 entity_impl!(FieldEntity);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ItemEntity(u32);
+entity_impl!(ItemEntity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParamEntity(u32);
+entity_impl!(ParamEntity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldDefEntity(u32);
+entity_impl!(FieldDefEntity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GenericParamEntity(u32);
+entity_impl!(GenericParamEntity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VariantEntity(u32);
+entity_impl!(VariantEntity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatEntity(u32);
+entity_impl!(PatEntity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldPatEntity(u32);
+entity_impl!(FieldPatEntity);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArmEntity(u32);
+entity_impl!(ArmEntity);
+
 #[derive(Debug, Default, Clone)]
 pub struct AstCtx {
     pub exprs: EntityArena<ExprEntity, Expr>,
     pub field_exprs: EntityArena<FieldEntity, StructFieldExpr>,
     pub types: EntityArena<TypeEntity, Type>,
+    pub items: EntityArena<ItemEntity, Item>,
+    pub params: EntityArena<ParamEntity, Param>,
+    pub field_defs: EntityArena<FieldDefEntity, FieldDef>,
+    pub generic_params: EntityArena<GenericParamEntity, GenericParam>,
+    pub variants: EntityArena<VariantEntity, Variant>,
+    pub pats: EntityArena<PatEntity, Pat>,
+    pub field_pats: EntityArena<FieldPatEntity, pat::FieldPat>,
+    pub arms: EntityArena<ArmEntity, expr::MatchArm>,
 }
 
 impl AstCtx {
@@ -33,9 +78,21 @@ impl AstCtx {
     }
 }
 
+/// A name together with its span, like the variant in `Shape::Circle`.
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathSegment {
+    pub name: StrId,
+    #[span]
+    pub span: Span,
+}
+
 #[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Block {
+    /// Statements of the block, in order.
     pub exprs: EntityList<ExprEntity>,
+    /// The final expression without a trailing `;`, which is the value of
+    /// the block. `None` when the block evaluates to unit.
+    pub tail: Option<ExprEntity>,
     #[span]
     pub span: Span,
 }
@@ -0,0 +1,61 @@
+use cranelift_entity::EntityList;
+use unnamed_common::{Span, Spanned, StrId};
+use unnamed_derive::Spanned;
+
+use crate::{ExprEntity, FieldPatEntity, PatEntity, PathSegment};
+
+/// A struct or enum variant pattern: `P { x }`, `E::V`, `E::V(a, b)` or
+/// `E::V { x: a }`.
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdtPat {
+    pub name: PathSegment,
+    /// The variant after `::`, `None` for structs.
+    pub variant: Option<PathSegment>,
+    pub fields: PatFields,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatFields {
+    /// No fields at all, as for unit variants.
+    Unit,
+    Tuple(EntityList<PatEntity>),
+    /// Named fields, where those left out match anything.
+    Named(EntityList<FieldPatEntity>),
+}
+
+/// `name: pat`, or just `name` which binds the field to a local of the same
+/// name.
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldPat {
+    pub name: StrId,
+    pub pat: PatEntity,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pat {
+    /// `_`, which matches anything.
+    Wild(Span),
+    /// A name, which matches anything and binds it to a new local.
+    Binding(StrId, Span),
+    /// A literal, possibly negated as in `-1`.
+    Lit(ExprEntity, Span),
+    Adt(AdtPat),
+    /// Placeholder for a pattern that failed to parse.
+    Error(Span),
+}
+
+impl Spanned for Pat {
+    fn span(&self) -> Span {
+        match self {
+            Pat::Wild(span) => *span,
+            Pat::Binding(_, span) => *span,
+            Pat::Lit(_, span) => *span,
+            Pat::Adt(adt_pat) => adt_pat.span(),
+            Pat::Error(span) => *span,
+        }
+    }
+}
@@ -0,0 +1,564 @@
+use std::fmt::Write;
+
+use unnamed_common::{Interner, Span, Spanned, StrId};
+
+use crate::{
+    AstCtx, Block, ExprEntity, FieldDefEntity, GenericParamEntity, Item, ItemEntity, Module,
+    PatEntity, TypeEntity,
+    expr::{ArrayExprKind, ElseExprKind, Expr},
+    item::VariantKind,
+    pat::{Pat, PatFields},
+    ty::TypeKind,
+};
+
+/// Prints `module` as an indented tree, one node per line followed by its
+/// span.
+pub fn print_module(ctx: &AstCtx, interner: &Interner, module: &Module) -> String {
+    let mut printer = AstPrinter::new(ctx, interner);
+    printer.module(module);
+
+    printer.out
+}
+
+/// Prints the tree of a single expression, in the format of
+/// [`print_module`].
+pub fn print_expr(ctx: &AstCtx, interner: &Interner, expr: ExprEntity) -> String {
+    let mut printer = AstPrinter::new(ctx, interner);
+    printer.expr(expr);
+
+    printer.out
+}
+
+struct AstPrinter<'a> {
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    out: String,
+    depth: usize,
+}
+
+impl<'a> AstPrinter<'a> {
+    fn new(ctx: &'a AstCtx, interner: &'a Interner) -> Self {
+        Self {
+            ctx,
+            interner,
+            out: String::new(),
+            depth: 0,
+        }
+    }
+
+    fn line(&mut self, label: impl AsRef<str>, span: Span) {
+        let _ = writeln!(
+            self.out,
+            "{:indent$}{} @ {span}",
+            "",
+            label.as_ref(),
+            indent = self.depth * 2
+        );
+    }
+
+    fn nested(&mut self, print: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        print(self);
+        self.depth -= 1;
+    }
+
+    fn name(&self, name: StrId) -> &'a str {
+        self.interner.get(name)
+    }
+
+    fn module(&mut self, module: &Module) {
+        self.line("Module", module.span);
+        self.nested(|printer| {
+            if let Some(docs) = module.docs {
+                printer.docs(docs);
+            }
+
+            printer.items(printer.ctx.items.list(module.items));
+        });
+    }
+
+    fn docs(&mut self, docs: StrId) {
+        let _ = writeln!(
+            self.out,
+            "{:indent$}Docs {:?}",
+            "",
+            self.name(docs),
+            indent = self.depth * 2
+        );
+    }
+
+    fn items(&mut self, items: &[ItemEntity]) {
+        for &item in items {
+            self.item(item);
+        }
+    }
+
+    fn item(&mut self, item: ItemEntity) {
+        match self.ctx.items[item] {
+            Item::Func(func) => {
+                self.line(format!("Func {}", self.name(func.name)), func.span);
+                self.nested(|printer| {
+                    if let Some(docs) = func.docs {
+                        printer.docs(docs);
+                    }
+
+                    printer.generics(printer.ctx.generic_params.list(func.generics));
+                    for &param in printer.ctx.params.list(func.params) {
+                        let param = printer.ctx.params[param];
+                        printer.line(format!("Param {}", printer.name(param.name)), param.span);
+                        if let Some(ty) = param.ty {
+                            printer.nested(|printer| printer.ty(ty));
+                        }
+                    }
+
+                    if let Some(ret) = func.ret {
+                        printer.line("Return type", printer.ctx.types[ret].span);
+                        printer.nested(|printer| printer.ty(ret));
+                    }
+
+                    if let Some(body) = func.body {
+                        printer.block(body);
+                    }
+                });
+            }
+            Item::Struct(struct_item) => {
+                self.line(
+                    format!("Struct {}", self.name(struct_item.name)),
+                    struct_item.span,
+                );
+                self.nested(|printer| {
+                    if let Some(docs) = struct_item.docs {
+                        printer.docs(docs);
+                    }
+
+                    printer.generics(printer.ctx.generic_params.list(struct_item.generics));
+                    printer.field_defs(printer.ctx.field_defs.list(struct_item.fields));
+                });
+            }
+            Item::Enum(enum_item) => {
+                self.line(
+                    format!("Enum {}", self.name(enum_item.name)),
+                    enum_item.span,
+                );
+                self.nested(|printer| {
+                    if let Some(docs) = enum_item.docs {
+                        printer.docs(docs);
+                    }
+
+                    printer.generics(printer.ctx.generic_params.list(enum_item.generics));
+                    for &variant in printer.ctx.variants.list(enum_item.variants) {
+                        let variant = printer.ctx.variants[variant];
+                        let name = printer.name(variant.name);
+                        printer.line(format!("Variant {name}"), variant.span);
+                        printer.nested(|printer| match variant.kind {
+                            VariantKind::Unit => {}
+                            VariantKind::Tuple(types) => {
+                                for &ty in printer.ctx.types.list(types) {
+                                    printer.ty(ty);
+                                }
+                            }
+                            VariantKind::Struct(fields) => {
+                                printer.field_defs(printer.ctx.field_defs.list(fields));
+                            }
+                        });
+                    }
+                });
+            }
+            Item::Trait(trait_item) => {
+                self.line(
+                    format!("Trait {}", self.name(trait_item.name)),
+                    trait_item.span,
+                );
+                self.nested(|printer| {
+                    if let Some(docs) = trait_item.docs {
+                        printer.docs(docs);
+                    }
+
+                    printer.items(printer.ctx.items.list(trait_item.items));
+                });
+            }
+            Item::Impl(impl_item) => {
+                self.line("Impl", impl_item.span);
+                self.nested(|printer| {
+                    if let Some(docs) = impl_item.docs {
+                        printer.docs(docs);
+                    }
+
+                    printer.generics(printer.ctx.generic_params.list(impl_item.generics));
+                    if let Some(trait_ref) = impl_item.trait_ref {
+                        printer.line("Trait", printer.ctx.types[trait_ref].span);
+                        printer.nested(|printer| printer.ty(trait_ref));
+                    }
+
+                    printer.ty(impl_item.ty);
+                    printer.items(printer.ctx.items.list(impl_item.items));
+                });
+            }
+        }
+    }
+
+    fn field_defs(&mut self, fields: &[FieldDefEntity]) {
+        for &field in fields {
+            let field = self.ctx.field_defs[field];
+            self.line(format!("Field {}", self.name(field.name)), field.span);
+            self.nested(|printer| printer.ty(field.ty));
+        }
+    }
+
+    fn generics(&mut self, generics: &[GenericParamEntity]) {
+        for &param in generics {
+            let param = self.ctx.generic_params[param];
+            self.line(format!("Generic {}", self.name(param.name)), param.span);
+            self.nested(|printer| {
+                for &bound in printer.ctx.types.list(param.bounds) {
+                    printer.ty(bound);
+                }
+            });
+        }
+    }
+
+    fn ty(&mut self, ty: TypeEntity) {
+        let ty = self.ctx.types[ty];
+
+        match ty.kind {
+            TypeKind::Named { name, args } => {
+                self.line(format!("Type {}", self.name(name)), ty.span);
+                self.nested(|printer| {
+                    for &arg in printer.ctx.types.list(args) {
+                        printer.ty(arg);
+                    }
+                });
+            }
+            TypeKind::Unit => self.line("Type ()", ty.span),
+            TypeKind::Error => self.line("Type <error>", ty.span),
+        }
+    }
+
+    fn types(&mut self, label: &str, types: &[TypeEntity], span: Span) {
+        if types.is_empty() {
+            return;
+        }
+
+        self.line(label, span);
+        self.nested(|printer| {
+            for &ty in types {
+                printer.ty(ty);
+            }
+        });
+    }
+
+    fn block(&mut self, block: Block) {
+        self.line("Block", block.span);
+        self.nested(|printer| {
+            for &expr in printer.ctx.exprs.list(block.exprs) {
+                printer.expr(expr);
+            }
+
+            if let Some(tail) = block.tail {
+                printer.line("Tail", printer.ctx.exprs[tail].span());
+                printer.nested(|printer| printer.expr(tail));
+            }
+        });
+    }
+
+    fn exprs(&mut self, exprs: &[ExprEntity]) {
+        for &expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: ExprEntity) {
+        let ctx = self.ctx;
+        let expr = ctx.exprs[expr];
+        let span = expr.span();
+
+        match expr {
+            Expr::Ident(name, ..) => self.line(format!("Ident {}", self.name(name)), span),
+            Expr::Str(value, ..) => self.line(format!("Str {:?}", self.name(value)), span),
+            Expr::Int(value, suffix, ..) => self.line(
+                format!("Int {value}{}", suffix.map_or("", |suffix| suffix.as_str())),
+                span,
+            ),
+            Expr::Float(value, suffix, ..) => self.line(
+                format!(
+                    "Float {value:?}{}",
+                    suffix.map_or("", |suffix| suffix.as_str())
+                ),
+                span,
+            ),
+            Expr::Char(value, ..) => self.line(format!("Char {value:?}"), span),
+            Expr::Bool(value, ..) => self.line(format!("Bool {value}"), span),
+            Expr::Unit(..) => self.line("Unit", span),
+            Expr::Path(path) => self.line(
+                format!(
+                    "Path {}::{}",
+                    self.name(path.ty.name),
+                    self.name(path.variant.name)
+                ),
+                span,
+            ),
+            Expr::Block(block) => self.block(block),
+            Expr::Let(let_expr) => {
+                self.line(format!("Let {}", self.name(let_expr.name)), span);
+                self.nested(|printer| {
+                    if let Some(ty) = let_expr.ty {
+                        printer.ty(ty);
+                    }
+                    if let Some(value) = let_expr.value {
+                        printer.expr(value);
+                    }
+                });
+            }
+            Expr::Assign(assign) => {
+                self.line(format!("Assign {}", self.name(assign.lhs)), span);
+                self.nested(|printer| printer.expr(assign.rhs));
+            }
+            Expr::Bin(bin) => {
+                self.line(format!("Bin {}", bin.op.as_str()), span);
+                self.nested(|printer| printer.exprs(&[bin.lhs, bin.rhs]));
+            }
+            Expr::Unary(unary) => {
+                self.line(format!("Unary {}", unary.op.as_str()), span);
+                self.nested(|printer| printer.expr(unary.value));
+            }
+            Expr::Call(call) => {
+                self.line("Call", span);
+                self.nested(|printer| {
+                    printer.expr(call.receiver);
+                    printer.types("Type args", ctx.types.list(call.ty_args), span);
+                    printer.exprs(ctx.exprs.list(call.args));
+                });
+            }
+            Expr::MethodCall(method_call) => {
+                self.line(
+                    format!("MethodCall {}", self.name(method_call.method)),
+                    span,
+                );
+                self.nested(|printer| {
+                    printer.expr(method_call.receiver);
+                    printer.types("Type args", ctx.types.list(method_call.ty_args), span);
+                    printer.exprs(ctx.exprs.list(method_call.args));
+                });
+            }
+            Expr::If(if_expr) => {
+                self.line("If", span);
+                self.nested(|printer| {
+                    printer.expr(if_expr.cond);
+                    printer.block(if_expr.then_branch);
+
+                    if let Some(else_branch) = if_expr.else_branch {
+                        printer.line("Else", else_branch.span);
+                        printer.nested(|printer| match else_branch.kind {
+                            ElseExprKind::If(expr) => printer.expr(expr),
+                            ElseExprKind::Else(block) => printer.block(block),
+                        });
+                    }
+                });
+            }
+            Expr::While(while_expr) => {
+                self.line("While", span);
+                self.nested(|printer| {
+                    printer.expr(while_expr.cond);
+                    printer.block(while_expr.body);
+                });
+            }
+            Expr::Match(match_expr) => {
+                self.line("Match", span);
+                self.nested(|printer| {
+                    printer.expr(match_expr.scrutinee);
+                    for &arm in ctx.arms.list(match_expr.arms) {
+                        let arm = ctx.arms[arm];
+                        printer.line("Arm", arm.span);
+                        printer.nested(|printer| {
+                            printer.pat(arm.pat);
+                            if let Some(guard) = arm.guard {
+                                printer.line("Guard", ctx.exprs[guard].span());
+                                printer.nested(|printer| printer.expr(guard));
+                            }
+                            printer.expr(arm.body);
+                        });
+                    }
+                });
+            }
+            Expr::Array(array) => match array.kind {
+                ArrayExprKind::List { values } => {
+                    self.line("Array", span);
+                    self.nested(|printer| printer.exprs(ctx.exprs.list(values)));
+                }
+                ArrayExprKind::Repeat { value, repeat } => {
+                    self.line("ArrayRepeat", span);
+                    self.nested(|printer| printer.exprs(&[value, repeat]));
+                }
+            },
+            Expr::Struct(struct_expr) => {
+                let variant = struct_expr
+                    .variant
+                    .map(|variant| format!("::{}", self.name(variant.name)))
+                    .unwrap_or_default();
+                self.line(
+                    format!("Struct {}{variant}", self.name(struct_expr.name)),
+                    span,
+                );
+                self.nested(|printer| {
+                    for &field in ctx.field_exprs.list(struct_expr.fields) {
+                        let field = ctx.field_exprs[field];
+                        printer.line(format!("Field {}", printer.name(field.name)), field.span);
+                        printer.nested(|printer| printer.expr(field.value));
+                    }
+                });
+            }
+            Expr::Field(field) => {
+                self.line(format!("Field {}", self.name(field.member)), span);
+                self.nested(|printer| printer.expr(field.base));
+            }
+            Expr::Index(index) => {
+                self.line("Index", span);
+                self.nested(|printer| printer.exprs(&[index.base, index.index]));
+            }
+            Expr::Return(return_expr) => {
+                self.line("Return", span);
+                if let Some(value) = return_expr.value {
+                    self.nested(|printer| printer.expr(value));
+                }
+            }
+            Expr::Error(..) => self.line("<error>", span),
+        }
+    }
+
+    fn pat(&mut self, pat: PatEntity) {
+        let ctx = self.ctx;
+        let pat = ctx.pats[pat];
+        let span = pat.span();
+
+        match pat {
+            Pat::Wild(..) => self.line("PatWild", span),
+            Pat::Binding(name, ..) => self.line(format!("PatBinding {}", self.name(name)), span),
+            Pat::Lit(expr, ..) => {
+                self.line("PatLit", span);
+                self.nested(|printer| printer.expr(expr));
+            }
+            Pat::Adt(adt) => {
+                let variant = adt
+                    .variant
+                    .map(|variant| format!("::{}", self.name(variant.name)))
+                    .unwrap_or_default();
+                self.line(
+                    format!("PatAdt {}{variant}", self.name(adt.name.name)),
+                    span,
+                );
+                self.nested(|printer| match adt.fields {
+                    PatFields::Unit => {}
+                    PatFields::Tuple(pats) => {
+                        for &pat in ctx.pats.list(pats) {
+                            printer.pat(pat);
+                        }
+                    }
+                    PatFields::Named(fields) => {
+                        for &field in ctx.field_pats.list(fields) {
+                            let field = ctx.field_pats[field];
+                            printer.line(format!("Field {}", printer.name(field.name)), field.span);
+                            printer.nested(|printer| printer.pat(field.pat));
+                        }
+                    }
+                });
+            }
+            Pat::Error(..) => self.line("<error>", span),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cranelift_entity::EntityList;
+    use unnamed_common::{Interner, Span};
+
+    use crate::{
+        AstCtx, BinExpr, BinOp, Block, Expr, Item, Module, Param, Type,
+        expr::LetExpr,
+        item::FuncItem,
+        printer::{print_expr, print_module},
+        ty::TypeKind,
+    };
+
+    #[test]
+    fn test_print_module() {
+        let interner = Interner::new();
+        let mut ctx = AstCtx::new();
+
+        let i64_ty = ctx.types.alloc(Type {
+            kind: TypeKind::Named {
+                name: interner.intern("i64"),
+                args: EntityList::new(),
+            },
+            span: Span::new(10, 13),
+        });
+        let param = ctx.params.alloc(Param {
+            name: interner.intern("a"),
+            ty: Some(i64_ty),
+            span: Span::new(7, 13),
+        });
+
+        let one = ctx.exprs.alloc(Expr::Int(1, None, Span::new(25, 26)));
+        let let_expr = ctx.exprs.alloc(Expr::Let(LetExpr {
+            name: interner.intern("x"),
+            name_span: Span::new(21, 22),
+            ty: None,
+            value: Some(one),
+            span: Span::new(17, 26),
+        }));
+        let lhs = ctx
+            .exprs
+            .alloc(Expr::Ident(interner.intern("a"), Span::new(28, 29)));
+        let rhs = ctx
+            .exprs
+            .alloc(Expr::Ident(interner.intern("x"), Span::new(32, 33)));
+        let sum = ctx.exprs.alloc(Expr::Bin(BinExpr {
+            lhs,
+            op: BinOp::Add,
+            rhs,
+            span: Span::new(28, 33),
+        }));
+
+        let params = ctx.params.alloc_list(&[param]);
+        let exprs = ctx.exprs.alloc_list(&[let_expr]);
+        let func = ctx.items.alloc(Item::Func(FuncItem {
+            docs: Some(interner.intern("Adds one.")),
+            name: interner.intern("f"),
+            name_span: Span::new(5, 6),
+            generics: EntityList::new(),
+            params,
+            ret: None,
+            body: Some(Block {
+                exprs,
+                tail: Some(sum),
+                span: Span::new(15, 35),
+            }),
+            span: Span::new(0, 35),
+        }));
+
+        let items = ctx.items.alloc_list(&[func]);
+        let module = Module {
+            docs: None,
+            items,
+            span: Span::new(0, 35),
+        };
+
+        assert_eq!(
+            print_module(&ctx, &interner, &module),
+            "Module @ 0..35
+  Func f @ 0..35
+    Docs \"Adds one.\"
+    Param a @ 7..13
+      Type i64 @ 10..13
+    Block @ 15..35
+      Let x @ 17..26
+        Int 1 @ 25..26
+      Tail @ 28..33
+        Bin + @ 28..33
+          Ident a @ 28..29
+          Ident x @ 32..33
+"
+        );
+        assert_eq!(print_expr(&ctx, &interner, one), "Int 1 @ 25..26\n");
+    }
+}
@@ -18,4 +18,6 @@ pub enum TypeKind {
         args: EntityList<TypeEntity>,
     },
     Unit,
+    /// Placeholder for a type that failed to parse.
+    Error,
 }
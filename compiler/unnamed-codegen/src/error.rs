@@ -0,0 +1,65 @@
+use ariadne::{Label, ReportKind};
+use unnamed_common::{IntoReport, Report, Source, Span, Spanned};
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    span: Span,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(span: Span, kind: ErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    MissingMain,
+    /// A `main` function with parameters, which nothing could pass.
+    MainParams,
+    /// A struct or enum that contains itself without an array in between,
+    /// so that its values would need infinite space.
+    InfiniteSize(String),
+    /// Cranelift rejected a function or the object could not be written,
+    /// which is a bug in the code generator.
+    Backend(String),
+}
+
+impl IntoReport for Error {
+    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
+        let (message, label_message) = match self.kind {
+            ErrorKind::MissingMain => (
+                "`main` function not found".to_owned(),
+                "add a `func main()` to compile this file".to_owned(),
+            ),
+            ErrorKind::MainParams => (
+                "`main` function cannot take parameters".to_owned(),
+                "declared with parameters here".to_owned(),
+            ),
+            ErrorKind::InfiniteSize(ty) => (
+                format!("recursive type `{ty}` has infinite size"),
+                "recursive without indirection".to_owned(),
+            ),
+            ErrorKind::Backend(error) => (
+                format!("code generation failed: {error}"),
+                "while compiling this item".to_owned(),
+            ),
+        };
+
+        Report::build(ReportKind::Error, source.span(self.span))
+            .with_message(message)
+            .with_label(Label::new(source.span(self.span)).with_message(label_message))
+            .finish()
+    }
+}
@@ -0,0 +1,1249 @@
+use std::{collections::HashMap, rc::Rc};
+
+use cranelift_codegen::ir::{
+    self, FuncRef, GlobalValue, InstBuilder, MemFlags, StackSlot, StackSlotData, StackSlotKind,
+    TrapCode, Type, Value,
+    condcodes::{FloatCC, IntCC},
+    types,
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_module::{DataId, FuncId, Module};
+use unnamed_ast::{
+    AstCtx, Item, ItemEntity, VariantEntity,
+    expr::{BinOp, UnaryOp},
+    item::VariantKind,
+};
+use unnamed_common::{Interner, Span, StrId};
+use unnamed_mir::{
+    self as mir, BlockCall, Body, Callee, Const, Inst, InstKind, Local, Place, Projection,
+    Terminator, ValueDef,
+    cfg::{loop_blocks, reverse_postorder},
+};
+use unnamed_resolve::Builtin;
+use unnamed_typeck::{InstanceId, TyId, TyKind, TypeckResults};
+
+use crate::{
+    Codegen, Error, InstanceSig,
+    layout::{ARRAY_HEADER, Field, Layout, Repr, Shape, TAG},
+    runtime::{Glue, RuntimeFn, STACK_BUDGET},
+};
+
+/// Arrays up to this size whose length is known at compile time are
+/// allocated on the stack.
+const MAX_STACK_ARRAY: u64 = 4096;
+
+/// Trap code of instructions that are never reached, like the end of a
+/// match whose arms are exhaustive.
+const UNREACHABLE: TrapCode = TrapCode::unwrap_user(1);
+
+/// Where the value of a local is kept.
+#[derive(Debug, Clone, Copy)]
+enum Storage {
+    Var(Variable),
+    /// The local's own copy of a struct or enum.
+    Slot(StackSlot),
+    Zst,
+}
+
+/// Where the value of a place is, once its projections are applied.
+#[derive(Debug, Clone, Copy)]
+enum Loc {
+    Var(Variable),
+    Addr(Value),
+    Zst,
+}
+
+/// Builds the body of one function, either an instance of the program or a
+/// function of the runtime.
+pub(crate) struct FuncTranslator<'b, 'a, M> {
+    pub cg: &'b mut Codegen<'a, M>,
+    pub builder: FunctionBuilder<'b>,
+    entry: ir::Block,
+    func_refs: HashMap<FuncId, FuncRef>,
+    data_refs: HashMap<DataId, GlobalValue>,
+    next_var: u32,
+    /// The Cranelift block of each block of the body.
+    blocks: HashMap<mir::Block, ir::Block>,
+    /// The Cranelift value of each value of the body, except for those
+    /// without a representation.
+    values: HashMap<mir::Value, Value>,
+    locals: HashMap<Local, Storage>,
+    /// The return type of the instance.
+    ret: Option<TyId>,
+    /// Where the returned value is copied to, for structs and enums.
+    ret_ptr: Option<Value>,
+    /// Whether the block being translated is part of a loop.
+    in_loop: bool,
+    /// Whether arrays must be allocated on the heap because the function can
+    /// return them.
+    heap_arrays: bool,
+}
+
+impl<'b, 'a, M: Module> FuncTranslator<'b, 'a, M> {
+    pub fn new(
+        cg: &'b mut Codegen<'a, M>,
+        func: &'b mut ir::Function,
+        context: &'b mut FunctionBuilderContext,
+    ) -> Self {
+        let mut builder = FunctionBuilder::new(func, context);
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+
+        Self {
+            cg,
+            builder,
+            entry,
+            func_refs: HashMap::new(),
+            data_refs: HashMap::new(),
+            next_var: 0,
+            blocks: HashMap::new(),
+            values: HashMap::new(),
+            locals: HashMap::new(),
+            ret: None,
+            ret_ptr: None,
+            in_loop: false,
+            heap_arrays: false,
+        }
+    }
+
+    pub fn finish(mut self) {
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+    }
+
+    /// The parameters of the function.
+    pub fn params(&self) -> Vec<Value> {
+        self.builder.block_params(self.entry).to_vec()
+    }
+
+    pub fn ctx(&self) -> &'a AstCtx {
+        self.cg.program.ctx
+    }
+
+    pub fn interner(&self) -> &'a Interner {
+        self.cg.program.interner
+    }
+
+    pub fn results(&mut self) -> &mut TypeckResults {
+        self.cg.program.results
+    }
+
+    pub fn layout(&mut self, ty: TyId) -> Result<Rc<Layout>, Error> {
+        self.cg.layout(ty)
+    }
+
+    /// Defines the C `main` function calling the program's `main`.
+    pub fn entry(&mut self, main: InstanceId) -> Result<(), Error> {
+        let sp = self.builder.ins().get_stack_pointer(self.cg.pointer);
+        let limit = self.builder.ins().iadd_imm(sp, -(STACK_BUDGET as i64));
+        let limit_addr = self.stack_limit_addr();
+        self.builder
+            .ins()
+            .store(MemFlags::trusted(), limit, limit_addr, 0);
+
+        let (func, sig) = self.cg.functions[&main].clone();
+        let result = self.call(func, &sig, Vec::new())?;
+
+        // Only the low byte of the result is the exit code, as with the
+        // interpreter.
+        let code = match (result, self.results().tcx[sig.ret]) {
+            (Some(value), TyKind::Int(int)) => {
+                let byte = if int.bits() > 8 {
+                    self.builder.ins().ireduce(types::I8, value)
+                } else {
+                    value
+                };
+                self.builder.ins().uextend(types::I32, byte)
+            }
+            _ => self.builder.ins().iconst(types::I32, 0),
+        };
+        self.builder.ins().return_(&[code]);
+
+        Ok(())
+    }
+
+    /// Translates `body`, the MIR of an instance with the signature `sig`.
+    pub fn instance(&mut self, body: &Body, sig: &InstanceSig) -> Result<(), Error> {
+        self.ret = Some(sig.ret);
+        self.heap_arrays = self.cg.contains_array(sig.ret)?;
+
+        let mut params = self.params().into_iter();
+        if self.layout(sig.ret)?.repr == Repr::Memory {
+            self.ret_ptr = params.next();
+        }
+
+        // Locals are kept even when optimizations remove their uses, so
+        // types of infinite size are reported at every level.
+        for local in body.locals.values() {
+            self.layout(local.ty)?;
+        }
+
+        for (block, data) in body.blocks.iter() {
+            let ir_block = self.builder.create_block();
+            for &param in &data.params {
+                if let Some(value) = self.block_param(ir_block, body.values[param].ty)? {
+                    self.values.insert(param, value);
+                }
+            }
+            self.blocks.insert(block, ir_block);
+        }
+
+        // The entry of the function cannot be jumped to, unlike that of the
+        // body, so it only passes the parameters on.
+        let args: Vec<_> = params.collect();
+        self.builder.ins().jump(self.blocks[&body.entry], &args);
+
+        // Unreachable blocks are left out.
+        let in_loop = loop_blocks(body);
+        for block in reverse_postorder(body) {
+            self.builder.switch_to_block(self.blocks[&block]);
+            self.in_loop = in_loop[block];
+            for &inst in &body.blocks[block].insts {
+                self.inst(body, inst)?;
+            }
+            self.terminator(body, body.blocks[block].terminator)?;
+        }
+
+        Ok(())
+    }
+
+    /// The Cranelift value of `value`, `None` if it has no representation.
+    fn value(&self, value: mir::Value) -> Option<Value> {
+        self.values.get(&value).copied()
+    }
+
+    fn inst(&mut self, body: &Body, inst: Inst) -> Result<(), Error> {
+        let data = body.insts[inst];
+        let ty = match data.result {
+            Some(result) => body.values[result].ty,
+            None => self.results().tcx.unit(),
+        };
+
+        let value = match data.kind {
+            InstKind::Const(value) => self.constant(value, ty)?,
+            InstKind::Binary { op, lhs, rhs } => {
+                let (lhs_ty, rhs_ty) = (body.values[lhs].ty, body.values[rhs].ty);
+                let (lhs, rhs) = (self.value(lhs), self.value(rhs));
+                self.primitive(op, lhs_ty, rhs_ty, lhs, rhs, data.span)?
+            }
+            InstKind::Unary { op, value } => {
+                let value_ty = body.values[value].ty;
+                self.unary(op, value_ty, self.value(value), data.span)?
+            }
+            InstKind::Call { callee, args } => {
+                self.call_inst(body, callee, body.list(args), data.span)?
+            }
+            InstKind::Load(place) => {
+                let (loc, ty) = self.place(body, place, data.span)?;
+                self.read(loc, ty)?
+            }
+            InstKind::Store { place, value } => {
+                let (loc, ty) = self.place(body, place, data.span)?;
+                self.write_to(loc, ty, self.value(value))?;
+                None
+            }
+            InstKind::IsVariant { place, variant } => {
+                let (loc, ty) = self.place(body, place, data.span)?;
+                let Loc::Addr(addr) = loc else {
+                    unreachable!("enums are in memory");
+                };
+
+                let item = self.adt_item(ty);
+                let index = self.variant_index(item, variant);
+                let tag = self.builder.ins().load(TAG, MemFlags::trusted(), addr, 0);
+                Some(self.icmp_imm(IntCC::Equal, tag, index as i64))
+            }
+            InstKind::Adt { variant, fields } => {
+                let values = body.list(fields).iter().map(|&field| self.value(field));
+                Some(self.adt(ty, variant, values.collect())?)
+            }
+            InstKind::Array(elements) => {
+                let values = body.list(elements).iter().map(|&elem| self.value(elem));
+                Some(self.array(ty, values.collect())?)
+            }
+            InstKind::Repeat { value, count } => {
+                Some(self.repeat(body, ty, value, count, data.span)?)
+            }
+        };
+
+        if let (Some(result), Some(value)) = (data.result, value) {
+            self.values.insert(result, value);
+        }
+
+        Ok(())
+    }
+
+    fn terminator(&mut self, body: &Body, terminator: Terminator) -> Result<(), Error> {
+        match terminator {
+            Terminator::Jump(dest) => {
+                let args = self.block_args(body, dest);
+                self.builder.ins().jump(self.blocks[&dest.block], &args);
+            }
+            Terminator::Branch {
+                cond,
+                then_dest,
+                else_dest,
+            } => {
+                let cond = self.value(cond).expect("conditions are bools");
+                let then_args = self.block_args(body, then_dest);
+                let else_args = self.block_args(body, else_dest);
+                self.builder.ins().brif(
+                    cond,
+                    self.blocks[&then_dest.block],
+                    &then_args,
+                    self.blocks[&else_dest.block],
+                    &else_args,
+                );
+            }
+            Terminator::Return(value) => self.ret(self.value(value))?,
+            Terminator::Unreachable => {
+                self.builder.ins().trap(UNREACHABLE);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The arguments passed to the parameters of a block, leaving out
+    /// those without a representation like the parameters themselves.
+    fn block_args(&self, body: &Body, dest: BlockCall) -> Vec<Value> {
+        body.list(dest.args)
+            .iter()
+            .filter_map(|&arg| self.value(arg))
+            .collect()
+    }
+
+    fn ret(&mut self, value: Option<Value>) -> Result<(), Error> {
+        match (self.ret_ptr, value) {
+            (Some(ptr), Some(value)) => {
+                let ty = self.ret.expect("returning from an instance");
+                let layout = self.layout(ty)?;
+                self.copy(ptr, value, &layout);
+                self.builder.ins().return_(&[]);
+            }
+            (None, Some(value)) => {
+                self.builder.ins().return_(&[value]);
+            }
+            _ => {
+                self.builder.ins().return_(&[]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `value`, or a placeholder of type `ty` if there is none
+    /// because the code is unreachable.
+    pub fn or_dummy(&mut self, value: Option<Value>, ty: TyId) -> Result<Option<Value>, Error> {
+        if value.is_some() {
+            return Ok(value);
+        }
+
+        let value = match self.layout(ty)?.repr {
+            Repr::Zst => None,
+            Repr::Scalar(types::F32) => Some(self.builder.ins().f32const(0.0)),
+            Repr::Scalar(types::F64) => Some(self.builder.ins().f64const(0.0)),
+            Repr::Scalar(ty) => Some(self.iconst(ty, 0)),
+            Repr::Memory => Some(self.iconst(self.cg.pointer, 0)),
+        };
+
+        Ok(value)
+    }
+
+    fn scalar(&mut self, ty: TyId) -> Result<Type, Error> {
+        match self.layout(ty)?.repr {
+            Repr::Scalar(ty) => Ok(ty),
+            _ => Ok(self.cg.pointer),
+        }
+    }
+
+    /// An integer constant, truncated to the width of `ty`.
+    pub fn iconst(&mut self, ty: Type, value: i64) -> Value {
+        let value = match ty.bits() {
+            64 => value,
+            bits => value & ((1 << bits) - 1),
+        };
+        self.builder.ins().iconst(ty, value)
+    }
+
+    pub fn icmp_imm(&mut self, cc: IntCC, value: Value, imm: i64) -> Value {
+        let ty = self.builder.func.dfg.value_type(value);
+        let imm = self.iconst(ty, imm);
+        self.builder.ins().icmp(cc, value, imm)
+    }
+
+    /// Widens an integer of type `ty` to 64 bits.
+    pub fn extend_to_i64(&mut self, value: Value, ty: TyId) -> Value {
+        match self.results().tcx[ty] {
+            TyKind::Int(int) if int.bits() < 64 && int.is_signed() => {
+                self.builder.ins().sextend(types::I64, value)
+            }
+            TyKind::Int(int) if int.bits() < 64 => self.builder.ins().uextend(types::I64, value),
+            _ => value,
+        }
+    }
+
+    fn constant(&mut self, value: Const, ty: TyId) -> Result<Option<Value>, Error> {
+        let value = match value {
+            Const::Int(value) => {
+                let ty = self.scalar(ty)?;
+                self.iconst(ty, value as i64)
+            }
+            Const::Float(value) => match self.scalar(ty)? {
+                types::F32 => self.builder.ins().f32const(value as f32),
+                _ => self.builder.ins().f64const(value),
+            },
+            Const::Bool(value) => self.iconst(types::I8, value.into()),
+            Const::Char(value) => self.iconst(types::I32, value as i64),
+            Const::Str(value) => {
+                let mut bytes = self.interner().get(value).as_bytes().to_vec();
+                bytes.push(0);
+                self.data_addr(&bytes)?
+            }
+            Const::Unit => return Ok(None),
+        };
+
+        Ok(Some(value))
+    }
+
+    /// The item of the struct or enum type `ty`.
+    fn adt_item(&mut self, ty: TyId) -> ItemEntity {
+        let TyKind::Adt(item, _) = self.results().tcx[ty] else {
+            unreachable!("only structs and enums have fields and variants");
+        };
+        item
+    }
+
+    /// The type of the elements of the array type `ty`.
+    fn elem(&mut self, ty: TyId) -> TyId {
+        let TyKind::Array(elem) = self.results().tcx[ty] else {
+            unreachable!("only arrays have elements");
+        };
+        elem
+    }
+
+    fn local(&mut self, local: Local, ty: TyId) -> Result<Storage, Error> {
+        if let Some(&storage) = self.locals.get(&local) {
+            return Ok(storage);
+        }
+
+        let layout = self.layout(ty)?;
+        let storage = match layout.repr {
+            Repr::Zst => Storage::Zst,
+            Repr::Scalar(ty) => {
+                let var = Variable::from_u32(self.next_var);
+                self.next_var += 1;
+                self.builder.declare_var(var, ty);
+                Storage::Var(var)
+            }
+            Repr::Memory => Storage::Slot(self.stack_slot(layout.size, layout.align)),
+        };
+        self.locals.insert(local, storage);
+
+        Ok(storage)
+    }
+
+    /// Applies the projections of `place`, returning where its value is and
+    /// its type. Indexing out of bounds panics at `span`.
+    fn place(&mut self, body: &Body, place: Place, span: Span) -> Result<(Loc, TyId), Error> {
+        let data = &body.places[place];
+        let mut ty = body.locals[data.local].ty;
+        let mut loc = match self.local(data.local, ty)? {
+            Storage::Var(var) => Loc::Var(var),
+            Storage::Slot(slot) => {
+                Loc::Addr(self.builder.ins().stack_addr(self.cg.pointer, slot, 0))
+            }
+            Storage::Zst => Loc::Zst,
+        };
+
+        let mut variant = None;
+        for &projection in &data.projections {
+            match projection {
+                Projection::Variant(selected) => variant = Some(selected),
+                Projection::Field(index) => {
+                    let fields = match variant.take() {
+                        Some(variant) => {
+                            let item = self.adt_item(ty);
+                            self.variant_layout(ty, item, variant)?
+                        }
+                        None => self.struct_layout(ty)?,
+                    };
+
+                    let field = fields[index as usize];
+                    let Loc::Addr(addr) = loc else {
+                        unreachable!("structs and enums are in memory");
+                    };
+                    loc = Loc::Addr(self.builder.ins().iadd_imm(addr, i64::from(field.offset)));
+                    ty = field.ty;
+                }
+                Projection::Index(index) => {
+                    let elem = self.elem(ty);
+                    let array = self.read(loc, ty)?.expect("arrays are pointers");
+                    let index_ty = body.values[index].ty;
+                    let index = self.value(index).expect("indices are integers");
+
+                    // Negative indices are larger than any length when
+                    // compared as unsigned.
+                    let index = self.extend_to_i64(index, index_ty);
+                    let len = self
+                        .builder
+                        .ins()
+                        .load(types::I64, MemFlags::trusted(), array, 0);
+                    let out_of_bounds =
+                        self.builder
+                            .ins()
+                            .icmp(IntCC::UnsignedGreaterThanOrEqual, index, len);
+                    self.panic_if(out_of_bounds, "index out of bounds", span)?;
+
+                    loc = Loc::Addr(self.element(array, index, elem)?);
+                    ty = elem;
+                }
+            }
+        }
+
+        Ok((loc, ty))
+    }
+
+    /// The value of type `ty` at `loc`. Structs and enums are copied to a
+    /// new stack slot, as the place may be stored to while the value is
+    /// still in use.
+    fn read(&mut self, loc: Loc, ty: TyId) -> Result<Option<Value>, Error> {
+        let addr = match loc {
+            Loc::Var(var) => return Ok(Some(self.builder.use_var(var))),
+            Loc::Addr(addr) => addr,
+            Loc::Zst => return Ok(None),
+        };
+
+        let layout = self.layout(ty)?;
+        if layout.repr != Repr::Memory {
+            return self.load(ty, addr, 0);
+        }
+
+        let dest = self.slot(&layout);
+        self.copy(dest, addr, &layout);
+        Ok(Some(dest))
+    }
+
+    fn write_to(&mut self, loc: Loc, ty: TyId, value: Option<Value>) -> Result<(), Error> {
+        match (loc, value) {
+            (Loc::Var(var), Some(value)) => self.builder.def_var(var, value),
+            (Loc::Addr(addr), _) => self.store(value, ty, addr, 0)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn stack_slot(&mut self, size: u32, align: u32) -> StackSlot {
+        let align_shift = align.trailing_zeros() as u8;
+        self.builder.create_sized_stack_slot(StackSlotData::new(
+            StackSlotKind::ExplicitSlot,
+            size,
+            align_shift,
+        ))
+    }
+
+    /// The address of a new stack slot for a value of `layout`.
+    pub fn slot(&mut self, layout: &Layout) -> Value {
+        let slot = self.stack_slot(layout.size, layout.align);
+        self.builder.ins().stack_addr(self.cg.pointer, slot, 0)
+    }
+
+    /// Copies a struct or enum from `src` to `dest`.
+    fn copy(&mut self, dest: Value, src: Value, layout: &Layout) {
+        let config = self.cg.module.target_config();
+        let align = layout.align as u8;
+        self.builder.emit_small_memory_copy(
+            config,
+            dest,
+            src,
+            layout.size.into(),
+            align,
+            align,
+            false,
+            MemFlags::trusted(),
+        );
+    }
+
+    /// Loads a value of type `ty` from `addr + offset`. Values in memory are
+    /// not loaded, only their address is computed.
+    pub fn load(&mut self, ty: TyId, addr: Value, offset: u32) -> Result<Option<Value>, Error> {
+        let value = match self.layout(ty)?.repr {
+            Repr::Zst => None,
+            Repr::Scalar(ty) => {
+                let flags = MemFlags::trusted();
+                Some(self.builder.ins().load(ty, flags, addr, offset as i32))
+            }
+            Repr::Memory => Some(self.builder.ins().iadd_imm(addr, i64::from(offset))),
+        };
+
+        Ok(value)
+    }
+
+    fn store(
+        &mut self,
+        value: Option<Value>,
+        ty: TyId,
+        addr: Value,
+        offset: u32,
+    ) -> Result<(), Error> {
+        let Some(value) = value else {
+            return Ok(());
+        };
+
+        let layout = self.layout(ty)?;
+        match layout.repr {
+            Repr::Zst => {}
+            Repr::Scalar(_) => {
+                let flags = MemFlags::trusted();
+                self.builder.ins().store(flags, value, addr, offset as i32);
+            }
+            Repr::Memory => {
+                let dest = self.builder.ins().iadd_imm(addr, i64::from(offset));
+                self.copy(dest, value, &layout);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The address of element `index` of an array of `elem`.
+    pub fn element(&mut self, array: Value, index: Value, elem: TyId) -> Result<Value, Error> {
+        let stride = self.layout(elem)?.size;
+        let offset = self.builder.ins().imul_imm(index, i64::from(stride));
+        let addr = self.builder.ins().iadd(array, offset);
+
+        Ok(self.builder.ins().iadd_imm(addr, i64::from(ARRAY_HEADER)))
+    }
+
+    /// Calls `body` with the index and address of each element of `array`.
+    pub fn for_each_element(
+        &mut self,
+        array: Value,
+        elem: TyId,
+        mut body: impl FnMut(&mut Self, Value, Value) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let len = self
+            .builder
+            .ins()
+            .load(types::I64, MemFlags::trusted(), array, 0);
+
+        let header = self.builder.create_block();
+        let index = self.builder.append_block_param(header, types::I64);
+        let body_block = self.builder.create_block();
+        let exit = self.builder.create_block();
+
+        let zero = self.iconst(types::I64, 0);
+        self.builder.ins().jump(header, &[zero]);
+
+        self.builder.switch_to_block(header);
+        let more = self.builder.ins().icmp(IntCC::UnsignedLessThan, index, len);
+        self.builder.ins().brif(more, body_block, &[], exit, &[]);
+
+        self.builder.switch_to_block(body_block);
+        let addr = self.element(array, index, elem)?;
+        body(self, index, addr)?;
+        let next = self.builder.ins().iadd_imm(index, 1);
+        self.builder.ins().jump(header, &[next]);
+
+        self.builder.switch_to_block(exit);
+        Ok(())
+    }
+
+    pub fn func_ref(&mut self, func: FuncId) -> FuncRef {
+        if let Some(&func_ref) = self.func_refs.get(&func) {
+            return func_ref;
+        }
+
+        let func_ref = self.cg.module.declare_func_in_func(func, self.builder.func);
+        self.func_refs.insert(func, func_ref);
+
+        func_ref
+    }
+
+    /// The address of constant data holding `bytes`.
+    pub fn data_addr(&mut self, bytes: &[u8]) -> Result<Value, Error> {
+        let data = self.cg.data(bytes)?;
+        Ok(self.global_addr(data))
+    }
+
+    fn global_addr(&mut self, data: DataId) -> Value {
+        let global = match self.data_refs.get(&data) {
+            Some(&global) => global,
+            None => {
+                let global = self.cg.module.declare_data_in_func(data, self.builder.func);
+                self.data_refs.insert(data, global);
+                global
+            }
+        };
+
+        self.builder.ins().symbol_value(self.cg.pointer, global)
+    }
+
+    fn stack_limit_addr(&mut self) -> Value {
+        let stack_limit = self.cg.runtime.stack_limit;
+        self.global_addr(stack_limit.expect("the runtime is declared first"))
+    }
+
+    /// Stops the program with a stack overflow error at `span` if the stack
+    /// has grown past its limit, before a call that may recurse.
+    fn check_stack(&mut self, span: Span) -> Result<(), Error> {
+        let limit_addr = self.stack_limit_addr();
+        let limit = self
+            .builder
+            .ins()
+            .load(self.cg.pointer, MemFlags::trusted(), limit_addr, 0);
+        let sp = self.builder.ins().get_stack_pointer(self.cg.pointer);
+        let overflow = self.builder.ins().icmp(IntCC::UnsignedLessThan, sp, limit);
+        self.panic_if(overflow, "stack overflow", span)
+    }
+
+    pub fn call_runtime(&mut self, func: RuntimeFn, args: &[Value]) -> Option<Value> {
+        let func_ref = self.func_ref(self.cg.runtime.functions[&func]);
+        let call = self.builder.ins().call(func_ref, args);
+        self.builder.inst_results(call).first().copied()
+    }
+
+    pub fn call_glue(
+        &mut self,
+        glue: Glue,
+        ty: TyId,
+        args: &[Value],
+    ) -> Result<Option<Value>, Error> {
+        let func = self.cg.glue(glue, ty)?;
+        let func_ref = self.func_ref(func);
+        let call = self.builder.ins().call(func_ref, args);
+
+        Ok(self.builder.inst_results(call).first().copied())
+    }
+
+    /// Calls a function with the signature `sig`, passing the address of a
+    /// new stack slot for returned values in memory.
+    fn call(
+        &mut self,
+        func: FuncId,
+        sig: &InstanceSig,
+        args: Vec<Option<Value>>,
+    ) -> Result<Option<Value>, Error> {
+        let ret = self.layout(sig.ret)?;
+        let mut values = Vec::with_capacity(args.len() + 1);
+
+        let ret_ptr = (ret.repr == Repr::Memory).then(|| self.slot(&ret));
+        values.extend(ret_ptr);
+
+        for (value, &ty) in args.into_iter().zip(&sig.params) {
+            values.extend(self.or_dummy(value, ty)?);
+        }
+
+        let func_ref = self.func_ref(func);
+        let call = self.builder.ins().call(func_ref, &values);
+
+        Ok(match ret.repr {
+            Repr::Zst => None,
+            Repr::Scalar(_) => Some(self.builder.inst_results(call)[0]),
+            Repr::Memory => ret_ptr,
+        })
+    }
+
+    fn call_instance(
+        &mut self,
+        callee: InstanceId,
+        args: Vec<Option<Value>>,
+    ) -> Result<Option<Value>, Error> {
+        let (func, sig) = self.cg.functions[&callee].clone();
+        self.call(func, &sig, args)
+    }
+
+    /// Stops the program with `message` if `cond` is true.
+    pub fn panic_if(&mut self, cond: Value, message: &str, span: Span) -> Result<(), Error> {
+        let fail = self.builder.create_block();
+        let next = self.builder.create_block();
+        self.builder.set_cold_block(fail);
+        self.builder.ins().brif(cond, fail, &[], next, &[]);
+
+        self.builder.switch_to_block(fail);
+        self.panic(message, Some(span))?;
+
+        self.builder.switch_to_block(next);
+        Ok(())
+    }
+
+    /// Prints `message` with the location of `span` to stderr and exits with
+    /// status 101.
+    pub fn panic(&mut self, message: &str, span: Option<Span>) -> Result<(), Error> {
+        let source = self.cg.program.source;
+        let text = match span {
+            Some(span) => {
+                let position = source.position(span);
+                format!(
+                    "error: {message}\n --> {}:{}:{}\n",
+                    source.file_name, position.line, position.column
+                )
+            }
+            None => format!("error: {message}\n"),
+        };
+
+        let ptr = self.data_addr(text.as_bytes())?;
+        let len = self.iconst(self.cg.pointer, text.len() as i64);
+        self.call_runtime(RuntimeFn::Panic, &[ptr, len]);
+        self.builder.ins().trap(UNREACHABLE);
+
+        Ok(())
+    }
+
+    fn unary(
+        &mut self,
+        op: UnaryOp,
+        ty: TyId,
+        value: Option<Value>,
+        span: Span,
+    ) -> Result<Option<Value>, Error> {
+        let Some(value) = value else {
+            return Ok(None);
+        };
+
+        let value = match (op, self.results().tcx[ty]) {
+            (UnaryOp::Sub, TyKind::Float(_)) => self.builder.ins().fneg(value),
+            (UnaryOp::Sub, TyKind::Int(int)) => {
+                let value_ty = self.builder.func.dfg.value_type(value);
+                let zero = self.iconst(value_ty, 0);
+                let (result, overflow) = if int.is_signed() {
+                    self.builder.ins().ssub_overflow(zero, value)
+                } else {
+                    self.builder.ins().usub_overflow(zero, value)
+                };
+                self.panic_if(overflow, OVERFLOW, span)?;
+                result
+            }
+            _ => value,
+        };
+
+        Ok(Some(value))
+    }
+
+    /// Applies an operator built into the language to operands of type
+    /// `ty`, and `rhs_ty` for the amount of shifts. Values of other types
+    /// are compared for equality field by field.
+    pub fn primitive(
+        &mut self,
+        op: BinOp,
+        ty: TyId,
+        rhs_ty: TyId,
+        lhs: Option<Value>,
+        rhs: Option<Value>,
+        span: Span,
+    ) -> Result<Option<Value>, Error> {
+        let kind = self.results().tcx[ty];
+        if kind == TyKind::Unit {
+            return Ok(Some(self.iconst(types::I8, (op == BinOp::Eq).into())));
+        }
+
+        let (Some(lhs), Some(rhs)) = (lhs, rhs) else {
+            return Ok(None);
+        };
+
+        let ins = self.builder.ins();
+        let value = match kind {
+            TyKind::Int(int) => return self.int_binary(op, int, rhs_ty, lhs, rhs, span).map(Some),
+            TyKind::Float(_) => match op {
+                BinOp::Add => ins.fadd(lhs, rhs),
+                BinOp::Sub => ins.fsub(lhs, rhs),
+                BinOp::Mul => ins.fmul(lhs, rhs),
+                BinOp::Div => ins.fdiv(lhs, rhs),
+                _ => ins.fcmp(float_cc(op), lhs, rhs),
+            },
+            TyKind::Bool | TyKind::Char => match op {
+                BinOp::BitAnd => ins.band(lhs, rhs),
+                BinOp::BitOr => ins.bor(lhs, rhs),
+                BinOp::BitXor => ins.bxor(lhs, rhs),
+                _ => ins.icmp(int_cc(op, false), lhs, rhs),
+            },
+            TyKind::Str => match op {
+                BinOp::Add => self
+                    .call_runtime(RuntimeFn::Concat, &[lhs, rhs])
+                    .expect("concatenation returns a string"),
+                _ => {
+                    let ordering = self
+                        .call_runtime(RuntimeFn::Strcmp, &[lhs, rhs])
+                        .expect("strcmp returns an int");
+                    self.icmp_imm(int_cc(op, true), ordering, 0)
+                }
+            },
+            _ => {
+                let eq = self
+                    .call_glue(Glue::Eq, ty, &[lhs, rhs])?
+                    .expect("equality returns a bool");
+                match op {
+                    BinOp::Neq => self.builder.ins().bxor_imm(eq, 1),
+                    _ => eq,
+                }
+            }
+        };
+
+        Ok(Some(value))
+    }
+
+    /// Integer arithmetic, which stops the program on overflow, division by
+    /// zero and shifts by at least the width of the integer.
+    fn int_binary(
+        &mut self,
+        op: BinOp,
+        int: unnamed_lexer::IntSuffix,
+        rhs_ty: TyId,
+        lhs: Value,
+        rhs: Value,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let signed = int.is_signed();
+
+        let value = match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mul => {
+                let ins = self.builder.ins();
+                let (value, overflow) = match (op, signed) {
+                    (BinOp::Add, true) => ins.sadd_overflow(lhs, rhs),
+                    (BinOp::Add, false) => ins.uadd_overflow(lhs, rhs),
+                    (BinOp::Sub, true) => ins.ssub_overflow(lhs, rhs),
+                    (BinOp::Sub, false) => ins.usub_overflow(lhs, rhs),
+                    (_, true) => ins.smul_overflow(lhs, rhs),
+                    (_, false) => ins.umul_overflow(lhs, rhs),
+                };
+                self.panic_if(overflow, OVERFLOW, span)?;
+                value
+            }
+            BinOp::Div => {
+                let zero = self.icmp_imm(IntCC::Equal, rhs, 0);
+                self.panic_if(zero, "attempt to divide by zero", span)?;
+
+                if signed {
+                    let min = self.icmp_imm(IntCC::Equal, lhs, int.min() as i64);
+                    let minus_one = self.icmp_imm(IntCC::Equal, rhs, -1);
+                    let overflow = self.builder.ins().band(min, minus_one);
+                    self.panic_if(overflow, OVERFLOW, span)?;
+                    self.builder.ins().sdiv(lhs, rhs)
+                } else {
+                    self.builder.ins().udiv(lhs, rhs)
+                }
+            }
+            BinOp::BitAnd => self.builder.ins().band(lhs, rhs),
+            BinOp::BitOr => self.builder.ins().bor(lhs, rhs),
+            BinOp::BitXor => self.builder.ins().bxor(lhs, rhs),
+            BinOp::BitShl | BinOp::BitShr => {
+                // Negative amounts are larger than any valid one when
+                // compared as unsigned.
+                let bits = int.bits();
+                let rhs = self.extend_to_i64(rhs, rhs_ty);
+                let out_of_range =
+                    self.icmp_imm(IntCC::UnsignedGreaterThanOrEqual, rhs, i64::from(bits));
+                let message = format!(
+                    "attempt to shift a {bits}-bit integer by an amount outside of 0..{bits}"
+                );
+                self.panic_if(out_of_range, &message, span)?;
+
+                match (op, signed) {
+                    (BinOp::BitShl, _) => self.builder.ins().ishl(lhs, rhs),
+                    (_, true) => self.builder.ins().sshr(lhs, rhs),
+                    (_, false) => self.builder.ins().ushr(lhs, rhs),
+                }
+            }
+            _ => self.builder.ins().icmp(int_cc(op, signed), lhs, rhs),
+        };
+
+        Ok(value)
+    }
+
+    fn call_inst(
+        &mut self,
+        body: &Body,
+        callee: Callee,
+        args: &[mir::Value],
+        span: Span,
+    ) -> Result<Option<Value>, Error> {
+        let values: Vec<_> = args.iter().map(|&arg| self.value(arg)).collect();
+
+        match callee {
+            Callee::Instance(instance) => {
+                self.check_stack(span)?;
+                self.call_instance(instance, values)
+            }
+            Callee::Builtin(builtin) => {
+                for (i, (&arg, value)) in args.iter().zip(values).enumerate() {
+                    if i > 0 {
+                        self.write_str(" ")?;
+                    }
+                    self.print(value, body.values[arg].ty)?;
+                }
+                if builtin == Builtin::Println {
+                    self.write_str("\n")?;
+                }
+
+                Ok(None)
+            }
+            Callee::Len => {
+                let ty = body.values[args[0]].ty;
+                let Some(receiver) = values[0] else {
+                    return Ok(None);
+                };
+
+                let len = match self.results().tcx[ty] {
+                    TyKind::Array(_) => {
+                        let flags = MemFlags::trusted();
+                        self.builder.ins().load(types::I64, flags, receiver, 0)
+                    }
+                    _ => self
+                        .call_runtime(RuntimeFn::Strlen, &[receiver])
+                        .expect("strlen returns a length"),
+                };
+                Ok(Some(len))
+            }
+        }
+    }
+
+    /// The parameter of `block` receiving values of type `ty`, if they have
+    /// a representation.
+    fn block_param(&mut self, block: ir::Block, ty: TyId) -> Result<Option<Value>, Error> {
+        let param = match self.layout(ty)?.repr {
+            Repr::Zst => None,
+            Repr::Scalar(ty) => Some(self.builder.append_block_param(block, ty)),
+            Repr::Memory => Some(self.builder.append_block_param(block, self.cg.pointer)),
+        };
+
+        Ok(param)
+    }
+
+    /// Continues in a new block if `cond` is true, and in `fail` otherwise.
+    pub fn branch_or_fail(&mut self, cond: Value, fail: ir::Block) {
+        let next = self.builder.create_block();
+        self.builder.ins().brif(cond, next, &[], fail, &[]);
+        self.builder.switch_to_block(next);
+    }
+
+    pub fn struct_layout(&mut self, ty: TyId) -> Result<Vec<Field>, Error> {
+        match &self.layout(ty)?.shape {
+            Shape::Struct(fields) => Ok(fields.clone()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// The fields of `variant` in the enum type `ty`.
+    fn variant_layout(
+        &mut self,
+        ty: TyId,
+        item: ItemEntity,
+        variant: VariantEntity,
+    ) -> Result<Vec<Field>, Error> {
+        let index = self.variant_index(item, variant);
+        match &self.layout(ty)?.shape {
+            Shape::Enum(variants) => Ok(variants[index].clone()),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    pub fn variant_index(&self, item: ItemEntity, variant: VariantEntity) -> usize {
+        let ctx = self.ctx();
+        let Item::Enum(enum_item) = ctx.items[item] else {
+            return 0;
+        };
+
+        ctx.variants
+            .list(enum_item.variants)
+            .iter()
+            .position(|&other| other == variant)
+            .unwrap_or(0)
+    }
+
+    pub fn struct_field_names(&mut self, item: ItemEntity) -> Vec<StrId> {
+        self.results().fields[&item]
+            .iter()
+            .map(|&(name, _)| name)
+            .collect()
+    }
+
+    /// The names of the fields of a struct variant, or nothing for other
+    /// variants.
+    pub fn variant_field_names(&self, variant: VariantEntity) -> Vec<StrId> {
+        let ctx = self.ctx();
+        match ctx.variants[variant].kind {
+            VariantKind::Struct(fields) => ctx
+                .field_defs
+                .list(fields)
+                .iter()
+                .map(|&field| ctx.field_defs[field].name)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Builds a value of the enum type `ty` in a new stack slot.
+    fn variant(
+        &mut self,
+        ty: TyId,
+        item: ItemEntity,
+        variant: VariantEntity,
+        values: Vec<Option<Value>>,
+    ) -> Result<Value, Error> {
+        let layout = self.layout(ty)?;
+        let index = self.variant_index(item, variant);
+        let fields = self.variant_layout(ty, item, variant)?;
+
+        let ptr = self.slot(&layout);
+        let tag = self.iconst(TAG, index as i64);
+        self.builder.ins().store(MemFlags::trusted(), tag, ptr, 0);
+        for (value, field) in values.into_iter().zip(&fields) {
+            self.store(value, field.ty, ptr, field.offset)?;
+        }
+
+        Ok(ptr)
+    }
+
+    /// Builds a struct, or an enum holding `variant`, in a new stack slot.
+    fn adt(
+        &mut self,
+        ty: TyId,
+        variant: Option<VariantEntity>,
+        values: Vec<Option<Value>>,
+    ) -> Result<Value, Error> {
+        if let Some(variant) = variant {
+            let item = self.adt_item(ty);
+            return self.variant(ty, item, variant, values);
+        }
+
+        let layout = self.layout(ty)?;
+        let fields = self.struct_layout(ty)?;
+        let ptr = self.slot(&layout);
+        for (value, field) in values.into_iter().zip(&fields) {
+            self.store(value, field.ty, ptr, field.offset)?;
+        }
+
+        Ok(ptr)
+    }
+
+    fn array(&mut self, ty: TyId, values: Vec<Option<Value>>) -> Result<Value, Error> {
+        let elem = self.elem(ty);
+        let stride = self.layout(elem)?.size;
+
+        let len = self.iconst(types::I64, values.len() as i64);
+        let ptr = self.alloc_array(len, Some(values.len() as u64), elem)?;
+        for (i, value) in values.into_iter().enumerate() {
+            let offset = ARRAY_HEADER + i as u32 * stride;
+            self.store(value, elem, ptr, offset)?;
+        }
+
+        Ok(ptr)
+    }
+
+    /// An array of `count` copies of `value`, panicking at `span` if the
+    /// count is negative.
+    fn repeat(
+        &mut self,
+        body: &Body,
+        ty: TyId,
+        value: mir::Value,
+        count: mir::Value,
+        span: Span,
+    ) -> Result<Value, Error> {
+        let elem = self.elem(ty);
+        let len_ty = body.values[count].ty;
+        let static_len = match body.values[count].def {
+            ValueDef::Inst(inst) => match body.insts[inst].kind {
+                InstKind::Const(Const::Int(len)) => u64::try_from(len).ok(),
+                _ => None,
+            },
+            ValueDef::Param(..) => None,
+        };
+
+        let len = self.value(count).expect("lengths are integers");
+        let len = self.extend_to_i64(len, len_ty);
+        if let TyKind::Int(int) = self.results().tcx[len_ty]
+            && int.is_signed()
+        {
+            let negative = self.icmp_imm(IntCC::SignedLessThan, len, 0);
+            self.panic_if(negative, "invalid array length", span)?;
+        }
+
+        let value = self.value(value);
+        let ptr = self.alloc_array(len, static_len, elem)?;
+        self.for_each_element(ptr, elem, |t, _, addr| t.store(value, elem, addr, 0))?;
+
+        Ok(ptr)
+    }
+
+    /// Allocates an array of `len` elements and stores its length. Arrays
+    /// are on the stack when their length is known and small, unless the
+    /// function can return them or they are created in a loop, where the
+    /// same slot would be reused while earlier arrays are still in use.
+    fn alloc_array(
+        &mut self,
+        len: Value,
+        static_len: Option<u64>,
+        elem: TyId,
+    ) -> Result<Value, Error> {
+        let layout = self.layout(elem)?;
+        let stride = u64::from(layout.size);
+
+        let ptr = match static_len {
+            Some(static_len)
+                if !self.heap_arrays && !self.in_loop && static_len * stride <= MAX_STACK_ARRAY =>
+            {
+                let size = u64::from(ARRAY_HEADER) + static_len * stride;
+                let align = layout.align.max(ARRAY_HEADER);
+                let slot = self.stack_slot(size as u32, align);
+                self.builder.ins().stack_addr(self.cg.pointer, slot, 0)
+            }
+            _ => {
+                let size = self.builder.ins().imul_imm(len, stride as i64);
+                let size = self.builder.ins().iadd_imm(size, i64::from(ARRAY_HEADER));
+                self.call_runtime(RuntimeFn::Alloc, &[size])
+                    .expect("alloc returns a pointer")
+            }
+        };
+
+        self.builder.ins().store(MemFlags::trusted(), len, ptr, 0);
+        Ok(ptr)
+    }
+
+    /// Writes `text` to stdout.
+    pub fn write_str(&mut self, text: &str) -> Result<(), Error> {
+        let ptr = self.data_addr(text.as_bytes())?;
+        let len = self.iconst(self.cg.pointer, text.len() as i64);
+        self.write(1, ptr, len);
+
+        Ok(())
+    }
+
+    /// Writes `len` bytes at `ptr` to the file descriptor `fd`.
+    pub fn write(&mut self, fd: i64, ptr: Value, len: Value) {
+        let fd = self.iconst(types::I32, fd);
+        self.call_runtime(RuntimeFn::Write, &[fd, ptr, len]);
+    }
+}
+
+pub(crate) const OVERFLOW: &str = "attempt to compute a value that overflows its type";
+
+fn int_cc(op: BinOp, signed: bool) -> IntCC {
+    match (op, signed) {
+        (BinOp::Eq, _) => IntCC::Equal,
+        (BinOp::Neq, _) => IntCC::NotEqual,
+        (BinOp::Lt, true) => IntCC::SignedLessThan,
+        (BinOp::Lt, false) => IntCC::UnsignedLessThan,
+        (BinOp::LtEq, true) => IntCC::SignedLessThanOrEqual,
+        (BinOp::LtEq, false) => IntCC::UnsignedLessThanOrEqual,
+        (BinOp::Gt, true) => IntCC::SignedGreaterThan,
+        (BinOp::Gt, false) => IntCC::UnsignedGreaterThan,
+        (BinOp::GtEq, true) => IntCC::SignedGreaterThanOrEqual,
+        (BinOp::GtEq, false) => IntCC::UnsignedGreaterThanOrEqual,
+        _ => unreachable!("`{}` is not a comparison", op.as_str()),
+    }
+}
+
+fn float_cc(op: BinOp) -> FloatCC {
+    match op {
+        BinOp::Eq => FloatCC::Equal,
+        BinOp::Neq => FloatCC::NotEqual,
+        BinOp::Lt => FloatCC::LessThan,
+        BinOp::LtEq => FloatCC::LessThanOrEqual,
+        BinOp::Gt => FloatCC::GreaterThan,
+        BinOp::GtEq => FloatCC::GreaterThanOrEqual,
+        _ => unreachable!("`{}` is not a float operator", op.as_str()),
+    }
+}
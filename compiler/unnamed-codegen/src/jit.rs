@@ -0,0 +1,460 @@
+//! Compilation into executable memory, for running programs without
+//! producing files and for calling their functions from Rust.
+
+use std::{collections::HashMap, fmt, mem, panic, thread};
+
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::FuncId;
+use unnamed_ast::Item;
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+use unnamed_typeck::{TyId, TyKind, TypeckResults};
+
+use crate::{Codegen, Error, ErrorKind, Program, host_isa, runtime::STACK_BUDGET};
+
+/// The stack size of the threads compiled code runs on, that of main
+/// threads by default, so that stack overflows are caught as they are in
+/// executables.
+const STACK_SIZE: usize = 8 << 20;
+
+/// The types of values that can be passed to and returned from compiled
+/// functions called from Rust.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JitTy {
+    Unit,
+    Bool,
+    Int(IntSuffix),
+    Float(FloatSuffix),
+    Char,
+}
+
+impl JitTy {
+    fn of(results: &TypeckResults, ty: TyId) -> Option<Self> {
+        match results.tcx[ty] {
+            TyKind::Unit => Some(JitTy::Unit),
+            TyKind::Bool => Some(JitTy::Bool),
+            TyKind::Int(int) => Some(JitTy::Int(int)),
+            TyKind::Float(float) => Some(JitTy::Float(float)),
+            TyKind::Char => Some(JitTy::Char),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for JitTy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JitTy::Unit => f.write_str("()"),
+            JitTy::Bool => f.write_str("bool"),
+            JitTy::Int(int) => f.write_str(int.as_str()),
+            JitTy::Float(float) => f.write_str(float.as_str()),
+            JitTy::Char => f.write_str("char"),
+        }
+    }
+}
+
+/// Why a compiled function could not be called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CallError {
+    /// No non-generic function has this name.
+    UnknownFunction(String),
+    /// The function takes or returns a value that cannot cross into Rust,
+    /// like a string or a struct.
+    UnsupportedSignature(String),
+    /// The Rust types of the call do not match the function's signature.
+    Mismatch {
+        name: String,
+        expected: String,
+        found: String,
+    },
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::UnknownFunction(name) => write!(f, "no function named `{name}`"),
+            CallError::UnsupportedSignature(name) => {
+                write!(f, "`{name}` takes or returns values that Rust cannot pass")
+            }
+            CallError::Mismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{name}` has type `{expected}`, but was called as `{found}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CallError {}
+
+/// A Rust type with the same representation as a type of the language.
+pub trait JitValue: Copy + Send {
+    /// The type passed to and returned from compiled code.
+    type Abi: Copy;
+    const TY: JitTy;
+
+    fn into_abi(self) -> Self::Abi;
+    fn from_abi(abi: Self::Abi) -> Self;
+}
+
+macro_rules! jit_value {
+    ($($ty:ty => $jit_ty:expr),* $(,)?) => {
+        $(
+            impl JitValue for $ty {
+                type Abi = $ty;
+                const TY: JitTy = $jit_ty;
+
+                fn into_abi(self) -> Self::Abi {
+                    self
+                }
+
+                fn from_abi(abi: Self::Abi) -> Self {
+                    abi
+                }
+            }
+        )*
+    };
+}
+
+jit_value! {
+    () => JitTy::Unit,
+    bool => JitTy::Bool,
+    i8 => JitTy::Int(IntSuffix::I8),
+    i16 => JitTy::Int(IntSuffix::I16),
+    i32 => JitTy::Int(IntSuffix::I32),
+    i64 => JitTy::Int(IntSuffix::I64),
+    u8 => JitTy::Int(IntSuffix::U8),
+    u16 => JitTy::Int(IntSuffix::U16),
+    u32 => JitTy::Int(IntSuffix::U32),
+    u64 => JitTy::Int(IntSuffix::U64),
+    f32 => JitTy::Float(FloatSuffix::F32),
+    f64 => JitTy::Float(FloatSuffix::F64),
+}
+
+/// Chars are passed as their code point.
+impl JitValue for char {
+    type Abi = u32;
+    const TY: JitTy = JitTy::Char;
+
+    fn into_abi(self) -> Self::Abi {
+        self.into()
+    }
+
+    fn from_abi(abi: Self::Abi) -> Self {
+        char::from_u32(abi).expect("compiled code returned an invalid char")
+    }
+}
+
+/// A tuple of arguments for a compiled function. Unit values have no
+/// representation in compiled code, so they cannot be arguments.
+pub trait JitArgs: Send {
+    fn tys() -> Vec<JitTy>;
+
+    /// Calls the function at `ptr` with the arguments.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a compiled function taking arguments of the types
+    /// returned by `tys` and returning `R`.
+    unsafe fn call<R: JitValue>(self, ptr: *const u8) -> R;
+}
+
+macro_rules! jit_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: JitValue),*> JitArgs for ($($arg,)*) {
+            fn tys() -> Vec<JitTy> {
+                vec![$($arg::TY),*]
+            }
+
+            #[allow(non_snake_case)]
+            unsafe fn call<R: JitValue>(self, ptr: *const u8) -> R {
+                let ($($arg,)*) = self;
+                // SAFETY: The caller guarantees that the function has this
+                // signature.
+                let func = unsafe {
+                    mem::transmute::<*const u8, extern "C" fn($($arg::Abi),*) -> R::Abi>(ptr)
+                };
+                R::from_abi(func($($arg.into_abi()),*))
+            }
+        }
+    };
+}
+
+jit_args!();
+jit_args!(A);
+jit_args!(A, B);
+jit_args!(A, B, C);
+jit_args!(A, B, C, D);
+
+#[derive(Debug, Clone)]
+struct JitFunction {
+    ptr: *const u8,
+    /// The parameter and return types, if they can cross into Rust.
+    sig: Option<(Vec<JitTy>, JitTy)>,
+}
+
+/// A program compiled into executable memory, which is freed when it is
+/// dropped. Compiled code runs on a thread of its own. Runtime errors in it,
+/// stack overflows included, print their message and exit the process with
+/// status 101, as compiled executables do.
+pub struct JitProgram {
+    module: Option<JITModule>,
+    /// The global holding the lowest address the stack may grow to.
+    stack_limit: *mut usize,
+    /// The C `main` function, or why the program has none.
+    main: Result<*const u8, Error>,
+    /// The non-generic functions by name.
+    functions: HashMap<String, JitFunction>,
+}
+
+impl JitProgram {
+    /// Calls `main`, returning the exit code a compiled executable would
+    /// have.
+    pub fn run_main(&self) -> Result<u8, Error> {
+        let main = self.main.clone()?;
+        // SAFETY: The entry point is defined by `Codegen::define_entry` with
+        // this signature.
+        let main = unsafe {
+            mem::transmute::<*const u8, extern "C" fn(i32, *const *const u8) -> i32>(main)
+        };
+
+        Ok(self.on_stack(move || main(0, std::ptr::null())) as u8)
+    }
+
+    /// Calls the non-generic function `name` with `args`, checking that the
+    /// types of the arguments and the result match its signature.
+    pub fn call<A: JitArgs, R: JitValue>(&self, name: &str, args: A) -> Result<R, CallError> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| CallError::UnknownFunction(name.to_owned()))?;
+        let (params, ret) = function
+            .sig
+            .as_ref()
+            .ok_or_else(|| CallError::UnsupportedSignature(name.to_owned()))?;
+
+        let tys = A::tys();
+        if *params != tys || *ret != R::TY {
+            return Err(CallError::Mismatch {
+                name: name.to_owned(),
+                expected: fn_ty(params, *ret),
+                found: fn_ty(&tys, R::TY),
+            });
+        }
+
+        let ptr = function.ptr as usize;
+        // SAFETY: The signature was just checked.
+        Ok(self.on_stack(move || unsafe { args.call(ptr as *const u8) }))
+    }
+
+    /// Runs `f` on a new thread with a stack of `STACK_SIZE`, after setting
+    /// the limit compiled code checks the stack against before calls.
+    fn on_stack<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        let stack_limit = self.stack_limit as usize;
+        thread::scope(|scope| {
+            let thread = thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, move || {
+                    let start = 0u8;
+                    let limit = (&raw const start as usize).saturating_sub(STACK_BUDGET);
+                    // SAFETY: The global is writable and pointer-sized, and
+                    // the program is not `Sync`, so no other thread runs its
+                    // code.
+                    unsafe { (stack_limit as *mut usize).write(limit) };
+                    f()
+                })
+                .expect("failed to spawn a thread for compiled code");
+
+            thread
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload))
+        })
+    }
+}
+
+impl Drop for JitProgram {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: Pointers to the compiled code do not outlive the
+            // program.
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+fn fn_ty(params: &[JitTy], ret: JitTy) -> String {
+    let params: Vec<_> = params.iter().map(JitTy::to_string).collect();
+    format!("func({}) -> {ret}", params.join(", "))
+}
+
+/// Compiles `program` into memory. A program without a valid `main` can be
+/// compiled, but not run.
+pub fn jit(program: Program) -> Result<JitProgram, Error> {
+    let span = program.module.span;
+    let backend = |error: String| Error::new(span, ErrorKind::Backend(error));
+
+    let builder = JITBuilder::with_isa(
+        host_isa().map_err(backend)?,
+        cranelift_module::default_libcall_names(),
+    );
+    let mut codegen = Codegen::new(JITModule::new(builder), program)?;
+    let main = codegen.main().and_then(|main| codegen.define_entry(main));
+
+    let Program {
+        ctx,
+        interner,
+        resolution,
+        instances,
+        ref results,
+        ..
+    } = codegen.program;
+
+    let mut functions = Vec::new();
+    for (&name, &item) in &resolution.functions {
+        let Item::Func(func) = ctx.items[item] else {
+            continue;
+        };
+        if !func.generics.is_empty() {
+            continue;
+        }
+
+        let Some(instance) = instances
+            .instances
+            .iter()
+            .find(|(_, instance)| instance.item == item)
+            .map(|(id, _)| id)
+        else {
+            continue;
+        };
+
+        let (id, sig) = &codegen.functions[&instance];
+        let params: Option<Vec<_>> = sig
+            .params
+            .iter()
+            .map(|&param| JitTy::of(results, param).filter(|&ty| ty != JitTy::Unit))
+            .collect();
+        let sig = params.zip(JitTy::of(results, sig.ret));
+        functions.push((interner.get(name).to_owned(), *id, sig));
+    }
+
+    let stack_limit = codegen
+        .runtime
+        .stack_limit
+        .expect("the runtime is declared first");
+    let mut module = codegen.into_module();
+    module
+        .finalize_definitions()
+        .map_err(|error| backend(error.to_string()))?;
+
+    let ptr = |module: &JITModule, id: FuncId| module.get_finalized_function(id);
+    let main = main.map(|main| ptr(&module, main));
+    let functions = functions
+        .into_iter()
+        .map(|(name, id, sig)| {
+            let ptr = ptr(&module, id);
+            (name, JitFunction { ptr, sig })
+        })
+        .collect();
+
+    let stack_limit = module.get_finalized_data(stack_limit).0 as *mut usize;
+    Ok(JitProgram {
+        module: Some(module),
+        stack_limit,
+        main,
+        functions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CallError, ErrorKind, JitProgram, jit, tests::with_program};
+
+    fn compile(content: &str) -> JitProgram {
+        with_program(content, jit).unwrap()
+    }
+
+    #[test]
+    fn test_call() {
+        let program = compile(
+            "
+            func add(a: i64, b: i64) -> i64 { a + b }
+            func is_even(n: u8) -> bool { n / 2 * 2 == n }
+            func next(c: char) -> char { if c == 'a' { 'b' } else { c } }
+            func half(x: f32) -> f32 { x / 2.0 }
+            func wide(a: i8, b: i16, c: u32, d: f64) -> f64 { d }
+            func nothing() {}
+            func fib(n: i32) -> i32 { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+            ",
+        );
+
+        assert_eq!(program.call::<_, i64>("add", (2i64, 40i64)), Ok(42));
+        assert_eq!(program.call::<_, i64>("add", (-2i64, -3i64)), Ok(-5));
+        assert_eq!(program.call::<_, bool>("is_even", (10u8,)), Ok(true));
+        assert_eq!(program.call::<_, bool>("is_even", (7u8,)), Ok(false));
+        assert_eq!(program.call::<_, char>("next", ('a',)), Ok('b'));
+        assert_eq!(program.call::<_, char>("next", ('é',)), Ok('é'));
+        assert_eq!(program.call::<_, f32>("half", (3.0f32,)), Ok(1.5));
+        assert_eq!(
+            program.call::<_, f64>("wide", (-1i8, 2i16, 3u32, 0.25)),
+            Ok(0.25)
+        );
+        assert_eq!(program.call::<_, ()>("nothing", ()), Ok(()));
+        assert_eq!(program.call::<_, i32>("fib", (20,)), Ok(6765));
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let program = compile(
+            "
+            func add(a: i64, b: i64) -> i64 { a + b }
+            func greet(name: str) -> str { name }
+            func id<T>(x: T) -> T { x }
+            ",
+        );
+
+        assert_eq!(
+            program.call::<_, i64>("missing", ()),
+            Err(CallError::UnknownFunction("missing".to_owned()))
+        );
+        assert_eq!(
+            program.call::<_, i64>("id", (1i64,)),
+            Err(CallError::UnknownFunction("id".to_owned()))
+        );
+        assert_eq!(
+            program.call::<_, ()>("greet", ()),
+            Err(CallError::UnsupportedSignature("greet".to_owned()))
+        );
+        assert_eq!(
+            program.call::<_, i32>("add", (1i32, 2i64)),
+            Err(CallError::Mismatch {
+                name: "add".to_owned(),
+                expected: "func(i64, i64) -> i64".to_owned(),
+                found: "func(i32, i64) -> i32".to_owned(),
+            })
+        );
+        assert_eq!(
+            program.run_main().unwrap_err().kind(),
+            &ErrorKind::MissingMain
+        );
+    }
+
+    #[test]
+    fn test_run_main() {
+        let program = compile("func main() -> u16 { 256u16 + 7 }");
+        assert_eq!(program.run_main().unwrap(), 7);
+
+        let program = compile("func main() { let x = 1; }");
+        assert_eq!(program.run_main().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        // Deeper than the stack of test threads allows, but within the
+        // budget of the threads compiled code runs on.
+        let program =
+            compile("func depth(n: i64) -> i64 { if n == 0 { 0 } else { depth(n - 1) + 1 } }");
+        assert_eq!(program.call::<_, i64>("depth", (50_000i64,)), Ok(50_000));
+    }
+}
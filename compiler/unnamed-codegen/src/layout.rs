@@ -0,0 +1,254 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use cranelift_codegen::ir::{Type, types};
+use unnamed_ast::{AstCtx, Item};
+use unnamed_common::{Interner, Spanned};
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+use unnamed_typeck::{Subst, TyId, TyKind, TypeckResults};
+
+use crate::{Error, ErrorKind};
+
+/// How values of a type are passed around in generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Repr {
+    /// No value at all, as for unit.
+    Zst,
+    /// A single Cranelift value. Strings and arrays are pointers.
+    Scalar(Type),
+    /// A pointer to the value in memory, for structs and enums.
+    Memory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Field {
+    pub offset: u32,
+    pub ty: TyId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Shape {
+    Primitive,
+    /// A pointer to the length as an `i64`, followed by the elements at
+    /// `ARRAY_HEADER` and every `size` bytes of the element type after that.
+    Array(TyId),
+    /// The fields in definition order.
+    Struct(Vec<Field>),
+    /// The fields of each variant, in definition order. The index of the
+    /// variant is stored as a `u32` at offset zero.
+    Enum(Vec<Vec<Field>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub size: u32,
+    pub align: u32,
+    pub repr: Repr,
+    pub shape: Shape,
+}
+
+/// The offset of the first element of an array.
+pub(crate) const ARRAY_HEADER: u32 = 8;
+
+/// The type of the variant index of enums.
+pub(crate) const TAG: Type = types::I32;
+
+/// The layouts of the concrete types used by the program, computed on
+/// demand.
+pub(crate) struct Layouts {
+    pointer: Type,
+    layouts: HashMap<TyId, Rc<Layout>>,
+    /// Types whose layout is being computed, to detect types containing
+    /// themselves.
+    pending: HashSet<TyId>,
+}
+
+impl Layouts {
+    pub fn new(pointer: Type) -> Self {
+        Self {
+            pointer,
+            layouts: HashMap::new(),
+            pending: HashSet::new(),
+        }
+    }
+
+    pub fn of(
+        &mut self,
+        ctx: &AstCtx,
+        interner: &Interner,
+        results: &mut TypeckResults,
+        ty: TyId,
+    ) -> Result<Rc<Layout>, Error> {
+        if let Some(layout) = self.layouts.get(&ty) {
+            return Ok(layout.clone());
+        }
+
+        let scalar = |ty: Type| Layout {
+            size: ty.bytes(),
+            align: ty.bytes(),
+            repr: Repr::Scalar(ty),
+            shape: Shape::Primitive,
+        };
+
+        let layout = match results.tcx[ty] {
+            TyKind::Bool => scalar(types::I8),
+            TyKind::Int(int) => scalar(int_type(int)),
+            TyKind::Float(FloatSuffix::F32) => scalar(types::F32),
+            TyKind::Float(FloatSuffix::F64) => scalar(types::F64),
+            TyKind::Char => scalar(types::I32),
+            TyKind::Str => scalar(self.pointer),
+            // The layout of the elements is not needed here, so arrays can
+            // contain the type they are part of.
+            TyKind::Array(elem) => Layout {
+                shape: Shape::Array(elem),
+                ..scalar(self.pointer)
+            },
+            TyKind::Adt(item, args) => {
+                if !self.pending.insert(ty) {
+                    let span = ctx.items[item].span();
+                    let name = results.tcx.display(ty, ctx, interner).to_string();
+                    return Err(Error::new(span, ErrorKind::InfiniteSize(name)));
+                }
+
+                let subst = Subst::new(&results.generics[&item], results.tcx.list(args), None);
+                let layout = match ctx.items[item] {
+                    Item::Enum(enum_item) => {
+                        let mut variants = Vec::new();
+                        for &variant in ctx.variants.list(enum_item.variants) {
+                            let tys = results.variants[&variant].clone();
+                            variants.push(subst_all(results, &tys, &subst));
+                        }
+                        self.enum_layout(ctx, interner, results, variants)?
+                    }
+                    _ => {
+                        let tys: Vec<_> = results.fields[&item].iter().map(|(_, ty)| *ty).collect();
+                        let tys = subst_all(results, &tys, &subst);
+                        let (fields, size, align) = self.fields(ctx, interner, results, &tys, 0)?;
+                        Layout {
+                            size: size.next_multiple_of(align),
+                            align,
+                            repr: Repr::Memory,
+                            shape: Shape::Struct(fields),
+                        }
+                    }
+                };
+
+                self.pending.remove(&ty);
+                layout
+            }
+            // Only unit and the type of diverging expressions are left, as
+            // no other type remains after monomorphization.
+            _ => Layout {
+                size: 0,
+                align: 1,
+                repr: Repr::Zst,
+                shape: Shape::Primitive,
+            },
+        };
+
+        let layout = Rc::new(layout);
+        self.layouts.insert(ty, layout.clone());
+
+        Ok(layout)
+    }
+
+    /// Whether values of `ty` can refer to arrays, which then must outlive
+    /// the function creating them if it returns such a value.
+    pub fn contains_array(
+        &mut self,
+        ctx: &AstCtx,
+        interner: &Interner,
+        results: &mut TypeckResults,
+        ty: TyId,
+    ) -> Result<bool, Error> {
+        let layout = self.of(ctx, interner, results, ty)?;
+        let fields: Vec<_> = match &layout.shape {
+            Shape::Primitive => return Ok(false),
+            Shape::Array(_) => return Ok(true),
+            Shape::Struct(fields) => fields.clone(),
+            Shape::Enum(variants) => variants.iter().flatten().copied().collect(),
+        };
+
+        for field in fields {
+            if self.contains_array(ctx, interner, results, field.ty)? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Lays out fields one after another starting at `start`, returning
+    /// their offsets along with the end of the last one and the largest
+    /// alignment.
+    fn fields(
+        &mut self,
+        ctx: &AstCtx,
+        interner: &Interner,
+        results: &mut TypeckResults,
+        tys: &[TyId],
+        start: u32,
+    ) -> Result<(Vec<Field>, u32, u32), Error> {
+        let mut fields = Vec::with_capacity(tys.len());
+        let mut offset = start;
+        let mut align = 1;
+
+        for &ty in tys {
+            let layout = self.of(ctx, interner, results, ty)?;
+            offset = offset.next_multiple_of(layout.align);
+            fields.push(Field { offset, ty });
+            offset += layout.size;
+            align = align.max(layout.align);
+        }
+
+        Ok((fields, offset, align))
+    }
+
+    /// Puts the fields of every variant after the tag, at the same offset
+    /// for all variants.
+    fn enum_layout(
+        &mut self,
+        ctx: &AstCtx,
+        interner: &Interner,
+        results: &mut TypeckResults,
+        variants: Vec<Vec<TyId>>,
+    ) -> Result<Layout, Error> {
+        let mut align = TAG.bytes();
+        for tys in &variants {
+            for &ty in tys {
+                align = align.max(self.of(ctx, interner, results, ty)?.align);
+            }
+        }
+
+        let start = TAG.bytes().next_multiple_of(align);
+        let mut size = start;
+        let mut shape = Vec::with_capacity(variants.len());
+        for tys in &variants {
+            let (fields, end, _) = self.fields(ctx, interner, results, tys, start)?;
+            size = size.max(end);
+            shape.push(fields);
+        }
+
+        Ok(Layout {
+            size: size.next_multiple_of(align),
+            align,
+            repr: Repr::Memory,
+            shape: Shape::Enum(shape),
+        })
+    }
+}
+
+fn subst_all(results: &mut TypeckResults, tys: &[TyId], subst: &Subst) -> Vec<TyId> {
+    tys.iter().map(|&ty| results.tcx.subst(ty, subst)).collect()
+}
+
+pub(crate) fn int_type(int: IntSuffix) -> Type {
+    match int.bits() {
+        8 => types::I8,
+        16 => types::I16,
+        32 => types::I32,
+        _ => types::I64,
+    }
+}
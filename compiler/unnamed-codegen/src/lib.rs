@@ -0,0 +1,724 @@
+//! Native code generation with Cranelift. The MIR body of every instance
+//! found by monomorphization is translated to Cranelift IR, together with a
+//! small runtime for printing and runtime errors built on top of the C
+//! library. The result is either emitted as a relocatable
+//! object file, or compiled into memory to be run right away.
+//!
+//! Integers, floats, `bool` and `char` are Cranelift values. Structs and
+//! enums live in stack slots and are passed around as pointers to them,
+//! copied whenever they are stored. Strings are pointers to NUL-terminated
+//! bytes, and arrays are pointers to their length followed by their
+//! elements, which are never modified after creation.
+
+pub mod error;
+mod func;
+pub mod jit;
+mod layout;
+pub mod link;
+mod runtime;
+
+use std::collections::{HashMap, HashSet};
+
+use cranelift_codegen::{
+    ir::{AbiParam, Signature, Type},
+    isa::OwnedTargetIsa,
+    settings::{self, Configurable},
+};
+use cranelift_module::{DataDescription, DataId, FuncId, Linkage};
+use cranelift_object::{ObjectBuilder, ObjectModule};
+use unnamed_ast::{AstCtx, Module};
+use unnamed_common::{Interner, Source, Span, Spanned};
+use unnamed_mir::Mir;
+use unnamed_resolve::Resolution;
+use unnamed_typeck::{InstanceId, Instances, TyId, TypeckResults};
+
+pub use error::{Error, ErrorKind};
+pub use jit::{CallError, JitProgram, jit};
+
+use crate::{
+    func::FuncTranslator,
+    layout::{Layout, Layouts, Repr},
+    runtime::{Glue, Runtime},
+};
+
+/// A module that was checked without errors, together with everything the
+/// code generator needs to know about it.
+pub struct Program<'a> {
+    pub ctx: &'a AstCtx,
+    pub module: &'a Module,
+    pub interner: &'a Interner,
+    /// The source of the module, for the locations of runtime errors.
+    pub source: Source<'a>,
+    pub resolution: &'a Resolution,
+    pub results: &'a mut TypeckResults,
+    pub instances: &'a Instances,
+    /// The bodies of the instances, optimized at the requested level.
+    pub mir: &'a Mir,
+}
+
+/// Compiles `program` into an object file for the host. The object exports
+/// a C `main` function that calls the program's `main` and exits with its
+/// result if that is an integer.
+pub fn emit_object(program: Program) -> Result<Vec<u8>, Error> {
+    let span = program.module.span;
+    let backend = |error: String| Error::new(span, ErrorKind::Backend(error));
+
+    let builder = ObjectBuilder::new(
+        host_isa().map_err(backend)?,
+        "unnamed",
+        cranelift_module::default_libcall_names(),
+    )
+    .map_err(|error| backend(error.to_string()))?;
+
+    let mut codegen = Codegen::new(ObjectModule::new(builder), program)?;
+    let main = codegen.main()?;
+    codegen.define_entry(main)?;
+
+    let product = codegen.into_module().finish();
+    product.emit().map_err(|error| backend(error.to_string()))
+}
+
+/// The Cranelift target for the machine the compiler runs on. Code is
+/// position independent, as linkers produce position independent
+/// executables by default.
+pub(crate) fn host_isa() -> Result<OwnedTargetIsa, String> {
+    let mut flags = settings::builder();
+    for (name, value) in [("opt_level", "speed"), ("is_pic", "true")] {
+        flags.set(name, value).map_err(|error| error.to_string())?;
+    }
+
+    cranelift_native::builder()?
+        .finish(settings::Flags::new(flags))
+        .map_err(|error| error.to_string())
+}
+
+/// The signature of an instance after substituting its type parameters.
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceSig {
+    pub params: Vec<TyId>,
+    pub ret: TyId,
+}
+
+/// State shared by the translation of all functions of a program.
+pub(crate) struct Codegen<'a, M> {
+    pub module: M,
+    pub program: Program<'a>,
+    pub pointer: Type,
+    layouts: Layouts,
+    pub runtime: Runtime,
+    /// The function and signature of every instance.
+    pub functions: HashMap<InstanceId, (FuncId, InstanceSig)>,
+    /// Constant data by content, for string literals and error messages.
+    data: HashMap<Vec<u8>, DataId>,
+    /// Functions printing or comparing values of a type.
+    glue: HashMap<(Glue, TyId), FuncId>,
+    /// Glue functions declared but not yet defined.
+    pending_glue: Vec<(Glue, TyId, FuncId)>,
+    /// Names of all declared functions, to keep them unique.
+    names: HashSet<String>,
+}
+
+impl<'a, M: cranelift_module::Module> Codegen<'a, M> {
+    /// Declares the runtime and every instance of `program`, and defines
+    /// them all.
+    pub fn new(module: M, program: Program<'a>) -> Result<Self, Error> {
+        let pointer = module.target_config().pointer_type();
+        let mut codegen = Self {
+            module,
+            program,
+            pointer,
+            layouts: Layouts::new(pointer),
+            runtime: Runtime::default(),
+            functions: HashMap::new(),
+            data: HashMap::new(),
+            glue: HashMap::new(),
+            pending_glue: Vec::new(),
+            names: HashSet::new(),
+        };
+
+        codegen.declare_runtime()?;
+        codegen.define_runtime()?;
+
+        let instances = codegen.program.instances;
+        for id in instances.instances.keys() {
+            codegen.declare_instance(id)?;
+        }
+        for id in instances.instances.keys() {
+            codegen.define_instance(id)?;
+        }
+
+        while let Some((glue, ty, id)) = codegen.pending_glue.pop() {
+            codegen.define_glue(glue, ty, id)?;
+        }
+
+        Ok(codegen)
+    }
+
+    pub fn into_module(self) -> M {
+        self.module
+    }
+
+    /// The instance of the non-generic `main` function.
+    pub fn main(&self) -> Result<InstanceId, Error> {
+        let Program {
+            ctx,
+            module,
+            interner,
+            resolution,
+            instances,
+            ..
+        } = self.program;
+
+        let main = resolution.functions.get(&interner.intern("main"));
+        let instance = instances
+            .instances
+            .iter()
+            .find(|(_, instance)| Some(&instance.item) == main)
+            .map(|(id, _)| id);
+
+        match instance {
+            Some(instance) if !self.functions[&instance].1.params.is_empty() => {
+                let span = ctx.items[*main.unwrap()].span();
+                Err(Error::new(span, ErrorKind::MainParams))
+            }
+            Some(instance) => Ok(instance),
+            None => Err(Error::new(module.span, ErrorKind::MissingMain)),
+        }
+    }
+
+    /// Defines the C `main` function, which calls the `main` instance and
+    /// returns its result as the exit code.
+    pub fn define_entry(&mut self, main: InstanceId) -> Result<FuncId, Error> {
+        let mut sig = self.module.make_signature();
+        sig.params
+            .push(AbiParam::new(cranelift_codegen::ir::types::I32));
+        sig.params.push(AbiParam::new(self.pointer));
+        sig.returns
+            .push(AbiParam::new(cranelift_codegen::ir::types::I32));
+
+        let id = self.declare_function("main", Linkage::Export, &sig)?;
+        self.translate(id, sig, None, |t| t.entry(main))?;
+
+        Ok(id)
+    }
+
+    pub fn layout(&mut self, ty: TyId) -> Result<std::rc::Rc<Layout>, Error> {
+        let Program {
+            ctx,
+            interner,
+            ref mut results,
+            ..
+        } = self.program;
+        self.layouts.of(ctx, interner, results, ty)
+    }
+
+    pub fn contains_array(&mut self, ty: TyId) -> Result<bool, Error> {
+        let Program {
+            ctx,
+            interner,
+            ref mut results,
+            ..
+        } = self.program;
+        self.layouts.contains_array(ctx, interner, results, ty)
+    }
+
+    /// Builds the Cranelift signature of a function with the given
+    /// parameter and return types. Values in memory are returned by copying
+    /// them to a pointer passed as the first parameter.
+    pub fn signature(&mut self, params: &[TyId], ret: TyId) -> Result<Signature, Error> {
+        let mut sig = self.module.make_signature();
+
+        match self.layout(ret)?.repr {
+            Repr::Zst => {}
+            Repr::Scalar(ty) => sig.returns.push(AbiParam::new(ty)),
+            Repr::Memory => sig.params.push(AbiParam::new(self.pointer)),
+        }
+
+        for &param in params {
+            match self.layout(param)?.repr {
+                Repr::Zst => {}
+                Repr::Scalar(ty) => sig.params.push(AbiParam::new(ty)),
+                Repr::Memory => sig.params.push(AbiParam::new(self.pointer)),
+            }
+        }
+
+        Ok(sig)
+    }
+
+    /// Declares a function under `name`, or under `name` with a numbered
+    /// suffix if another function already uses it.
+    pub fn declare_function(
+        &mut self,
+        name: &str,
+        linkage: Linkage,
+        sig: &Signature,
+    ) -> Result<FuncId, Error> {
+        let mut unique = name.to_owned();
+        if linkage == Linkage::Local {
+            let mut suffix = 1;
+            while !self.names.insert(unique.clone()) {
+                suffix += 1;
+                unique = format!("{name}#{suffix}");
+            }
+        }
+
+        self.module
+            .declare_function(&unique, linkage, sig)
+            .map_err(|error| self.backend(self.program.module.span, error))
+    }
+
+    /// Returns constant data holding `bytes`, declaring it the first time.
+    pub fn data(&mut self, bytes: &[u8]) -> Result<DataId, Error> {
+        if let Some(&id) = self.data.get(bytes) {
+            return Ok(id);
+        }
+
+        let span = self.program.module.span;
+        let id = self
+            .module
+            .declare_anonymous_data(false, false)
+            .map_err(|error| self.backend(span, error))?;
+
+        let mut data = DataDescription::new();
+        data.define(bytes.into());
+        self.module
+            .define_data(id, &data)
+            .map_err(|error| self.backend(span, error))?;
+        self.data.insert(bytes.to_vec(), id);
+
+        Ok(id)
+    }
+
+    /// Returns the function printing or comparing values of `ty`, declaring
+    /// it the first time.
+    pub fn glue(&mut self, glue: Glue, ty: TyId) -> Result<FuncId, Error> {
+        if let Some(&id) = self.glue.get(&(glue, ty)) {
+            return Ok(id);
+        }
+
+        let sig = glue.signature(self);
+        let Program {
+            ctx,
+            interner,
+            ref results,
+            ..
+        } = self.program;
+        let name = format!(
+            "unnamed_rt::{}<{}>",
+            glue.name(),
+            results.tcx.display(ty, ctx, interner)
+        );
+
+        let id = self.declare_function(&name, Linkage::Local, &sig)?;
+        self.glue.insert((glue, ty), id);
+        self.pending_glue.push((glue, ty, id));
+
+        Ok(id)
+    }
+
+    pub fn backend(&self, span: Span, error: impl ToString) -> Error {
+        Error::new(span, ErrorKind::Backend(error.to_string()))
+    }
+
+    fn declare_instance(&mut self, id: InstanceId) -> Result<(), Error> {
+        let Program {
+            ctx,
+            interner,
+            ref mut results,
+            instances,
+            ..
+        } = self.program;
+
+        let item = instances.instances[id].item;
+        let subst = instances.subst(results, id);
+        let sig = results.signatures[&item].clone();
+        let params = sig
+            .params
+            .iter()
+            .map(|&param| results.tcx.subst(param, &subst))
+            .collect();
+        let ret = results.tcx.subst(sig.ret, &subst);
+        let name = format!("unnamed::{}", instances.display(id, ctx, interner, results));
+
+        let sig = InstanceSig { params, ret };
+        let signature = self.signature(&sig.params, sig.ret)?;
+        let func = self.declare_function(&name, Linkage::Local, &signature)?;
+        self.functions.insert(id, (func, sig));
+
+        Ok(())
+    }
+
+    fn define_instance(&mut self, id: InstanceId) -> Result<(), Error> {
+        let (func, sig) = self.functions[&id].clone();
+        let signature = self.signature(&sig.params, sig.ret)?;
+
+        let mir = self.program.mir;
+        self.translate(func, signature, Some(id), |t| {
+            t.instance(&mir.bodies[id], &sig)
+        })
+    }
+
+    fn define_glue(&mut self, glue: Glue, ty: TyId, id: FuncId) -> Result<(), Error> {
+        let sig = glue.signature(self);
+        self.translate(id, sig, None, |t| t.glue(glue, ty))
+    }
+
+    /// Defines the function `id` with a body built by `body`. Errors of
+    /// Cranelift are reported at the item of `instance`, or at the module
+    /// for functions of the runtime.
+    pub fn translate(
+        &mut self,
+        id: FuncId,
+        sig: Signature,
+        instance: Option<InstanceId>,
+        body: impl FnOnce(&mut FuncTranslator<'_, 'a, M>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut context = self.module.make_context();
+        context.func.signature = sig;
+        let mut builder_context = cranelift_frontend::FunctionBuilderContext::new();
+
+        {
+            let mut translator = FuncTranslator::new(self, &mut context.func, &mut builder_context);
+            body(&mut translator)?;
+            translator.finish();
+        }
+
+        let span = match instance {
+            Some(instance) => {
+                let item = self.program.instances.instances[instance].item;
+                self.program.ctx.items[item].span()
+            }
+            None => self.program.module.span,
+        };
+
+        self.module
+            .define_function(id, &mut context)
+            .map_err(|error| self.backend(span, format!("{error:?}")))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        env, fs,
+        process::Command,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use unnamed_common::{Interner, Source};
+    use unnamed_mir::OptLevel;
+
+    use crate::{ErrorKind, Program, emit_object, link::link};
+
+    /// Checks `content`, which must be free of errors, and passes it to `f`
+    /// with its MIR optimized at the default level.
+    pub(crate) fn with_program<R>(content: &str, f: impl FnOnce(Program) -> R) -> R {
+        with_program_at(content, OptLevel::default(), f)
+    }
+
+    pub(crate) fn with_program_at<R>(
+        content: &str,
+        level: OptLevel,
+        f: impl FnOnce(Program) -> R,
+    ) -> R {
+        let interner = Interner::new();
+        let source = Source::new(content, "test_file");
+        let parsed = unnamed_parser::parse(source, &interner);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let mut results =
+            unnamed_typeck::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+        assert!(results.errors.is_empty(), "{:?}", results.errors);
+        let hir = unnamed_hir::lower(&parsed.ctx, &parsed.module, &resolution, &mut results);
+        let instances = unnamed_typeck::monomorphize(
+            &parsed.ctx,
+            &parsed.module,
+            &interner,
+            &resolution,
+            &mut results,
+        );
+        let mut mir = unnamed_mir::lower(&hir, &instances, &mut results);
+        unnamed_mir::optimize(&mut mir, level, &interner, &results);
+        let errors = unnamed_mir::verify(&mir, &parsed.ctx, &interner, &mut results);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        f(Program {
+            ctx: &parsed.ctx,
+            module: &parsed.module,
+            interner: &interner,
+            source,
+            resolution: &resolution,
+            results: &mut results,
+            instances: &instances,
+            mir: &mir,
+        })
+    }
+
+    /// Compiles `content` at every optimization level into an executable,
+    /// runs it and returns its stdout, stderr and exit code, which must be
+    /// the same for all levels.
+    fn run(content: &str) -> Result<(String, String, i32), ErrorKind> {
+        let result = run_at(content, OptLevel::O0);
+        for level in [OptLevel::O1, OptLevel::O2] {
+            assert_eq!(run_at(content, level), result, "at {level:?}");
+        }
+
+        result
+    }
+
+    fn run_at(content: &str, level: OptLevel) -> Result<(String, String, i32), ErrorKind> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let object =
+            with_program_at(content, level, emit_object).map_err(|error| error.kind().clone())?;
+
+        let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let base = env::temp_dir().join(format!("unnamed-codegen-{}-{id}", std::process::id()));
+        let object_path = base.with_extension("o");
+        fs::write(&object_path, object).unwrap();
+        let linked = link(&object_path, &base).unwrap();
+        assert!(linked.status.success(), "{linked:?}");
+
+        let output = Command::new(&base).output().unwrap();
+        fs::remove_file(&object_path).unwrap();
+        fs::remove_file(&base).unwrap();
+
+        Ok((
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+            output.status.code().unwrap(),
+        ))
+    }
+
+    /// Prints the values of the comma-separated `exprs`.
+    fn output(exprs: &str) -> String {
+        let (stdout, stderr, code) = run(&format!("func main() {{ println({exprs}); }}")).unwrap();
+        assert_eq!((stderr.as_str(), code), ("", 0));
+        stdout.trim_end().to_owned()
+    }
+
+    fn error(expr: &str) -> String {
+        let (_, stderr, code) = run(&format!("func main() {{ println({expr}); }}")).unwrap();
+        assert_eq!(code, 101);
+        stderr.lines().next().unwrap().to_owned()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(
+            output("1 + 2 * 3 - 8 / 2, -7 / 2, 6 & 3 | 8 ^ 1"),
+            "3 -3 11"
+        );
+        assert_eq!(
+            output("1 << 4 >> 2, -5 >> 1, 255u8 >> 4, 1u8 << 7"),
+            "4 -3 15 128"
+        );
+        assert_eq!(
+            output("250u8 + 5, -127i8 / 1, 3u16 * 7, -(5i32)"),
+            "255 -127 21 -5"
+        );
+        assert_eq!(
+            output("1.5 * 2.0, 0.1 + 0.2, 1.0 / 3.0, -2.5f32"),
+            "3.0 0.30000000000000004 0.3333333333333333 -2.5"
+        );
+        assert_eq!(
+            output("1e20, 1.5e-7, 0.0001, 100.0f32"),
+            "1e20 1.5e-7 0.0001 100.0"
+        );
+        assert_eq!(output("\"ab\" + \"cd\", 'é', true, ()"), "abcd é true ()");
+        assert_eq!(
+            output("-9223372036854775807 - 1, 18446744073709551615u64"),
+            "-9223372036854775808 18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_eq!(
+            output("1 < 2, 2 <= 1, 3 > 3, 3 >= 3, 1 == 1, 1 != 1"),
+            "true false false true true false"
+        );
+        assert_eq!(
+            output("-1 < 0, 255u8 > 1u8, 'a' < 'b', \"ab\" < \"b\""),
+            "true true true true"
+        );
+        assert_eq!(
+            output("1.0 < 2.0, true && false, false || true"),
+            "true false true"
+        );
+        assert_eq!(
+            output("[1, 2] == [1, 2], [1] != [1, 2], () == ()"),
+            "true true true"
+        );
+        assert_eq!(output("1 < 2 && 'a' != 'b' || 1 / 0 == 0"), "true");
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        assert_eq!(
+            error("255u8 + 1"),
+            "error: attempt to compute a value that overflows its type"
+        );
+        assert_eq!(
+            error("(-127i8 - 1) / -1"),
+            "error: attempt to compute a value that overflows its type"
+        );
+        assert_eq!(error("1 / 0"), "error: attempt to divide by zero");
+        assert_eq!(error("[1, 2][2]"), "error: index out of bounds");
+        assert_eq!(error("[1, 2][-1]"), "error: index out of bounds");
+        assert_eq!(error("[0; -1]"), "error: invalid array length");
+        assert!(error("1 << 64").starts_with("error: attempt to shift a 64-bit integer"));
+
+        let (_, stderr, _) = run("func main() {\n    1 / 0;\n}").unwrap();
+        assert!(stderr.contains(" --> test_file:2:5"), "{stderr}");
+
+        let content = "func f(n: i64) -> i64 {\n    f(n + 1) + 1\n}\nfunc main() -> i64 { f(0) }";
+        let (_, stderr, code) = run(content).unwrap();
+        assert_eq!(code, 101);
+        assert!(
+            stderr.starts_with("error: stack overflow\n --> test_file:2:5"),
+            "{stderr}"
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let content = "
+            func fib(n: i64) -> i64 {
+                if n < 2 { n } else if n == 2 { 1 } else { fib(n - 1) + fib(n - 2) }
+            }
+
+            func main() -> i64 {
+                let i = 0;
+                while i < 5 {
+                    print(i);
+                    i = i + 1;
+                    if i == 3 { return 7; }
+                }
+                fib(20)
+            }
+        ";
+        assert_eq!(run(content).unwrap(), ("012".to_owned(), String::new(), 7));
+
+        let content = "func main() -> i64 { let s = 0; match 3 { 1 => s = 1, 3 => s = 3, _ => s = 9 } s + 300 }";
+        assert_eq!(run(content).unwrap().2, 303 % 256);
+    }
+
+    #[test]
+    fn test_structs_and_enums() {
+        let content = "
+            struct Point { x: i64, y: i64 }
+            struct Empty {}
+            enum Shape { Circle(f64), Rect { w: i64, h: i64 }, Dot }
+
+            impl Eq for Shape { func eq(self, other: Shape) -> bool { area(self) == area(other) } }
+
+            func area(shape: Shape) -> i64 {
+                match shape {
+                    Shape::Circle(_) => 3,
+                    Shape::Rect { w, h } if w > 0 => w * h,
+                    _ => 0,
+                }
+            }
+
+            func swap(p: Point) -> Point { Point { x: p.y, y: p.x } }
+
+            func main() {
+                let p = Point { x: 1, y: 2 };
+                let q = p;
+                q = swap(q);
+                println(p, q, Empty {}, [q.x, q.y]);
+                println(Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }, Shape::Dot);
+                println(area(Shape::Rect { w: 4, h: 5 }), area(Shape::Dot), [Shape::Dot] == [Shape::Dot]);
+            }
+        ";
+        let (stdout, _, _) = run(content).unwrap();
+        assert_eq!(
+            stdout,
+            "Point { x: 1, y: 2 } Point { x: 2, y: 1 } Empty [2, 1]\n\
+             Shape::Circle(1.5) Shape::Rect { w: 2, h: 3 } Shape::Dot\n\
+             20 0 true\n"
+        );
+    }
+
+    #[test]
+    fn test_generics_and_traits() {
+        let content = "
+            struct V { x: i64 }
+            impl Add for V { func add(self, other: V) -> V { V { x: self.x + other.x } } }
+            impl Eq for V { func eq(self, other: V) -> bool { self.x == other.x } }
+            impl Ord for V { func cmp(self, other: V) -> i64 { self.x - other.x } }
+
+            enum Opt<T> { Some(T), None }
+
+            func unwrap_or<T>(opt: Opt<T>, default: T) -> T {
+                match opt { Opt::Some(value) => value, Opt::None => default }
+            }
+
+            func main() {
+                let v = V { x: 1 } + V { x: 2 };
+                println(v, v == V { x: 3 }, v != V { x: 3 }, v < V { x: 2 });
+                println(unwrap_or(Opt::Some([1, 2]), [0]), unwrap_or(Opt::None, \"none\"));
+            }
+        ";
+        let (stdout, _, _) = run(content).unwrap();
+        assert_eq!(stdout, "V { x: 3 } true false false\n[1, 2] none\n");
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(
+            output("[1, 2, 3], [0u8; 3], [[1], []].len(), \"héllo\".len()"),
+            "[1, 2, 3] [0, 0, 0] 2 6"
+        );
+
+        let content = "
+            func main() {
+                let rows = [[0; 0]; 0];
+                let i = 0;
+                while i < 3 {
+                    let row = [i; 2];
+                    if i == 0 { rows = [row]; } else { rows = [row, rows[0]]; }
+                    i = i + 1;
+                }
+                println(rows, rows[1][0]);
+            }
+        ";
+        assert_eq!(run(content).unwrap().0, "[[2, 2], [1, 1]] 1\n");
+    }
+
+    #[test]
+    fn test_main_errors() {
+        assert_eq!(run("func f() {}").unwrap_err(), ErrorKind::MissingMain);
+        assert_eq!(
+            run("func main(x: i64) {}").unwrap_err(),
+            ErrorKind::MainParams
+        );
+        assert_eq!(
+            run("enum L { Cons(L), Nil }\nfunc main() { let l = L::Nil; }").unwrap_err(),
+            ErrorKind::InfiniteSize("L".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_opt_level() {
+        // The branch printing `big` is folded away at O2 only, where the
+        // loads of `x` and `y` are replaced by the constants stored.
+        let content = "
+            func main() -> i64 {
+                let x = 2;
+                let y = x * 3;
+                if y > 100 { println(\"big\"); }
+                y
+            }
+        ";
+        let contains_big = |level| {
+            let object = with_program_at(content, level, emit_object).unwrap();
+            object.windows(4).any(|bytes| bytes == b"big\0")
+        };
+        assert!(contains_big(OptLevel::O0));
+        assert!(!contains_big(OptLevel::O2));
+        assert_eq!(run(content).unwrap().2, 6);
+    }
+}
@@ -0,0 +1,19 @@
+//! Linking of object files into executables with the system C compiler,
+//! which knows where the C library and the startup files are.
+
+use std::{
+    io,
+    path::Path,
+    process::{Command, Output},
+};
+
+/// The C compiler used for linking, unless overridden by the `CC`
+/// environment variable.
+const DEFAULT_CC: &str = "cc";
+
+/// Links `object` into the executable `output`. The output of the C
+/// compiler is returned so that its errors can be shown.
+pub fn link(object: &Path, output: &Path) -> io::Result<Output> {
+    let cc = std::env::var_os("CC").unwrap_or_else(|| DEFAULT_CC.into());
+    Command::new(cc).arg(object).arg("-o").arg(output).output()
+}
@@ -0,0 +1,821 @@
+use std::collections::HashMap;
+
+use cranelift_codegen::ir::{
+    self, AbiParam, InstBuilder, MemFlags, Signature, TrapCode, Type, Value,
+    condcodes::{FloatCC, IntCC},
+    types,
+};
+use cranelift_frontend::Switch;
+use cranelift_module::{DataDescription, DataId, FuncId, Linkage, Module};
+use unnamed_ast::{Item, expr::BinOp, item::VariantKind};
+use unnamed_common::{Span, StrId};
+use unnamed_lexer::FloatSuffix;
+use unnamed_typeck::{TyId, TyKind};
+
+use crate::{
+    Codegen, Error,
+    func::FuncTranslator,
+    layout::{Field, Shape, TAG},
+};
+
+/// A function of the C library, or a helper of the runtime defined in
+/// every object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum RuntimeFn {
+    Write,
+    Exit,
+    Malloc,
+    Strlen,
+    Strcmp,
+    Strchr,
+    Strtol,
+    Strfromd,
+    Strtod,
+    /// Prints a NUL-terminated string.
+    PrintStr,
+    PrintInt,
+    PrintUint,
+    /// Prints a float like Rust's `{:?}`, taking whether it is an `f32`.
+    PrintFloat,
+    PrintChar,
+    /// Writes a message of the given length to stderr and exits with status
+    /// 101.
+    Panic,
+    /// `malloc` that stops the program when out of memory.
+    Alloc,
+    /// Concatenates two strings into a new one.
+    Concat,
+}
+
+impl RuntimeFn {
+    const ALL: [RuntimeFn; 17] = [
+        RuntimeFn::Write,
+        RuntimeFn::Exit,
+        RuntimeFn::Malloc,
+        RuntimeFn::Strlen,
+        RuntimeFn::Strcmp,
+        RuntimeFn::Strchr,
+        RuntimeFn::Strtol,
+        RuntimeFn::Strfromd,
+        RuntimeFn::Strtod,
+        RuntimeFn::PrintStr,
+        RuntimeFn::PrintInt,
+        RuntimeFn::PrintUint,
+        RuntimeFn::PrintFloat,
+        RuntimeFn::PrintChar,
+        RuntimeFn::Panic,
+        RuntimeFn::Alloc,
+        RuntimeFn::Concat,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RuntimeFn::Write => "write",
+            RuntimeFn::Exit => "exit",
+            RuntimeFn::Malloc => "malloc",
+            RuntimeFn::Strlen => "strlen",
+            RuntimeFn::Strcmp => "strcmp",
+            RuntimeFn::Strchr => "strchr",
+            RuntimeFn::Strtol => "strtol",
+            RuntimeFn::Strfromd => "strfromd",
+            RuntimeFn::Strtod => "strtod",
+            RuntimeFn::PrintStr => "unnamed_rt::print_str",
+            RuntimeFn::PrintInt => "unnamed_rt::print_int",
+            RuntimeFn::PrintUint => "unnamed_rt::print_uint",
+            RuntimeFn::PrintFloat => "unnamed_rt::print_float",
+            RuntimeFn::PrintChar => "unnamed_rt::print_char",
+            RuntimeFn::Panic => "unnamed_rt::panic",
+            RuntimeFn::Alloc => "unnamed_rt::alloc",
+            RuntimeFn::Concat => "unnamed_rt::concat",
+        }
+    }
+
+    /// Whether the function comes from the C library.
+    pub fn is_import(self) -> bool {
+        matches!(
+            self,
+            RuntimeFn::Write
+                | RuntimeFn::Exit
+                | RuntimeFn::Malloc
+                | RuntimeFn::Strlen
+                | RuntimeFn::Strcmp
+                | RuntimeFn::Strchr
+                | RuntimeFn::Strtol
+                | RuntimeFn::Strfromd
+                | RuntimeFn::Strtod
+        )
+    }
+
+    /// The parameter and return types, with sizes as pointer-sized integers.
+    fn types(self, pointer: Type) -> (Vec<Type>, Vec<Type>) {
+        use types::{F64, I8, I32, I64};
+
+        match self {
+            RuntimeFn::Write => (vec![I32, pointer, pointer], vec![pointer]),
+            RuntimeFn::Exit => (vec![I32], vec![]),
+            RuntimeFn::Malloc | RuntimeFn::Strlen | RuntimeFn::Alloc => {
+                (vec![pointer], vec![pointer])
+            }
+            RuntimeFn::Strcmp => (vec![pointer, pointer], vec![I32]),
+            RuntimeFn::Strchr => (vec![pointer, I32], vec![pointer]),
+            RuntimeFn::Strtol => (vec![pointer, pointer, I32], vec![I64]),
+            RuntimeFn::Strfromd => (vec![pointer, pointer, pointer, F64], vec![I32]),
+            RuntimeFn::Strtod => (vec![pointer, pointer], vec![F64]),
+            RuntimeFn::PrintStr => (vec![pointer], vec![]),
+            RuntimeFn::PrintInt | RuntimeFn::PrintUint => (vec![I64], vec![]),
+            RuntimeFn::PrintFloat => (vec![F64, I8], vec![]),
+            RuntimeFn::PrintChar => (vec![I32], vec![]),
+            RuntimeFn::Panic => (vec![pointer, pointer], vec![]),
+            RuntimeFn::Concat => (vec![pointer, pointer], vec![pointer]),
+        }
+    }
+}
+
+/// How far the stack may grow below where the program started before calls
+/// stop it with a stack overflow error, instead of letting it crash once the
+/// stack runs out. Main threads get 8 MiB by default, which leaves room for
+/// the frame of the last call and for the C library.
+pub(crate) const STACK_BUDGET: usize = 4 << 20;
+
+#[derive(Debug, Default)]
+pub(crate) struct Runtime {
+    pub functions: HashMap<RuntimeFn, FuncId>,
+    /// A pointer-sized global holding the lowest address the stack may grow
+    /// to, or zero if it may grow without bound.
+    pub stack_limit: Option<DataId>,
+}
+
+/// A function generated for each type whose values are printed or
+/// compared, and that is not a single Cranelift value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Glue {
+    Print,
+    Eq,
+}
+
+impl Glue {
+    pub fn name(self) -> &'static str {
+        match self {
+            Glue::Print => "print",
+            Glue::Eq => "eq",
+        }
+    }
+
+    /// Glue takes values by pointer, as only strings, arrays, structs and
+    /// enums need it.
+    pub fn signature<M: Module>(self, cg: &Codegen<'_, M>) -> Signature {
+        let mut sig = cg.module.make_signature();
+        match self {
+            Glue::Print => sig.params.push(AbiParam::new(cg.pointer)),
+            Glue::Eq => {
+                sig.params.push(AbiParam::new(cg.pointer));
+                sig.params.push(AbiParam::new(cg.pointer));
+                sig.returns.push(AbiParam::new(types::I8));
+            }
+        }
+
+        sig
+    }
+}
+
+/// Formats for `strfromd`, which only takes a constant precision, each
+/// padded to the same length.
+const FORMAT_LEN: i64 = 6;
+
+/// `%.0e` to `%.16e`, which are enough for any `f64` to survive a round
+/// trip.
+const MAX_EXP_PRECISION: i64 = 16;
+
+/// `%.1f` to `%.20f`, for the largest number of digits after the point in
+/// numbers printed without an exponent.
+const MAX_FIXED_PRECISION: i64 = 20;
+
+fn formats(precisions: impl Iterator<Item = i64>, conversion: char) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for precision in precisions {
+        let mut format = format!("%.{precision}{conversion}").into_bytes();
+        format.resize(FORMAT_LEN as usize, 0);
+        bytes.extend(format);
+    }
+
+    bytes
+}
+
+impl<'a, M: Module> Codegen<'a, M> {
+    pub fn declare_runtime(&mut self) -> Result<(), Error> {
+        for func in RuntimeFn::ALL {
+            let sig = self.runtime_signature(func);
+            let linkage = match func.is_import() {
+                true => Linkage::Import,
+                false => Linkage::Local,
+            };
+
+            let id = self.declare_function(func.name(), linkage, &sig)?;
+            self.runtime.functions.insert(func, id);
+        }
+
+        let span = self.program.module.span;
+        let stack_limit = self
+            .module
+            .declare_data("unnamed_rt::stack_limit", Linkage::Local, true, false)
+            .map_err(|error| self.backend(span, error))?;
+        let mut data = DataDescription::new();
+        data.define_zeroinit(self.pointer.bytes() as usize);
+        self.module
+            .define_data(stack_limit, &data)
+            .map_err(|error| self.backend(span, error))?;
+        self.runtime.stack_limit = Some(stack_limit);
+
+        Ok(())
+    }
+
+    pub fn define_runtime(&mut self) -> Result<(), Error> {
+        for func in RuntimeFn::ALL {
+            if func.is_import() {
+                continue;
+            }
+
+            let sig = self.runtime_signature(func);
+            let id = self.runtime.functions[&func];
+            self.translate(id, sig, None, |t| t.runtime(func))?;
+        }
+
+        Ok(())
+    }
+
+    fn runtime_signature(&self, func: RuntimeFn) -> Signature {
+        let mut sig = self.module.make_signature();
+        let (params, returns) = func.types(self.pointer);
+        sig.params.extend(params.into_iter().map(AbiParam::new));
+        sig.returns.extend(returns.into_iter().map(AbiParam::new));
+
+        sig
+    }
+}
+
+impl<M: Module> FuncTranslator<'_, '_, M> {
+    /// Prints `value` of type `ty` to stdout.
+    pub fn print(&mut self, value: Option<Value>, ty: TyId) -> Result<(), Error> {
+        let kind = self.results().tcx[ty];
+        if kind == TyKind::Unit {
+            return self.write_str("()");
+        }
+
+        let Some(value) = value else {
+            return Ok(());
+        };
+
+        match kind {
+            TyKind::Bool => {
+                let true_ptr = self.data_addr(b"true")?;
+                let false_ptr = self.data_addr(b"false")?;
+                let ptr = self.builder.ins().select(value, true_ptr, false_ptr);
+                let true_len = self.iconst(self.cg.pointer, 4);
+                let false_len = self.iconst(self.cg.pointer, 5);
+                let len = self.builder.ins().select(value, true_len, false_len);
+                self.write(1, ptr, len);
+            }
+            TyKind::Int(int) => {
+                let value = self.extend_to_i64(value, ty);
+                let func = match int.is_signed() {
+                    true => RuntimeFn::PrintInt,
+                    false => RuntimeFn::PrintUint,
+                };
+                self.call_runtime(func, &[value]);
+            }
+            TyKind::Float(float) => {
+                let (value, is_f32) = match float {
+                    FloatSuffix::F32 => (self.builder.ins().fpromote(types::F64, value), 1),
+                    FloatSuffix::F64 => (value, 0),
+                };
+                let is_f32 = self.iconst(types::I8, is_f32);
+                self.call_runtime(RuntimeFn::PrintFloat, &[value, is_f32]);
+            }
+            TyKind::Char => {
+                self.call_runtime(RuntimeFn::PrintChar, &[value]);
+            }
+            TyKind::Str => {
+                self.call_runtime(RuntimeFn::PrintStr, &[value]);
+            }
+            TyKind::Array(_) | TyKind::Adt(..) => {
+                self.call_glue(Glue::Print, ty, &[value])?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn glue(&mut self, glue: Glue, ty: TyId) -> Result<(), Error> {
+        match glue {
+            Glue::Print => self.print_glue(ty)?,
+            Glue::Eq => self.eq_glue(ty)?,
+        }
+
+        Ok(())
+    }
+
+    fn print_glue(&mut self, ty: TyId) -> Result<(), Error> {
+        let value = self.params()[0];
+
+        match self.results().tcx[ty] {
+            TyKind::Array(elem) => {
+                self.write_str("[")?;
+                self.for_each_element(value, elem, |t, index, addr| {
+                    let separator = t.builder.create_block();
+                    let next = t.builder.create_block();
+                    t.builder.ins().brif(index, separator, &[], next, &[]);
+
+                    t.builder.switch_to_block(separator);
+                    t.write_str(", ")?;
+                    t.builder.ins().jump(next, &[]);
+
+                    t.builder.switch_to_block(next);
+                    let elem_value = t.load(elem, addr, 0)?;
+                    t.print(elem_value, elem)
+                })?;
+                self.write_str("]")?;
+            }
+            TyKind::Adt(item, _) => match self.ctx().items[item] {
+                Item::Enum(enum_item) => {
+                    let ctx = self.ctx();
+                    let variants = ctx.variants.list(enum_item.variants);
+                    let Shape::Enum(layouts) = self.layout(ty)?.shape.clone() else {
+                        unreachable!("enum without an enum layout");
+                    };
+
+                    let tag = self.builder.ins().load(TAG, MemFlags::trusted(), value, 0);
+                    let done = self.builder.create_block();
+                    let mut switch = Switch::new();
+                    let mut blocks = Vec::with_capacity(variants.len());
+                    for index in 0..variants.len() {
+                        let block = self.builder.create_block();
+                        switch.set_entry(index as u128, block);
+                        blocks.push(block);
+                    }
+                    let otherwise = self.builder.create_block();
+                    switch.emit(&mut self.builder, tag, otherwise);
+
+                    let enum_name = self.interner().get(enum_item.name);
+                    for ((&entity, fields), block) in variants.iter().zip(&layouts).zip(blocks) {
+                        self.builder.switch_to_block(block);
+
+                        let variant = ctx.variants[entity];
+                        let name = self.interner().get(variant.name);
+                        self.write_str(&format!("{enum_name}::{name}"))?;
+                        match variant.kind {
+                            VariantKind::Unit => {}
+                            VariantKind::Tuple(_) => {
+                                self.write_str("(")?;
+                                for (i, field) in fields.iter().enumerate() {
+                                    if i > 0 {
+                                        self.write_str(", ")?;
+                                    }
+                                    let field_value = self.load(field.ty, value, field.offset)?;
+                                    self.print(field_value, field.ty)?;
+                                }
+                                self.write_str(")")?;
+                            }
+                            VariantKind::Struct(_) => {
+                                let names = self.variant_field_names(entity);
+                                self.print_fields(value, fields, &names)?;
+                            }
+                        }
+                        self.builder.ins().jump(done, &[]);
+                    }
+
+                    self.builder.switch_to_block(otherwise);
+                    self.builder.ins().trap(TrapCode::unwrap_user(1));
+
+                    self.builder.switch_to_block(done);
+                }
+                Item::Struct(struct_item) => {
+                    let fields = self.struct_layout(ty)?;
+                    let names = self.struct_field_names(item);
+                    self.write_str(self.interner().get(struct_item.name))?;
+                    self.print_fields(value, &fields, &names)?;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        self.builder.ins().return_(&[]);
+        Ok(())
+    }
+
+    /// Prints ` { name: value, .. }`, or nothing without fields.
+    fn print_fields(
+        &mut self,
+        value: Value,
+        fields: &[Field],
+        names: &[StrId],
+    ) -> Result<(), Error> {
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        for (i, (field, &name)) in fields.iter().zip(names).enumerate() {
+            let separator = if i == 0 { " { " } else { ", " };
+            let name = self.interner().get(name);
+            self.write_str(&format!("{separator}{name}: "))?;
+
+            let field_value = self.load(field.ty, value, field.offset)?;
+            self.print(field_value, field.ty)?;
+        }
+        self.write_str(" }")
+    }
+
+    /// Compares two values of `ty` field by field, or element by element
+    /// for arrays.
+    fn eq_glue(&mut self, ty: TyId) -> Result<(), Error> {
+        let params = self.params();
+        let (lhs, rhs) = (params[0], params[1]);
+        let not_equal = self.builder.create_block();
+
+        match self.results().tcx[ty] {
+            TyKind::Array(elem) => {
+                let flags = MemFlags::trusted();
+                let lhs_len = self.builder.ins().load(types::I64, flags, lhs, 0);
+                let rhs_len = self.builder.ins().load(types::I64, flags, rhs, 0);
+                let same_len = self.builder.ins().icmp(IntCC::Equal, lhs_len, rhs_len);
+                self.branch_or_fail(same_len, not_equal);
+
+                self.for_each_element(lhs, elem, |t, index, lhs_addr| {
+                    let rhs_addr = t.element(rhs, index, elem)?;
+                    t.compare_field(
+                        Field {
+                            offset: 0,
+                            ty: elem,
+                        },
+                        lhs_addr,
+                        rhs_addr,
+                        not_equal,
+                    )
+                })?;
+            }
+            TyKind::Adt(..) => match self.layout(ty)?.shape.clone() {
+                Shape::Struct(fields) => {
+                    for field in fields {
+                        self.compare_field(field, lhs, rhs, not_equal)?;
+                    }
+                }
+                Shape::Enum(variants) => {
+                    let flags = MemFlags::trusted();
+                    let lhs_tag = self.builder.ins().load(TAG, flags, lhs, 0);
+                    let rhs_tag = self.builder.ins().load(TAG, flags, rhs, 0);
+                    let same_tag = self.builder.ins().icmp(IntCC::Equal, lhs_tag, rhs_tag);
+                    self.branch_or_fail(same_tag, not_equal);
+
+                    let equal = self.builder.create_block();
+                    let mut switch = Switch::new();
+                    let mut blocks = Vec::with_capacity(variants.len());
+                    for index in 0..variants.len() {
+                        let block = self.builder.create_block();
+                        switch.set_entry(index as u128, block);
+                        blocks.push(block);
+                    }
+                    let otherwise = self.builder.create_block();
+                    switch.emit(&mut self.builder, lhs_tag, otherwise);
+
+                    for (fields, block) in variants.into_iter().zip(blocks) {
+                        self.builder.switch_to_block(block);
+                        for field in fields {
+                            self.compare_field(field, lhs, rhs, not_equal)?;
+                        }
+                        self.builder.ins().jump(equal, &[]);
+                    }
+
+                    self.builder.switch_to_block(otherwise);
+                    self.builder.ins().trap(TrapCode::unwrap_user(1));
+
+                    self.builder.switch_to_block(equal);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        let one = self.iconst(types::I8, 1);
+        self.builder.ins().return_(&[one]);
+
+        self.builder.switch_to_block(not_equal);
+        let zero = self.iconst(types::I8, 0);
+        self.builder.ins().return_(&[zero]);
+
+        Ok(())
+    }
+
+    /// Jumps to `not_equal` unless `field` is the same in `lhs` and `rhs`.
+    fn compare_field(
+        &mut self,
+        field: Field,
+        lhs: Value,
+        rhs: Value,
+        not_equal: ir::Block,
+    ) -> Result<(), Error> {
+        let lhs = self.load(field.ty, lhs, field.offset)?;
+        let rhs = self.load(field.ty, rhs, field.offset)?;
+        let eq = self.primitive(BinOp::Eq, field.ty, field.ty, lhs, rhs, Span::default())?;
+        if let Some(eq) = eq {
+            self.branch_or_fail(eq, not_equal);
+        }
+
+        Ok(())
+    }
+
+    /// Defines a helper of the runtime.
+    pub fn runtime(&mut self, func: RuntimeFn) -> Result<(), Error> {
+        let params = self.params();
+        let pointer = self.cg.pointer;
+
+        match func {
+            RuntimeFn::PrintStr => {
+                let len = self.strlen(params[0]);
+                self.write(1, params[0], len);
+                self.builder.ins().return_(&[]);
+            }
+            RuntimeFn::PrintInt => {
+                let value = params[0];
+                let negative = self.builder.create_block();
+                let print = self.builder.create_block();
+                let magnitude = self.builder.append_block_param(print, types::I64);
+
+                let is_negative = self.icmp_imm(IntCC::SignedLessThan, value, 0);
+                self.builder
+                    .ins()
+                    .brif(is_negative, negative, &[], print, &[value]);
+
+                // The magnitude of the minimum is only representable as an
+                // unsigned integer.
+                self.builder.switch_to_block(negative);
+                self.write_str("-")?;
+                let negated = self.builder.ins().ineg(value);
+                self.builder.ins().jump(print, &[negated]);
+
+                self.builder.switch_to_block(print);
+                self.call_runtime(RuntimeFn::PrintUint, &[magnitude]);
+                self.builder.ins().return_(&[]);
+            }
+            RuntimeFn::PrintUint => {
+                // Digits are written backwards from the end of the buffer.
+                const DIGITS: i64 = 20;
+                let slot = self.stack_slot(DIGITS as u32, 1);
+                let buffer = self.builder.ins().stack_addr(pointer, slot, 0);
+
+                let digit = self.builder.create_block();
+                let value = self.builder.append_block_param(digit, types::I64);
+                let start = self.builder.append_block_param(digit, pointer);
+                let done = self.builder.create_block();
+                let first = self.builder.append_block_param(done, pointer);
+
+                let end = self.iconst(pointer, DIGITS);
+                self.builder.ins().jump(digit, &[params[0], end]);
+
+                self.builder.switch_to_block(digit);
+                let start = self.builder.ins().iadd_imm(start, -1);
+                let rem = self.builder.ins().urem_imm(value, 10);
+                let rem = self.builder.ins().ireduce(types::I8, rem);
+                let char = self.builder.ins().iadd_imm(rem, i64::from(b'0'));
+                let addr = self.builder.ins().iadd(buffer, start);
+                self.builder.ins().store(MemFlags::trusted(), char, addr, 0);
+                let value = self.builder.ins().udiv_imm(value, 10);
+                self.builder
+                    .ins()
+                    .brif(value, digit, &[value, start], done, &[start]);
+
+                self.builder.switch_to_block(done);
+                let ptr = self.builder.ins().iadd(buffer, first);
+                let len = self.builder.ins().irsub_imm(first, DIGITS);
+                self.write(1, ptr, len);
+                self.builder.ins().return_(&[]);
+            }
+            RuntimeFn::PrintFloat => self.print_float(params[0], params[1])?,
+            RuntimeFn::PrintChar => self.print_char(params[0]),
+            RuntimeFn::Panic => {
+                self.write(2, params[0], params[1]);
+                let status = self.iconst(types::I32, 101);
+                self.call_runtime(RuntimeFn::Exit, &[status]);
+                self.builder.ins().trap(TrapCode::unwrap_user(1));
+            }
+            RuntimeFn::Alloc => {
+                let ptr = self
+                    .call_runtime(RuntimeFn::Malloc, &[params[0]])
+                    .expect("malloc returns a pointer");
+
+                let fail = self.builder.create_block();
+                let next = self.builder.create_block();
+                self.builder.set_cold_block(fail);
+                self.builder.ins().brif(ptr, next, &[], fail, &[]);
+
+                self.builder.switch_to_block(fail);
+                self.panic("out of memory", None)?;
+
+                self.builder.switch_to_block(next);
+                self.builder.ins().return_(&[ptr]);
+            }
+            RuntimeFn::Concat => {
+                let (lhs, rhs) = (params[0], params[1]);
+                let lhs_len = self.strlen(lhs);
+                let rhs_len = self.strlen(rhs);
+
+                // The terminator of `rhs` is copied as well.
+                let rhs_size = self.builder.ins().iadd_imm(rhs_len, 1);
+                let size = self.builder.ins().iadd(lhs_len, rhs_size);
+                let ptr = self
+                    .call_runtime(RuntimeFn::Alloc, &[size])
+                    .expect("alloc returns a pointer");
+
+                let config = self.cg.module.target_config();
+                self.builder.call_memcpy(config, ptr, lhs, lhs_len);
+                let dest = self.builder.ins().iadd(ptr, lhs_len);
+                self.builder.call_memcpy(config, dest, rhs, rhs_size);
+                self.builder.ins().return_(&[ptr]);
+            }
+            _ => unreachable!("`{}` is imported from the C library", func.name()),
+        }
+
+        Ok(())
+    }
+
+    fn strlen(&mut self, ptr: Value) -> Value {
+        self.call_runtime(RuntimeFn::Strlen, &[ptr])
+            .expect("strlen returns a length")
+    }
+
+    /// Prints the shortest digits that read back as the same float, without
+    /// an exponent for magnitudes from `1e-4` up to `1e16` like Rust does.
+    fn print_float(&mut self, value: Value, is_f32: Value) -> Result<(), Error> {
+        const BUFFER: i64 = 64;
+        let pointer = self.cg.pointer;
+
+        let nan = self.builder.create_block();
+        let infinite = self.builder.create_block();
+        let finite = self.builder.create_block();
+        let not_nan = self.builder.create_block();
+
+        let is_nan = self.builder.ins().fcmp(FloatCC::Unordered, value, value);
+        self.builder.ins().brif(is_nan, nan, &[], not_nan, &[]);
+
+        self.builder.switch_to_block(nan);
+        self.write_str("NaN")?;
+        self.builder.ins().return_(&[]);
+
+        self.builder.switch_to_block(not_nan);
+        let abs = self.builder.ins().fabs(value);
+        let inf = self.builder.ins().f64const(f64::INFINITY);
+        let is_infinite = self.builder.ins().fcmp(FloatCC::Equal, abs, inf);
+        self.builder
+            .ins()
+            .brif(is_infinite, infinite, &[], finite, &[]);
+
+        self.builder.switch_to_block(infinite);
+        let zero = self.builder.ins().f64const(0.0);
+        let negative = self.builder.ins().fcmp(FloatCC::LessThan, value, zero);
+        let text = self.data_addr(b"-inf")?;
+        let positive_text = self.builder.ins().iadd_imm(text, 1);
+        let ptr = self.builder.ins().select(negative, text, positive_text);
+        let len = self.builder.ins().uextend(pointer, negative);
+        let len = self.builder.ins().iadd_imm(len, 3);
+        self.write(1, ptr, len);
+        self.builder.ins().return_(&[]);
+
+        // Finds the smallest precision of `%e` that round-trips.
+        self.builder.switch_to_block(finite);
+        let slot = self.stack_slot(BUFFER as u32, 1);
+        let buffer = self.builder.ins().stack_addr(pointer, slot, 0);
+        let buffer_len = self.iconst(pointer, BUFFER);
+        let null = self.iconst(pointer, 0);
+        let exp_formats = formats(0..=MAX_EXP_PRECISION, 'e');
+        let exp_formats = self.data_addr(&exp_formats)?;
+
+        let search = self.builder.create_block();
+        let precision = self.builder.append_block_param(search, types::I64);
+        let found = self.builder.create_block();
+        let digits = self.builder.append_block_param(found, types::I64);
+        let zero = self.iconst(types::I64, 0);
+        self.builder.ins().jump(search, &[zero]);
+
+        self.builder.switch_to_block(search);
+        let offset = self.builder.ins().imul_imm(precision, FORMAT_LEN);
+        let format = self.builder.ins().iadd(exp_formats, offset);
+        self.call_runtime(RuntimeFn::Strfromd, &[buffer, buffer_len, format, value]);
+        let parsed = self
+            .call_runtime(RuntimeFn::Strtod, &[buffer, null])
+            .expect("strtod returns a float");
+        let same_f64 = self.builder.ins().fcmp(FloatCC::Equal, parsed, value);
+        let value_f32 = self.builder.ins().fdemote(types::F32, value);
+        let parsed_f32 = self.builder.ins().fdemote(types::F32, parsed);
+        let same_f32 = self
+            .builder
+            .ins()
+            .fcmp(FloatCC::Equal, parsed_f32, value_f32);
+        let same = self.builder.ins().select(is_f32, same_f32, same_f64);
+        let last = self.icmp_imm(
+            IntCC::SignedGreaterThanOrEqual,
+            precision,
+            MAX_EXP_PRECISION,
+        );
+        let stop = self.builder.ins().bor(same, last);
+        let next = self.builder.ins().iadd_imm(precision, 1);
+        self.builder
+            .ins()
+            .brif(stop, found, &[precision], search, &[next]);
+
+        self.builder.switch_to_block(found);
+        let e = self.iconst(types::I32, i64::from(b'e'));
+        let e_ptr = self
+            .call_runtime(RuntimeFn::Strchr, &[buffer, e])
+            .expect("strchr returns a pointer");
+        let exp_ptr = self.builder.ins().iadd_imm(e_ptr, 1);
+        let ten = self.iconst(types::I32, 10);
+        let exp = self
+            .call_runtime(RuntimeFn::Strtol, &[exp_ptr, null, ten])
+            .expect("strtol returns an integer");
+
+        let fixed = self.builder.create_block();
+        let scientific = self.builder.create_block();
+        let not_small = self.icmp_imm(IntCC::SignedGreaterThanOrEqual, exp, -4);
+        let not_large = self.icmp_imm(IntCC::SignedLessThan, exp, 16);
+        let in_range = self.builder.ins().band(not_small, not_large);
+        self.builder
+            .ins()
+            .brif(in_range, fixed, &[], scientific, &[]);
+
+        // The digits after the point, and at least one.
+        self.builder.switch_to_block(fixed);
+        let fixed_formats = formats(1..=MAX_FIXED_PRECISION, 'f');
+        let fixed_formats = self.data_addr(&fixed_formats)?;
+        let decimals = self.builder.ins().isub(digits, exp);
+        let one = self.iconst(types::I64, 1);
+        let decimals = self.builder.ins().smax(decimals, one);
+        let index = self.builder.ins().iadd_imm(decimals, -1);
+        let offset = self.builder.ins().imul_imm(index, FORMAT_LEN);
+        let format = self.builder.ins().iadd(fixed_formats, offset);
+        self.call_runtime(RuntimeFn::Strfromd, &[buffer, buffer_len, format, value]);
+        self.call_runtime(RuntimeFn::PrintStr, &[buffer]);
+        self.builder.ins().return_(&[]);
+
+        // `1.5e-7` rather than the `1.5e-07` of C.
+        self.builder.switch_to_block(scientific);
+        let len = self.builder.ins().isub(e_ptr, buffer);
+        self.write(1, buffer, len);
+        self.write_str("e")?;
+        self.call_runtime(RuntimeFn::PrintInt, &[exp]);
+        self.builder.ins().return_(&[]);
+
+        Ok(())
+    }
+
+    /// Encodes a char as UTF-8, with one block for each length.
+    fn print_char(&mut self, value: Value) {
+        const LIMITS: [i64; 3] = [0x80, 0x800, 0x1_0000];
+        const PREFIXES: [i64; 4] = [0x00, 0xc0, 0xe0, 0xf0];
+
+        let pointer = self.cg.pointer;
+        let slot = self.stack_slot(4, 1);
+        let buffer = self.builder.ins().stack_addr(pointer, slot, 0);
+        let done = self.builder.create_block();
+        let len = self.builder.append_block_param(done, pointer);
+
+        for (i, prefix) in PREFIXES.into_iter().enumerate() {
+            let encode = self.builder.create_block();
+            let next = self.builder.create_block();
+            match LIMITS.get(i) {
+                Some(&limit) => {
+                    let fits = self.icmp_imm(IntCC::UnsignedLessThan, value, limit);
+                    self.builder.ins().brif(fits, encode, &[], next, &[]);
+                }
+                None => {
+                    self.builder.ins().jump(encode, &[]);
+                }
+            }
+
+            self.builder.switch_to_block(encode);
+            for byte in 0..=i {
+                let shift = 6 * (i - byte) as i64;
+                let bits = self.builder.ins().ushr_imm(value, shift);
+                let bits = match byte {
+                    0 => self.builder.ins().bor_imm(bits, prefix),
+                    _ => {
+                        let bits = self.builder.ins().band_imm(bits, 0x3f);
+                        self.builder.ins().bor_imm(bits, 0x80)
+                    }
+                };
+                let bits = self.builder.ins().ireduce(types::I8, bits);
+                self.builder
+                    .ins()
+                    .store(MemFlags::trusted(), bits, buffer, byte as i32);
+            }
+            let count = self.iconst(pointer, i as i64 + 1);
+            self.builder.ins().jump(done, &[count]);
+
+            self.builder.switch_to_block(next);
+        }
+        self.builder.ins().trap(TrapCode::unwrap_user(1));
+
+        self.builder.switch_to_block(done);
+        self.write(1, buffer, len);
+        self.builder.ins().return_(&[]);
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::Index;
 
-use cranelift_entity::{EntityRef, ListPool, PrimaryMap, packed_option::ReservedValue};
+use cranelift_entity::{EntityList, EntityRef, ListPool, PrimaryMap, packed_option::ReservedValue};
 
 #[derive(Debug, Clone)]
 pub struct EntityArena<E: EntityRef + ReservedValue, T> {
@@ -21,6 +21,18 @@ impl<E: EntityRef + ReservedValue, T> EntityArena<E, T> {
     pub fn new() -> Self {
         Self::default()
     }
+
+    pub fn alloc(&mut self, value: T) -> E {
+        self.map.push(value)
+    }
+
+    pub fn alloc_list(&mut self, entities: &[E]) -> EntityList<E> {
+        EntityList::from_slice(entities, &mut self.pool)
+    }
+
+    pub fn list(&self, list: EntityList<E>) -> &[E] {
+        list.as_slice(&self.pool)
+    }
 }
 
 impl<E: EntityRef + ReservedValue, T> Index<E> for EntityArena<E, T> {
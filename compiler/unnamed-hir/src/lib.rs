@@ -0,0 +1,297 @@
+//! The high-level IR: the checked AST of a module with every name resolved
+//! and every node typed, and with surface constructs reduced to a smaller
+//! set of forms. `else if` chains become nested `if`s, every `if` has an
+//! `else`, `while` becomes a `loop` around an `if`, `&&` and `||` become
+//! `if`s, method calls become calls with the receiver as first argument, and
+//! variant and struct constructors become `Adt` nodes with field indices.
+//!
+//! It is lowered once the module resolves and type checks without errors,
+//! and is then either evaluated by the interpreter or lowered to the MIR
+//! that native code is generated from.
+
+mod lower;
+pub mod printer;
+
+use std::collections::HashMap;
+
+use cranelift_entity::{EntityList, PrimaryMap, SecondaryMap, entity_impl};
+use unnamed_ast::{BinOp, ExprEntity, ItemEntity, VariantEntity, expr::UnaryOp};
+use unnamed_common::{EntityArena, Span, StrId};
+use unnamed_derive::Spanned;
+use unnamed_resolve::{Builtin, BuiltinTrait, LocalId};
+use unnamed_typeck::{TyId, TyList};
+
+pub use lower::lower;
+
+/// An item of the module, or a variant of one of its enums. Ids are handed
+/// out in source order, each item directly followed by its methods or
+/// variants, so they only change when the items before them do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DefId(u32);
+entity_impl!(DefId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+entity_impl!(ExprId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldExprId(u32);
+entity_impl!(FieldExprId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatId(u32);
+entity_impl!(PatId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldPatId(u32);
+entity_impl!(FieldPatId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArmId(u32);
+entity_impl!(ArmId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefKind {
+    Func,
+    Struct,
+    Enum,
+    Variant(VariantEntity),
+    Trait,
+    Impl,
+}
+
+impl DefKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            DefKind::Func => "Func",
+            DefKind::Struct => "Struct",
+            DefKind::Enum => "Enum",
+            DefKind::Variant(_) => "Variant",
+            DefKind::Trait => "Trait",
+            DefKind::Impl => "Impl",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Def {
+    pub kind: DefKind,
+    /// `None` for impls.
+    pub name: Option<StrId>,
+    /// The item of the AST, which is the enum for variants.
+    pub item: ItemEntity,
+    /// The trait or impl of methods, and the enum of variants.
+    pub parent: Option<DefId>,
+    pub span: Span,
+}
+
+/// The body of a function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Body {
+    /// Parameter locals, starting with `self` for methods.
+    pub params: Vec<LocalId>,
+    /// The block of the function.
+    pub value: ExprId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Local {
+    pub name: StrId,
+    pub ty: TyId,
+    /// Span of the name at the binding.
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: TyId,
+    #[span]
+    pub span: Span,
+    /// The expression of the AST this node was lowered from, or was
+    /// introduced for when desugaring it. `None` for function bodies. The
+    /// callees of calls and operators in instances of generic functions are
+    /// found by it in `Instances::calls`.
+    pub ast: Option<ExprEntity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExprKind {
+    Lit(Lit),
+    Local(LocalId),
+    Block {
+        stmts: EntityList<ExprId>,
+        /// The value of the block, which is unit when `None`.
+        tail: Option<ExprId>,
+    },
+    Let {
+        local: LocalId,
+        init: Option<ExprId>,
+    },
+    Assign {
+        local: LocalId,
+        value: ExprId,
+    },
+    /// An operator other than `&&` and `||`. Operands of other than a
+    /// primitive type call the method of the `overload` trait instead.
+    Binary {
+        op: BinOp,
+        lhs: ExprId,
+        rhs: ExprId,
+        overload: Option<BuiltinTrait>,
+    },
+    Unary {
+        op: UnaryOp,
+        value: ExprId,
+        overload: Option<BuiltinTrait>,
+    },
+    Call {
+        callee: Callee,
+        /// One for each type parameter of the callee.
+        ty_args: TyList,
+        args: EntityList<ExprId>,
+    },
+    /// A value of the struct or variant, with fields in the order they are
+    /// written in, which is the order they are evaluated in.
+    Adt {
+        def: DefId,
+        fields: EntityList<FieldExprId>,
+    },
+    If {
+        cond: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    },
+    /// Repeats `body` until it breaks.
+    Loop {
+        body: ExprId,
+    },
+    /// Leaves the innermost loop.
+    Break,
+    Match {
+        scrutinee: ExprId,
+        arms: EntityList<ArmId>,
+    },
+    Array(EntityList<ExprId>),
+    Repeat {
+        value: ExprId,
+        count: ExprId,
+    },
+    Field {
+        base: ExprId,
+        /// The index of the field in the definition of the struct.
+        index: u32,
+    },
+    Index {
+        base: ExprId,
+        index: ExprId,
+    },
+    Return(Option<ExprId>),
+    Error,
+}
+
+/// A literal, whose type is that of its expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lit {
+    Int(u64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(StrId),
+    Unit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callee {
+    /// A function or method. Calls of trait methods refer to the method
+    /// declared in the trait.
+    Def(DefId),
+    Builtin(Builtin),
+    /// The built-in `len` of arrays and strings.
+    Len,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldExpr {
+    /// The index of the field in the definition of the struct or variant.
+    pub index: u32,
+    pub value: ExprId,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pat {
+    pub kind: PatKind,
+    /// The type of the values the pattern matches.
+    pub ty: TyId,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatKind {
+    Wild,
+    Binding(LocalId),
+    /// A literal expression, possibly negated.
+    Lit(ExprId),
+    /// A struct or variant, with patterns for some of its fields.
+    Adt {
+        def: DefId,
+        fields: EntityList<FieldPatId>,
+    },
+    Error,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldPat {
+    /// The index of the field in the definition of the struct or variant.
+    pub index: u32,
+    pub pat: PatId,
+    #[span]
+    pub span: Span,
+}
+
+#[derive(Spanned, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arm {
+    pub pat: PatId,
+    pub guard: Option<ExprId>,
+    pub body: ExprId,
+    #[span]
+    pub span: Span,
+}
+
+/// The HIR of a module.
+#[derive(Debug, Default)]
+pub struct Hir {
+    pub defs: PrimaryMap<DefId, Def>,
+    /// The definition of each item, methods included.
+    pub items: HashMap<ItemEntity, DefId>,
+    pub variants: HashMap<VariantEntity, DefId>,
+    /// The body of every function that has one.
+    pub bodies: SecondaryMap<DefId, Option<Body>>,
+    /// Every local of the module, with the ids given by name resolution.
+    pub locals: PrimaryMap<LocalId, Local>,
+    pub exprs: EntityArena<ExprId, Expr>,
+    pub field_exprs: EntityArena<FieldExprId, FieldExpr>,
+    pub pats: EntityArena<PatId, Pat>,
+    pub field_pats: EntityArena<FieldPatId, FieldPat>,
+    pub arms: EntityArena<ArmId, Arm>,
+}
+
+impl Hir {
+    pub fn def(&self, item: ItemEntity) -> DefId {
+        self.items[&item]
+    }
+
+    pub fn body(&self, def: DefId) -> Option<&Body> {
+        self.bodies[def].as_ref()
+    }
+
+    /// The functions with a body, in source order.
+    pub fn functions(&self) -> impl Iterator<Item = (DefId, &Body)> {
+        self.defs
+            .keys()
+            .filter_map(|def| Some((def, self.body(def)?)))
+    }
+}
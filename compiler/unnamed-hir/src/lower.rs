@@ -0,0 +1,722 @@
+use cranelift_entity::EntityList;
+use unnamed_ast::{
+    AstCtx, Block, ExprEntity, ItemEntity, Module, PatEntity, VariantEntity,
+    expr::{ArrayExprKind, BinOp, ElseExprKind, Expr as AstExpr},
+    item::{Item, VariantKind},
+    pat::{Pat as AstPat, PatFields},
+};
+use unnamed_common::{Span, Spanned, StrId};
+use unnamed_resolve::{Res, Resolution};
+use unnamed_typeck::{TyId, TyKind, TyList, TypeckResults};
+
+use crate::{
+    Arm, Body, Callee, Def, DefId, DefKind, Expr, ExprId, ExprKind, FieldExpr, FieldPat, Hir, Lit,
+    Local, Pat, PatId, PatKind,
+};
+
+/// Lowers `module`, which must have resolved and type checked without
+/// errors. Types are those inferred by `results`, which is only changed to
+/// intern the types of nodes introduced by desugaring.
+pub fn lower(
+    ctx: &AstCtx,
+    module: &Module,
+    resolution: &Resolution,
+    results: &mut TypeckResults,
+) -> Hir {
+    let mut lowerer = Lowerer {
+        ctx,
+        resolution,
+        results,
+        hir: Hir::default(),
+    };
+
+    for (id, local) in resolution.locals.iter() {
+        let ty = match lowerer.results.locals[id] {
+            Some(ty) => ty,
+            None => lowerer.results.tcx.error(),
+        };
+        lowerer.hir.locals.push(Local {
+            name: local.name,
+            ty,
+            span: local.span,
+        });
+    }
+
+    let items = ctx.items.list(module.items);
+    for &item in items {
+        lowerer.define(item, None);
+    }
+
+    let functions = lowerer
+        .hir
+        .defs
+        .iter()
+        .filter(|(_, def)| def.kind == DefKind::Func)
+        .map(|(id, def)| (id, def.item))
+        .collect::<Vec<_>>();
+    for (def, item) in functions {
+        lowerer.function(def, item);
+    }
+
+    lowerer.hir
+}
+
+struct Lowerer<'a> {
+    ctx: &'a AstCtx,
+    resolution: &'a Resolution,
+    results: &'a mut TypeckResults,
+    hir: Hir,
+}
+
+impl Lowerer<'_> {
+    /// Hands out ids to `item` and then to its methods or variants.
+    fn define(&mut self, item: ItemEntity, parent: Option<DefId>) {
+        let ctx = self.ctx;
+        let (kind, name) = match ctx.items[item] {
+            Item::Func(func) => (DefKind::Func, Some(func.name)),
+            Item::Struct(struct_item) => (DefKind::Struct, Some(struct_item.name)),
+            Item::Enum(enum_item) => (DefKind::Enum, Some(enum_item.name)),
+            Item::Trait(trait_item) => (DefKind::Trait, Some(trait_item.name)),
+            Item::Impl(_) => (DefKind::Impl, None),
+        };
+        let def = self.hir.defs.push(Def {
+            kind,
+            name,
+            item,
+            parent,
+            span: ctx.items[item].span(),
+        });
+        self.hir.items.insert(item, def);
+
+        match ctx.items[item] {
+            Item::Enum(enum_item) => {
+                for &variant in ctx.variants.list(enum_item.variants) {
+                    let data = ctx.variants[variant];
+                    let id = self.hir.defs.push(Def {
+                        kind: DefKind::Variant(variant),
+                        name: Some(data.name),
+                        item,
+                        parent: Some(def),
+                        span: data.span,
+                    });
+                    self.hir.variants.insert(variant, id);
+                }
+            }
+            Item::Trait(trait_item) => {
+                for &method in ctx.items.list(trait_item.items) {
+                    self.define(method, Some(def));
+                }
+            }
+            Item::Impl(impl_item) => {
+                for &method in ctx.items.list(impl_item.items) {
+                    self.define(method, Some(def));
+                }
+            }
+            Item::Func(_) | Item::Struct(_) => {}
+        }
+    }
+
+    fn function(&mut self, def: DefId, item: ItemEntity) {
+        let ctx = self.ctx;
+        let Item::Func(func) = ctx.items[item] else {
+            return;
+        };
+        let Some(block) = func.body else {
+            return;
+        };
+
+        let params = ctx
+            .params
+            .list(func.params)
+            .iter()
+            .filter_map(|&param| self.resolution.params[param])
+            .collect();
+        let value = self.block(block, None);
+
+        self.hir.bodies[def] = Some(Body { params, value });
+    }
+
+    fn alloc(&mut self, kind: ExprKind, ty: TyId, span: Span, ast: Option<ExprEntity>) -> ExprId {
+        self.hir.exprs.alloc(Expr {
+            kind,
+            ty,
+            span,
+            ast,
+        })
+    }
+
+    fn ty(&mut self, expr: ExprEntity) -> TyId {
+        match self.results.exprs[expr] {
+            Some(ty) => ty,
+            None => self.results.tcx.error(),
+        }
+    }
+
+    fn def(&self, res: Option<Res>) -> Option<DefId> {
+        match res? {
+            Res::Func(item) | Res::Struct(item) | Res::Enum(item) | Res::Trait(item) => {
+                Some(self.hir.items[&item])
+            }
+            Res::Variant(_, variant) => Some(self.hir.variants[&variant]),
+            _ => None,
+        }
+    }
+
+    /// The names of the fields of the struct or variant, in definition
+    /// order.
+    fn field_names(&self, res: Option<Res>) -> Vec<StrId> {
+        match res {
+            Some(Res::Struct(item)) => self
+                .results
+                .fields
+                .get(&item)
+                .map(|fields| fields.iter().map(|&(name, _)| name).collect())
+                .unwrap_or_default(),
+            Some(Res::Variant(_, variant)) => self.variant_field_names(variant),
+            _ => Vec::new(),
+        }
+    }
+
+    fn variant_field_names(&self, variant: VariantEntity) -> Vec<StrId> {
+        let ctx = self.ctx;
+        match ctx.variants[variant].kind {
+            VariantKind::Struct(fields) => ctx
+                .field_defs
+                .list(fields)
+                .iter()
+                .map(|&field| ctx.field_defs[field].name)
+                .collect(),
+            VariantKind::Unit | VariantKind::Tuple(_) => Vec::new(),
+        }
+    }
+
+    fn exprs(&mut self, exprs: EntityList<ExprEntity>) -> EntityList<ExprId> {
+        let ctx = self.ctx;
+        let exprs = ctx
+            .exprs
+            .list(exprs)
+            .iter()
+            .map(|&expr| self.expr(expr))
+            .collect::<Vec<_>>();
+
+        self.hir.exprs.alloc_list(&exprs)
+    }
+
+    /// Lowers `block`, typed like the checker types blocks: by their tail,
+    /// or as never returning if one of their statements never does.
+    fn block(&mut self, block: Block, ast: Option<ExprEntity>) -> ExprId {
+        let stmts = self.exprs(block.exprs);
+        let tail = block.tail.map(|tail| self.expr(tail));
+
+        let ty = match tail {
+            Some(tail) => self.hir.exprs[tail].ty,
+            None => {
+                let never = self.results.tcx.never();
+                let diverges = self
+                    .hir
+                    .exprs
+                    .list(stmts)
+                    .iter()
+                    .any(|&stmt| self.hir.exprs[stmt].ty == never);
+                if diverges {
+                    never
+                } else {
+                    self.results.tcx.unit()
+                }
+            }
+        };
+
+        self.alloc(ExprKind::Block { stmts, tail }, ty, block.span, ast)
+    }
+
+    fn unit_block(&mut self, span: Span, ast: Option<ExprEntity>) -> ExprId {
+        let kind = ExprKind::Block {
+            stmts: EntityList::new(),
+            tail: None,
+        };
+        let unit = self.results.tcx.unit();
+
+        self.alloc(kind, unit, span, ast)
+    }
+
+    fn expr(&mut self, expr: ExprEntity) -> ExprId {
+        let ctx = self.ctx;
+        let ty = self.ty(expr);
+        let span = ctx.exprs[expr].span();
+        let res = self.resolution.exprs[expr];
+
+        let kind = match ctx.exprs[expr] {
+            AstExpr::Ident(..) => match res {
+                Some(Res::Local(local)) => ExprKind::Local(local),
+                _ => ExprKind::Error,
+            },
+            AstExpr::Str(value, _) => ExprKind::Lit(Lit::Str(value)),
+            AstExpr::Int(value, ..) => ExprKind::Lit(Lit::Int(value)),
+            AstExpr::Float(value, ..) => ExprKind::Lit(Lit::Float(value)),
+            AstExpr::Char(value, _) => ExprKind::Lit(Lit::Char(value)),
+            AstExpr::Bool(value, _) => ExprKind::Lit(Lit::Bool(value)),
+            AstExpr::Unit(_) => ExprKind::Lit(Lit::Unit),
+            AstExpr::Path(_) => match self.def(res) {
+                Some(def) => ExprKind::Adt {
+                    def,
+                    fields: EntityList::new(),
+                },
+                None => ExprKind::Error,
+            },
+            AstExpr::Block(block) => return self.block(block, Some(expr)),
+            AstExpr::Let(let_expr) => match self.resolution.lets[expr] {
+                Some(local) => ExprKind::Let {
+                    local,
+                    init: let_expr.value.map(|value| self.expr(value)),
+                },
+                None => ExprKind::Error,
+            },
+            AstExpr::Assign(assign) => match res {
+                Some(Res::Local(local)) => ExprKind::Assign {
+                    local,
+                    value: self.expr(assign.rhs),
+                },
+                _ => ExprKind::Error,
+            },
+            AstExpr::Bin(bin_expr) => {
+                let lhs = self.expr(bin_expr.lhs);
+                let rhs = self.expr(bin_expr.rhs);
+
+                // `a && b` is `if a { b } else { false }`, and `a || b` is
+                // `if a { true } else { b }`.
+                let short_circuit = |this: &mut Self, value| {
+                    let lit = ExprKind::Lit(Lit::Bool(value));
+                    this.alloc(lit, ty, bin_expr.span, Some(expr))
+                };
+                match bin_expr.op {
+                    BinOp::And => ExprKind::If {
+                        cond: lhs,
+                        then_branch: rhs,
+                        else_branch: short_circuit(self, false),
+                    },
+                    BinOp::Or => ExprKind::If {
+                        cond: lhs,
+                        then_branch: short_circuit(self, true),
+                        else_branch: rhs,
+                    },
+                    op => ExprKind::Binary {
+                        op,
+                        lhs,
+                        rhs,
+                        overload: self.results.operators[expr],
+                    },
+                }
+            }
+            AstExpr::Unary(unary_expr) => ExprKind::Unary {
+                op: unary_expr.op,
+                value: self.expr(unary_expr.value),
+                overload: self.results.operators[expr],
+            },
+            AstExpr::Call(call) => {
+                let receiver = self.resolution.exprs[call.receiver];
+                let args = self.exprs(call.args);
+
+                match receiver {
+                    Some(Res::Builtin(builtin)) => ExprKind::Call {
+                        callee: Callee::Builtin(builtin),
+                        ty_args: TyList::EMPTY,
+                        args,
+                    },
+                    Some(Res::Variant(..)) => {
+                        let fields = self.hir.exprs.list(args).to_vec();
+                        let fields = fields
+                            .into_iter()
+                            .enumerate()
+                            .map(|(index, value)| {
+                                self.hir.field_exprs.alloc(FieldExpr {
+                                    index: index as u32,
+                                    value,
+                                    span: self.hir.exprs[value].span,
+                                })
+                            })
+                            .collect::<Vec<_>>();
+                        ExprKind::Adt {
+                            def: self.def(receiver).expect("variants are defined"),
+                            fields: self.hir.field_exprs.alloc_list(&fields),
+                        }
+                    }
+                    Some(Res::Func(item)) => ExprKind::Call {
+                        callee: Callee::Def(self.hir.items[&item]),
+                        ty_args: self.results.type_args[expr].unwrap_or(TyList::EMPTY),
+                        args,
+                    },
+                    _ => ExprKind::Error,
+                }
+            }
+            AstExpr::MethodCall(call) => {
+                let mut args = vec![self.expr(call.receiver)];
+                for &arg in ctx.exprs.list(call.args) {
+                    args.push(self.expr(arg));
+                }
+
+                let callee = match self.results.method_calls[expr] {
+                    Some(method) => Callee::Def(self.hir.items[&method]),
+                    None => Callee::Len,
+                };
+                ExprKind::Call {
+                    callee,
+                    ty_args: self.results.type_args[expr].unwrap_or(TyList::EMPTY),
+                    args: self.hir.exprs.alloc_list(&args),
+                }
+            }
+            AstExpr::If(if_expr) => {
+                let cond = self.expr(if_expr.cond);
+                let then_branch = self.block(if_expr.then_branch, Some(expr));
+                // `else if` chains nest, with the inner `if` as the else
+                // branch of the outer one.
+                let else_branch = match if_expr.else_branch {
+                    Some(else_expr) => match else_expr.kind {
+                        ElseExprKind::If(else_if) => self.expr(else_if),
+                        ElseExprKind::Else(block) => self.block(block, Some(expr)),
+                    },
+                    None => self.unit_block(if_expr.span, Some(expr)),
+                };
+
+                ExprKind::If {
+                    cond,
+                    then_branch,
+                    else_branch,
+                }
+            }
+            AstExpr::While(while_expr) => {
+                // `while cond { body }` is `loop { if cond { body } else {
+                // break } }`.
+                let cond = self.expr(while_expr.cond);
+                let then_branch = self.block(while_expr.body, Some(expr));
+                let never = self.results.tcx.never();
+                let break_ = self.alloc(ExprKind::Break, never, while_expr.span, Some(expr));
+                let if_ = ExprKind::If {
+                    cond,
+                    then_branch,
+                    else_branch: break_,
+                };
+                let unit = self.results.tcx.unit();
+                let body = self.alloc(if_, unit, while_expr.span, Some(expr));
+
+                ExprKind::Loop { body }
+            }
+            AstExpr::Match(match_expr) => {
+                let scrutinee = self.expr(match_expr.scrutinee);
+                let arms = ctx
+                    .arms
+                    .list(match_expr.arms)
+                    .iter()
+                    .map(|&arm| {
+                        let arm = ctx.arms[arm];
+                        let arm = Arm {
+                            pat: self.pat(arm.pat),
+                            guard: arm.guard.map(|guard| self.expr(guard)),
+                            body: self.expr(arm.body),
+                            span: arm.span,
+                        };
+                        self.hir.arms.alloc(arm)
+                    })
+                    .collect::<Vec<_>>();
+
+                ExprKind::Match {
+                    scrutinee,
+                    arms: self.hir.arms.alloc_list(&arms),
+                }
+            }
+            AstExpr::Array(array_expr) => match array_expr.kind {
+                ArrayExprKind::List { values } => ExprKind::Array(self.exprs(values)),
+                ArrayExprKind::Repeat { value, repeat } => ExprKind::Repeat {
+                    value: self.expr(value),
+                    count: self.expr(repeat),
+                },
+            },
+            AstExpr::Struct(struct_expr) => {
+                let names = self.field_names(res);
+                let fields = ctx
+                    .field_exprs
+                    .list(struct_expr.fields)
+                    .iter()
+                    .map(|&field| {
+                        let field = ctx.field_exprs[field];
+                        let index = names.iter().position(|&name| name == field.name);
+                        let field = FieldExpr {
+                            index: index.unwrap_or_default() as u32,
+                            value: self.expr(field.value),
+                            span: field.span,
+                        };
+                        self.hir.field_exprs.alloc(field)
+                    })
+                    .collect::<Vec<_>>();
+
+                match self.def(res) {
+                    Some(def) => ExprKind::Adt {
+                        def,
+                        fields: self.hir.field_exprs.alloc_list(&fields),
+                    },
+                    None => ExprKind::Error,
+                }
+            }
+            AstExpr::Field(field) => {
+                let base_ty = self.ty(field.base);
+                let base = self.expr(field.base);
+                let index = match self.results.tcx[base_ty] {
+                    TyKind::Adt(item, _) => self.results.fields.get(&item).and_then(|fields| {
+                        fields.iter().position(|&(name, _)| name == field.member)
+                    }),
+                    _ => None,
+                };
+
+                match index {
+                    Some(index) => ExprKind::Field {
+                        base,
+                        index: index as u32,
+                    },
+                    None => ExprKind::Error,
+                }
+            }
+            AstExpr::Index(index) => ExprKind::Index {
+                base: self.expr(index.base),
+                index: self.expr(index.index),
+            },
+            AstExpr::Return(return_expr) => {
+                ExprKind::Return(return_expr.value.map(|value| self.expr(value)))
+            }
+            AstExpr::Error(_) => ExprKind::Error,
+        };
+
+        self.alloc(kind, ty, span, Some(expr))
+    }
+
+    fn pat(&mut self, pat: PatEntity) -> PatId {
+        let ctx = self.ctx;
+        let ty = match self.results.pats[pat] {
+            Some(ty) => ty,
+            None => self.results.tcx.error(),
+        };
+        let span = ctx.pats[pat].span();
+
+        let kind = match ctx.pats[pat] {
+            AstPat::Wild(_) => PatKind::Wild,
+            AstPat::Binding(..) => match self.resolution.bindings[pat] {
+                Some(local) => PatKind::Binding(local),
+                None => PatKind::Error,
+            },
+            AstPat::Lit(expr, _) => PatKind::Lit(self.expr(expr)),
+            AstPat::Adt(adt_pat) => {
+                let res = self.resolution.pats[pat];
+                let fields = match adt_pat.fields {
+                    PatFields::Unit => Vec::new(),
+                    PatFields::Tuple(pats) => ctx
+                        .pats
+                        .list(pats)
+                        .iter()
+                        .enumerate()
+                        .map(|(index, &pat)| FieldPat {
+                            index: index as u32,
+                            pat: self.pat(pat),
+                            span: ctx.pats[pat].span(),
+                        })
+                        .collect(),
+                    PatFields::Named(field_pats) => {
+                        let names = self.field_names(res);
+                        self.ctx
+                            .field_pats
+                            .list(field_pats)
+                            .iter()
+                            .map(|&field| {
+                                let field = ctx.field_pats[field];
+                                let index = names.iter().position(|&name| name == field.name);
+                                FieldPat {
+                                    index: index.unwrap_or_default() as u32,
+                                    pat: self.pat(field.pat),
+                                    span: field.span,
+                                }
+                            })
+                            .collect()
+                    }
+                };
+                let fields = fields
+                    .into_iter()
+                    .map(|field| self.hir.field_pats.alloc(field))
+                    .collect::<Vec<_>>();
+
+                match self.def(res) {
+                    Some(def) => PatKind::Adt {
+                        def,
+                        fields: self.hir.field_pats.alloc_list(&fields),
+                    },
+                    None => PatKind::Error,
+                }
+            }
+            AstPat::Error(_) => PatKind::Error,
+        };
+
+        self.hir.pats.alloc(Pat { kind, ty, span })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_common::{Interner, Source};
+
+    use crate::printer::print_hir;
+
+    fn print(content: &str) -> String {
+        let interner = Interner::new();
+        let parsed = unnamed_parser::parse(Source::new(content, "test_file"), &interner);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let mut results =
+            unnamed_typeck::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+        assert!(results.errors.is_empty(), "{:?}", results.errors);
+
+        let hir = super::lower(&parsed.ctx, &parsed.module, &resolution, &mut results);
+        print_hir(&hir, &parsed.ctx, &interner, &results)
+    }
+
+    fn headers(content: &str) -> Vec<String> {
+        print(content)
+            .lines()
+            .filter(|line| line.starts_with("def"))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn test_def_ids() {
+        let content = "trait Area { func area(self) -> f64; } \
+                       enum Shape { Circle(f64), Square { side: f64 } } \
+                       impl Area for Shape { func area(self) -> f64 { 1.0 } } \
+                       func main() {}";
+        assert_eq!(
+            headers(content),
+            [
+                "def0 Trait Area",
+                "def1 Func Area::area",
+                "def2 Enum Shape",
+                "def3 Variant Shape::Circle",
+                "def4 Variant Shape::Square",
+                "def5 Impl Shape",
+                "def6 Func Shape::area",
+                "def7 Func main",
+            ]
+        );
+
+        // Items keep their ids when items after them change.
+        let extended = headers(&format!("{content} func helper() {{}}"));
+        assert_eq!(extended[..8], headers(content)[..]);
+        assert_eq!(extended[8], "def8 Func helper");
+    }
+
+    #[test]
+    fn test_desugar() {
+        assert_eq!(
+            print(
+                "func f(a: i64) -> i64 { \
+                     let n; n = 0; \
+                     while n < 3 && a > 0 { n = n + 1; } \
+                     if a == 1 { [1, 2].len() } else if a == 2 { n } else { -a } \
+                 }"
+            ),
+            "def0 Func f
+  Param a#0: i64
+  Block: i64
+    Let n#1: i64
+    Assign n#1: ()
+      Lit 0: i64
+    Loop: ()
+      If: ()
+        If: bool
+          Binary <: bool
+            Local n#1: i64
+            Lit 3: i64
+          Binary >: bool
+            Local a#0: i64
+            Lit 0: i64
+          Lit false: bool
+        Block: ()
+          Assign n#1: ()
+            Binary +: i64
+              Local n#1: i64
+              Lit 1: i64
+        Break: !
+    Tail
+      If: i64
+        Binary ==: bool
+          Local a#0: i64
+          Lit 1: i64
+        Block: i64
+          Tail
+            Call len: i64
+              Array: [i64]
+                Lit 1: i64
+                Lit 2: i64
+        If: i64
+          Binary ==: bool
+            Local a#0: i64
+            Lit 2: i64
+          Block: i64
+            Tail
+              Local n#1: i64
+          Block: i64
+            Tail
+              Unary -: i64
+                Local a#0: i64
+"
+        );
+    }
+
+    #[test]
+    fn test_adts() {
+        assert_eq!(
+            print(
+                "enum Shape { Circle(f64), Rect { w: f64, h: f64 } } \
+                 struct P { x: i64, y: i64 } \
+                 impl Add for P { func add(self, other: P) -> P { self } } \
+                 func f(s: Shape) -> P { \
+                     let p = P { y: 2, x: 1 }; \
+                     match s { Shape::Circle(r) => p, Shape::Rect { h, w: _ } => p + p } \
+                 }"
+            ),
+            "def0 Enum Shape
+def1 Variant Shape::Circle
+def2 Variant Shape::Rect
+def3 Struct P
+def4 Impl P
+def5 Func P::add
+  Param self#0: P
+  Param other#1: P
+  Block: P
+    Tail
+      Local self#0: P
+def6 Func f
+  Param s#2: Shape
+  Block: P
+    Let p#3: P
+      Adt P: P
+        Field 1
+          Lit 2: i64
+        Field 0
+          Lit 1: i64
+    Tail
+      Match: P
+        Local s#2: Shape
+        Arm
+          Adt Shape::Circle: Shape
+            Field 0
+              Binding r#4: f64
+          Local p#3: P
+        Arm
+          Adt Shape::Rect: Shape
+            Field 1
+              Binding h#5: f64
+            Field 0
+              Wild: f64
+          Binary + via Add: P
+            Local p#3: P
+            Local p#3: P
+"
+        );
+    }
+}
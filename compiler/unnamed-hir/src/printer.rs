@@ -0,0 +1,298 @@
+use std::fmt::Write;
+
+use unnamed_ast::AstCtx;
+use unnamed_common::Interner;
+use unnamed_resolve::LocalId;
+use unnamed_typeck::{TyId, TypeckResults};
+
+use crate::{Callee, DefId, ExprId, ExprKind, Hir, Lit, PatId, PatKind};
+
+/// Prints every definition of `hir` on its own line, followed by the bodies
+/// of functions as indented trees with one node and its type per line.
+pub fn print_hir(hir: &Hir, ctx: &AstCtx, interner: &Interner, results: &TypeckResults) -> String {
+    let mut printer = HirPrinter {
+        hir,
+        ctx,
+        interner,
+        results,
+        out: String::new(),
+        depth: 0,
+    };
+    for def in hir.defs.keys() {
+        printer.def(def);
+    }
+
+    printer.out
+}
+
+struct HirPrinter<'a> {
+    hir: &'a Hir,
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    results: &'a TypeckResults,
+    out: String,
+    depth: usize,
+}
+
+impl HirPrinter<'_> {
+    fn line(&mut self, label: impl AsRef<str>) {
+        let _ = writeln!(
+            self.out,
+            "{:indent$}{}",
+            "",
+            label.as_ref(),
+            indent = self.depth * 2
+        );
+    }
+
+    /// A line for a node of type `ty`.
+    fn typed(&mut self, label: impl AsRef<str>, ty: TyId) {
+        let ty = self.ty(ty);
+        self.line(format!("{}: {ty}", label.as_ref()));
+    }
+
+    fn nested(&mut self, print: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        print(self);
+        self.depth -= 1;
+    }
+
+    fn ty(&self, ty: TyId) -> String {
+        self.results
+            .tcx
+            .display(ty, self.ctx, self.interner)
+            .to_string()
+    }
+
+    /// The path of a definition, like `Shape::Circle` for variants and
+    /// `Point::area` for methods. Impls are named after their `Self` type.
+    fn path(&self, def: DefId) -> String {
+        let data = self.hir.defs[def];
+        let name = match data.name {
+            Some(name) => self.interner.get(name).to_owned(),
+            None => self.ty(self.results.self_tys[&data.item]),
+        };
+
+        match data.parent {
+            Some(parent) => format!("{}::{name}", self.path(parent)),
+            None => name,
+        }
+    }
+
+    fn local(&self, local: LocalId) -> String {
+        let name = self.interner.get(self.hir.locals[local].name);
+        format!("{name}#{}", local.as_u32())
+    }
+
+    fn def(&mut self, def: DefId) {
+        let kind = self.hir.defs[def].kind.description();
+        self.line(format!("def{} {kind} {}", def.as_u32(), self.path(def)));
+
+        let Some(body) = self.hir.body(def) else {
+            return;
+        };
+        self.nested(|printer| {
+            for &param in &body.params {
+                let ty = printer.hir.locals[param].ty;
+                printer.typed(format!("Param {}", printer.local(param)), ty);
+            }
+            printer.expr(body.value);
+        });
+    }
+
+    fn exprs(&mut self, exprs: &[ExprId]) {
+        for &expr in exprs {
+            self.expr(expr);
+        }
+    }
+
+    fn expr(&mut self, expr: ExprId) {
+        let hir = self.hir;
+        let data = hir.exprs[expr];
+
+        match data.kind {
+            ExprKind::Lit(lit) => {
+                let label = match lit {
+                    Lit::Int(value) => format!("Lit {value}"),
+                    Lit::Float(value) => format!("Lit {value:?}"),
+                    Lit::Bool(value) => format!("Lit {value}"),
+                    Lit::Char(value) => format!("Lit {value:?}"),
+                    Lit::Str(value) => format!("Lit {:?}", self.interner.get(value)),
+                    Lit::Unit => "Lit ()".to_owned(),
+                };
+                self.typed(label, data.ty);
+            }
+            ExprKind::Local(local) => {
+                self.typed(format!("Local {}", self.local(local)), data.ty);
+            }
+            ExprKind::Block { stmts, tail } => {
+                self.typed("Block", data.ty);
+                self.nested(|printer| {
+                    printer.exprs(hir.exprs.list(stmts));
+                    if let Some(tail) = tail {
+                        printer.line("Tail");
+                        printer.nested(|printer| printer.expr(tail));
+                    }
+                });
+            }
+            ExprKind::Let { local, init } => {
+                let ty = hir.locals[local].ty;
+                self.typed(format!("Let {}", self.local(local)), ty);
+                if let Some(init) = init {
+                    self.nested(|printer| printer.expr(init));
+                }
+            }
+            ExprKind::Assign { local, value } => {
+                self.typed(format!("Assign {}", self.local(local)), data.ty);
+                self.nested(|printer| printer.expr(value));
+            }
+            ExprKind::Binary {
+                op,
+                lhs,
+                rhs,
+                overload,
+            } => {
+                let label = match overload {
+                    Some(trait_) => format!("Binary {} via {}", op.as_str(), trait_.name()),
+                    None => format!("Binary {}", op.as_str()),
+                };
+                self.typed(label, data.ty);
+                self.nested(|printer| {
+                    printer.expr(lhs);
+                    printer.expr(rhs);
+                });
+            }
+            ExprKind::Unary {
+                op,
+                value,
+                overload,
+            } => {
+                let label = match overload {
+                    Some(trait_) => format!("Unary {} via {}", op.as_str(), trait_.name()),
+                    None => format!("Unary {}", op.as_str()),
+                };
+                self.typed(label, data.ty);
+                self.nested(|printer| printer.expr(value));
+            }
+            ExprKind::Call {
+                callee,
+                ty_args,
+                args,
+            } => {
+                let mut label = match callee {
+                    Callee::Def(def) => format!("Call {}", self.path(def)),
+                    Callee::Builtin(builtin) => format!("Call {}", builtin.name()),
+                    Callee::Len => "Call len".to_owned(),
+                };
+                let ty_args = self.results.tcx.list(ty_args);
+                if !ty_args.is_empty() {
+                    let ty_args = ty_args.iter().map(|&ty| self.ty(ty)).collect::<Vec<_>>();
+                    let _ = write!(label, "::<{}>", ty_args.join(", "));
+                }
+                self.typed(label, data.ty);
+                self.nested(|printer| printer.exprs(hir.exprs.list(args)));
+            }
+            ExprKind::Adt { def, fields } => {
+                self.typed(format!("Adt {}", self.path(def)), data.ty);
+                self.nested(|printer| {
+                    for &field in hir.field_exprs.list(fields) {
+                        let field = hir.field_exprs[field];
+                        printer.line(format!("Field {}", field.index));
+                        printer.nested(|printer| printer.expr(field.value));
+                    }
+                });
+            }
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                self.typed("If", data.ty);
+                self.nested(|printer| {
+                    printer.expr(cond);
+                    printer.expr(then_branch);
+                    printer.expr(else_branch);
+                });
+            }
+            ExprKind::Loop { body } => {
+                self.typed("Loop", data.ty);
+                self.nested(|printer| printer.expr(body));
+            }
+            ExprKind::Break => self.typed("Break", data.ty),
+            ExprKind::Match { scrutinee, arms } => {
+                self.typed("Match", data.ty);
+                self.nested(|printer| {
+                    printer.expr(scrutinee);
+                    for &arm in hir.arms.list(arms) {
+                        let arm = hir.arms[arm];
+                        printer.line("Arm");
+                        printer.nested(|printer| {
+                            printer.pat(arm.pat);
+                            if let Some(guard) = arm.guard {
+                                printer.line("Guard");
+                                printer.nested(|printer| printer.expr(guard));
+                            }
+                            printer.expr(arm.body);
+                        });
+                    }
+                });
+            }
+            ExprKind::Array(values) => {
+                self.typed("Array", data.ty);
+                self.nested(|printer| printer.exprs(hir.exprs.list(values)));
+            }
+            ExprKind::Repeat { value, count } => {
+                self.typed("Repeat", data.ty);
+                self.nested(|printer| {
+                    printer.expr(value);
+                    printer.expr(count);
+                });
+            }
+            ExprKind::Field { base, index } => {
+                self.typed(format!("Field {index}"), data.ty);
+                self.nested(|printer| printer.expr(base));
+            }
+            ExprKind::Index { base, index } => {
+                self.typed("Index", data.ty);
+                self.nested(|printer| {
+                    printer.expr(base);
+                    printer.expr(index);
+                });
+            }
+            ExprKind::Return(value) => {
+                self.typed("Return", data.ty);
+                if let Some(value) = value {
+                    self.nested(|printer| printer.expr(value));
+                }
+            }
+            ExprKind::Error => self.typed("Error", data.ty),
+        }
+    }
+
+    fn pat(&mut self, pat: PatId) {
+        let hir = self.hir;
+        let data = hir.pats[pat];
+
+        match data.kind {
+            PatKind::Wild => self.typed("Wild", data.ty),
+            PatKind::Binding(local) => {
+                self.typed(format!("Binding {}", self.local(local)), data.ty);
+            }
+            PatKind::Lit(expr) => {
+                self.typed("Lit", data.ty);
+                self.nested(|printer| printer.expr(expr));
+            }
+            PatKind::Adt { def, fields } => {
+                self.typed(format!("Adt {}", self.path(def)), data.ty);
+                self.nested(|printer| {
+                    for &field in hir.field_pats.list(fields) {
+                        let field = hir.field_pats[field];
+                        printer.line(format!("Field {}", field.index));
+                        printer.nested(|printer| printer.pat(field.pat));
+                    }
+                });
+            }
+            PatKind::Error => self.typed("Error", data.ty),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use ariadne::{Label, ReportKind};
+use unnamed_common::{IntoReport, Report, Source, Span, Spanned};
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    span: Span,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(span: Span, kind: ErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// Errors that depend on the values computed. Everything else is reported
+/// by the checks the module passed before being evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    MissingMain,
+    IntegerOverflow,
+    DivisionByZero,
+    ShiftOverflow { amount: i128, bits: u32 },
+    IndexOutOfBounds { index: i128, len: usize },
+    InvalidArrayLength(i128),
+    StackOverflow,
+    Io(String),
+}
+
+impl IntoReport for Error {
+    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
+        let (message, label_message) = match self.kind {
+            ErrorKind::MissingMain => (
+                "`main` function not found".to_owned(),
+                "add a `func main()` to run this file".to_owned(),
+            ),
+            ErrorKind::IntegerOverflow => (
+                "attempt to compute a value that overflows its type".to_owned(),
+                "integer overflow".to_owned(),
+            ),
+            ErrorKind::DivisionByZero => (
+                "attempt to divide by zero".to_owned(),
+                "division by zero".to_owned(),
+            ),
+            ErrorKind::ShiftOverflow { amount, bits } => (
+                format!("attempt to shift a {bits}-bit integer by {amount}"),
+                format!("shift amount must be between 0 and {}", bits - 1),
+            ),
+            ErrorKind::IndexOutOfBounds { index, len } => (
+                format!("index out of bounds: the len is {len} but the index is {index}"),
+                "index out of bounds".to_owned(),
+            ),
+            ErrorKind::InvalidArrayLength(len) => (
+                format!("invalid array length {len}"),
+                "array length must not be negative".to_owned(),
+            ),
+            ErrorKind::StackOverflow => (
+                "stack overflow".to_owned(),
+                "too many nested calls".to_owned(),
+            ),
+            ErrorKind::Io(error) => (
+                format!("failed to write output: {error}"),
+                "while evaluating this call".to_owned(),
+            ),
+        };
+
+        Report::build(ReportKind::Error, source.span(self.span))
+            .with_message(message)
+            .with_label(Label::new(source.span(self.span)).with_message(label_message))
+            .finish()
+    }
+}
@@ -0,0 +1,820 @@
+//! A tree-walking interpreter that evaluates the HIR of a module, which must
+//! have type checked and been monomorphized without errors. Each call
+//! evaluates the body of the instance the call resolved to, with the types
+//! of that instance, so that values have the same types, and calls and
+//! operators the same targets, as in the generated code. The only errors
+//! left are those depending on the values computed, like dividing by zero.
+
+pub mod error;
+mod ops;
+pub mod value;
+
+use std::{collections::HashMap, io::Write};
+
+use unnamed_ast::{AstCtx, ExprEntity, Module, expr::BinOp, item::VariantKind};
+use unnamed_common::{Interner, Span};
+use unnamed_hir::{Callee, DefId, DefKind, ExprId, ExprKind, Hir, Lit, PatId, PatKind};
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+use unnamed_resolve::{Builtin, LocalId};
+use unnamed_typeck::{InstanceId, Instances, Subst, TyId, TyKind, TypeckResults};
+
+pub use error::{Error, ErrorKind};
+pub use value::{EnumValue, Float, Int, StructValue, Value, VariantFields};
+
+/// How deep calls can be nested before evaluation stops with a
+/// [`ErrorKind::StackOverflow`] error.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/// Non-local exits from the evaluation of an expression.
+enum Unwind {
+    Return(Value),
+    /// Leaves the innermost loop.
+    Break,
+    Error(Error),
+}
+
+impl From<Error> for Unwind {
+    fn from(error: Error) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+type Eval<T = Value> = Result<T, Unwind>;
+
+/// A call being evaluated.
+struct Frame {
+    instance: InstanceId,
+    /// What the type parameters of the called function stand for.
+    subst: Subst,
+    /// The values of the locals assigned so far. Type checking makes sure
+    /// that no local is used before it is assigned.
+    locals: HashMap<LocalId, Value>,
+}
+
+pub struct Interpreter<'a, W> {
+    ctx: &'a AstCtx,
+    hir: &'a Hir,
+    instances: &'a Instances,
+    results: &'a mut TypeckResults,
+    interner: &'a Interner,
+    out: W,
+    span: Span,
+    /// The calls being evaluated, innermost last.
+    frames: Vec<Frame>,
+}
+
+impl<'a, W: Write> Interpreter<'a, W> {
+    /// Creates an interpreter for the instances of `module`. Output of
+    /// `print` and `println` is written to `out`.
+    pub fn new(
+        ctx: &'a AstCtx,
+        module: &Module,
+        hir: &'a Hir,
+        instances: &'a Instances,
+        results: &'a mut TypeckResults,
+        interner: &'a Interner,
+        out: W,
+    ) -> Self {
+        Self {
+            ctx,
+            hir,
+            instances,
+            results,
+            interner,
+            out,
+            span: module.span,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn into_output(self) -> W {
+        self.out
+    }
+
+    /// Calls the `main` function of the module without arguments.
+    pub fn run_main(&mut self) -> Result<Value, Error> {
+        let main = self.interner.intern("main");
+        let instance = self.instances.instances.iter().find(|(_, instance)| {
+            let def = self.hir.defs[self.hir.def(instance.item)];
+            def.kind == DefKind::Func && def.parent.is_none() && def.name == Some(main)
+        });
+        let Some((instance, _)) = instance else {
+            return Err(Error::new(self.span, ErrorKind::MissingMain));
+        };
+
+        self.call(instance, Vec::new(), self.span)
+    }
+
+    /// Calls `instance` with already evaluated arguments.
+    fn call(&mut self, instance: InstanceId, args: Vec<Value>, span: Span) -> Result<Value, Error> {
+        let hir = self.hir;
+        let item = self.instances.instances[instance].item;
+        let body = hir.body(hir.def(item)).expect("instances have a body");
+
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(Error::new(span, ErrorKind::StackOverflow));
+        }
+
+        self.frames.push(Frame {
+            instance,
+            subst: self.instances.subst(self.results, instance),
+            locals: body.params.iter().copied().zip(args).collect(),
+        });
+        let result = self.eval_expr(body.value);
+        self.frames.pop();
+
+        match result {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(error)) => Err(error),
+            Err(Unwind::Break) => unreachable!("`break` is inside a loop"),
+        }
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("a call is being evaluated")
+    }
+
+    /// The instance called by the expression of the AST in the body being
+    /// evaluated, unless it is an operator applied to primitive operands.
+    fn callee(&mut self, ast: Option<ExprEntity>) -> Option<InstanceId> {
+        let instance = self.frame().instance;
+        self.instances.calls.get(&(instance, ast?)).copied()
+    }
+
+    /// The type `ty` of the body being evaluated stands for.
+    fn ty(&mut self, ty: TyId) -> TyKind {
+        let ty = self.results.tcx.subst(
+            ty,
+            &self.frames.last().expect("a call is being evaluated").subst,
+        );
+        self.results.tcx[ty]
+    }
+
+    fn int(&mut self, value: i128, ty: TyId, span: Span) -> Result<Value, Error> {
+        let ty = match self.ty(ty) {
+            TyKind::Int(int) => int,
+            _ => IntSuffix::I64,
+        };
+
+        ops::checked(Int::new(value, ty), span)
+    }
+
+    fn print(&mut self, args: &[Value], newline: bool, span: Span) -> Result<Value, Error> {
+        let text = args
+            .iter()
+            .map(|arg| arg.display(self.interner).to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let result = if newline {
+            writeln!(self.out, "{text}")
+        } else {
+            write!(self.out, "{text}")
+        };
+        result.map_err(|error| Error::new(span, ErrorKind::Io(error.to_string())))?;
+
+        Ok(Value::Unit)
+    }
+
+    fn eval_expr(&mut self, expr: ExprId) -> Eval {
+        // Deeply nested expressions and calls recurse deeply, so grow the
+        // native stack on demand instead of overflowing it.
+        stacker::maybe_grow(64 * 1024, 1024 * 1024, || self.eval_expr_inner(expr))
+    }
+
+    fn eval_expr_inner(&mut self, expr: ExprId) -> Eval {
+        let hir = self.hir;
+        let data = hir.exprs[expr];
+        let span = data.span;
+
+        let value = match data.kind {
+            ExprKind::Lit(lit) => match lit {
+                Lit::Int(value) => self.int(value.into(), data.ty, span)?,
+                Lit::Float(value) => {
+                    let ty = match self.ty(data.ty) {
+                        TyKind::Float(float) => float,
+                        _ => FloatSuffix::F64,
+                    };
+                    Value::Float(Float::new(value, ty))
+                }
+                Lit::Bool(value) => Value::Bool(value),
+                Lit::Char(value) => Value::Char(value),
+                Lit::Str(value) => Value::Str(self.interner.get(value).to_owned()),
+                Lit::Unit => Value::Unit,
+            },
+            ExprKind::Local(local) => self.frame().locals[&local].clone(),
+            ExprKind::Block { stmts, tail } => {
+                for &stmt in hir.exprs.list(stmts) {
+                    self.eval_expr(stmt)?;
+                }
+                match tail {
+                    Some(tail) => self.eval_expr(tail)?,
+                    None => Value::Unit,
+                }
+            }
+            ExprKind::Let { local, init } => {
+                if let Some(init) = init {
+                    let value = self.eval_expr(init)?;
+                    self.frame().locals.insert(local, value);
+                }
+                Value::Unit
+            }
+            ExprKind::Assign { local, value } => {
+                let value = self.eval_expr(value)?;
+                self.frame().locals.insert(local, value);
+                Value::Unit
+            }
+            ExprKind::Binary { op, lhs, rhs, .. } => {
+                let lhs = self.eval_expr(lhs)?;
+                let rhs = self.eval_expr(rhs)?;
+
+                match self.callee(data.ast) {
+                    Some(callee) => {
+                        let result = self.call(callee, vec![lhs, rhs], span)?;
+                        ops::overloaded(op, result)
+                    }
+                    None => ops::binary(op, lhs, rhs, span)?,
+                }
+            }
+            ExprKind::Unary { op, value, .. } => {
+                let value = self.eval_expr(value)?;
+
+                match self.callee(data.ast) {
+                    Some(callee) => self.call(callee, vec![value], span)?,
+                    None => ops::unary(op, value, span)?,
+                }
+            }
+            ExprKind::Call { callee, args, .. } => {
+                let args = self.eval_exprs(hir.exprs.list(args))?;
+
+                match callee {
+                    Callee::Def(_) => {
+                        let callee = self.callee(data.ast).expect("calls are monomorphized");
+                        self.call(callee, args, span)?
+                    }
+                    Callee::Builtin(builtin) => {
+                        self.print(&args, builtin == Builtin::Println, span)?
+                    }
+                    Callee::Len => {
+                        let len = match &args[0] {
+                            Value::Array(values) => values.len(),
+                            Value::Str(value) => value.len(),
+                            value => unreachable!("{value:?} has no length"),
+                        };
+                        self.int(len as i128, data.ty, span)?
+                    }
+                }
+            }
+            ExprKind::Adt { def, fields } => {
+                // Fields are evaluated in the order they are written in.
+                let mut values = Vec::new();
+                for &field in hir.field_exprs.list(fields) {
+                    let field = hir.field_exprs[field];
+                    values.push((field.index, self.eval_expr(field.value)?));
+                }
+                values.sort_by_key(|&(index, _)| index);
+
+                self.adt(def, values.into_iter().map(|(_, value)| value).collect())
+            }
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                if self.eval_bool(cond)? {
+                    self.eval_expr(then_branch)?
+                } else {
+                    self.eval_expr(else_branch)?
+                }
+            }
+            ExprKind::Loop { body } => loop {
+                match self.eval_expr(body) {
+                    Ok(_) => {}
+                    Err(Unwind::Break) => break Value::Unit,
+                    Err(unwind) => return Err(unwind),
+                }
+            },
+            ExprKind::Break => return Err(Unwind::Break),
+            ExprKind::Match { scrutinee, arms } => {
+                let value = self.eval_expr(scrutinee)?;
+
+                for &arm in hir.arms.list(arms) {
+                    let arm = hir.arms[arm];
+                    if !self.match_pat(arm.pat, &value)? {
+                        continue;
+                    }
+                    if let Some(guard) = arm.guard
+                        && !self.eval_bool(guard)?
+                    {
+                        continue;
+                    }
+
+                    return self.eval_expr(arm.body);
+                }
+
+                unreachable!("matches are exhaustive")
+            }
+            ExprKind::Array(values) => Value::Array(self.eval_exprs(hir.exprs.list(values))?),
+            ExprKind::Repeat { value, count } => {
+                let value = self.eval_expr(value)?;
+                let len = self.eval_int(count)?;
+                if len < 0 {
+                    let span = hir.exprs[count].span;
+                    return Err(Error::new(span, ErrorKind::InvalidArrayLength(len)).into());
+                }
+
+                Value::Array(vec![value; len as usize])
+            }
+            ExprKind::Field { base, index } => match self.eval_expr(base)? {
+                Value::Struct(mut value) => value.fields.swap_remove(index as usize).1,
+                value => unreachable!("{value:?} has no fields"),
+            },
+            ExprKind::Index { base, index } => {
+                let Value::Array(mut values) = self.eval_expr(base)? else {
+                    unreachable!("only arrays are indexed");
+                };
+                let index = self.eval_int(index)?;
+
+                if index < 0 || index as usize >= values.len() {
+                    return Err(Error::new(
+                        span,
+                        ErrorKind::IndexOutOfBounds {
+                            index,
+                            len: values.len(),
+                        },
+                    )
+                    .into());
+                }
+
+                values.swap_remove(index as usize)
+            }
+            ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval_expr(value)?,
+                    None => Value::Unit,
+                };
+
+                return Err(Unwind::Return(value));
+            }
+            ExprKind::Error => unreachable!("the module checked without errors"),
+        };
+
+        Ok(value)
+    }
+
+    fn eval_exprs(&mut self, exprs: &[ExprId]) -> Eval<Vec<Value>> {
+        exprs.iter().map(|&expr| self.eval_expr(expr)).collect()
+    }
+
+    fn eval_bool(&mut self, expr: ExprId) -> Eval<bool> {
+        match self.eval_expr(expr)? {
+            Value::Bool(value) => Ok(value),
+            value => unreachable!("{value:?} is not a `bool`"),
+        }
+    }
+
+    fn eval_int(&mut self, expr: ExprId) -> Eval<i128> {
+        match self.eval_expr(expr)? {
+            Value::Int(int) => Ok(int.value),
+            value => unreachable!("{value:?} is not an integer"),
+        }
+    }
+
+    /// A value of the struct or variant `def`, with `values` for its fields
+    /// in definition order.
+    fn adt(&self, def: DefId, values: Vec<Value>) -> Value {
+        let data = self.hir.defs[def];
+        let name = data.name.expect("structs and variants are named");
+
+        let DefKind::Variant(variant) = data.kind else {
+            let names = self.results.fields[&data.item]
+                .iter()
+                .map(|&(name, _)| name);
+            return Value::Struct(StructValue {
+                name,
+                fields: names.zip(values).collect(),
+            });
+        };
+
+        let fields = match self.ctx.variants[variant].kind {
+            VariantKind::Unit => VariantFields::Unit,
+            VariantKind::Tuple(_) => VariantFields::Tuple(values),
+            VariantKind::Struct(field_defs) => {
+                let field_defs = self.ctx.field_defs.list(field_defs);
+                let names = field_defs
+                    .iter()
+                    .map(|&field| self.ctx.field_defs[field].name);
+                VariantFields::Struct(names.zip(values).collect())
+            }
+        };
+        let parent = data.parent.expect("variants have an enum");
+
+        Value::Enum(EnumValue {
+            name: self.hir.defs[parent].name.expect("enums are named"),
+            variant: name,
+            fields,
+        })
+    }
+
+    /// Checks whether `pat` matches `value`, assigning the locals it binds.
+    fn match_pat(&mut self, pat: PatId, value: &Value) -> Eval<bool> {
+        let hir = self.hir;
+        let data = hir.pats[pat];
+
+        let (def, fields) = match data.kind {
+            PatKind::Wild => return Ok(true),
+            PatKind::Binding(local) => {
+                self.frame().locals.insert(local, value.clone());
+                return Ok(true);
+            }
+            PatKind::Lit(lit) => {
+                let lit = self.eval_expr(lit)?;
+                let equal = ops::binary(BinOp::Eq, value.clone(), lit, data.span)?;
+                return Ok(equal == Value::Bool(true));
+            }
+            PatKind::Adt { def, fields } => (def, fields),
+            PatKind::Error => unreachable!("the module checked without errors"),
+        };
+
+        let values: Vec<&Value> = match value {
+            Value::Struct(value) => value.fields.iter().map(|(_, value)| value).collect(),
+            Value::Enum(value) if Some(value.variant) == hir.defs[def].name => {
+                match &value.fields {
+                    VariantFields::Unit => Vec::new(),
+                    VariantFields::Tuple(values) => values.iter().collect(),
+                    VariantFields::Struct(fields) => {
+                        fields.iter().map(|(_, value)| value).collect()
+                    }
+                }
+            }
+            _ => return Ok(false),
+        };
+
+        for &field in hir.field_pats.list(fields) {
+            let field = hir.field_pats[field];
+            if !self.match_pat(field.pat, values[field.index as usize])? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_common::{Interner, Source};
+
+    use crate::{Error, ErrorKind, Interpreter, Value};
+
+    fn run(content: &str) -> (Result<Value, Error>, String) {
+        let interner = Interner::new();
+        let parsed = unnamed_parser::parse(Source::new(content, "test_file"), &interner);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let mut results =
+            unnamed_typeck::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+        assert!(results.errors.is_empty(), "{:?}", results.errors);
+        let hir = unnamed_hir::lower(&parsed.ctx, &parsed.module, &resolution, &mut results);
+        let instances = unnamed_typeck::monomorphize(
+            &parsed.ctx,
+            &parsed.module,
+            &interner,
+            &resolution,
+            &mut results,
+        );
+        assert!(instances.errors.is_empty(), "{:?}", instances.errors);
+
+        let mut interpreter = Interpreter::new(
+            &parsed.ctx,
+            &parsed.module,
+            &hir,
+            &instances,
+            &mut results,
+            &interner,
+            Vec::new(),
+        );
+        let result = interpreter.run_main();
+        let output = String::from_utf8(interpreter.into_output()).unwrap();
+
+        (result, output)
+    }
+
+    /// Evaluates the statements and expression `block` in `main`, returning
+    /// how its value is printed.
+    fn eval(block: &str) -> String {
+        let (result, output) = run(&format!("func main() {{ print({{ {block} }}); }}"));
+        assert!(result.is_ok(), "{result:?}");
+        output
+    }
+
+    fn eval_error(block: &str) -> ErrorKind {
+        let (result, _) = run(&format!("func main() {{ print({{ {block} }}); }}"));
+        result.unwrap_err().kind().clone()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("1 + 2 * 3 - 8 / 2"), "3");
+        assert_eq!(eval("-7 / 2"), "-3");
+        assert_eq!(eval("6 & 3 | 8 ^ 1"), "11");
+        assert_eq!(eval("1 << 4 >> 2"), "4");
+        assert_eq!(eval("1.5 * 2.0"), "3.0");
+        assert_eq!(eval("\"ab\" + \"cd\""), "abcd");
+        assert_eq!(eval("1 < 2 && 'a' != 'b' || 1 / 0 == 0"), "true");
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        assert_eq!(eval("255u8 - 1"), "254");
+        assert_eq!(eval_error("255u8 + 1"), ErrorKind::IntegerOverflow);
+        assert_eq!(eval_error("0u32 - 1"), ErrorKind::IntegerOverflow);
+        assert_eq!(
+            eval_error("-(-9223372036854775807 - 1)"),
+            ErrorKind::IntegerOverflow
+        );
+        assert_eq!(
+            eval_error("(-9223372036854775807 - 1) / -1"),
+            ErrorKind::IntegerOverflow
+        );
+        assert_eq!(eval_error("1 / 0"), ErrorKind::DivisionByZero);
+    }
+
+    #[test]
+    fn test_typed_values() {
+        assert_eq!(
+            eval("let x: u64 = 18446744073709551615; x"),
+            "18446744073709551615"
+        );
+        assert_eq!(eval("let x: u8 = 200; x + 55"), "255");
+        assert_eq!(
+            eval_error("let x: u8 = 200; x + 100"),
+            ErrorKind::IntegerOverflow
+        );
+        assert_eq!(eval("let x: f32 = 0.1; x + 0.2"), "0.3");
+        assert_eq!(eval("0.1 + 0.2"), "0.30000000000000004");
+        assert_eq!(eval("16777216.0f32 + 1.0"), "16777216.0");
+
+        // Values take the types of the instance they are computed in.
+        let (result, output) = run("
+            func add<T: Add>(a: T, b: T) -> T { a + b }
+
+            func main() {
+                println(add(1.0f32, 1e-8), add(1.0, 1e-8));
+                add(200u8, 100);
+            }
+        ");
+        assert_eq!(output, "1.0 1.00000001\n");
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::IntegerOverflow);
+    }
+
+    #[test]
+    fn test_shifts() {
+        assert_eq!(eval("3u8 << 7"), "128");
+        assert_eq!(eval("1i8 << 7"), "-128");
+        assert_eq!(eval("-16 >> 2"), "-4");
+        assert_eq!(
+            eval_error("1u32 << 32"),
+            ErrorKind::ShiftOverflow {
+                amount: 32,
+                bits: 32
+            }
+        );
+        assert!(matches!(
+            eval_error("1 >> -1"),
+            ErrorKind::ShiftOverflow { amount: -1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let (result, output) = run("
+            func fib(n: i64) -> i64 {
+                if n < 2 { return n; }
+                fib(n - 1) + fib(n - 2)
+            }
+
+            func main() -> i64 {
+                let i = 0;
+                let sum = 0;
+                while i < 5 {
+                    println(i, fib(i));
+                    sum = sum + i;
+                    i = i + 1;
+                }
+                sum
+            }
+        ");
+
+        assert_eq!(result.unwrap(), Value::int(10));
+        assert_eq!(output, "0 0\n1 1\n2 1\n3 2\n4 3\n");
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert_eq!(eval("let x = 1; { let x = 2; x = 3; } x"), "1");
+        assert_eq!(eval("let x; if false { 1 } else { x = 2; x }"), "2");
+        assert_eq!(
+            eval("let i = 0; let n = 0; while i < 3 { let x; x = i; n = n + x; i = i + 1; } n"),
+            "3"
+        );
+    }
+
+    #[test]
+    fn test_structs() {
+        let (result, output) = run("
+            struct Point { x: i64, y: i64 }
+
+            impl Point {
+                func sum(self, z: i64) -> i64 { self.x + self.y + z }
+            }
+
+            func main() -> i64 {
+                let p = Point { y: 2, x: 1 };
+                println(p);
+                p.sum(3) + p.x
+            }
+        ");
+
+        assert_eq!(result.unwrap(), Value::int(7));
+        assert_eq!(output, "Point { x: 1, y: 2 }\n");
+    }
+
+    #[test]
+    fn test_traits() {
+        let (result, output) = run("
+            impl Describe for Point {
+                func name(self) -> str { \"point\" }
+            }
+
+            trait Describe {
+                func name(self) -> str;
+                func describe(self) { println(self.name(), self.size()); }
+                func size(self) -> i64 { 0 }
+                func label(self) -> str { \"trait\" }
+            }
+
+            struct Point { x: i64 }
+
+            impl Point {
+                func label(self) -> str { \"inherent\" }
+            }
+
+            impl Describe for i64 {
+                func name(self) -> str { \"int\" }
+                func size(self) -> i64 { self }
+            }
+
+            func main() {
+                let p = Point { x: 3 };
+                p.describe();
+                7.describe();
+                println(p.label(), 7.label());
+            }
+        ");
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(output, "point 0\nint 7\ninherent trait\n");
+    }
+
+    #[test]
+    fn test_method_dispatch() {
+        let (result, output) = run("
+            trait A { func m(self) -> str; }
+
+            struct P<T> { v: T }
+
+            impl A for P<i64> { func m(self) -> str { \"i64\" } }
+            impl A for P<bool> { func m(self) -> str { \"bool\" } }
+            impl A for u8 { func m(self) -> str { \"u8\" } }
+
+            func describe<T: A>(value: T) -> str { value.m() }
+
+            func main() {
+                let x: u8 = 1;
+                println(P { v: 1 }.m(), P { v: true }.m(), x.m());
+                println(describe(P { v: false }), describe(2u8));
+            }
+        ");
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(output, "i64 bool u8\nbool u8\n");
+    }
+
+    #[test]
+    fn test_operators() {
+        let (result, output) = run("
+            struct V { x: i64, y: i64 }
+
+            impl Add for V {
+                func add(self, other: V) -> V { V { x: self.x + other.x, y: self.y + other.y } }
+            }
+
+            impl Neg for V {
+                func neg(self) -> V { V { x: -self.x, y: -self.y } }
+            }
+
+            impl Eq for V {
+                func eq(self, other: V) -> bool { self.x == other.x }
+            }
+
+            impl Ord for V {
+                func cmp(self, other: V) -> i64 { self.x - other.x }
+            }
+
+            func main() {
+                let a = V { x: 1, y: 2 } + V { x: 3, y: 4 };
+                let b = -a;
+                println(a.x, a.y, b.x, b.y);
+                println(a == V { x: 4, y: 0 }, a != V { x: 4, y: 0 });
+                println(a < b, a >= b, b <= a);
+            }
+        ");
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(output, "4 6 -4 -6\ntrue false\nfalse true true\n");
+    }
+
+    #[test]
+    fn test_enums_and_match() {
+        let (result, output) = run("
+            enum Shape { Empty, Circle(f64), Rect { w: f64, h: f64 } }
+
+            impl Shape {
+                func area(self) -> f64 {
+                    match self {
+                        Shape::Empty => 0.0,
+                        Shape::Circle(r) => 3.0 * r * r,
+                        Shape::Rect { w, h: height } => w * height,
+                    }
+                }
+            }
+
+            impl Eq for Shape {
+                func eq(self, other: Shape) -> bool { self.area() == other.area() }
+            }
+
+            func classify(n: i64) -> str {
+                match n { 0 => \"zero\", -1 => \"minus one\", x if x > 0 => \"positive\", _ => \"negative\" }
+            }
+
+            func main() {
+                let shapes = [Shape::Empty, Shape::Circle(1.0), Shape::Rect { w: 2.0, h: 3.0 }];
+                println(shapes[1], shapes[2], shapes[0].area(), shapes[2].area());
+                println(classify(0), classify(-1), classify(5), classify(-5));
+                println(Shape::Circle(1.0) == Shape::Circle(1.0), Shape::Empty == Shape::Circle(1.0));
+            }
+        ");
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(
+            output,
+            "Shape::Circle(1.0) Shape::Rect { w: 2.0, h: 3.0 } 0.0 6.0\n\
+             zero minus one positive negative\n\
+             true false\n"
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(eval("let a = [1, 2, 3]; a[0] + a[2]"), "4");
+        assert_eq!(eval("[0; 4].len()"), "4");
+        assert_eq!(eval("[1, 2] == [1, 2]"), "true");
+        assert_eq!(
+            eval_error("[1, 2][2]"),
+            ErrorKind::IndexOutOfBounds { index: 2, len: 2 }
+        );
+        assert_eq!(eval_error("[0; -1]"), ErrorKind::InvalidArrayLength(-1));
+    }
+
+    #[test]
+    fn test_generics() {
+        let (result, output) = run("
+            struct Pair<T> { a: T, b: T }
+
+            impl<T> Pair<T> {
+                func first(self) -> T { self.a }
+            }
+
+            func max<T: Ord>(a: T, b: T) -> T { if a > b { a } else { b } }
+
+            func main() {
+                let p = Pair { a: 1u8, b: 2u8 };
+                println(p.first(), max(p.a, p.b), max(\"x\", \"y\"), max(1.5, -1.5));
+            }
+        ");
+
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(output, "1 2 y 1.5\n");
+    }
+
+    #[test]
+    fn test_calls() {
+        let (result, _) = run("func f() { f() } func main() { f() }");
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::StackOverflow);
+
+        let (result, _) = run("func f() {}");
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::MissingMain);
+    }
+}
@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+
+use unnamed_ast::expr::{BinOp, UnaryOp};
+use unnamed_common::Span;
+use unnamed_lexer::IntSuffix;
+
+use crate::{
+    Error, ErrorKind,
+    value::{Float, Int, Value},
+};
+
+/// Applies a primitive binary operator to two evaluated operands, whose
+/// types the type checker made sure it supports. `&&` and `||` are
+/// desugared to `if`s, and operators of other types call the method
+/// overloading them instead.
+///
+/// Integer arithmetic is checked: results outside of the range of the type
+/// are an error, as is dividing by zero or shifting by at least the bit
+/// width. Bits shifted out by `<<` are discarded.
+pub(crate) fn binary(op: BinOp, lhs: Value, rhs: Value, span: Span) -> Result<Value, Error> {
+    let value = match (&lhs, &rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => return int_binary(op, *lhs, *rhs, span),
+        (Value::Float(lhs), Value::Float(rhs)) => match op {
+            BinOp::Add => Value::Float(Float::new(lhs.value + rhs.value, lhs.ty)),
+            BinOp::Sub => Value::Float(Float::new(lhs.value - rhs.value, lhs.ty)),
+            BinOp::Mul => Value::Float(Float::new(lhs.value * rhs.value, lhs.ty)),
+            BinOp::Div => Value::Float(Float::new(lhs.value / rhs.value, lhs.ty)),
+            _ => match lhs.value.partial_cmp(&rhs.value) {
+                Some(ordering) => compare(op, ordering),
+                // Every comparison with NaN is false, except `!=`.
+                None => Value::Bool(op == BinOp::Neq),
+            },
+        },
+        (Value::Bool(l), Value::Bool(r)) => match op {
+            BinOp::BitAnd => Value::Bool(l & r),
+            BinOp::BitOr => Value::Bool(l | r),
+            BinOp::BitXor => Value::Bool(l ^ r),
+            _ => compare(op, l.cmp(r)),
+        },
+        (Value::Char(l), Value::Char(r)) => compare(op, l.cmp(r)),
+        (Value::Str(l), Value::Str(r)) => match op {
+            BinOp::Add => Value::Str(format!("{l}{r}")),
+            _ => compare(op, l.cmp(r)),
+        },
+        // Other values can only be compared for equality, field by field
+        // and element by element.
+        _ => Value::Bool((lhs == rhs) == (op == BinOp::Eq)),
+    };
+
+    Ok(value)
+}
+
+/// Turns the result of an overloading method into the value of `op`, so
+/// `a != b` negates `a.eq(b)` and `a < b` compares `a.cmp(b)` with zero.
+pub(crate) fn overloaded(op: BinOp, result: Value) -> Value {
+    match (op, result) {
+        (BinOp::Neq, Value::Bool(value)) => Value::Bool(!value),
+        (BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq, Value::Int(int)) => {
+            compare(op, int.value.cmp(&0))
+        }
+        (_, result) => result,
+    }
+}
+
+/// Evaluates a comparison operator from the ordering of its operands.
+fn compare(op: BinOp, ordering: Ordering) -> Value {
+    let result = match op {
+        BinOp::Eq => ordering.is_eq(),
+        BinOp::Neq => ordering.is_ne(),
+        BinOp::Lt => ordering.is_lt(),
+        BinOp::LtEq => ordering.is_le(),
+        BinOp::Gt => ordering.is_gt(),
+        BinOp::GtEq => ordering.is_ge(),
+        _ => unreachable!("`{}` is not a comparison", op.as_str()),
+    };
+
+    Value::Bool(result)
+}
+
+fn int_binary(op: BinOp, lhs: Int, rhs: Int, span: Span) -> Result<Value, Error> {
+    if matches!(op, BinOp::BitShl | BinOp::BitShr) {
+        return shift(op, lhs, rhs, span);
+    }
+
+    let (l, r) = (lhs.value, rhs.value);
+    let value = match op {
+        BinOp::Add => l + r,
+        BinOp::Sub => l - r,
+        BinOp::Mul => l
+            .checked_mul(r)
+            .ok_or_else(|| Error::new(span, ErrorKind::IntegerOverflow))?,
+        BinOp::Div if r == 0 => return Err(Error::new(span, ErrorKind::DivisionByZero)),
+        BinOp::Div => l / r,
+        BinOp::BitAnd => l & r,
+        BinOp::BitOr => l | r,
+        BinOp::BitXor => l ^ r,
+        _ => return Ok(compare(op, l.cmp(&r))),
+    };
+
+    checked(Int::new(value, lhs.ty), span)
+}
+
+/// Shifts `lhs` by `rhs`, which can be of any integer type.
+fn shift(op: BinOp, lhs: Int, rhs: Int, span: Span) -> Result<Value, Error> {
+    let bits = lhs.ty.bits();
+    if rhs.value < 0 || rhs.value >= i128::from(bits) {
+        return Err(Error::new(
+            span,
+            ErrorKind::ShiftOverflow {
+                amount: rhs.value,
+                bits,
+            },
+        ));
+    }
+
+    let amount = rhs.value as u32;
+    let value = match op {
+        BinOp::BitShl => wrap((lhs.value as u128) << amount, lhs.ty),
+        _ => lhs.value >> amount,
+    };
+
+    Ok(Value::Int(Int::new(value, lhs.ty)))
+}
+
+/// Truncates `bits` to the width of `ty`, reinterpreting the result as a
+/// signed value for signed types.
+fn wrap(bits: u128, ty: IntSuffix) -> i128 {
+    let width = ty.bits();
+    let value = (bits & ((1 << width) - 1)) as i128;
+
+    if ty.is_signed() && value > ty.max() {
+        value - (1 << width)
+    } else {
+        value
+    }
+}
+
+/// Applies a primitive unary operator to a number.
+pub(crate) fn unary(op: UnaryOp, value: Value, span: Span) -> Result<Value, Error> {
+    match (op, value) {
+        (UnaryOp::Add, value) => Ok(value),
+        (UnaryOp::Sub, Value::Int(int)) => checked(Int::new(-int.value, int.ty), span),
+        (UnaryOp::Sub, Value::Float(float)) => Ok(Value::Float(Float::new(-float.value, float.ty))),
+        (UnaryOp::Sub, value) => unreachable!("cannot negate {value:?}"),
+    }
+}
+
+/// Returns the integer as a value, or an overflow error if it does not fit
+/// in its type.
+pub(crate) fn checked(int: Int, span: Span) -> Result<Value, Error> {
+    if int.value < int.ty.min() || int.value > int.ty.max() {
+        return Err(Error::new(span, ErrorKind::IntegerOverflow));
+    }
+
+    Ok(Value::Int(int))
+}
@@ -0,0 +1,178 @@
+use std::fmt::{self, Display};
+
+use unnamed_common::{Interner, StrId};
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+
+/// An integer together with its type, which is the type checked one of
+/// the expression that computed it.
+#[derive(Debug, Clone, Copy)]
+pub struct Int {
+    pub value: i128,
+    pub ty: IntSuffix,
+}
+
+impl Int {
+    pub fn new(value: i128, ty: IntSuffix) -> Self {
+        Self { value, ty }
+    }
+}
+
+impl PartialEq for Int {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+/// A float together with its type. Values of `f32` are rounded to it, so
+/// that arithmetic on them, done in `f64` and rounded again, gives the same
+/// results as arithmetic in `f32`.
+#[derive(Debug, Clone, Copy)]
+pub struct Float {
+    pub value: f64,
+    pub ty: FloatSuffix,
+}
+
+impl Float {
+    pub fn new(value: f64, ty: FloatSuffix) -> Self {
+        let value = match ty {
+            FloatSuffix::F32 => f64::from(value as f32),
+            FloatSuffix::F64 => value,
+        };
+
+        Self { value, ty }
+    }
+}
+
+impl PartialEq for Float {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    pub name: StrId,
+    /// Field values in the order of the struct definition.
+    pub fields: Vec<(StrId, Value)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValue {
+    pub name: StrId,
+    pub variant: StrId,
+    pub fields: VariantFields,
+}
+
+/// The payload of an enum value, shaped like the declaration of its variant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VariantFields {
+    Unit,
+    Tuple(Vec<Value>),
+    /// Field values in the order of the variant definition.
+    Struct(Vec<(StrId, Value)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Int(Int),
+    Float(Float),
+    Char(char),
+    Str(String),
+    Array(Vec<Value>),
+    Struct(StructValue),
+    Enum(EnumValue),
+}
+
+impl Value {
+    /// An `i64`.
+    pub fn int(value: i128) -> Self {
+        Value::Int(Int::new(value, IntSuffix::I64))
+    }
+
+    /// Returns a wrapper that formats the value the way `print` shows it.
+    pub fn display<'a>(&'a self, interner: &'a Interner) -> DisplayValue<'a> {
+        DisplayValue {
+            value: self,
+            interner,
+        }
+    }
+}
+
+pub struct DisplayValue<'a> {
+    value: &'a Value,
+    interner: &'a Interner,
+}
+
+impl Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Value::Unit => f.write_str("()"),
+            Value::Bool(value) => write!(f, "{value}"),
+            Value::Int(int) => write!(f, "{}", int.value),
+            Value::Float(float) => match float.ty {
+                FloatSuffix::F32 => write!(f, "{:?}", float.value as f32),
+                FloatSuffix::F64 => write!(f, "{:?}", float.value),
+            },
+            Value::Char(value) => write!(f, "{value}"),
+            Value::Str(value) => f.write_str(value),
+            Value::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value.display(self.interner))?;
+                }
+                f.write_str("]")
+            }
+            Value::Struct(value) => {
+                f.write_str(self.interner.get(value.name))?;
+                self.fmt_fields(&value.fields, f)
+            }
+            Value::Enum(value) => {
+                let name = self.interner.get(value.name);
+                write!(f, "{name}::{}", self.interner.get(value.variant))?;
+
+                match &value.fields {
+                    VariantFields::Unit => Ok(()),
+                    VariantFields::Tuple(values) => {
+                        f.write_str("(")?;
+                        for (i, value) in values.iter().enumerate() {
+                            if i > 0 {
+                                f.write_str(", ")?;
+                            }
+                            write!(f, "{}", value.display(self.interner))?;
+                        }
+                        f.write_str(")")
+                    }
+                    VariantFields::Struct(fields) => self.fmt_fields(fields, f),
+                }
+            }
+        }
+    }
+}
+
+impl DisplayValue<'_> {
+    /// Writes ` { name: value, .. }`, or nothing without fields.
+    fn fmt_fields(&self, fields: &[(StrId, Value)], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str(" { ")?;
+        for (i, (name, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(
+                f,
+                "{}: {}",
+                self.interner.get(*name),
+                value.display(self.interner)
+            )?;
+        }
+        f.write_str(" }")
+    }
+}
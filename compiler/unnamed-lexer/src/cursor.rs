@@ -36,6 +36,10 @@ impl<'s> Cursor<'s> {
         self.chars.peek().ok_or(Error::unexpected_eof()).copied()
     }
 
+    pub fn position(&self) -> usize {
+        self.current
+    }
+
     pub fn is_eof(&self) -> bool {
         self.current == self.source.content.len()
     }
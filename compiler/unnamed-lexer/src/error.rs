@@ -1,5 +1,7 @@
+use std::fmt::{self, Display};
+
 use ariadne::{Label, ReportKind};
-use unnamed_common::{IntoReport, Report, Source, Span};
+use unnamed_common::{IntoReport, Report, Source, Span, Spanned};
 
 #[derive(Debug, Clone)]
 pub struct Error {
@@ -20,13 +22,71 @@ impl Error {
         Self::new(span, ErrorKind::UnclosedStringLiteral)
     }
 
+    pub fn unclosed_raw_string_literal(span: Span, hashes: usize) -> Self {
+        Self::new(span, ErrorKind::UnclosedRawStringLiteral { hashes })
+    }
+
     pub fn invalid_escape_sequence(span: Span) -> Self {
         Self::new(span, ErrorKind::InvalidEscapeSequence)
     }
 
+    pub fn unclosed_block_comment(span: Span) -> Self {
+        Self::new(span, ErrorKind::UnclosedBlockComment)
+    }
+
+    pub fn integer_overflow(span: Span) -> Self {
+        Self::new(span, ErrorKind::IntegerOverflow)
+    }
+
+    pub fn float_overflow(span: Span) -> Self {
+        Self::new(span, ErrorKind::FloatOverflow)
+    }
+
+    pub fn empty_integer_literal(span: Span) -> Self {
+        Self::new(span, ErrorKind::EmptyIntegerLiteral)
+    }
+
+    pub fn invalid_literal_suffix(span: Span) -> Self {
+        Self::new(span, ErrorKind::InvalidLiteralSuffix)
+    }
+
+    pub fn out_of_range_hex_escape(span: Span) -> Self {
+        Self::new(span, ErrorKind::OutOfRangeHexEscape)
+    }
+
+    pub fn overlong_unicode_escape(span: Span) -> Self {
+        Self::new(span, ErrorKind::OverlongUnicodeEscape)
+    }
+
+    pub fn invalid_code_point(span: Span) -> Self {
+        Self::new(span, ErrorKind::InvalidCodePoint)
+    }
+
+    pub fn unclosed_char_literal(span: Span) -> Self {
+        Self::new(span, ErrorKind::UnclosedCharLiteral)
+    }
+
+    pub fn empty_char_literal(span: Span) -> Self {
+        Self::new(span, ErrorKind::EmptyCharLiteral)
+    }
+
+    pub fn multiple_chars_in_char_literal(span: Span) -> Self {
+        Self::new(span, ErrorKind::MultipleCharsInCharLiteral)
+    }
+
     pub fn unexpected_eof() -> Self {
         Self::new(Span::ZERO, ErrorKind::UnexpectedEof)
     }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -34,28 +94,92 @@ pub enum ErrorKind {
     InvalidToken,
     UnexpectedEof,
     UnclosedStringLiteral,
+    UnclosedRawStringLiteral { hashes: usize },
     InvalidEscapeSequence,
+    UnclosedBlockComment,
+    IntegerOverflow,
+    FloatOverflow,
+    EmptyIntegerLiteral,
+    InvalidLiteralSuffix,
+    OutOfRangeHexEscape,
+    OverlongUnicodeEscape,
+    InvalidCodePoint,
+    UnclosedCharLiteral,
+    EmptyCharLiteral,
+    MultipleCharsInCharLiteral,
 }
 
-impl IntoReport for Error {
-    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
-        let message = match self.kind {
+impl ErrorKind {
+    pub fn message(&self) -> &'static str {
+        match self {
             ErrorKind::InvalidToken => "invalid token",
             ErrorKind::UnexpectedEof => "unexpected eof",
             ErrorKind::UnclosedStringLiteral => "unclosed string literal",
+            ErrorKind::UnclosedRawStringLiteral { .. } => "unclosed raw string literal",
             ErrorKind::InvalidEscapeSequence => "invalid escape sequence",
-        };
+            ErrorKind::UnclosedBlockComment => "unclosed block comment",
+            ErrorKind::IntegerOverflow => "integer literal is too large",
+            ErrorKind::FloatOverflow => "float literal is out of range",
+            ErrorKind::EmptyIntegerLiteral => "no valid digits found for number",
+            ErrorKind::InvalidLiteralSuffix => "invalid suffix for number literal",
+            ErrorKind::OutOfRangeHexEscape => "out of range hex escape",
+            ErrorKind::OverlongUnicodeEscape => "overlong unicode escape",
+            ErrorKind::InvalidCodePoint => "invalid unicode character escape",
+            ErrorKind::UnclosedCharLiteral => "unclosed character literal",
+            ErrorKind::EmptyCharLiteral => "empty character literal",
+            ErrorKind::MultipleCharsInCharLiteral => {
+                "character literal may only contain one code point"
+            }
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind.message())
+    }
+}
+
+impl IntoReport for Error {
+    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
+        let message = self.kind.message();
 
         let label_message = match self.kind {
-            ErrorKind::UnclosedStringLiteral => Some("this string literal is not closed"),
+            ErrorKind::UnclosedStringLiteral | ErrorKind::UnclosedRawStringLiteral { .. } => {
+                Some("this string literal is not closed")
+            }
+            ErrorKind::UnclosedBlockComment => Some("this comment is not closed"),
+            ErrorKind::IntegerOverflow => Some("this value does not fit in 64 bits"),
+            ErrorKind::FloatOverflow => Some("this value is too large for its float type"),
+            ErrorKind::OutOfRangeHexEscape => {
+                Some("must be a character in the range [\\x00-\\x7f]")
+            }
+            ErrorKind::OverlongUnicodeEscape => Some("must have at most 6 hex digits"),
+            ErrorKind::InvalidCodePoint => {
+                Some("must be a unicode scalar value, not a surrogate or above 10FFFF")
+            }
+            ErrorKind::UnclosedCharLiteral => Some("this character literal is not closed"),
+            ErrorKind::MultipleCharsInCharLiteral => Some("use a string literal instead"),
+            ErrorKind::InvalidLiteralSuffix => Some(
+                "valid suffixes are `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, \
+                 and `f32` or `f64` for decimal literals",
+            ),
             _ => None,
         };
 
-        Report::build(ReportKind::Error, source.span(self.span))
+        let mut report = Report::build(ReportKind::Error, source.span(self.span))
             .with_message(message)
             .with_label(
                 Label::new(source.span(self.span)).with_message(label_message.unwrap_or(message)),
-            )
-            .finish()
+            );
+
+        if let ErrorKind::UnclosedRawStringLiteral { hashes } = self.kind {
+            report = report.with_help(format!(
+                "this raw string must be closed with `\"{}`",
+                "#".repeat(hashes)
+            ));
+        }
+
+        report.finish()
     }
 }
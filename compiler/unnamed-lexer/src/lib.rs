@@ -3,21 +3,39 @@ pub mod error;
 pub mod token;
 
 use unicode_xid::UnicodeXID;
-use unnamed_common::Source;
+use unnamed_common::{Source, Span};
 
 pub use cursor::Cursor;
 pub use error::{Error, ErrorKind};
-pub use token::{Base, Token, TokenData, TokenKind};
+pub use token::{Base, FloatSuffix, IntSuffix, Suffix, Token, TokenData, TokenKind};
 
 #[derive(Debug, Clone)]
 pub struct Lexer<'s> {
     cursor: Cursor<'s>,
+    keep_comments: bool,
+    /// Errors in tokens that were still produced, like an invalid escape in
+    /// a string or an unclosed raw string running to the end of the source.
+    /// They are returned after the token.
+    pending_errors: Vec<Error>,
 }
 
 impl<'s> Lexer<'s> {
     pub fn new(source: Source<'s>) -> Self {
         Self {
             cursor: Cursor::new(source),
+            keep_comments: false,
+            pending_errors: Vec::new(),
+        }
+    }
+
+    /// Creates a lexer that also produces `//` and `/* */` comments as
+    /// tokens, so that together with the spans of the tokens no part of the
+    /// source except whitespace is lost.
+    pub fn with_comments(source: Source<'s>) -> Self {
+        Self {
+            cursor: Cursor::new(source),
+            keep_comments: true,
+            pending_errors: Vec::new(),
         }
     }
 
@@ -39,6 +57,26 @@ impl<'s> Lexer<'s> {
         Ok(char == '"')
     }
 
+    /// Checks for `r"` or `r#...#"`, the start of a raw string literal.
+    pub fn is_raw_string_start(&self) -> bool {
+        if self.cursor.lookahead(0) != Some('r') {
+            return false;
+        }
+
+        let mut n = 1;
+        while self.cursor.lookahead(n) == Some('#') {
+            n += 1;
+        }
+
+        self.cursor.lookahead(n) == Some('"')
+    }
+
+    pub fn is_char_start(&mut self) -> Result<bool, Error> {
+        let char = self.cursor.peek()?;
+
+        Ok(char == '\'')
+    }
+
     pub fn is_ident_start(&mut self) -> Result<bool, Error> {
         let char = self.cursor.peek()?;
 
@@ -60,49 +98,330 @@ impl<'s> Lexer<'s> {
         Ok(())
     }
 
-    pub fn parse_number(slice: &'s str, base: Base) -> u64 {
+    pub fn is_doc_comment_start(&self) -> bool {
+        let is_outer =
+            self.cursor.lookahead(2) == Some('/') && self.cursor.lookahead(3) != Some('/');
+        let is_inner = self.cursor.lookahead(2) == Some('!');
+
+        self.cursor.lookahead(0) == Some('/')
+            && self.cursor.lookahead(1) == Some('/')
+            && (is_outer || is_inner)
+    }
+
+    pub fn is_comment_start(&self) -> bool {
+        match (self.cursor.lookahead(0), self.cursor.lookahead(1)) {
+            (Some('/'), Some('/')) => !self.is_doc_comment_start(),
+            (Some('/'), Some('*')) => true,
+            _ => false,
+        }
+    }
+
+    /// Skips whitespace and comments, leaving doc comments to be lexed as
+    /// tokens. Comments are left as well when they are kept as tokens.
+    pub fn skip_trivia(&mut self) -> Result<(), Error> {
+        loop {
+            self.skip_whitespaces()?;
+
+            if self.keep_comments || !self.is_comment_start() {
+                return Ok(());
+            }
+
+            self.comment()?;
+        }
+    }
+
+    /// Lexes a `//` or `/* */` comment.
+    pub fn comment(&mut self) -> Result<Token<'s>, Error> {
+        if self.cursor.lookahead(1) == Some('*') {
+            self.block_comment()
+        } else {
+            self.line_comment()
+        }
+    }
+
+    fn line_comment(&mut self) -> Result<Token<'s>, Error> {
+        while !self.cursor.is_eof() && self.cursor.peek()? != '\n' {
+            self.cursor.next_char()?;
+        }
+        let (slice, span) = self.cursor.consume();
+
+        Ok(Token::new(span, slice, TokenKind::LineComment))
+    }
+
+    /// Lexes a `/* */` comment, which may contain nested block comments.
+    fn block_comment(&mut self) -> Result<Token<'s>, Error> {
+        let start = self.cursor.position();
+        self.cursor.skip(2)?;
+        let opener_span = Span::new(start, start + 2);
+
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.cursor.lookahead(0), self.cursor.lookahead(1)) {
+                (None, _) => {
+                    self.cursor.consume();
+                    return Err(Error::unclosed_block_comment(opener_span));
+                }
+                (Some('/'), Some('*')) => {
+                    self.cursor.skip(2)?;
+                    depth += 1;
+                }
+                (Some('*'), Some('/')) => {
+                    self.cursor.skip(2)?;
+                    depth -= 1;
+                }
+                _ => {
+                    self.cursor.next_char()?;
+                }
+            }
+        }
+        let (slice, span) = self.cursor.consume();
+
+        Ok(Token::new(span, slice, TokenKind::BlockComment))
+    }
+
+    /// Lexes a `///` or `//!` comment, keeping its text without the marker
+    /// and the first space after it.
+    pub fn doc_comment(&mut self) -> Result<Token<'s>, Error> {
+        self.cursor.skip(2)?;
+        let kind = match self.cursor.next_char()? {
+            '!' => TokenKind::InnerDocComment,
+            _ => TokenKind::DocComment,
+        };
+
+        while !self.cursor.is_eof() && self.cursor.peek()? != '\n' {
+            self.cursor.next_char()?;
+        }
+
+        let (slice, span) = self.cursor.consume();
+        let text = &slice[3..];
+        let text = text
+            .strip_prefix(' ')
+            .unwrap_or(text)
+            .trim_end_matches('\r');
+
+        Ok(Token::new(span, slice, kind).with_data(TokenData::String(text.to_owned())))
+    }
+
+    /// Parses the digits of an integer literal, returning `None` when the
+    /// value does not fit in 64 bits.
+    pub fn parse_number(slice: &'s str, base: Base) -> Option<u64> {
         slice
             .chars()
             .filter(|char| *char != '_')
-            .map(|digit| digit.to_digit(base.radix()).expect("invalid digit") as u64)
-            .reduce(|acc, digit| acc * (base.radix() as u64) + digit)
-            .unwrap_or_default()
+            .try_fold(0u64, |acc, char| {
+                let digit = char.to_digit(base.radix())?;
+
+                acc.checked_mul(base.radix() as u64)?
+                    .checked_add(digit as u64)
+            })
+    }
+
+    fn eat_digits(&mut self, base: Base) -> Result<(), Error> {
+        while !self.cursor.is_eof() && self.is_number(base)? {
+            self.cursor.next_char()?;
+        }
+
+        Ok(())
+    }
+
+    /// A `.` continues a number only when a digit follows it, so `1.foo()`
+    /// is a method call on `1` rather than the float `1.` followed by `foo`.
+    pub fn is_fraction_start(&self) -> bool {
+        self.cursor.lookahead(0) == Some('.')
+            && self
+                .cursor
+                .lookahead(1)
+                .is_some_and(|char| char.is_ascii_digit())
+    }
+
+    pub fn is_exponent_start(&self) -> bool {
+        let digit_at = match (self.cursor.lookahead(0), self.cursor.lookahead(1)) {
+            (Some('e' | 'E'), Some('+' | '-')) => 2,
+            (Some('e' | 'E'), _) => 1,
+            _ => return false,
+        };
+
+        self.cursor
+            .lookahead(digit_at)
+            .is_some_and(|char| char.is_ascii_digit())
     }
 
     pub fn number(&mut self) -> Result<Token<'s>, Error> {
-        let (base_span, base) = if self.cursor.peek()? == '0' {
+        let base = match (self.cursor.lookahead(0), self.cursor.lookahead(1)) {
+            (Some('0'), Some('x' | 'X')) => Base::Hexadecimal,
+            (Some('0'), Some('o' | 'O')) => Base::Octal,
+            (Some('0'), Some('b' | 'B')) => Base::Binary,
+            _ => Base::Decimal,
+        };
+
+        let start = self.cursor.span().start;
+        if base != Base::Decimal {
+            self.cursor.skip(2)?;
+        }
+
+        let digits_start = self.cursor.span().end - start;
+        self.eat_digits(base)?;
+
+        let mut is_float = false;
+        if base == Base::Decimal && self.is_fraction_start() {
             self.cursor.next_char()?;
+            self.eat_digits(base)?;
+            is_float = true;
+        }
 
-            let base = match self.cursor.peek()? {
-                'x' | 'X' => Base::Hexadecimal,
-                'o' | 'O' => Base::Octal,
-                'b' | 'B' => Base::Binary,
-                _ => {
-                    let (slice, span) = self.cursor.consume();
-                    return Ok(
-                        Token::new(span, slice, TokenKind::Int).with_data(TokenData::Number(0))
-                    );
-                }
-            };
+        if base == Base::Decimal && self.is_exponent_start() {
+            if matches!(self.cursor.lookahead(1), Some('+' | '-')) {
+                self.cursor.skip(2)?;
+            } else {
+                self.cursor.next_char()?;
+            }
+
+            self.eat_digits(base)?;
+            is_float = true;
+        }
+
+        let digits_end = self.cursor.span().end - start;
+        while !self.cursor.is_eof() && self.is_ident_continue()? {
             self.cursor.next_char()?;
-            let (.., span) = self.cursor.consume();
+        }
 
-            (span, base)
-        } else {
-            (self.cursor.span(), Base::Decimal)
+        let (slice, span) = self.cursor.consume();
+        let digits = &slice[digits_start..digits_end];
+        let suffix_span = Span::new(span.start + digits_end, span.end);
+
+        let suffix = match &slice[digits_end..] {
+            "" => None,
+            suffix => {
+                Some(Suffix::parse(suffix).ok_or(Error::invalid_literal_suffix(suffix_span))?)
+            }
         };
 
-        while !self.cursor.is_eof() && self.is_number(base)? {
+        let float_suffix = match suffix {
+            None => None,
+            Some(Suffix::Float(suffix)) if base == Base::Decimal => Some(suffix),
+            Some(Suffix::Int(..)) if !is_float => None,
+            Some(..) => return Err(Error::invalid_literal_suffix(suffix_span)),
+        };
+
+        if is_float || float_suffix.is_some() {
+            let value: f64 = digits
+                .replace('_', "")
+                .parse()
+                .expect("valid float literal");
+
+            // Parsing rounds values beyond the largest finite float to
+            // infinity instead of failing.
+            let overflows = match float_suffix {
+                Some(FloatSuffix::F32) => (value as f32).is_infinite(),
+                _ => value.is_infinite(),
+            };
+            if overflows {
+                return Err(Error::float_overflow(span));
+            }
+
+            return Ok(Token::new(span, slice, TokenKind::Float)
+                .with_data(TokenData::Float(value, float_suffix)));
+        }
+
+        if digits.chars().all(|char| char == '_') {
+            return Err(Error::empty_integer_literal(span));
+        }
+
+        let value = Self::parse_number(digits, base).ok_or(Error::integer_overflow(span))?;
+        let int_suffix = match suffix {
+            Some(Suffix::Int(suffix)) => Some(suffix),
+            _ => None,
+        };
+
+        Ok(Token::new(span, slice, TokenKind::Int).with_data(TokenData::Number(value, int_suffix)))
+    }
+
+    /// Lexes the escape sequence after a `\\` that was just consumed. The
+    /// returned errors span the whole escape, starting at the backslash.
+    pub fn escape(&mut self) -> Result<char, Error> {
+        let start = self.cursor.position() - 1;
+        let span = |cursor: &Cursor| Span::new(start, cursor.position());
+
+        let char = match self.cursor.next_char()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '\'' => '\'',
+            '"' => '"',
+            'x' => {
+                let mut value = 0;
+                for _ in 0..2 {
+                    let Some(digit) = self.cursor.lookahead(0).and_then(|char| char.to_digit(16))
+                    else {
+                        return Err(Error::invalid_escape_sequence(span(&self.cursor)));
+                    };
+
+                    self.cursor.next_char()?;
+                    value = value * 16 + digit;
+                }
+
+                if value > 0x7f {
+                    return Err(Error::out_of_range_hex_escape(span(&self.cursor)));
+                }
+
+                char::from_u32(value).expect("ascii character")
+            }
+            'u' => return self.unicode_escape(start),
+            _ => return Err(Error::invalid_escape_sequence(span(&self.cursor))),
+        };
+
+        Ok(char)
+    }
+
+    /// Lexes an escape sequence in a string or character literal. An invalid
+    /// one becomes U+FFFD and its error is reported after the literal, so
+    /// that the literal is still a token and lexing resumes after it.
+    fn escape_or_replacement(&mut self) -> char {
+        self.escape().unwrap_or_else(|error| {
+            self.pending_errors.push(error);
+            char::REPLACEMENT_CHARACTER
+        })
+    }
+
+    /// Lexes the `{...}` part of a `\\u{...}` escape starting at `start`.
+    fn unicode_escape(&mut self, start: usize) -> Result<char, Error> {
+        let span = |cursor: &Cursor| Span::new(start, cursor.position());
+
+        if self.cursor.lookahead(0) != Some('{') {
+            return Err(Error::invalid_escape_sequence(span(&self.cursor)));
+        }
+        self.cursor.next_char()?;
+
+        let mut value = 0u32;
+        let mut digits = 0;
+        loop {
+            match self.cursor.lookahead(0) {
+                Some('}') => break,
+                Some('_') => {}
+                Some(char) if char.is_ascii_hexdigit() => {
+                    digits += 1;
+                    value = value
+                        .saturating_mul(16)
+                        .saturating_add(char.to_digit(16).expect("hex digit"));
+                }
+                _ => return Err(Error::invalid_escape_sequence(span(&self.cursor))),
+            }
+
             self.cursor.next_char()?;
         }
+        self.cursor.next_char()?;
 
-        let (slice, span) = self.cursor.consume();
-        let span = base_span + span;
+        if digits == 0 {
+            return Err(Error::invalid_escape_sequence(span(&self.cursor)));
+        }
 
-        let value = Self::parse_number(slice, base);
+        if digits > 6 {
+            return Err(Error::overlong_unicode_escape(span(&self.cursor)));
+        }
 
-        Ok(Token::new(span, self.cursor.slice_at(span), TokenKind::Int)
-            .with_data(TokenData::Number(value)))
+        char::from_u32(value).ok_or_else(|| Error::invalid_code_point(span(&self.cursor)))
     }
 
     pub fn string(&mut self) -> Result<Token<'s>, Error> {
@@ -113,14 +432,10 @@ impl<'s> Lexer<'s> {
         while !self.cursor.is_eof() && self.cursor.peek()? != '"' {
             let char = self.cursor.next_char()?;
 
-            if char == '\\' {
-                match self.cursor.next_char()? {
-                    'n' => content.push('\n'),
-                    't' => content.push('\t'),
-                    '\\' => content.push('\\'),
-                    '"' => content.push('"'),
-                    _ => return Err(Error::invalid_escape_sequence(self.cursor.current_span()?)),
-                }
+            if char == '\\' && self.is_line_continuation() {
+                self.skip_line_continuation()?;
+            } else if char == '\\' {
+                content.push(self.escape_or_replacement());
             } else {
                 content.push(char);
             }
@@ -131,10 +446,108 @@ impl<'s> Lexer<'s> {
             .map_err(|_| Error::unclosed_string_literal(left_delimiter_span))?;
 
         let (slice, span) = self.cursor.consume();
+        Ok(Token::new(span, slice, TokenKind::Str).with_data(TokenData::String(content)))
+    }
+
+    /// Checks whether the `\\` that was just consumed ends the line.
+    fn is_line_continuation(&self) -> bool {
+        match self.cursor.lookahead(0) {
+            Some('\n') => true,
+            Some('\r') => self.cursor.lookahead(1) == Some('\n'),
+            _ => false,
+        }
+    }
+
+    /// Skips the line break after a `\\` together with the indentation of the
+    /// following lines, so that long strings can be split over several lines.
+    fn skip_line_continuation(&mut self) -> Result<(), Error> {
+        while self
+            .cursor
+            .lookahead(0)
+            .is_some_and(|char| char.is_whitespace())
+        {
+            self.cursor.next_char()?;
+        }
+
+        Ok(())
+    }
+
+    /// Lexes `r"..."`, or `r#"..."#` with any number of `#`, where the closing
+    /// quote must be followed by as many `#` as the opening one. Escapes are
+    /// not processed.
+    pub fn raw_string(&mut self) -> Result<Token<'s>, Error> {
+        self.cursor.next_char()?;
+
+        let mut hashes = 0;
+        while self.cursor.next_char()? == '#' {
+            hashes += 1;
+        }
+        let left_delimiter_span = self.cursor.span();
+
+        let content_start = self.cursor.position();
+        let content_end = loop {
+            // An unclosed string still becomes a token holding the rest of
+            // the source, so that the parser does not report the end of
+            // the source as well.
+            if self.cursor.is_eof() {
+                self.pending_errors.push(Error::unclosed_raw_string_literal(
+                    left_delimiter_span,
+                    hashes,
+                ));
+                break self.cursor.position();
+            }
+
+            let content_end = self.cursor.position();
+            if self.cursor.next_char()? == '"'
+                && (0..hashes).all(|n| self.cursor.lookahead(n) == Some('#'))
+            {
+                self.cursor.skip(hashes)?;
+                break content_end;
+            }
+        };
+
+        let content = self
+            .cursor
+            .slice_at(Span::new(content_start, content_end))
+            .to_owned();
+        let (slice, span) = self.cursor.consume();
 
         Ok(Token::new(span, slice, TokenKind::Str).with_data(TokenData::String(content)))
     }
 
+    pub fn char(&mut self) -> Result<Token<'s>, Error> {
+        self.cursor.next_char()?;
+        let left_delimiter_span = self.cursor.span();
+
+        let mut chars = Vec::new();
+        while let Some(char) = self.cursor.lookahead(0) {
+            if char == '\'' || char == '\n' {
+                break;
+            }
+
+            self.cursor.next_char()?;
+            if char == '\\' {
+                chars.push(self.escape_or_replacement());
+            } else {
+                chars.push(char);
+            }
+        }
+
+        if self.cursor.lookahead(0) != Some('\'') {
+            self.cursor.consume();
+            return Err(Error::unclosed_char_literal(left_delimiter_span));
+        }
+        self.cursor.next_char()?;
+
+        let (slice, span) = self.cursor.consume();
+
+        match chars[..] {
+            [char] => Ok(Token::new(span, slice, TokenKind::Char).with_data(TokenData::Char(char))),
+            [] => Err(Error::empty_char_literal(span)),
+            _ => Err(Error::multiple_chars_in_char_literal(span)),
+        }
+    }
+
     pub fn ident(&mut self) -> Result<Token<'s>, Error> {
         self.cursor.next_char()?;
         while !self.cursor.is_eof() && self.is_ident_continue()? {
@@ -147,8 +560,12 @@ impl<'s> Lexer<'s> {
             "func" => TokenKind::FuncKw,
             "trait" => TokenKind::TraitKw,
             "impl" => TokenKind::ImplKw,
+            "struct" => TokenKind::StructKw,
+            "enum" => TokenKind::EnumKw,
+            "match" => TokenKind::MatchKw,
             "while" => TokenKind::WhileKw,
             "let" => TokenKind::LetKw,
+            "for" => TokenKind::ForKw,
             "if" => TokenKind::IfKw,
             "else" => TokenKind::ElseKw,
             "return" => TokenKind::ReturnKw,
@@ -164,11 +581,13 @@ impl<'s> Lexer<'s> {
     pub fn operator(&mut self) -> Result<Token<'s>, Error> {
         let (kind, chars) = match (self.cursor.peek()?, self.cursor.lookahead(1)) {
             ('+', _) => (TokenKind::Add, 1),
+            ('-', Some('>')) => (TokenKind::Arrow, 2),
             ('-', _) => (TokenKind::Sub, 1),
             ('*', _) => (TokenKind::Mul, 1),
             ('/', _) => (TokenKind::Div, 1),
 
             ('=', Some('=')) => (TokenKind::Eq, 2),
+            ('=', Some('>')) => (TokenKind::FatArrow, 2),
             ('=', _) => (TokenKind::Asgmt, 1),
 
             ('!', Some('=')) => (TokenKind::Neq, 2),
@@ -189,6 +608,7 @@ impl<'s> Lexer<'s> {
             ('^', _) => (TokenKind::BitXor, 1),
 
             (',', _) => (TokenKind::Comma, 1),
+            ('.', _) => (TokenKind::Dot, 1),
             ('(', _) => (TokenKind::LeftParen, 1),
             (')', _) => (TokenKind::RightParen, 1),
             ('{', _) => (TokenKind::LeftBrace, 1),
@@ -215,10 +635,18 @@ impl<'s> Lexer<'s> {
     }
 
     pub fn token(&mut self) -> Result<Token<'s>, Error> {
-        if self.is_number_start()? {
+        if self.is_doc_comment_start() {
+            self.doc_comment()
+        } else if self.is_comment_start() {
+            self.comment()
+        } else if self.is_number_start()? {
             self.number()
+        } else if self.is_raw_string_start() {
+            self.raw_string()
         } else if self.is_string_start()? {
             self.string()
+        } else if self.is_char_start()? {
+            self.char()
         } else if self.is_ident_start()? {
             self.ident()
         } else {
@@ -227,7 +655,11 @@ impl<'s> Lexer<'s> {
     }
 
     pub fn next_token(&mut self) -> Result<Option<Token<'s>>, Error> {
-        self.skip_whitespaces()?;
+        if !self.pending_errors.is_empty() {
+            return Err(self.pending_errors.remove(0));
+        }
+
+        self.skip_trivia()?;
 
         if self.cursor.is_eof() {
             Ok(None)
@@ -244,3 +676,398 @@ impl<'s> Iterator for Lexer<'s> {
         self.next_token().transpose()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use unnamed_common::{Source, Span, Spanned};
+
+    use crate::{Error, ErrorKind, FloatSuffix, IntSuffix, Lexer, Token, TokenKind};
+
+    fn kinds(content: &str) -> Vec<TokenKind> {
+        Lexer::new(Source::new(content, "test_file"))
+            .map(|token| token.unwrap().kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let floats = |content: &str| {
+            Lexer::new(Source::new(content, "test_file"))
+                .map(|token| token.unwrap())
+                .filter(|token| token.kind == TokenKind::Float)
+                .map(|token| token.float())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            floats("1.5 0.25 1e3 2.5E-2 1_000.000_1 3e+2 0e0"),
+            [1.5, 0.25, 1e3, 2.5e-2, 1000.0001, 3e2, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_dot_after_integer() {
+        assert_eq!(
+            kinds("1.foo() 2.e3 0x1.5"),
+            [
+                TokenKind::Int,
+                TokenKind::Dot,
+                TokenKind::Ident,
+                TokenKind::LeftParen,
+                TokenKind::RightParen,
+                TokenKind::Int,
+                TokenKind::Dot,
+                TokenKind::Ident,
+                TokenKind::Int,
+                TokenKind::Dot,
+                TokenKind::Int,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zero_at_end_of_input() {
+        let tokens = Lexer::new(Source::new("0", "test_file"))
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].number(), 0);
+    }
+
+    #[test]
+    fn test_int_suffixes() {
+        let tokens = Lexer::new(Source::new(
+            "10u8 0xffi32 7 1_000_i64 2f32 1.5f64",
+            "test_file",
+        ))
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+        assert_eq!(tokens[0].number(), 10);
+        assert_eq!(tokens[0].int_suffix(), Some(IntSuffix::U8));
+        assert_eq!(tokens[1].number(), 0xff);
+        assert_eq!(tokens[1].int_suffix(), Some(IntSuffix::I32));
+        assert_eq!(tokens[2].int_suffix(), None);
+        assert_eq!(tokens[3].number(), 1000);
+        assert_eq!(tokens[3].int_suffix(), Some(IntSuffix::I64));
+
+        assert_eq!(tokens[4].kind, TokenKind::Float);
+        assert_eq!(tokens[4].float(), 2.0);
+        assert_eq!(tokens[4].float_suffix(), Some(FloatSuffix::F32));
+        assert_eq!(tokens[5].float_suffix(), Some(FloatSuffix::F64));
+    }
+
+    #[test]
+    fn test_invalid_suffixes() {
+        let errors = Lexer::new(Source::new("1abc 1.5u8 0o7f32 0b1f32 1e", "test_file"))
+            .map(|token| token.unwrap_err())
+            .collect::<Vec<_>>();
+
+        assert_eq!(errors.len(), 5);
+        assert!(
+            errors
+                .iter()
+                .all(|error| matches!(error.kind(), ErrorKind::InvalidLiteralSuffix))
+        );
+        assert_eq!(errors[0].span(), Span::new(1, 4));
+        assert_eq!(errors[4].span(), Span::new(26, 27));
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let mut lexer = Lexer::new(Source::new(
+            "18446744073709551615 18446744073709551616 0x1_0000_0000_0000_0000",
+            "test_file",
+        ));
+
+        assert_eq!(lexer.next().unwrap().unwrap().number(), u64::MAX);
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::IntegerOverflow));
+        assert_eq!(error.span(), Span::new(21, 41));
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::IntegerOverflow));
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_float_overflow() {
+        let mut lexer = Lexer::new(Source::new("1e308 1e400 3e38f32 1e39f32", "test_file"));
+
+        assert_eq!(lexer.next().unwrap().unwrap().float(), 1e308);
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::FloatOverflow));
+        assert_eq!(error.span(), Span::new(6, 11));
+
+        assert_eq!(lexer.next().unwrap().unwrap().float(), 3e38);
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::FloatOverflow));
+        assert_eq!(error.span(), Span::new(20, 27));
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_empty_integer_literal() {
+        let mut lexer = Lexer::new(Source::new("0x 0b__u8 0o7", "test_file"));
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::EmptyIntegerLiteral));
+        assert_eq!(error.span(), Span::new(0, 2));
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::EmptyIntegerLiteral));
+        assert_eq!(error.span(), Span::new(3, 9));
+
+        assert_eq!(lexer.next().unwrap().unwrap().number(), 7);
+    }
+
+    fn lex_one(content: &str) -> Result<Token<'_>, Error> {
+        let mut lexer = Lexer::new(Source::new(content, "test_file"));
+        let token = lexer.next().unwrap();
+        assert!(lexer.next().is_none(), "more than one token in {content:?}");
+
+        token
+    }
+
+    #[test]
+    fn test_char_literals() {
+        let chars = Lexer::new(Source::new(
+            r"'a' 'é' '\n' '\'' '\x41' '\u{1F680}' '\u{10_FFFF}' '\0' '\\'",
+            "test_file",
+        ))
+        .map(|token| token.unwrap().char())
+        .collect::<String>();
+
+        assert_eq!(chars, "aé\n'A🚀\u{10FFFF}\0\\");
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let token = lex_one(r#""\r\0\'\"\x7f\u{e9}\t""#).unwrap();
+        assert_eq!(token.string(), "\r\0'\"\x7f\u{e9}\t");
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        // The literal is still lexed, followed by the error of its escape.
+        let error = |content: &str| {
+            let mut lexer = Lexer::new(Source::new(content, "test_file"));
+            let token = lexer.next().unwrap().unwrap();
+            assert!(matches!(token.kind, TokenKind::Str | TokenKind::Char));
+            assert_eq!(token.span, Span::new(0, content.len()));
+
+            let error = lexer.next().unwrap().unwrap_err();
+            assert!(lexer.next().is_none(), "more than one error in {content:?}");
+            (error.kind().clone(), error.span())
+        };
+
+        assert!(matches!(
+            error(r#""ab\x80""#),
+            (ErrorKind::OutOfRangeHexEscape, span) if span == Span::new(3, 7)
+        ));
+        assert!(matches!(
+            error(r#""\xZ1""#),
+            (ErrorKind::InvalidEscapeSequence, span) if span == Span::new(1, 3)
+        ));
+        assert!(matches!(
+            error(r"'\u{1234567}'"),
+            (ErrorKind::OverlongUnicodeEscape, span) if span == Span::new(1, 12)
+        ));
+        assert!(matches!(
+            error(r"'\u{D800}'"),
+            (ErrorKind::InvalidCodePoint, span) if span == Span::new(1, 9)
+        ));
+        assert!(matches!(
+            error(r"'\u{110000}'"),
+            (ErrorKind::InvalidCodePoint, ..)
+        ));
+        assert!(matches!(
+            error(r#""\u{}""#),
+            (ErrorKind::InvalidEscapeSequence, ..)
+        ));
+        assert!(matches!(
+            error(r#""\u12""#),
+            (ErrorKind::InvalidEscapeSequence, span) if span == Span::new(1, 3)
+        ));
+        assert!(matches!(
+            error(r#""a\q""#),
+            (ErrorKind::InvalidEscapeSequence, span) if span == Span::new(2, 4)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_escape_replaced() {
+        let mut lexer = Lexer::new(Source::new(r#""a\qb\x80" '\u{110000}' x"#, "test_file"));
+
+        assert_eq!(
+            lexer.next().unwrap().unwrap().string(),
+            "a\u{FFFD}b\u{FFFD}"
+        );
+        let errors = [lexer.next(), lexer.next()].map(|error| error.unwrap().unwrap_err());
+        assert!(matches!(errors[0].kind(), ErrorKind::InvalidEscapeSequence));
+        assert!(matches!(errors[1].kind(), ErrorKind::OutOfRangeHexEscape));
+
+        assert_eq!(lexer.next().unwrap().unwrap().char(), '\u{FFFD}');
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::InvalidCodePoint));
+
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Ident);
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        assert_eq!(lex_one(r#"r"\d+\.\d*""#).unwrap().string(), r"\d+\.\d*");
+        assert_eq!(
+            lex_one(r###"r##"a "# b"##"###).unwrap().string(),
+            r##"a "# b"##
+        );
+        assert_eq!(lex_one("r\"C:\\path\n\"").unwrap().string(), "C:\\path\n");
+
+        let tokens = Lexer::new(Source::new("r #r", "test_file"))
+            .map(|token| token.map(|token| token.kind))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            tokens[..],
+            [Ok(TokenKind::Ident), Err(_), Ok(TokenKind::Ident)]
+        ));
+    }
+
+    #[test]
+    fn test_unclosed_raw_string() {
+        let mut lexer = Lexer::new(Source::new(r###"r##"abc"# "###, "test_file"));
+
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.kind, TokenKind::Str);
+        assert_eq!(token.string(), "abc\"# ");
+        assert_eq!(token.span, Span::new(0, 10));
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(lexer.next().is_none());
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnclosedRawStringLiteral { hashes: 2 }
+        ));
+        assert_eq!(error.span(), Span::new(0, 4));
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        let token = lex_one("\"one \\\n     two \\\r\n\tthree\"").unwrap();
+        assert_eq!(token.string(), "one two three");
+
+        let token = lex_one("\"a\\\n\n  b\"").unwrap();
+        assert_eq!(token.string(), "ab");
+    }
+
+    #[test]
+    fn test_invalid_char_literals() {
+        assert!(matches!(
+            lex_one("''").unwrap_err().kind(),
+            ErrorKind::EmptyCharLiteral
+        ));
+        assert!(matches!(
+            lex_one("'ab'").unwrap_err().kind(),
+            ErrorKind::MultipleCharsInCharLiteral
+        ));
+
+        let error = lex_one("'a").unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::UnclosedCharLiteral));
+        assert_eq!(error.span(), Span::new(0, 1));
+    }
+
+    #[test]
+    fn test_kept_comments() {
+        let content = "a // one\n/* two /* three */ */ /// doc\nb";
+        let tokens = Lexer::with_comments(Source::new(content, "test_file"))
+            .map(|token| {
+                let token = token.unwrap();
+                (token.kind, token.slice)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            [
+                (TokenKind::Ident, "a"),
+                (TokenKind::LineComment, "// one"),
+                (TokenKind::BlockComment, "/* two /* three */ */"),
+                (TokenKind::DocComment, "/// doc"),
+                (TokenKind::Ident, "b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_tokens() {
+        assert_eq!(
+            kinds("enum match => = =="),
+            [
+                TokenKind::EnumKw,
+                TokenKind::MatchKw,
+                TokenKind::FatArrow,
+                TokenKind::Asgmt,
+                TokenKind::Eq
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_comments() {
+        assert_eq!(
+            kinds("a // comment / with * symbols\n/ b //\n// last"),
+            [TokenKind::Ident, TokenKind::Div, TokenKind::Ident]
+        );
+    }
+
+    #[test]
+    fn test_nested_block_comments() {
+        assert_eq!(
+            kinds("a /* outer /* inner */ still comment */ * b /**/"),
+            [TokenKind::Ident, TokenKind::Mul, TokenKind::Ident]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_block_comment() {
+        let mut lexer = Lexer::new(Source::new("a /* outer /* inner */", "test_file"));
+
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Ident);
+
+        let error = lexer.next().unwrap().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::UnclosedBlockComment));
+        assert_eq!(error.span(), Span::new(2, 4));
+
+        assert!(lexer.next().is_none());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let tokens = Lexer::new(Source::new(
+            "//! module docs\n/// item docs\n///no space\n//// plain comment\nfunc",
+            "test_file",
+        ))
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+
+        let kinds = tokens.iter().map(|token| token.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::InnerDocComment,
+                TokenKind::DocComment,
+                TokenKind::DocComment,
+                TokenKind::FuncKw
+            ]
+        );
+
+        assert_eq!(tokens[0].string(), "module docs");
+        assert_eq!(tokens[1].string(), "item docs");
+        assert_eq!(tokens[2].string(), "no space");
+        assert_eq!(tokens[1].slice, "/// item docs");
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt::{self, Display};
+
 use unnamed_common::Span;
 
 #[derive(Debug, Clone)]
@@ -25,7 +27,35 @@ impl<'s> Token<'s> {
 
     pub fn number(&self) -> u64 {
         match &self.data {
-            Some(TokenData::Number(value)) => *value,
+            Some(TokenData::Number(value, ..)) => *value,
+            _ => panic!("invalid token data"),
+        }
+    }
+
+    pub fn int_suffix(&self) -> Option<IntSuffix> {
+        match &self.data {
+            Some(TokenData::Number(.., suffix)) => *suffix,
+            _ => panic!("invalid token data"),
+        }
+    }
+
+    pub fn float(&self) -> f64 {
+        match &self.data {
+            Some(TokenData::Float(value, ..)) => *value,
+            _ => panic!("invalid token data"),
+        }
+    }
+
+    pub fn float_suffix(&self) -> Option<FloatSuffix> {
+        match &self.data {
+            Some(TokenData::Float(.., suffix)) => *suffix,
+            _ => panic!("invalid token data"),
+        }
+    }
+
+    pub fn char(&self) -> char {
+        match &self.data {
+            Some(TokenData::Char(value)) => *value,
             _ => panic!("invalid token data"),
         }
     }
@@ -40,11 +70,116 @@ impl<'s> Token<'s> {
 
 #[derive(Debug, Clone)]
 pub enum TokenData {
-    Number(u64),
+    Number(u64, Option<IntSuffix>),
+    Float(f64, Option<FloatSuffix>),
+    Char(char),
     String(String),
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A type suffix written right after a number literal, as in `10u8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Suffix {
+    Int(IntSuffix),
+    Float(FloatSuffix),
+}
+
+impl Suffix {
+    pub fn parse(suffix: &str) -> Option<Self> {
+        let suffix = match suffix {
+            "i8" => Suffix::Int(IntSuffix::I8),
+            "i16" => Suffix::Int(IntSuffix::I16),
+            "i32" => Suffix::Int(IntSuffix::I32),
+            "i64" => Suffix::Int(IntSuffix::I64),
+            "u8" => Suffix::Int(IntSuffix::U8),
+            "u16" => Suffix::Int(IntSuffix::U16),
+            "u32" => Suffix::Int(IntSuffix::U32),
+            "u64" => Suffix::Int(IntSuffix::U64),
+            "f32" => Suffix::Float(FloatSuffix::F32),
+            "f64" => Suffix::Float(FloatSuffix::F64),
+            _ => return None,
+        };
+
+        Some(suffix)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntSuffix {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IntSuffix::I8 => "i8",
+            IntSuffix::I16 => "i16",
+            IntSuffix::I32 => "i32",
+            IntSuffix::I64 => "i64",
+            IntSuffix::U8 => "u8",
+            IntSuffix::U16 => "u16",
+            IntSuffix::U32 => "u32",
+            IntSuffix::U64 => "u64",
+        }
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            IntSuffix::I8 | IntSuffix::U8 => 8,
+            IntSuffix::I16 | IntSuffix::U16 => 16,
+            IntSuffix::I32 | IntSuffix::U32 => 32,
+            IntSuffix::I64 | IntSuffix::U64 => 64,
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            IntSuffix::I8 | IntSuffix::I16 | IntSuffix::I32 | IntSuffix::I64
+        )
+    }
+
+    /// The smallest value of the integer type.
+    pub fn min(self) -> i128 {
+        if self.is_signed() {
+            -(1 << (self.bits() - 1))
+        } else {
+            0
+        }
+    }
+
+    /// The largest value of the integer type.
+    pub fn max(self) -> i128 {
+        if self.is_signed() {
+            (1 << (self.bits() - 1)) - 1
+        } else {
+            (1 << self.bits()) - 1
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FloatSuffix {
+    F32,
+    F64,
+}
+
+impl FloatSuffix {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FloatSuffix::F32 => "f32",
+            FloatSuffix::F64 => "f64",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Base {
     Hexadecimal,
     Decimal,
@@ -70,8 +205,18 @@ impl Base {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenKind {
     Int,
+    Float,
     Str,
+    Char,
     Ident,
+    DocComment,
+    InnerDocComment,
+    LineComment,
+    BlockComment,
+    /// Source text that could not be lexed. The lexer reports it as an
+    /// error; the parser keeps it as a token so that the error is not
+    /// followed by others about the text missing.
+    Error,
 
     Add,
     Sub,
@@ -95,6 +240,7 @@ pub enum TokenKind {
     BitShl,
 
     Comma,
+    Dot,
     LeftParen,
     RightParen,
     LeftBrace,
@@ -104,6 +250,8 @@ pub enum TokenKind {
     Semicolon,
     Colon,
     DoubleColon,
+    Arrow,
+    FatArrow,
 
     TrueKw,
     FalseKw,
@@ -111,6 +259,8 @@ pub enum TokenKind {
     TraitKw,
     ImplKw,
     StructKw,
+    EnumKw,
+    MatchKw,
     WhileKw,
     LetKw,
     ForKw,
@@ -118,3 +268,71 @@ pub enum TokenKind {
     ElseKw,
     ReturnKw,
 }
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let string = match self {
+            TokenKind::Int => "integer literal",
+            TokenKind::Float => "float literal",
+            TokenKind::Str => "string literal",
+            TokenKind::Char => "character literal",
+            TokenKind::Ident => "identifier",
+            TokenKind::DocComment => "doc comment",
+            TokenKind::InnerDocComment => "inner doc comment",
+            TokenKind::LineComment | TokenKind::BlockComment => "comment",
+            TokenKind::Error => "invalid token",
+
+            TokenKind::Add => "`+`",
+            TokenKind::Sub => "`-`",
+            TokenKind::Mul => "`*`",
+            TokenKind::Div => "`/`",
+
+            TokenKind::Eq => "`==`",
+            TokenKind::Asgmt => "`=`",
+            TokenKind::Neq => "`!=`",
+            TokenKind::Lt => "`<`",
+            TokenKind::LtEq => "`<=`",
+            TokenKind::Gt => "`>`",
+            TokenKind::GtEq => "`>=`",
+            TokenKind::And => "`&&`",
+            TokenKind::Or => "`||`",
+
+            TokenKind::BitOr => "`|`",
+            TokenKind::BitAnd => "`&`",
+            TokenKind::BitXor => "`^`",
+            TokenKind::BitShr => "`>>`",
+            TokenKind::BitShl => "`<<`",
+
+            TokenKind::Comma => "`,`",
+            TokenKind::Dot => "`.`",
+            TokenKind::LeftParen => "`(`",
+            TokenKind::RightParen => "`)`",
+            TokenKind::LeftBrace => "`{`",
+            TokenKind::RightBrace => "`}`",
+            TokenKind::LeftSquare => "`[`",
+            TokenKind::RightSquare => "`]`",
+            TokenKind::Semicolon => "`;`",
+            TokenKind::Colon => "`:`",
+            TokenKind::DoubleColon => "`::`",
+            TokenKind::Arrow => "`->`",
+            TokenKind::FatArrow => "`=>`",
+
+            TokenKind::TrueKw => "`true`",
+            TokenKind::FalseKw => "`false`",
+            TokenKind::FuncKw => "`func`",
+            TokenKind::TraitKw => "`trait`",
+            TokenKind::ImplKw => "`impl`",
+            TokenKind::StructKw => "`struct`",
+            TokenKind::EnumKw => "`enum`",
+            TokenKind::MatchKw => "`match`",
+            TokenKind::WhileKw => "`while`",
+            TokenKind::LetKw => "`let`",
+            TokenKind::ForKw => "`for`",
+            TokenKind::IfKw => "`if`",
+            TokenKind::ElseKw => "`else`",
+            TokenKind::ReturnKw => "`return`",
+        };
+
+        f.write_str(string)
+    }
+}
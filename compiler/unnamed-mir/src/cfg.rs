@@ -0,0 +1,307 @@
+use std::mem;
+
+use cranelift_entity::{SecondaryMap, packed_option::PackedOption};
+
+use crate::{Block, Body};
+
+/// The blocks reachable from the entry, and which of them dominate which.
+#[derive(Debug, Clone)]
+pub struct DomTree {
+    /// Reachable blocks in reverse postorder, starting with the entry.
+    rpo: Vec<Block>,
+    /// The position of each block in `rpo`, `u32::MAX` for unreachable
+    /// ones.
+    rpo_number: SecondaryMap<Block, u32>,
+    /// The immediate dominator of each reachable block but the entry.
+    idom: SecondaryMap<Block, PackedOption<Block>>,
+}
+
+impl DomTree {
+    /// Computes the tree with the algorithm of Cooper, Harvey and Kennedy,
+    /// which iterates over the blocks in reverse postorder until the
+    /// immediate dominators stop changing.
+    pub fn new(body: &Body) -> Self {
+        let rpo = reverse_postorder(body);
+        let mut rpo_number = SecondaryMap::with_default(u32::MAX);
+        for (i, &block) in rpo.iter().enumerate() {
+            rpo_number[block] = i as u32;
+        }
+
+        let preds = predecessors(body);
+
+        let mut idom: SecondaryMap<Block, PackedOption<Block>> = SecondaryMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in &rpo[1..] {
+                let mut new_idom = None;
+                for &pred in &preds[block] {
+                    // Skip predecessors not processed yet.
+                    if pred != body.entry && idom[pred].is_none() {
+                        continue;
+                    }
+
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(other) => {
+                            let (mut a, mut b) = (pred, other);
+                            while a != b {
+                                while rpo_number[a] > rpo_number[b] {
+                                    a = idom[a].expect("processed blocks have a dominator");
+                                }
+                                while rpo_number[b] > rpo_number[a] {
+                                    b = idom[b].expect("processed blocks have a dominator");
+                                }
+                            }
+                            a
+                        }
+                    });
+                }
+
+                if let Some(new_idom) = new_idom
+                    && idom[block].expand() != Some(new_idom)
+                {
+                    idom[block] = new_idom.into();
+                    changed = true;
+                }
+            }
+        }
+
+        Self {
+            rpo,
+            rpo_number,
+            idom,
+        }
+    }
+
+    pub fn rpo(&self) -> &[Block] {
+        &self.rpo
+    }
+
+    pub fn is_reachable(&self, block: Block) -> bool {
+        self.rpo_number[block] != u32::MAX
+    }
+
+    /// The closest block other than `block` that dominates it, `None` for
+    /// the entry and unreachable blocks.
+    pub fn idom(&self, block: Block) -> Option<Block> {
+        self.idom[block].expand()
+    }
+
+    /// Whether every path from the entry to `b` goes through `a`, which is
+    /// the case when they are the same block.
+    pub fn dominates(&self, a: Block, mut b: Block) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.idom(b) {
+                Some(idom) => b = idom,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// The reachable blocks that jump or branch to each block, once per edge.
+pub fn predecessors(body: &Body) -> SecondaryMap<Block, Vec<Block>> {
+    let mut preds: SecondaryMap<Block, Vec<Block>> = SecondaryMap::new();
+    for block in reverse_postorder(body) {
+        for dest in body.blocks[block].terminator.successors() {
+            preds[dest.block].push(block);
+        }
+    }
+
+    preds
+}
+
+/// Whether each block is part of a loop, so that it can run again while
+/// values it defined are still in use. Loops are found from their back
+/// edges, which go to a block dominating their source.
+pub fn loop_blocks(body: &Body) -> SecondaryMap<Block, bool> {
+    let domtree = DomTree::new(body);
+    let preds = predecessors(body);
+
+    let mut in_loop = SecondaryMap::new();
+    for &block in domtree.rpo() {
+        for dest in body.blocks[block].terminator.successors() {
+            let header = dest.block;
+            if !domtree.dominates(header, block) {
+                continue;
+            }
+
+            // The loop is made of the blocks reaching the source of the
+            // back edge without going through the header.
+            let mut seen = SecondaryMap::<Block, bool>::new();
+            seen[header] = true;
+            in_loop[header] = true;
+            let mut stack = vec![block];
+            while let Some(block) = stack.pop() {
+                if !mem::replace(&mut seen[block], true) {
+                    in_loop[block] = true;
+                    stack.extend(&preds[block]);
+                }
+            }
+        }
+    }
+
+    in_loop
+}
+
+/// The blocks reachable from the entry, each before its successors unless
+/// it is reached through a back edge.
+pub fn reverse_postorder(body: &Body) -> Vec<Block> {
+    let mut visited = SecondaryMap::<Block, bool>::new();
+    let mut postorder = Vec::new();
+    // Blocks with the index of the next successor to visit.
+    let mut stack = vec![(body.entry, 0)];
+    visited[body.entry] = true;
+
+    while let Some((block, next)) = stack.pop() {
+        let successors = body.blocks[block].terminator.successors();
+        match successors.get(next) {
+            Some(dest) => {
+                stack.push((block, next + 1));
+                if !visited[dest.block] {
+                    visited[dest.block] = true;
+                    stack.push((dest.block, 0));
+                }
+            }
+            None => postorder.push(block),
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use cranelift_entity::SecondaryMap;
+
+    use crate::{
+        Block, Body, Terminator,
+        lower::tests::with_mir,
+        opt::{OptLevel, optimize},
+    };
+
+    use super::{DomTree, loop_blocks};
+
+    #[test]
+    fn test_dominators() {
+        with_mir(
+            "func f(a: i64) { while a > 0 { if a == 1 { return; } } }",
+            |mir, _, _, _, _| {
+                let body = mir.bodies.values().next().unwrap();
+                let domtree = DomTree::new(body);
+                let idoms = body
+                    .blocks
+                    .keys()
+                    .map(|block| domtree.idom(block).map(Block::as_u32))
+                    .collect::<Vec<_>>();
+
+                // block1 is the loop header, block3 its body and block4 its
+                // exit, and block6 returns from the `if`.
+                let expected = [
+                    None,
+                    Some(0),
+                    Some(4),
+                    Some(1),
+                    Some(1),
+                    Some(8),
+                    Some(3),
+                    Some(3),
+                    Some(7),
+                ];
+                assert_eq!(idoms, expected);
+                assert!(domtree.dominates(Block::from_u32(1), Block::from_u32(5)));
+                assert!(!domtree.dominates(Block::from_u32(3), Block::from_u32(2)));
+                assert_eq!(domtree.rpo().len(), body.blocks.len());
+            },
+        );
+    }
+
+    /// Whether there is a path of zero or more edges from `from` to `to`.
+    fn reaches(body: &Body, from: Block, to: Block) -> bool {
+        let mut seen = SecondaryMap::<Block, bool>::new();
+        let mut stack = vec![from];
+        while let Some(block) = stack.pop() {
+            if block == to {
+                return true;
+            }
+            if !mem::replace(&mut seen[block], true) {
+                let successors = body.blocks[block].terminator.successors();
+                stack.extend(successors.iter().map(|dest| dest.block));
+            }
+        }
+
+        false
+    }
+
+    #[test]
+    fn test_loop_blocks() {
+        let content =
+            "func f(a: i64) { while a > 0 { if false { return; } if a == 1 { return; } } }";
+        for level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+            with_mir(content, |mir, _, _, interner, results| {
+                optimize(mir, level, interner, results);
+                let body = mir.bodies.values().next().unwrap();
+                let domtree = DomTree::new(body);
+                let in_loop = loop_blocks(body);
+
+                let domtree = &domtree;
+                let mut headers = domtree
+                    .rpo()
+                    .iter()
+                    .flat_map(|&block| {
+                        let successors = body.blocks[block].terminator.successors();
+                        successors
+                            .into_iter()
+                            .map(|dest| dest.block)
+                            .filter(move |&dest| domtree.dominates(dest, block))
+                    })
+                    .collect::<Vec<_>>();
+                headers.dedup();
+                let [header] = headers[..] else {
+                    panic!("expected one loop header at {level:?}, found {headers:?}");
+                };
+
+                // The loop is exactly the blocks on a cycle through its
+                // header, which leaves out the entry, the returns and any
+                // block that optimizations made unreachable.
+                for (block, data) in body.blocks.iter() {
+                    let expected = domtree.is_reachable(block)
+                        && reaches(body, header, block)
+                        && reaches(body, block, header);
+                    assert_eq!(in_loop[block], expected, "{block} at {level:?}");
+
+                    if matches!(data.terminator, Terminator::Return(_)) {
+                        assert!(!in_loop[block], "{block} at {level:?}");
+                    }
+                }
+                assert!(in_loop[header]);
+                assert!(!in_loop[body.entry]);
+
+                // Optimizing folds the `if false` away and removes its
+                // `return` along with every other unreachable block.
+                let returns = body
+                    .blocks
+                    .values()
+                    .filter(|data| matches!(data.terminator, Terminator::Return(_)))
+                    .count();
+                if level == OptLevel::O0 {
+                    assert_eq!(returns, 3);
+                } else {
+                    assert_eq!(returns, 2);
+                    assert_eq!(domtree.rpo().len(), body.blocks.len());
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::{HashMap, HashSet};
+
+use cranelift_entity::SecondaryMap;
+use unnamed_ast::{BinOp, expr::UnaryOp};
+use unnamed_typeck::{TyKind, TypeckResults};
+
+use crate::{
+    Block, Body, Const, Inst, InstKind, Local, Place, Projection, Value,
+    cfg::{predecessors, reverse_postorder},
+    fold::constant,
+    opt::resolve,
+};
+
+/// The value of places of each local, by the projections selecting them.
+type Known = HashMap<Local, Vec<(Vec<Projection>, Value)>>;
+
+/// Replaces loads of places by the value last stored to or loaded from the
+/// same place, if the local was not stored to since on any path to them.
+pub(crate) fn forward_loads(body: &mut Body) -> bool {
+    let rpo = reverse_postorder(body);
+    let preds = predecessors(body);
+
+    // What is known when entering each block, with the values known on
+    // every path to it. Edges from blocks not visited yet are ignored, and
+    // visiting them again can only forget more.
+    let mut entries = SecondaryMap::<Block, Option<Known>>::new();
+    let mut exits = SecondaryMap::<Block, Option<Known>>::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &block in &rpo {
+            let mut known = None;
+            for &pred in &preds[block] {
+                let Some(exit) = &exits[pred] else {
+                    continue;
+                };
+                known = Some(match known {
+                    None => exit.clone(),
+                    Some(known) => meet(known, exit),
+                });
+            }
+            let known = known.unwrap_or_default();
+
+            let mut exit = known.clone();
+            transfer(body, block, &mut exit, &mut HashMap::new());
+            if entries[block].as_ref() != Some(&known) {
+                entries[block] = Some(known);
+                exits[block] = Some(exit);
+                changed = true;
+            }
+        }
+    }
+
+    let mut replaced = HashMap::new();
+    for &block in &rpo {
+        let mut known = entries[block].take().expect("reachable blocks are visited");
+        transfer(body, block, &mut known, &mut replaced);
+    }
+
+    if replaced.is_empty() {
+        return false;
+    }
+
+    body.map_values(|value| resolve(&replaced, value));
+    true
+}
+
+/// What is known on every path, from what is known on some of them and on
+/// another.
+fn meet(mut known: Known, other: &Known) -> Known {
+    known.retain(|local, places| {
+        let Some(other) = other.get(local) else {
+            return false;
+        };
+        places.retain(|place| other.contains(place));
+        !places.is_empty()
+    });
+
+    known
+}
+
+/// Updates what is known through the loads and stores of `block`, recording
+/// the loads of known places in `replaced`.
+fn transfer(body: &Body, block: Block, known: &mut Known, replaced: &mut HashMap<Value, Value>) {
+    for &inst in &body.blocks[block].insts {
+        let data = body.insts[inst];
+        match data.kind {
+            InstKind::Load(place) => {
+                let result = data.result.expect("loads define a value");
+                let place = &body.places[place];
+                let places = known.entry(place.local).or_default();
+                let same = places
+                    .iter()
+                    .find(|(projections, _)| *projections == place.projections);
+                match same {
+                    Some(&(_, value)) => {
+                        replaced.insert(result, value);
+                    }
+                    None => places.push((place.projections.clone(), result)),
+                }
+            }
+            InstKind::Store { place, value } => {
+                let place = &body.places[place];
+                known.insert(place.local, vec![(place.projections.clone(), value)]);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Removes stores to locals that are stored to again or never loaded from
+/// afterwards. Stores to elements of arrays are kept, as they check that the
+/// index is within bounds.
+pub(crate) fn remove_dead_stores(body: &mut Body) -> bool {
+    let live_out = live_locals(body);
+    let mut changed = false;
+
+    for block in body.blocks.keys() {
+        let mut live = live_out[block].clone();
+        let mut dead = Vec::new();
+        for &inst in body.blocks[block].insts.iter().rev() {
+            match body.insts[inst].kind {
+                InstKind::Store { place, .. } => {
+                    let data = &body.places[place];
+                    if !live.contains(&data.local) && !has_index(body, place) {
+                        dead.push(inst);
+                    } else if data.projections.is_empty() {
+                        live.remove(&data.local);
+                    }
+                }
+                InstKind::Load(place) | InstKind::IsVariant { place, .. } => {
+                    live.insert(body.places[place].local);
+                }
+                _ => {}
+            }
+        }
+
+        if !dead.is_empty() {
+            body.blocks[block].insts.retain(|inst| !dead.contains(inst));
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// The locals whose value may still be loaded after each block.
+fn live_locals(body: &Body) -> SecondaryMap<Block, HashSet<Local>> {
+    // The locals each block loads before storing all of them, and those it
+    // stores all of.
+    let mut uses = SecondaryMap::<Block, HashSet<Local>>::new();
+    let mut defs = SecondaryMap::<Block, HashSet<Local>>::new();
+    for (block, data) in body.blocks.iter() {
+        for &inst in data.insts.iter().rev() {
+            match body.insts[inst].kind {
+                InstKind::Store { place, .. } if body.places[place].projections.is_empty() => {
+                    let local = body.places[place].local;
+                    uses[block].remove(&local);
+                    defs[block].insert(local);
+                }
+                InstKind::Load(place) | InstKind::IsVariant { place, .. } => {
+                    uses[block].insert(body.places[place].local);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let preds = predecessors(body);
+    let mut live_in = SecondaryMap::<Block, HashSet<Local>>::new();
+    let mut live_out = SecondaryMap::<Block, HashSet<Local>>::new();
+    // Visiting blocks after their successors converges fastest.
+    let mut worklist = reverse_postorder(body);
+    while let Some(block) = worklist.pop() {
+        let mut live = live_out[block].clone();
+        live.retain(|local| !defs[block].contains(local));
+        live.extend(&uses[block]);
+        if live == live_in[block] {
+            continue;
+        }
+
+        for &pred in &preds[block] {
+            let before = live_out[pred].len();
+            live_out[pred].extend(&live);
+            if live_out[pred].len() != before && !worklist.contains(&pred) {
+                worklist.push(pred);
+            }
+        }
+        live_in[block] = live;
+    }
+
+    live_out
+}
+
+fn has_index(body: &Body, place: Place) -> bool {
+    let projections = &body.places[place].projections;
+    projections
+        .iter()
+        .any(|projection| matches!(projection, Projection::Index(_)))
+}
+
+/// Removes the instructions whose values are not used and that cannot stop
+/// the program, and the parameters of blocks that are not used.
+pub(crate) fn remove_dead_values(body: &mut Body, results: &TypeckResults) -> bool {
+    let mut uses = SecondaryMap::<Value, u32>::new();
+    for (block, data) in body.blocks.iter() {
+        for &inst in &data.insts {
+            for value in body.inst_args(inst) {
+                uses[value] += 1;
+            }
+        }
+        for value in body.terminator_args(block) {
+            uses[value] += 1;
+        }
+    }
+
+    let mut changed = false;
+
+    // Values are used after they are defined, except through parameters,
+    // so users are visited first.
+    let mut postorder = reverse_postorder(body);
+    postorder.reverse();
+    for &block in &postorder {
+        let mut dead = Vec::new();
+        for &inst in body.blocks[block].insts.iter().rev() {
+            let unused = body.insts[inst]
+                .result
+                .is_some_and(|result| uses[result] == 0);
+            if unused && is_pure(body, inst, results) {
+                for value in body.inst_args(inst) {
+                    uses[value] -= 1;
+                }
+                dead.push(inst);
+            }
+        }
+
+        if !dead.is_empty() {
+            body.blocks[block].insts.retain(|inst| !dead.contains(inst));
+            changed = true;
+        }
+    }
+
+    for block in body.blocks.keys() {
+        if block == body.entry {
+            continue;
+        }
+        for index in (0..body.blocks[block].params.len()).rev() {
+            if uses[body.blocks[block].params[index]] == 0 {
+                body.remove_block_param(block, index);
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// Whether `inst` only defines its value, without ever stopping the program
+/// or changing a local.
+fn is_pure(body: &Body, inst: Inst, results: &TypeckResults) -> bool {
+    let int = |value: Value| matches!(results.tcx[body.values[value].ty], TyKind::Int(_));
+
+    match body.insts[inst].kind {
+        InstKind::Const(_) | InstKind::Adt { .. } | InstKind::Array(_) => true,
+        InstKind::Load(place) | InstKind::IsVariant { place, .. } => !has_index(body, place),
+        InstKind::Binary { op, lhs, .. } => match op {
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::BitShl | BinOp::BitShr => {
+                !int(lhs)
+            }
+            _ => true,
+        },
+        InstKind::Unary { op, value } => op == UnaryOp::Add || !int(value),
+        InstKind::Repeat { count, .. } => {
+            matches!(constant(body, count), Some(Const::Int(count)) if count >= 0)
+        }
+        InstKind::Call { .. } | InstKind::Store { .. } => false,
+    }
+}
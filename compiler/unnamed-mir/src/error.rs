@@ -0,0 +1,87 @@
+use ariadne::{Label, ReportKind};
+use unnamed_common::{IntoReport, Report, Source, Span, Spanned};
+
+/// A body that breaks an invariant of the MIR, which is a bug in whatever
+/// produced it.
+#[derive(Debug, Clone)]
+pub struct Error {
+    span: Span,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(span: Span, kind: ErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// What is wrong, each naming the instruction, or the block for
+/// terminators, that is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A value used where its definition does not dominate, or that no
+    /// block or instruction defines.
+    UndominatedUse { value: String, user: String },
+    TypeMismatch {
+        user: String,
+        expected: String,
+        found: String,
+    },
+    ArgCount {
+        user: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An operator, constant or projection applied to a type it does not
+    /// support.
+    InvalidType { user: String, ty: String },
+    /// A load of a local that some path to it does not store to.
+    UnstoredLoad { local: String, user: String },
+}
+
+impl IntoReport for Error {
+    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
+        let message = match self.kind {
+            ErrorKind::UndominatedUse { value, user } => {
+                format!("`{value}` is used by `{user}` without being defined before it")
+            }
+            ErrorKind::TypeMismatch {
+                user,
+                expected,
+                found,
+            } => format!("`{user}` expects a value of type `{expected}`, found `{found}`"),
+            ErrorKind::ArgCount {
+                user,
+                expected,
+                found,
+            } => {
+                let expected = match expected {
+                    1 => "1 is".to_owned(),
+                    _ => format!("{expected} are"),
+                };
+                format!("`{user}` passes {found} values where {expected} expected")
+            }
+            ErrorKind::InvalidType { user, ty } => {
+                format!("`{user}` cannot be applied to type `{ty}`")
+            }
+            ErrorKind::UnstoredLoad { local, user } => {
+                format!("`{user}` reads `{local}`, which is not stored to on every path to it")
+            }
+        };
+
+        Report::build(ReportKind::Error, source.span(self.span))
+            .with_message(format!("invalid MIR: {message}"))
+            .with_label(Label::new(source.span(self.span)).with_message("while lowering this"))
+            .finish()
+    }
+}
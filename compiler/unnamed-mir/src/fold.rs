@@ -0,0 +1,232 @@
+use unnamed_ast::{BinOp, expr::UnaryOp};
+use unnamed_common::Interner;
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+use unnamed_typeck::{TyKind, TypeckResults};
+
+use crate::{Body, Const, InstKind, Value, ValueDef, cfg::reverse_postorder};
+
+/// Replaces operators applied to constants by their result. Operations that
+/// would stop the program, like overflowing ones or divisions by zero, are
+/// kept so that they still do at run time.
+pub(crate) fn fold_constants(
+    body: &mut Body,
+    interner: &Interner,
+    results: &TypeckResults,
+) -> bool {
+    let mut changed = false;
+
+    // Dominating blocks come first, so operands are folded before the
+    // instructions using them.
+    for block in reverse_postorder(body) {
+        for i in 0..body.blocks[block].insts.len() {
+            let inst = body.blocks[block].insts[i];
+            let folded = match body.insts[inst].kind {
+                InstKind::Binary { op, lhs, rhs } => {
+                    let kind = results.tcx[body.values[lhs].ty];
+                    match (constant(body, lhs), constant(body, rhs)) {
+                        (Some(lhs), Some(rhs)) => binary(op, kind, lhs, rhs, interner),
+                        // Units are all equal.
+                        _ if kind == TyKind::Unit => match op {
+                            BinOp::Eq => Some(Const::Bool(true)),
+                            BinOp::Neq => Some(Const::Bool(false)),
+                            _ => None,
+                        },
+                        _ => None,
+                    }
+                }
+                InstKind::Unary { op, value } => {
+                    let kind = results.tcx[body.values[value].ty];
+                    constant(body, value).and_then(|value| unary(op, kind, value))
+                }
+                _ => None,
+            };
+
+            if let Some(folded) = folded {
+                body.insts[inst].kind = InstKind::Const(folded);
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+/// The constant `value` is defined as, if any.
+pub(crate) fn constant(body: &Body, value: Value) -> Option<Const> {
+    match body.values[value].def {
+        ValueDef::Inst(inst) => match body.insts[inst].kind {
+            InstKind::Const(value) => Some(value),
+            _ => None,
+        },
+        ValueDef::Param(..) => None,
+    }
+}
+
+fn binary(op: BinOp, kind: TyKind, lhs: Const, rhs: Const, interner: &Interner) -> Option<Const> {
+    let folded = match (kind, lhs, rhs) {
+        (TyKind::Int(int), Const::Int(lhs), Const::Int(rhs)) => {
+            return int_binary(op, int, lhs, rhs);
+        }
+        (TyKind::Float(float), Const::Float(lhs), Const::Float(rhs)) => match op {
+            BinOp::Add => float_value(float, lhs + rhs),
+            BinOp::Sub => float_value(float, lhs - rhs),
+            BinOp::Mul => float_value(float, lhs * rhs),
+            BinOp::Div => float_value(float, lhs / rhs),
+            _ => Const::Bool(compare(op, lhs.partial_cmp(&rhs))?),
+        },
+        (TyKind::Bool, Const::Bool(lhs), Const::Bool(rhs)) => match op {
+            BinOp::BitAnd => Const::Bool(lhs & rhs),
+            BinOp::BitOr => Const::Bool(lhs | rhs),
+            BinOp::BitXor => Const::Bool(lhs ^ rhs),
+            _ => Const::Bool(compare(op, Some(lhs.cmp(&rhs)))?),
+        },
+        (TyKind::Char, Const::Char(lhs), Const::Char(rhs)) => {
+            Const::Bool(compare(op, Some(lhs.cmp(&rhs)))?)
+        }
+        (TyKind::Str, Const::Str(lhs), Const::Str(rhs)) => {
+            let (lhs, rhs) = (interner.get(lhs), interner.get(rhs));
+            match op {
+                BinOp::Add => Const::Str(interner.intern(&format!("{lhs}{rhs}"))),
+                _ => Const::Bool(compare(op, Some(lhs.cmp(rhs)))?),
+            }
+        }
+        _ => return None,
+    };
+
+    Some(folded)
+}
+
+/// Integer arithmetic, which does not fold on overflow, division by zero
+/// and shifts by an amount outside of the width of the integer.
+fn int_binary(op: BinOp, int: IntSuffix, lhs: i128, rhs: i128) -> Option<Const> {
+    let bits = int.bits();
+    let value = match op {
+        BinOp::Add => lhs.checked_add(rhs)?,
+        BinOp::Sub => lhs.checked_sub(rhs)?,
+        BinOp::Mul => lhs.checked_mul(rhs)?,
+        // Dividing the minimum by -1 overflows, which the range check
+        // below catches.
+        BinOp::Div => lhs.checked_div(rhs)?,
+        BinOp::BitAnd => lhs & rhs,
+        BinOp::BitOr => lhs | rhs,
+        BinOp::BitXor => lhs ^ rhs,
+        BinOp::BitShl | BinOp::BitShr if !(0..i128::from(bits)).contains(&rhs) => return None,
+        // Bits shifted out are lost, and the sign of the result is that of
+        // the last bit shifted in.
+        BinOp::BitShl => {
+            let shifted = (lhs as u128) << rhs;
+            let value = (shifted << (128 - bits)) as i128;
+            match int.is_signed() {
+                true => value >> (128 - bits),
+                false => ((value as u128) >> (128 - bits)) as i128,
+            }
+        }
+        // Values of unsigned types are never negative, so shifting them
+        // arithmetically shifts in zeros.
+        BinOp::BitShr => lhs >> rhs,
+        _ => return Some(Const::Bool(compare(op, Some(lhs.cmp(&rhs)))?)),
+    };
+
+    (int.min()..=int.max())
+        .contains(&value)
+        .then_some(Const::Int(value))
+}
+
+fn unary(op: UnaryOp, kind: TyKind, value: Const) -> Option<Const> {
+    match (op, kind, value) {
+        (UnaryOp::Add, _, value) => Some(value),
+        (UnaryOp::Sub, TyKind::Int(int), Const::Int(value)) => (int.min()..=int.max())
+            .contains(&-value)
+            .then_some(Const::Int(-value)),
+        (UnaryOp::Sub, TyKind::Float(_), Const::Float(value)) => Some(Const::Float(-value)),
+        _ => None,
+    }
+}
+
+/// A float of type `float`, rounding `value` for `f32`.
+fn float_value(float: FloatSuffix, value: f64) -> Const {
+    match float {
+        FloatSuffix::F32 => Const::Float(f64::from(value as f32)),
+        FloatSuffix::F64 => Const::Float(value),
+    }
+}
+
+/// The result of a comparison operator, given how its operands compare.
+/// Values that are not ordered, like NaN, are only unequal.
+fn compare(op: BinOp, ordering: Option<std::cmp::Ordering>) -> Option<bool> {
+    let Some(ordering) = ordering else {
+        return match op {
+            BinOp::Neq => Some(true),
+            BinOp::Eq | BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq => Some(false),
+            _ => None,
+        };
+    };
+
+    let result = match op {
+        BinOp::Eq => ordering.is_eq(),
+        BinOp::Neq => ordering.is_ne(),
+        BinOp::Lt => ordering.is_lt(),
+        BinOp::LtEq => ordering.is_le(),
+        BinOp::Gt => ordering.is_gt(),
+        BinOp::GtEq => ordering.is_ge(),
+        _ => return None,
+    };
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_ast::BinOp;
+    use unnamed_lexer::IntSuffix;
+
+    use super::int_binary;
+    use crate::Const;
+
+    #[test]
+    fn test_int_overflow() {
+        assert_eq!(
+            int_binary(BinOp::Add, IntSuffix::I8, 100, 27),
+            Some(Const::Int(127))
+        );
+        assert_eq!(int_binary(BinOp::Add, IntSuffix::I8, 100, 28), None);
+        assert_eq!(int_binary(BinOp::Sub, IntSuffix::U8, 0, 1), None);
+        assert_eq!(
+            int_binary(BinOp::Mul, IntSuffix::U64, 1 << 32, 1 << 32),
+            None
+        );
+        assert_eq!(
+            int_binary(BinOp::Div, IntSuffix::I64, -7, 2),
+            Some(Const::Int(-3))
+        );
+        assert_eq!(int_binary(BinOp::Div, IntSuffix::I8, -128, -1), None);
+        assert_eq!(int_binary(BinOp::Div, IntSuffix::I64, 1, 0), None);
+    }
+
+    #[test]
+    fn test_shifts() {
+        assert_eq!(
+            int_binary(BinOp::BitShl, IntSuffix::I64, 1, 5),
+            Some(Const::Int(32))
+        );
+        assert_eq!(
+            int_binary(BinOp::BitShl, IntSuffix::I8, 1, 7),
+            Some(Const::Int(-128))
+        );
+        assert_eq!(
+            int_binary(BinOp::BitShl, IntSuffix::U8, 0xff, 4),
+            Some(Const::Int(0xf0))
+        );
+        assert_eq!(int_binary(BinOp::BitShl, IntSuffix::I32, 1, 32), None);
+        assert_eq!(int_binary(BinOp::BitShl, IntSuffix::I32, 1, -1), None);
+        assert_eq!(
+            int_binary(BinOp::BitShr, IntSuffix::I16, -16, 2),
+            Some(Const::Int(-4))
+        );
+        assert_eq!(
+            int_binary(BinOp::BitShr, IntSuffix::U64, u64::MAX.into(), 60),
+            Some(Const::Int(15))
+        );
+        assert_eq!(int_binary(BinOp::BitShr, IntSuffix::U8, 1, 8), None);
+    }
+}
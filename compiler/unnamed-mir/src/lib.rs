@@ -0,0 +1,450 @@
+//! The mid-level IR: the body of every instance as a control-flow graph of
+//! basic blocks. Temporaries are SSA values, each defined once by an
+//! instruction or as a parameter of a block, and passed between blocks as
+//! arguments of the terminators. Locals are instead read and written through
+//! places, which may select a field of a struct or variant, or an element of
+//! an array.
+//!
+//! Bodies are lowered from the HIR of each instance, so types are concrete
+//! and overloaded operators are calls. Operators of the language keep their
+//! checks, like panicking on overflow.
+//!
+//! [`optimize`] then folds constants and branches and removes dead code, at
+//! the level chosen with `-O`.
+
+pub mod cfg;
+mod dce;
+pub mod error;
+mod fold;
+mod lower;
+pub mod opt;
+pub mod printer;
+pub mod verify;
+
+use cranelift_entity::{EntityList, ListPool, PrimaryMap, entity_impl};
+use unnamed_ast::{BinOp, VariantEntity, expr::UnaryOp};
+use unnamed_common::{Span, StrId};
+use unnamed_resolve::Builtin;
+use unnamed_typeck::{InstanceId, TyId};
+
+pub use error::{Error, ErrorKind};
+pub use lower::lower;
+pub use opt::{OptLevel, optimize};
+pub use verify::verify;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Block(u32);
+entity_impl!(Block, "block");
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Value(u32);
+entity_impl!(Value, "v");
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Inst(u32);
+entity_impl!(Inst, "inst");
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Local(u32);
+entity_impl!(Local, "local");
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Place(u32);
+entity_impl!(Place, "place");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalData {
+    pub ty: TyId,
+    /// The name of the local in the source, `None` for temporaries.
+    pub name: Option<StrId>,
+}
+
+/// A local, or a part of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaceData {
+    pub local: Local,
+    /// Applied in order to the local.
+    pub projections: Vec<Projection>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    /// A field of a struct, or of the variant selected before.
+    Field(u32),
+    /// The enum value as the given variant, whose fields can then be
+    /// selected. The variant has to be the one the value holds.
+    Variant(VariantEntity),
+    /// An element of an array, which panics when out of bounds.
+    Index(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueDef {
+    Inst(Inst),
+    /// The parameter of the block at the index.
+    Param(Block, u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueData {
+    pub ty: TyId,
+    pub def: ValueDef,
+}
+
+/// A constant, of the type of the value it defines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Const {
+    /// An integer within the range of its type.
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    Str(StrId),
+    Unit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Callee {
+    Instance(InstanceId),
+    Builtin(Builtin),
+    /// The built-in `len` of arrays and strings.
+    Len,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstKind {
+    Const(Const),
+    /// An operator of the language other than `&&` and `||`, applied to
+    /// operands of the same type, except for the amount of shifts. `==` and
+    /// `!=` compare arrays, structs and enums field by field.
+    Binary {
+        op: BinOp,
+        lhs: Value,
+        rhs: Value,
+    },
+    Unary {
+        op: UnaryOp,
+        value: Value,
+    },
+    Call {
+        callee: Callee,
+        args: EntityList<Value>,
+    },
+    Load(Place),
+    Store {
+        place: Place,
+        value: Value,
+    },
+    /// Whether the enum at the place holds the variant.
+    IsVariant {
+        place: Place,
+        variant: VariantEntity,
+    },
+    /// A struct, or an enum holding the variant, from its fields in
+    /// definition order.
+    Adt {
+        variant: Option<VariantEntity>,
+        fields: EntityList<Value>,
+    },
+    Array(EntityList<Value>),
+    /// An array of `count` copies of `value`, which panics for a negative
+    /// count.
+    Repeat {
+        value: Value,
+        count: Value,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstData {
+    pub kind: InstKind,
+    /// The value the instruction defines. Only stores have none.
+    pub result: Option<Value>,
+    /// The expression the instruction was lowered from, where it reports
+    /// panics.
+    pub span: Span,
+}
+
+/// A jump target together with the values passed to its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCall {
+    pub block: Block,
+    pub args: EntityList<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terminator {
+    Jump(BlockCall),
+    Branch {
+        cond: Value,
+        then_dest: BlockCall,
+        else_dest: BlockCall,
+    },
+    Return(Value),
+    /// Control never reaches the end of the block, like after a `match`
+    /// whose arms are exhaustive.
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockCall> {
+        match *self {
+            Terminator::Jump(dest) => vec![dest],
+            Terminator::Branch {
+                then_dest,
+                else_dest,
+                ..
+            } => vec![then_dest, else_dest],
+            Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockData {
+    pub params: Vec<Value>,
+    pub insts: Vec<Inst>,
+    pub terminator: Terminator,
+}
+
+/// The body of one instance.
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub instance: InstanceId,
+    /// The function the instance is of.
+    pub span: Span,
+    /// The first block, whose parameters are those of the function.
+    pub entry: Block,
+    pub ret: TyId,
+    pub locals: PrimaryMap<Local, LocalData>,
+    pub places: PrimaryMap<Place, PlaceData>,
+    pub blocks: PrimaryMap<Block, BlockData>,
+    pub insts: PrimaryMap<Inst, InstData>,
+    pub values: PrimaryMap<Value, ValueData>,
+    pub value_lists: ListPool<Value>,
+}
+
+impl Body {
+    /// An empty body, whose entry block has no parameters and does not
+    /// terminate yet.
+    pub fn new(instance: InstanceId, span: Span, ret: TyId) -> Self {
+        let mut blocks = PrimaryMap::new();
+        let entry = blocks.push(BlockData {
+            params: Vec::new(),
+            insts: Vec::new(),
+            terminator: Terminator::Unreachable,
+        });
+
+        Self {
+            instance,
+            span,
+            entry,
+            ret,
+            locals: PrimaryMap::new(),
+            places: PrimaryMap::new(),
+            blocks,
+            insts: PrimaryMap::new(),
+            values: PrimaryMap::new(),
+            value_lists: ListPool::new(),
+        }
+    }
+
+    /// The types of the parameters of the function.
+    pub fn params(&self) -> Vec<TyId> {
+        let params = &self.blocks[self.entry].params;
+        params.iter().map(|&param| self.values[param].ty).collect()
+    }
+
+    pub fn create_block(&mut self) -> Block {
+        self.blocks.push(BlockData {
+            params: Vec::new(),
+            insts: Vec::new(),
+            terminator: Terminator::Unreachable,
+        })
+    }
+
+    pub fn append_block_param(&mut self, block: Block, ty: TyId) -> Value {
+        let index = self.blocks[block].params.len() as u32;
+        let value = self.values.push(ValueData {
+            ty,
+            def: ValueDef::Param(block, index),
+        });
+        self.blocks[block].params.push(value);
+
+        value
+    }
+
+    /// Appends an instruction to `block`, defining a value of type `ty`
+    /// unless it is `None`.
+    pub fn append_inst(
+        &mut self,
+        block: Block,
+        kind: InstKind,
+        ty: Option<TyId>,
+        span: Span,
+    ) -> Option<Value> {
+        let inst = self.insts.push(InstData {
+            kind,
+            result: None,
+            span,
+        });
+        let result = ty.map(|ty| {
+            self.values.push(ValueData {
+                ty,
+                def: ValueDef::Inst(inst),
+            })
+        });
+        self.insts[inst].result = result;
+        self.blocks[block].insts.push(inst);
+
+        result
+    }
+
+    pub fn add_local(&mut self, ty: TyId, name: Option<StrId>) -> Local {
+        self.locals.push(LocalData { ty, name })
+    }
+
+    pub fn add_place(&mut self, local: Local, projections: Vec<Projection>) -> Place {
+        self.places.push(PlaceData { local, projections })
+    }
+
+    pub fn value_list(&mut self, values: &[Value]) -> EntityList<Value> {
+        EntityList::from_slice(values, &mut self.value_lists)
+    }
+
+    pub fn block_call(&mut self, block: Block, args: &[Value]) -> BlockCall {
+        BlockCall {
+            block,
+            args: self.value_list(args),
+        }
+    }
+
+    pub fn list(&self, list: EntityList<Value>) -> &[Value] {
+        list.as_slice(&self.value_lists)
+    }
+
+    /// The values `inst` uses, including the indices of its place.
+    pub fn inst_args(&self, inst: Inst) -> Vec<Value> {
+        match self.insts[inst].kind {
+            InstKind::Const(_) => Vec::new(),
+            InstKind::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            InstKind::Unary { value, .. } => vec![value],
+            InstKind::Call { args, .. }
+            | InstKind::Adt { fields: args, .. }
+            | InstKind::Array(args) => self.list(args).to_vec(),
+            InstKind::Load(place) | InstKind::IsVariant { place, .. } => self.place_args(place),
+            InstKind::Store { place, value } => {
+                let mut args = self.place_args(place);
+                args.push(value);
+                args
+            }
+            InstKind::Repeat { value, count } => vec![value, count],
+        }
+    }
+
+    fn place_args(&self, place: Place) -> Vec<Value> {
+        self.places[place]
+            .projections
+            .iter()
+            .filter_map(|projection| match *projection {
+                Projection::Index(index) => Some(index),
+                Projection::Field(_) | Projection::Variant(_) => None,
+            })
+            .collect()
+    }
+
+    /// Replaces every use of a value, in instructions, places and
+    /// terminators, by the value `f` maps it to.
+    pub fn map_values(&mut self, mut f: impl FnMut(Value) -> Value) {
+        for data in self.insts.values_mut() {
+            match &mut data.kind {
+                InstKind::Const(_) | InstKind::Load(_) | InstKind::IsVariant { .. } => {}
+                InstKind::Binary { lhs, rhs, .. } => {
+                    *lhs = f(*lhs);
+                    *rhs = f(*rhs);
+                }
+                InstKind::Unary { value, .. } | InstKind::Store { value, .. } => *value = f(*value),
+                InstKind::Call { args, .. }
+                | InstKind::Adt { fields: args, .. }
+                | InstKind::Array(args) => {
+                    for value in args.as_mut_slice(&mut self.value_lists) {
+                        *value = f(*value);
+                    }
+                }
+                InstKind::Repeat { value, count } => {
+                    *value = f(*value);
+                    *count = f(*count);
+                }
+            }
+        }
+
+        for data in self.places.values_mut() {
+            for projection in &mut data.projections {
+                if let Projection::Index(index) = projection {
+                    *index = f(*index);
+                }
+            }
+        }
+
+        for data in self.blocks.values_mut() {
+            match &mut data.terminator {
+                Terminator::Branch { cond, .. } => *cond = f(*cond),
+                Terminator::Return(value) => *value = f(*value),
+                Terminator::Jump(_) | Terminator::Unreachable => {}
+            }
+            for mut dest in data.terminator.successors() {
+                for value in dest.args.as_mut_slice(&mut self.value_lists) {
+                    *value = f(*value);
+                }
+            }
+        }
+    }
+
+    /// Removes the parameter of `block` at `index`, together with the
+    /// argument every jump to it passes for it.
+    pub fn remove_block_param(&mut self, block: Block, index: usize) {
+        self.blocks[block].params.remove(index);
+        for (i, &param) in self.blocks[block].params.iter().enumerate().skip(index) {
+            self.values[param].def = ValueDef::Param(block, i as u32);
+        }
+
+        for data in self.blocks.values_mut() {
+            let dests = match &mut data.terminator {
+                Terminator::Jump(dest) => vec![dest],
+                Terminator::Branch {
+                    then_dest,
+                    else_dest,
+                    ..
+                } => vec![then_dest, else_dest],
+                Terminator::Return(_) | Terminator::Unreachable => Vec::new(),
+            };
+            for dest in dests {
+                if dest.block == block {
+                    dest.args.remove(index, &mut self.value_lists);
+                }
+            }
+        }
+    }
+
+    /// The values the terminator of `block` uses, block arguments included.
+    pub fn terminator_args(&self, block: Block) -> Vec<Value> {
+        let terminator = self.blocks[block].terminator;
+        let mut args = match terminator {
+            Terminator::Branch { cond, .. } => vec![cond],
+            Terminator::Return(value) => vec![value],
+            Terminator::Jump(_) | Terminator::Unreachable => Vec::new(),
+        };
+        for dest in terminator.successors() {
+            args.extend_from_slice(self.list(dest.args));
+        }
+
+        args
+    }
+}
+
+/// The MIR of a module.
+#[derive(Debug, Default)]
+pub struct Mir {
+    /// The body of every instance, in the order of `Instances`.
+    pub bodies: PrimaryMap<InstanceId, Body>,
+}
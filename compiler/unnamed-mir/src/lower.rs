@@ -0,0 +1,635 @@
+use std::collections::HashMap;
+
+use unnamed_ast::{BinOp, ExprEntity};
+use unnamed_common::Span;
+use unnamed_hir::{self as hir, ExprId, ExprKind, Hir, Lit, PatId, PatKind};
+use unnamed_typeck::{InstanceId, Instances, Subst, TyId, TypeckResults};
+
+use crate::{Block, Body, Callee, Const, InstKind, Local, Mir, Projection, Terminator, Value};
+
+/// Lowers the body of every instance. Calls and overloaded operators refer
+/// to the instances `instances` resolved them to.
+pub fn lower(hir: &Hir, instances: &Instances, results: &mut TypeckResults) -> Mir {
+    let mut mir = Mir::default();
+    for instance in instances.instances.keys() {
+        let body = BodyLowerer::new(hir, instances, results, instance).lower();
+        mir.bodies.push(body);
+    }
+
+    mir
+}
+
+struct BodyLowerer<'a> {
+    hir: &'a Hir,
+    instances: &'a Instances,
+    results: &'a mut TypeckResults,
+    instance: InstanceId,
+    subst: Subst,
+    body: Body,
+    /// The block instructions are appended to.
+    block: Block,
+    locals: HashMap<unnamed_resolve::LocalId, Local>,
+    /// The block after each enclosing loop, innermost last.
+    loops: Vec<Block>,
+}
+
+impl<'a> BodyLowerer<'a> {
+    fn new(
+        hir: &'a Hir,
+        instances: &'a Instances,
+        results: &'a mut TypeckResults,
+        instance: InstanceId,
+    ) -> Self {
+        let item = instances.instances[instance].item;
+        let subst = instances.subst(results, instance);
+        let ret = results.signatures[&item].ret;
+        let ret = results.tcx.subst(ret, &subst);
+        let span = hir.defs[hir.def(item)].span;
+
+        let body = Body::new(instance, span, ret);
+        Self {
+            hir,
+            instances,
+            results,
+            instance,
+            subst,
+            block: body.entry,
+            body,
+            locals: HashMap::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    fn lower(mut self) -> Body {
+        let item = self.instances.instances[self.instance].item;
+        let hir_body = self
+            .hir
+            .body(self.hir.def(item))
+            .expect("instances have a body");
+
+        for &param in &hir_body.params {
+            let local = self.local(param);
+            let ty = self.body.locals[local].ty;
+            let value = self.body.append_block_param(self.body.entry, ty);
+            self.store(local, Vec::new(), value, self.body.span);
+        }
+
+        if let Some(value) = self.expr(hir_body.value) {
+            self.terminate(Terminator::Return(value));
+        }
+
+        self.body
+    }
+
+    fn ty(&mut self, ty: TyId) -> TyId {
+        self.results.tcx.subst(ty, &self.subst)
+    }
+
+    fn local(&mut self, local: unnamed_resolve::LocalId) -> Local {
+        if let Some(&mir_local) = self.locals.get(&local) {
+            return mir_local;
+        }
+
+        let data = self.hir.locals[local];
+        let ty = self.ty(data.ty);
+        let mir_local = self.body.add_local(ty, Some(data.name));
+        self.locals.insert(local, mir_local);
+
+        mir_local
+    }
+
+    /// The instance called by the expression of the AST, unless it is an
+    /// operator applied to primitive operands.
+    fn callee(&self, ast: Option<ExprEntity>) -> Option<InstanceId> {
+        self.instances.calls.get(&(self.instance, ast?)).copied()
+    }
+
+    fn ret_ty(&mut self, instance: InstanceId) -> TyId {
+        let item = self.instances.instances[instance].item;
+        let subst = self.instances.subst(self.results, instance);
+        let ret = self.results.signatures[&item].ret;
+
+        self.results.tcx.subst(ret, &subst)
+    }
+
+    fn inst(&mut self, kind: InstKind, ty: TyId, span: Span) -> Value {
+        self.body
+            .append_inst(self.block, kind, Some(ty), span)
+            .expect("instructions with a type define a value")
+    }
+
+    fn constant(&mut self, value: Const, ty: TyId, span: Span) -> Value {
+        self.inst(InstKind::Const(value), ty, span)
+    }
+
+    fn unit(&mut self, span: Span) -> Value {
+        let unit = self.results.tcx.unit();
+        self.constant(Const::Unit, unit, span)
+    }
+
+    fn call(&mut self, callee: Callee, args: &[Value], ty: TyId, span: Span) -> Value {
+        let args = self.body.value_list(args);
+        self.inst(InstKind::Call { callee, args }, ty, span)
+    }
+
+    fn load(&mut self, local: Local, projections: Vec<Projection>, ty: TyId, span: Span) -> Value {
+        let place = self.body.add_place(local, projections);
+        self.inst(InstKind::Load(place), ty, span)
+    }
+
+    fn store(&mut self, local: Local, projections: Vec<Projection>, value: Value, span: Span) {
+        let place = self.body.add_place(local, projections);
+        self.body
+            .append_inst(self.block, InstKind::Store { place, value }, None, span);
+    }
+
+    /// Ends the current block. Instructions after it go to a block created
+    /// and switched to by the caller.
+    fn terminate(&mut self, terminator: Terminator) {
+        self.body.blocks[self.block].terminator = terminator;
+    }
+
+    fn jump(&mut self, block: Block, args: &[Value]) {
+        let dest = self.body.block_call(block, args);
+        self.terminate(Terminator::Jump(dest));
+    }
+
+    fn branch(&mut self, cond: Value, then_block: Block, else_block: Block) {
+        let then_dest = self.body.block_call(then_block, &[]);
+        let else_dest = self.body.block_call(else_block, &[]);
+        self.terminate(Terminator::Branch {
+            cond,
+            then_dest,
+            else_dest,
+        });
+    }
+
+    /// Continues in a new block if `cond` holds, and in `fail` otherwise.
+    fn branch_or_fail(&mut self, cond: Value, fail: Block) {
+        let next = self.body.create_block();
+        self.branch(cond, next, fail);
+        self.block = next;
+    }
+
+    /// Lowers `expr`, returning its value unless control never reaches its
+    /// end.
+    fn expr(&mut self, expr: ExprId) -> Option<Value> {
+        let hir = self.hir;
+        let data = hir.exprs[expr];
+        let ty = self.ty(data.ty);
+        let span = data.span;
+
+        let value = match data.kind {
+            ExprKind::Lit(lit) => {
+                let value = match lit {
+                    Lit::Int(value) => Const::Int(value.into()),
+                    Lit::Float(value) => Const::Float(value),
+                    Lit::Bool(value) => Const::Bool(value),
+                    Lit::Char(value) => Const::Char(value),
+                    Lit::Str(value) => Const::Str(value),
+                    Lit::Unit => Const::Unit,
+                };
+                self.constant(value, ty, span)
+            }
+            ExprKind::Local(local) => {
+                let local = self.local(local);
+                self.load(local, Vec::new(), ty, span)
+            }
+            ExprKind::Block { stmts, tail } => {
+                for &stmt in hir.exprs.list(stmts) {
+                    self.expr(stmt)?;
+                }
+                match tail {
+                    Some(tail) => self.expr(tail)?,
+                    None => self.unit(span),
+                }
+            }
+            ExprKind::Let { local, init } => {
+                let local = self.local(local);
+                if let Some(init) = init {
+                    let value = self.expr(init)?;
+                    self.store(local, Vec::new(), value, span);
+                }
+                self.unit(span)
+            }
+            ExprKind::Assign { local, value } => {
+                let local = self.local(local);
+                let value = self.expr(value)?;
+                self.store(local, Vec::new(), value, span);
+                self.unit(span)
+            }
+            ExprKind::Binary { op, lhs, rhs, .. } => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+
+                match self.callee(data.ast) {
+                    Some(callee) => {
+                        let ret = self.ret_ty(callee);
+                        let result = self.call(Callee::Instance(callee), &[lhs, rhs], ret, span);
+
+                        // `!=` negates the result of `eq`, and comparisons
+                        // compare the result of `cmp` with zero.
+                        match op {
+                            BinOp::Neq => {
+                                let false_ = self.constant(Const::Bool(false), ty, span);
+                                let kind = InstKind::Binary {
+                                    op: BinOp::Eq,
+                                    lhs: result,
+                                    rhs: false_,
+                                };
+                                self.inst(kind, ty, span)
+                            }
+                            BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq => {
+                                let zero = self.constant(Const::Int(0), ret, span);
+                                let kind = InstKind::Binary {
+                                    op,
+                                    lhs: result,
+                                    rhs: zero,
+                                };
+                                self.inst(kind, ty, span)
+                            }
+                            _ => result,
+                        }
+                    }
+                    None => self.inst(InstKind::Binary { op, lhs, rhs }, ty, span),
+                }
+            }
+            ExprKind::Unary { op, value, .. } => {
+                let value = self.expr(value)?;
+                match self.callee(data.ast) {
+                    Some(callee) => self.call(Callee::Instance(callee), &[value], ty, span),
+                    None => self.inst(InstKind::Unary { op, value }, ty, span),
+                }
+            }
+            ExprKind::Call { callee, args, .. } => {
+                let mut values = Vec::new();
+                for &arg in hir.exprs.list(args) {
+                    values.push(self.expr(arg)?);
+                }
+
+                let (callee, ty) = match callee {
+                    hir::Callee::Def(_) => {
+                        let callee = self.callee(data.ast).expect("calls are monomorphized");
+                        (Callee::Instance(callee), self.ret_ty(callee))
+                    }
+                    hir::Callee::Builtin(builtin) => (Callee::Builtin(builtin), ty),
+                    hir::Callee::Len => (Callee::Len, ty),
+                };
+                self.call(callee, &values, ty, span)
+            }
+            ExprKind::Adt { def, fields } => {
+                // Fields are evaluated in the order they are written in.
+                let mut values = Vec::new();
+                for &field in hir.field_exprs.list(fields) {
+                    let field = hir.field_exprs[field];
+                    values.push((field.index, self.expr(field.value)?));
+                }
+                values.sort_by_key(|&(index, _)| index);
+
+                let variant = match hir.defs[def].kind {
+                    hir::DefKind::Variant(variant) => Some(variant),
+                    _ => None,
+                };
+                let values = values
+                    .into_iter()
+                    .map(|(_, value)| value)
+                    .collect::<Vec<_>>();
+                let fields = self.body.value_list(&values);
+                self.inst(InstKind::Adt { variant, fields }, ty, span)
+            }
+            ExprKind::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => {
+                let cond = self.expr(cond)?;
+                let then_block = self.body.create_block();
+                let else_block = self.body.create_block();
+                let merge = self.body.create_block();
+                let result = self.body.append_block_param(merge, ty);
+                self.branch(cond, then_block, else_block);
+
+                let mut reached = false;
+                for (block, branch) in [(then_block, then_branch), (else_block, else_branch)] {
+                    self.block = block;
+                    if let Some(value) = self.expr(branch) {
+                        self.jump(merge, &[value]);
+                        reached = true;
+                    }
+                }
+
+                self.block = merge;
+                if !reached {
+                    return None;
+                }
+                result
+            }
+            ExprKind::Loop { body } => {
+                let header = self.body.create_block();
+                let exit = self.body.create_block();
+                self.jump(header, &[]);
+
+                self.block = header;
+                self.loops.push(exit);
+                if self.expr(body).is_some() {
+                    self.jump(header, &[]);
+                }
+                self.loops.pop();
+
+                self.block = exit;
+                self.unit(span)
+            }
+            ExprKind::Break => {
+                let exit = *self.loops.last().expect("`break` is inside a loop");
+                self.jump(exit, &[]);
+                return None;
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                let scrutinee_ty = self.ty(hir.exprs[scrutinee].ty);
+                let value = self.expr(scrutinee)?;
+                let local = self.body.add_local(scrutinee_ty, None);
+                self.store(local, Vec::new(), value, span);
+
+                let merge = self.body.create_block();
+                let result = self.body.append_block_param(merge, ty);
+                let mut reached = false;
+                for &arm in hir.arms.list(arms) {
+                    let arm = hir.arms[arm];
+                    let next = self.body.create_block();
+
+                    self.pat(arm.pat, local, Vec::new(), next);
+                    let guard = match arm.guard {
+                        Some(guard) => self.expr(guard).map(Some),
+                        None => Some(None),
+                    };
+                    if let Some(guard) = guard {
+                        if let Some(guard) = guard {
+                            self.branch_or_fail(guard, next);
+                        }
+                        if let Some(value) = self.expr(arm.body) {
+                            self.jump(merge, &[value]);
+                            reached = true;
+                        }
+                    }
+
+                    self.block = next;
+                }
+
+                // Arms are exhaustive, so that the last one never fails.
+                self.terminate(Terminator::Unreachable);
+                self.block = merge;
+                if !reached {
+                    return None;
+                }
+                result
+            }
+            ExprKind::Array(values) => {
+                let mut elements = Vec::new();
+                for &value in hir.exprs.list(values) {
+                    elements.push(self.expr(value)?);
+                }
+                let elements = self.body.value_list(&elements);
+                self.inst(InstKind::Array(elements), ty, span)
+            }
+            ExprKind::Repeat { value, count } => {
+                let value = self.expr(value)?;
+                let count = self.expr(count)?;
+                self.inst(InstKind::Repeat { value, count }, ty, span)
+            }
+            ExprKind::Field { .. } | ExprKind::Index { .. } => {
+                let (local, projections) = self.place(expr)?;
+                self.load(local, projections, ty, span)
+            }
+            ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => self.unit(span),
+                };
+                self.terminate(Terminator::Return(value));
+                return None;
+            }
+            ExprKind::Error => {
+                self.terminate(Terminator::Unreachable);
+                return None;
+            }
+        };
+
+        Some(value)
+    }
+
+    /// The place `expr` refers to. Values that are not stored in a local
+    /// are stored in a new one.
+    fn place(&mut self, expr: ExprId) -> Option<(Local, Vec<Projection>)> {
+        let data = self.hir.exprs[expr];
+
+        let place = match data.kind {
+            ExprKind::Local(local) => (self.local(local), Vec::new()),
+            ExprKind::Field { base, index } => {
+                let (local, mut projections) = self.place(base)?;
+                projections.push(Projection::Field(index));
+                (local, projections)
+            }
+            ExprKind::Index { base, index } => {
+                let (local, mut projections) = self.place(base)?;
+                let index = self.expr(index)?;
+                projections.push(Projection::Index(index));
+                (local, projections)
+            }
+            _ => {
+                let ty = self.ty(data.ty);
+                let value = self.expr(expr)?;
+                let local = self.body.add_local(ty, None);
+                self.store(local, Vec::new(), value, data.span);
+                (local, Vec::new())
+            }
+        };
+
+        Some(place)
+    }
+
+    /// Tests whether the value at the place matches `pat`, continuing in
+    /// `fail` if it does not, and binds its names.
+    fn pat(&mut self, pat: PatId, local: Local, projections: Vec<Projection>, fail: Block) {
+        let hir = self.hir;
+        let data = hir.pats[pat];
+        let ty = self.ty(data.ty);
+
+        match data.kind {
+            PatKind::Wild | PatKind::Error => {}
+            PatKind::Binding(binding) => {
+                let value = self.load(local, projections, ty, data.span);
+                let binding = self.local(binding);
+                self.store(binding, Vec::new(), value, data.span);
+            }
+            PatKind::Lit(expr) => {
+                let value = self.load(local, projections, ty, data.span);
+                let Some(lit) = self.expr(expr) else {
+                    return;
+                };
+                let bool = self.results.tcx.bool();
+                let kind = InstKind::Binary {
+                    op: BinOp::Eq,
+                    lhs: value,
+                    rhs: lit,
+                };
+                let eq = self.inst(kind, bool, data.span);
+                self.branch_or_fail(eq, fail);
+            }
+            PatKind::Adt { def, fields } => {
+                let mut projections = projections;
+                if let hir::DefKind::Variant(variant) = hir.defs[def].kind {
+                    let place = self.body.add_place(local, projections.clone());
+                    let bool = self.results.tcx.bool();
+                    let is_variant =
+                        self.inst(InstKind::IsVariant { place, variant }, bool, data.span);
+                    self.branch_or_fail(is_variant, fail);
+                    projections.push(Projection::Variant(variant));
+                }
+
+                for &field in hir.field_pats.list(fields) {
+                    let field = hir.field_pats[field];
+                    let mut projections = projections.clone();
+                    projections.push(Projection::Field(field.index));
+                    self.pat(field.pat, local, projections, fail);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use unnamed_ast::AstCtx;
+    use unnamed_common::{Interner, Source};
+    use unnamed_typeck::{Instances, TypeckResults};
+
+    use crate::{Mir, printer::print_mir};
+
+    /// Lowers `content`, which has to check without errors, and hands the
+    /// MIR of its instances to `f`.
+    pub(crate) fn with_mir<R>(
+        content: &str,
+        f: impl FnOnce(&mut Mir, &Instances, &AstCtx, &Interner, &mut TypeckResults) -> R,
+    ) -> R {
+        let interner = Interner::new();
+        let parsed = unnamed_parser::parse(Source::new(content, "test_file"), &interner);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let mut results =
+            unnamed_typeck::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+        assert!(results.errors.is_empty(), "{:?}", results.errors);
+
+        let hir = unnamed_hir::lower(&parsed.ctx, &parsed.module, &resolution, &mut results);
+        let instances = unnamed_typeck::monomorphize(
+            &parsed.ctx,
+            &parsed.module,
+            &interner,
+            &resolution,
+            &mut results,
+        );
+        let mut mir = super::lower(&hir, &instances, &mut results);
+        f(&mut mir, &instances, &parsed.ctx, &interner, &mut results)
+    }
+
+    fn print(content: &str) -> String {
+        with_mir(content, |mir, instances, ctx, interner, results| {
+            print_mir(mir, instances, ctx, interner, results)
+        })
+    }
+
+    #[test]
+    fn test_if() {
+        assert_eq!(
+            print("func f(a: i64) -> i64 { if a > 0 { a } else { -a } }"),
+            "fn f(i64) -> i64 {
+    local0: i64 (a)
+block0(v0: i64):
+    store local0, v0
+    v1: i64 = load local0
+    v2: i64 = const 0
+    v3: bool = gt v1, v2
+    branch v3, block1, block2
+block1:
+    v5: i64 = load local0
+    jump block3(v5)
+block2:
+    v6: i64 = load local0
+    v7: i64 = neg v6
+    jump block3(v7)
+block3(v4: i64):
+    return v4
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_places() {
+        assert_eq!(
+            print(
+                "enum E { A(i64), B } \
+                 struct S { k: i64, e: E } \
+                 func f(s: S, i: i64) -> i64 { \
+                     let n = [s.k, 2][i]; \
+                     match s.e { E::A(x) => x + n, E::B => n } \
+                 }"
+            ),
+            "fn f(S, i64) -> i64 {
+    local0: S (s)
+    local1: i64 (i)
+    local2: i64 (n)
+    local3: [i64]
+    local4: E
+    local5: i64 (x)
+block0(v0: S, v1: i64):
+    store local0, v0
+    store local1, v1
+    v2: i64 = load local0.0
+    v3: i64 = const 2
+    v4: [i64] = array [v2, v3]
+    store local3, v4
+    v5: i64 = load local1
+    v6: i64 = load local3[v5]
+    store local2, v6
+    v7: () = const ()
+    v8: E = load local0.1
+    store local4, v8
+    v10: bool = is_variant local4, A
+    branch v10, block3, block2
+block1(v9: i64):
+    return v9
+block2:
+    v15: bool = is_variant local4, B
+    branch v15, block5, block4
+block3:
+    v11: i64 = load (local4 as A).0
+    store local5, v11
+    v12: i64 = load local5
+    v13: i64 = load local2
+    v14: i64 = add v12, v13
+    jump block1(v14)
+block4:
+    unreachable
+block5:
+    v16: i64 = load local2
+    jump block1(v16)
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_overloaded_operators() {
+        let printed = print(
+            "struct V { x: i64 } \
+             impl Add for V { func add(self, other: V) -> V { V { x: self.x + other.x } } } \
+             impl Eq for V { func eq(self, other: V) -> bool { self.x == other.x } } \
+             func main() -> bool { let v = V { x: 1 }; v + v != v }",
+        );
+        let main = &printed[printed.find("fn main").unwrap()..];
+        assert!(main.contains("= call V::add(v"), "{main}");
+        assert!(main.contains("= call V::eq(v"), "{main}");
+        assert!(main.contains("= eq v"), "{main}");
+    }
+}
@@ -0,0 +1,346 @@
+//! Simplifications of bodies that keep what programs print and return,
+//! including where they stop with a runtime error.
+
+use std::{collections::HashMap, mem};
+
+use cranelift_entity::{PrimaryMap, SecondaryMap, packed_option::PackedOption};
+use unnamed_common::Interner;
+use unnamed_typeck::TypeckResults;
+
+use crate::{
+    Block, BlockData, Body, Const, Mir, Terminator, Value, ValueDef,
+    cfg::{predecessors, reverse_postorder},
+    dce::{forward_loads, remove_dead_stores, remove_dead_values},
+    fold::{constant, fold_constants},
+};
+
+/// How much effort to spend on optimizing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// Keep bodies the way they were lowered.
+    O0,
+    /// Run every pass once, except for those about locals.
+    #[default]
+    O1,
+    /// Also forward stored values to loads and remove dead stores, and run
+    /// all passes until they stop finding anything to simplify.
+    O2,
+}
+
+/// Optimizes every body of `mir` at the given level.
+pub fn optimize(mir: &mut Mir, level: OptLevel, interner: &Interner, results: &TypeckResults) {
+    for body in mir.bodies.values_mut() {
+        match level {
+            OptLevel::O0 => {}
+            OptLevel::O1 => {
+                fold_constants(body, interner, results);
+                fold_branches(body);
+                merge_blocks(body);
+                remove_unreachable_blocks(body);
+                remove_trivial_params(body);
+                remove_dead_values(body, results);
+            }
+            OptLevel::O2 => loop {
+                // Every pass has to run, even once one of them changes
+                // something.
+                let changed = [
+                    fold_constants(body, interner, results),
+                    forward_loads(body),
+                    fold_branches(body),
+                    merge_blocks(body),
+                    remove_unreachable_blocks(body),
+                    remove_trivial_params(body),
+                    remove_dead_stores(body),
+                    remove_dead_values(body, results),
+                ];
+                if !changed.contains(&true) {
+                    break;
+                }
+            },
+        }
+    }
+}
+
+/// Turns branches on constant conditions, and those going to the same place
+/// either way, into jumps.
+pub(crate) fn fold_branches(body: &mut Body) -> bool {
+    let mut changed = false;
+
+    for block in body.blocks.keys() {
+        let Terminator::Branch {
+            cond,
+            then_dest,
+            else_dest,
+        } = body.blocks[block].terminator
+        else {
+            continue;
+        };
+
+        let dest = match constant(body, cond) {
+            Some(Const::Bool(true)) => then_dest,
+            Some(Const::Bool(false)) => else_dest,
+            _ if then_dest.block == else_dest.block
+                && body.list(then_dest.args) == body.list(else_dest.args) =>
+            {
+                then_dest
+            }
+            _ => continue,
+        };
+        body.blocks[block].terminator = Terminator::Jump(dest);
+        changed = true;
+    }
+
+    changed
+}
+
+/// Appends blocks to the block jumping to them, if no other block does,
+/// replacing their parameters by the arguments of the jump. The appended
+/// blocks are left unreachable.
+pub(crate) fn merge_blocks(body: &mut Body) -> bool {
+    let mut preds = predecessors(body);
+    let mut replaced = HashMap::new();
+    let mut changed = false;
+
+    for block in reverse_postorder(body) {
+        // Blocks already appended to another one are left empty.
+        while let Terminator::Jump(dest) = body.blocks[block].terminator {
+            let next = dest.block;
+            if next == block || next == body.entry || preds[next].len() != 1 {
+                break;
+            }
+
+            let args = body.list(dest.args).to_vec();
+            let data = mem::replace(
+                &mut body.blocks[next],
+                BlockData {
+                    params: Vec::new(),
+                    insts: Vec::new(),
+                    terminator: Terminator::Unreachable,
+                },
+            );
+            for (param, arg) in data.params.into_iter().zip(args) {
+                replaced.insert(param, resolve(&replaced, arg));
+            }
+            for succ in data.terminator.successors() {
+                for pred in &mut preds[succ.block] {
+                    if *pred == next {
+                        *pred = block;
+                    }
+                }
+            }
+            preds[next].clear();
+
+            let merged = &mut body.blocks[block];
+            merged.insts.extend(data.insts);
+            merged.terminator = data.terminator;
+            changed = true;
+        }
+    }
+
+    if !replaced.is_empty() {
+        body.map_values(|value| resolve(&replaced, value));
+    }
+
+    changed
+}
+
+/// Removes the blocks that cannot be reached from the entry, numbering the
+/// remaining ones in the same order as before.
+pub(crate) fn remove_unreachable_blocks(body: &mut Body) -> bool {
+    let rpo = reverse_postorder(body);
+    if rpo.len() == body.blocks.len() {
+        return false;
+    }
+
+    let mut reachable = SecondaryMap::<Block, bool>::new();
+    for &block in &rpo {
+        reachable[block] = true;
+    }
+
+    let mut blocks = PrimaryMap::<Block, _>::new();
+    let mut renamed = SecondaryMap::<Block, PackedOption<Block>>::new();
+    for (block, data) in mem::take(&mut body.blocks) {
+        if reachable[block] {
+            renamed[block] = blocks.push(data).into();
+        }
+    }
+    let rename = |block: Block| renamed[block].expect("blocks jumped to are reachable");
+
+    body.entry = rename(body.entry);
+    for (block, data) in blocks.iter_mut() {
+        for (i, &param) in data.params.iter().enumerate() {
+            body.values[param].def = ValueDef::Param(block, i as u32);
+        }
+        match &mut data.terminator {
+            Terminator::Jump(dest) => dest.block = rename(dest.block),
+            Terminator::Branch {
+                then_dest,
+                else_dest,
+                ..
+            } => {
+                then_dest.block = rename(then_dest.block);
+                else_dest.block = rename(else_dest.block);
+            }
+            Terminator::Return(_) | Terminator::Unreachable => {}
+        }
+    }
+    body.blocks = blocks;
+
+    true
+}
+
+/// Replaces the parameters of blocks that are passed the same value by every
+/// jump to them, other than themselves, by that value. It dominates the
+/// block, as it dominates every block jumping to it.
+pub(crate) fn remove_trivial_params(body: &mut Body) -> bool {
+    let preds = predecessors(body);
+    let mut replaced = HashMap::new();
+
+    for block in body.blocks.keys() {
+        if block == body.entry {
+            continue;
+        }
+
+        for index in (0..body.blocks[block].params.len()).rev() {
+            let param = body.blocks[block].params[index];
+            let mut incoming = None;
+            let mut trivial = true;
+            for &pred in &preds[block] {
+                for dest in body.blocks[pred].terminator.successors() {
+                    if dest.block != block {
+                        continue;
+                    }
+                    let arg = resolve(&replaced, body.list(dest.args)[index]);
+                    if arg == param || incoming == Some(arg) {
+                        continue;
+                    }
+                    trivial &= incoming.is_none();
+                    incoming = Some(arg);
+                }
+            }
+
+            if let (true, Some(incoming)) = (trivial, incoming) {
+                replaced.insert(param, incoming);
+                body.remove_block_param(block, index);
+            }
+        }
+    }
+
+    if replaced.is_empty() {
+        return false;
+    }
+
+    body.map_values(|value| resolve(&replaced, value));
+    true
+}
+
+/// The value that replaces `value`, through any number of replacements.
+pub(crate) fn resolve(replaced: &HashMap<Value, Value>, mut value: Value) -> Value {
+    while let Some(&replacement) = replaced.get(&value) {
+        value = replacement;
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OptLevel, optimize};
+    use crate::{lower::tests::with_mir, printer::print_mir, verify};
+
+    fn print(content: &str, level: OptLevel) -> String {
+        with_mir(content, |mir, instances, ctx, interner, results| {
+            optimize(mir, level, interner, results);
+            let errors = verify(mir, ctx, interner, results);
+            assert!(errors.is_empty(), "{errors:?}");
+            print_mir(mir, instances, ctx, interner, results)
+        })
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(
+            print(
+                "func f() -> i64 { if false { println(\"no\"); } 1 << 3 + 2 }",
+                OptLevel::O1
+            ),
+            "fn f() -> i64 {
+block0:
+    v10: i64 = const 32
+    return v10
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_runtime_errors_kept() {
+        let printed = print(
+            "func f() -> i64 { \
+                 let a = [1]; let x = 127i8 + 1i8; let y = 1 / 0; let z = 1 << 64; \
+                 let w = -(0u8 - 1u8); let e = a[5]; \
+                 0 \
+             }",
+            OptLevel::O2,
+        );
+        for kept in ["add", "div", "shl", "sub", "load local0[v"] {
+            assert!(printed.contains(&format!("= {kept}")), "{printed}");
+        }
+    }
+
+    #[test]
+    fn test_dead_stores() {
+        assert_eq!(
+            print(
+                "func f(a: i64) -> i64 { let x = 2; let y = x * 3; x = a; if a > y { x } else { y } }",
+                OptLevel::O2
+            ),
+            "fn f(i64) -> i64 {
+    local0: i64 (a)
+    local1: i64 (x)
+    local2: i64 (y)
+block0(v0: i64):
+    v5: i64 = const 6
+    v11: bool = gt v0, v5
+    branch v11, block1, block2
+block1:
+    jump block3(v0)
+block2:
+    jump block3(v5)
+block3(v12: i64):
+    return v12
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_levels() {
+        let content = "enum Shape { Circle(f64), Square { side: f64 } } \
+                       struct V { x: i64 } \
+                       impl Add for V { func add(self, other: V) -> V { V { x: self.x + other.x } } } \
+                       impl Eq for V { func eq(self, other: V) -> bool { self.x == other.x } } \
+                       func area(s: Shape) -> f64 { \
+                           match s { Shape::Circle(r) => r * r, Shape::Square { side } => side * side } \
+                       } \
+                       func main() -> i64 { \
+                           let a = [1, 2, 3]; let n = 0; let i = 0; \
+                           while i < a.len() || false { n = n + a[i]; i = i + 1; if n > 100 { return n; } } \
+                           let v = V { x: 1 } + V { x: 2 }; \
+                           if v != v && \"a\" + \"b\" == \"ab\" { return 1; } \
+                           area(Shape::Square { side: 2.0 * 0.5 }); \
+                           let c = 'a' < 'b'; \
+                           n + [0; 2].len() \
+                       }";
+
+        let o0 = print(content, OptLevel::O0);
+        let o1 = print(content, OptLevel::O1);
+        let o2 = print(content, OptLevel::O2);
+        assert!(o0.lines().count() > o1.lines().count());
+        assert!(o1.lines().count() > o2.lines().count());
+        // The comparison of strings is folded away with their concatenation.
+        assert!(o0.contains(": str = add"), "{o0}");
+        assert!(!o1.contains(": str"), "{o1}");
+        assert!(o1.contains("f64 = const 1.0"), "{o1}");
+    }
+}
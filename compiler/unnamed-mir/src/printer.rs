@@ -0,0 +1,236 @@
+use std::fmt::Write;
+
+use unnamed_ast::{AstCtx, BinOp, expr::UnaryOp};
+use unnamed_common::Interner;
+use unnamed_typeck::{Instances, TyId, TypeckResults};
+
+use crate::{
+    Block, BlockCall, Body, Callee, Const, Inst, InstKind, Mir, Place, Projection, Terminator,
+    Value,
+};
+
+/// Prints every body of `mir` as its locals followed by its blocks, with one
+/// instruction per line.
+pub fn print_mir(
+    mir: &Mir,
+    instances: &Instances,
+    ctx: &AstCtx,
+    interner: &Interner,
+    results: &mut TypeckResults,
+) -> String {
+    let mut out = String::new();
+    for (i, body) in mir.bodies.values().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let name = instances
+            .display(body.instance, ctx, interner, results)
+            .to_string();
+        let mut printer = MirPrinter {
+            instances,
+            body,
+            ctx,
+            interner,
+            results: &mut *results,
+            out: &mut out,
+        };
+        printer.body(&name);
+    }
+
+    out
+}
+
+struct MirPrinter<'a> {
+    instances: &'a Instances,
+    body: &'a Body,
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    results: &'a mut TypeckResults,
+    out: &'a mut String,
+}
+
+impl MirPrinter<'_> {
+    fn ty(&self, ty: TyId) -> String {
+        self.results
+            .tcx
+            .display(ty, self.ctx, self.interner)
+            .to_string()
+    }
+
+    fn typed_value(&self, value: Value) -> String {
+        format!("{value}: {}", self.ty(self.body.values[value].ty))
+    }
+
+    fn values(&self, values: &[Value]) -> String {
+        let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+        values.join(", ")
+    }
+
+    fn body(&mut self, name: &str) {
+        let body = self.body;
+        let params = body.blocks[body.entry].params.clone();
+        let params = params.iter().map(|&param| self.ty(body.values[param].ty));
+        let params = params.collect::<Vec<_>>().join(", ");
+        let ret = self.ty(body.ret);
+        let _ = writeln!(self.out, "fn {name}({params}) -> {ret} {{");
+
+        for (local, data) in body.locals.iter() {
+            let mut line = format!("{local}: {}", self.ty(data.ty));
+            if let Some(name) = data.name {
+                let _ = write!(line, " ({})", self.interner.get(name));
+            }
+            let _ = writeln!(self.out, "    {line}");
+        }
+
+        for block in body.blocks.keys() {
+            self.block(block);
+        }
+        let _ = writeln!(self.out, "}}");
+    }
+
+    fn block(&mut self, block: Block) {
+        let data = &self.body.blocks[block];
+        let params = data.params.iter().map(|&param| self.typed_value(param));
+        let params = params.collect::<Vec<_>>();
+        let header = match params.is_empty() {
+            true => block.to_string(),
+            false => format!("{block}({})", params.join(", ")),
+        };
+        let _ = writeln!(self.out, "{header}:");
+
+        for &inst in &data.insts {
+            let line = self.inst(inst);
+            let _ = writeln!(self.out, "    {line}");
+        }
+        let line = self.terminator(data.terminator);
+        let _ = writeln!(self.out, "    {line}");
+    }
+
+    fn inst(&mut self, inst: Inst) -> String {
+        let body = self.body;
+        let data = body.insts[inst];
+
+        let kind = match data.kind {
+            InstKind::Const(value) => match value {
+                Const::Int(value) => format!("const {value}"),
+                Const::Float(value) => format!("const {value:?}"),
+                Const::Bool(value) => format!("const {value}"),
+                Const::Char(value) => format!("const {value:?}"),
+                Const::Str(value) => format!("const {:?}", self.interner.get(value)),
+                Const::Unit => "const ()".to_owned(),
+            },
+            InstKind::Binary { op, lhs, rhs } => format!("{} {lhs}, {rhs}", binary_name(op)),
+            InstKind::Unary { op, value } => format!("{} {value}", unary_name(op)),
+            InstKind::Call { callee, args } => {
+                let callee = match callee {
+                    Callee::Instance(instance) => self
+                        .instances
+                        .display(instance, self.ctx, self.interner, self.results)
+                        .to_string(),
+                    Callee::Builtin(builtin) => builtin.name().to_owned(),
+                    Callee::Len => "len".to_owned(),
+                };
+                format!("call {callee}({})", self.values(body.list(args)))
+            }
+            InstKind::Load(place) => format!("load {}", self.place(place)),
+            InstKind::Store { place, value } => format!("store {}, {value}", self.place(place)),
+            InstKind::IsVariant { place, variant } => {
+                let name = self.interner.get(self.ctx.variants[variant].name);
+                format!("is_variant {}, {name}", self.place(place))
+            }
+            InstKind::Adt { variant, fields } => {
+                let fields = self.values(body.list(fields));
+                match variant {
+                    Some(variant) => {
+                        let name = self.interner.get(self.ctx.variants[variant].name);
+                        format!("adt {name}({fields})")
+                    }
+                    None => format!("adt ({fields})"),
+                }
+            }
+            InstKind::Array(elements) => format!("array [{}]", self.values(body.list(elements))),
+            InstKind::Repeat { value, count } => format!("repeat {value}, {count}"),
+        };
+
+        match data.result {
+            Some(result) => format!("{} = {kind}", self.typed_value(result)),
+            None => kind,
+        }
+    }
+
+    /// A place like `(local0 as Circle).0[v2]`.
+    fn place(&self, place: Place) -> String {
+        let data = &self.body.places[place];
+        let mut out = data.local.to_string();
+        for &projection in &data.projections {
+            match projection {
+                Projection::Field(index) => {
+                    let _ = write!(out, ".{index}");
+                }
+                Projection::Variant(variant) => {
+                    let name = self.interner.get(self.ctx.variants[variant].name);
+                    out = format!("({out} as {name})");
+                }
+                Projection::Index(index) => {
+                    let _ = write!(out, "[{index}]");
+                }
+            }
+        }
+
+        out
+    }
+
+    fn block_call(&self, dest: BlockCall) -> String {
+        let args = self.body.list(dest.args);
+        match args.is_empty() {
+            true => dest.block.to_string(),
+            false => format!("{}({})", dest.block, self.values(args)),
+        }
+    }
+
+    fn terminator(&self, terminator: Terminator) -> String {
+        match terminator {
+            Terminator::Jump(dest) => format!("jump {}", self.block_call(dest)),
+            Terminator::Branch {
+                cond,
+                then_dest,
+                else_dest,
+            } => format!(
+                "branch {cond}, {}, {}",
+                self.block_call(then_dest),
+                self.block_call(else_dest)
+            ),
+            Terminator::Return(value) => format!("return {value}"),
+            Terminator::Unreachable => "unreachable".to_owned(),
+        }
+    }
+}
+
+fn binary_name(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "add",
+        BinOp::Sub => "sub",
+        BinOp::Mul => "mul",
+        BinOp::Div => "div",
+        BinOp::Eq => "eq",
+        BinOp::Neq => "ne",
+        BinOp::Lt => "lt",
+        BinOp::LtEq => "le",
+        BinOp::Gt => "gt",
+        BinOp::GtEq => "ge",
+        BinOp::And => "and",
+        BinOp::Or => "or",
+        BinOp::BitOr => "bor",
+        BinOp::BitAnd => "band",
+        BinOp::BitXor => "bxor",
+        BinOp::BitShr => "shr",
+        BinOp::BitShl => "shl",
+    }
+}
+
+fn unary_name(op: UnaryOp) -> &'static str {
+    match op {
+        UnaryOp::Add => "pos",
+        UnaryOp::Sub => "neg",
+    }
+}
@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+
+use cranelift_entity::{EntityRef, SecondaryMap};
+use unnamed_ast::{AstCtx, BinOp, Item, ItemEntity, VariantEntity};
+use unnamed_common::{Interner, Span};
+use unnamed_typeck::{Subst, TyId, TyKind, TyList, TypeckResults};
+
+use crate::{
+    Block, BlockCall, Body, Callee, Const, Error, ErrorKind, Inst, InstKind, Mir, Place,
+    Projection, Terminator, Value,
+    cfg::{DomTree, predecessors},
+};
+
+/// Checks that every value of every body is defined before it is used, in a
+/// block dominating the use, that every local is stored to on every path to
+/// its loads, and that instructions and terminators are applied to values of
+/// the types they expect.
+pub fn verify(
+    mir: &Mir,
+    ctx: &AstCtx,
+    interner: &Interner,
+    results: &mut TypeckResults,
+) -> Vec<Error> {
+    let mut errors = Vec::new();
+    for body in mir.bodies.values() {
+        let mut verifier = Verifier {
+            mir,
+            body,
+            ctx,
+            interner,
+            results: &mut *results,
+            defs: HashMap::new(),
+            errors: Vec::new(),
+        };
+        verifier.verify();
+        errors.append(&mut verifier.errors);
+    }
+
+    errors
+}
+
+/// Where a value is defined: parameters at position 0 of their block, and
+/// the results of instructions after them in order.
+type DefPos = (Block, usize);
+
+/// The position of terminators within their block.
+const TERMINATOR: usize = usize::MAX;
+
+struct Verifier<'a> {
+    mir: &'a Mir,
+    body: &'a Body,
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    results: &'a mut TypeckResults,
+    defs: HashMap<Value, DefPos>,
+    errors: Vec<Error>,
+}
+
+impl Verifier<'_> {
+    fn verify(&mut self) {
+        let body = self.body;
+        for (block, data) in body.blocks.iter() {
+            for &param in &data.params {
+                self.defs.insert(param, (block, 0));
+            }
+            for (i, &inst) in data.insts.iter().enumerate() {
+                if let Some(result) = body.insts[inst].result {
+                    self.defs.insert(result, (block, i + 1));
+                }
+            }
+        }
+
+        let domtree = DomTree::new(body);
+        for &block in domtree.rpo() {
+            let data = &body.blocks[block];
+            for (i, &inst) in data.insts.iter().enumerate() {
+                let span = body.insts[inst].span;
+                for value in body.inst_args(inst) {
+                    self.check_dominance(&domtree, value, (block, i + 1), inst.to_string(), span);
+                }
+            }
+            for value in body.terminator_args(block) {
+                let pos = (block, TERMINATOR);
+                self.check_dominance(&domtree, value, pos, block.to_string(), body.span);
+            }
+        }
+
+        self.check_stores(&domtree);
+
+        for (block, data) in body.blocks.iter() {
+            for &inst in &data.insts {
+                self.check_inst(inst);
+            }
+            self.check_terminator(block);
+        }
+    }
+
+    /// Checks that each local is stored to on every path from the entry to
+    /// each load of it. Which locals are stored at the end of each block is
+    /// computed by iterating over the blocks until it stops changing,
+    /// starting from all of them but at the entry.
+    fn check_stores(&mut self, domtree: &DomTree) {
+        let body = self.body;
+        let preds = predecessors(body);
+        let mut exits = SecondaryMap::with_default(vec![true; body.locals.len()]);
+
+        let entry_state = |block: Block, exits: &SecondaryMap<Block, Vec<bool>>| {
+            let mut stored = vec![block != body.entry; body.locals.len()];
+            if block != body.entry {
+                for &pred in &preds[block] {
+                    for (stored, &pred_stored) in stored.iter_mut().zip(&exits[pred]) {
+                        *stored &= pred_stored;
+                    }
+                }
+            }
+            stored
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in domtree.rpo() {
+                let mut stored = entry_state(block, &exits);
+                for &inst in &body.blocks[block].insts {
+                    if let InstKind::Store { place, .. } = body.insts[inst].kind {
+                        stored[body.places[place].local.index()] = true;
+                    }
+                }
+
+                if exits[block] != stored {
+                    exits[block] = stored;
+                    changed = true;
+                }
+            }
+        }
+
+        for &block in domtree.rpo() {
+            let mut stored = entry_state(block, &exits);
+            for &inst in &body.blocks[block].insts {
+                let (place, is_store) = match body.insts[inst].kind {
+                    InstKind::Store { place, .. } => (place, true),
+                    InstKind::Load(place) | InstKind::IsVariant { place, .. } => (place, false),
+                    _ => continue,
+                };
+
+                let local = body.places[place].local;
+                if !is_store && !stored[local.index()] {
+                    let kind = ErrorKind::UnstoredLoad {
+                        local: local.to_string(),
+                        user: inst.to_string(),
+                    };
+                    self.error(body.insts[inst].span, kind);
+                }
+                stored[local.index()] = true;
+            }
+        }
+    }
+
+    fn check_dominance(
+        &mut self,
+        domtree: &DomTree,
+        value: Value,
+        (block, pos): DefPos,
+        user: String,
+        span: Span,
+    ) {
+        let defined = match self.defs.get(&value) {
+            Some(&(def_block, def_pos)) if def_block == block => def_pos < pos,
+            Some(&(def_block, _)) => domtree.dominates(def_block, block),
+            None => false,
+        };
+
+        if !defined {
+            let value = value.to_string();
+            self.error(span, ErrorKind::UndominatedUse { value, user });
+        }
+    }
+
+    fn error(&mut self, span: Span, kind: ErrorKind) {
+        self.errors.push(Error::new(span, kind));
+    }
+
+    fn display(&self, ty: TyId) -> String {
+        self.results
+            .tcx
+            .display(ty, self.ctx, self.interner)
+            .to_string()
+    }
+
+    fn ty(&self, value: Value) -> TyId {
+        self.body.values[value].ty
+    }
+
+    fn kind(&self, ty: TyId) -> TyKind {
+        self.results.tcx[ty]
+    }
+
+    fn expect_ty(&mut self, user: &str, span: Span, expected: TyId, found: TyId) {
+        if expected != found {
+            let kind = ErrorKind::TypeMismatch {
+                user: user.to_owned(),
+                expected: self.display(expected),
+                found: self.display(found),
+            };
+            self.error(span, kind);
+        }
+    }
+
+    /// Checks that the values have the given types, one for each.
+    fn expect_tys(&mut self, user: &str, span: Span, expected: &[TyId], values: &[Value]) {
+        if expected.len() != values.len() {
+            let kind = ErrorKind::ArgCount {
+                user: user.to_owned(),
+                expected: expected.len(),
+                found: values.len(),
+            };
+            self.error(span, kind);
+            return;
+        }
+
+        for (&expected, &value) in expected.iter().zip(values) {
+            self.expect_ty(user, span, expected, self.ty(value));
+        }
+    }
+
+    fn invalid_type(&mut self, user: &str, span: Span, ty: TyId) {
+        let ty = self.display(ty);
+        let user = user.to_owned();
+        self.error(span, ErrorKind::InvalidType { user, ty });
+    }
+
+    /// The field types of the struct, or of the variant of the enum, with
+    /// the type arguments of `args`.
+    fn fields(
+        &mut self,
+        item: ItemEntity,
+        args: TyList,
+        variant: Option<VariantEntity>,
+    ) -> Option<Vec<TyId>> {
+        let fields = match variant {
+            Some(variant) => {
+                let Item::Enum(enum_item) = self.ctx.items[item] else {
+                    return None;
+                };
+                if !self
+                    .ctx
+                    .variants
+                    .list(enum_item.variants)
+                    .contains(&variant)
+                {
+                    return None;
+                }
+                self.results.variants[&variant].clone()
+            }
+            None => {
+                let fields = self.results.fields.get(&item)?;
+                fields.iter().map(|&(_, ty)| ty).collect()
+            }
+        };
+
+        let args = self.results.tcx.list(args).to_vec();
+        let subst = Subst::new(&self.results.generics[&item], &args, None);
+        let fields = fields
+            .into_iter()
+            .map(|ty| self.results.tcx.subst(ty, &subst))
+            .collect();
+
+        Some(fields)
+    }
+
+    /// The type of the value at the place, `None` if its projections do not
+    /// apply to the types they select from.
+    fn place_ty(&mut self, place: Place, user: &str, span: Span) -> Option<TyId> {
+        let data = &self.body.places[place];
+        let mut ty = self.body.locals[data.local].ty;
+        let mut variant = None;
+
+        for &projection in &data.projections {
+            let next = match (projection, self.kind(ty)) {
+                (Projection::Field(index), TyKind::Adt(item, args)) => self
+                    .fields(item, args, variant.take())
+                    .and_then(|fields| fields.get(index as usize).copied()),
+                (Projection::Variant(selected), TyKind::Adt(item, args)) if variant.is_none() => {
+                    variant = Some(selected);
+                    self.fields(item, args, variant).map(|_| ty)
+                }
+                (Projection::Index(index), TyKind::Array(elem)) => {
+                    if !matches!(self.kind(self.ty(index)), TyKind::Int(_)) {
+                        self.invalid_type(user, span, self.ty(index));
+                    }
+                    Some(elem)
+                }
+                _ => None,
+            };
+
+            match next {
+                Some(next) => ty = next,
+                None => {
+                    self.invalid_type(user, span, ty);
+                    return None;
+                }
+            }
+        }
+
+        Some(ty)
+    }
+
+    fn check_inst(&mut self, inst: Inst) {
+        let body = self.body;
+        let data = body.insts[inst];
+        let user = inst.to_string();
+        let user = user.as_str();
+        let span = data.span;
+
+        let Some(result) = data.result else {
+            if let InstKind::Store { place, value } = data.kind
+                && let Some(ty) = self.place_ty(place, user, span)
+            {
+                self.expect_ty(user, span, ty, self.ty(value));
+            }
+            return;
+        };
+        let ty = self.ty(result);
+        let bool = self.results.tcx.bool();
+
+        match data.kind {
+            InstKind::Const(value) => {
+                let valid = match (value, self.kind(ty)) {
+                    (Const::Int(value), TyKind::Int(int)) => {
+                        (int.min()..=int.max()).contains(&value)
+                    }
+                    (Const::Float(_), TyKind::Float(_))
+                    | (Const::Bool(_), TyKind::Bool)
+                    | (Const::Char(_), TyKind::Char)
+                    | (Const::Str(_), TyKind::Str)
+                    | (Const::Unit, TyKind::Unit) => true,
+                    _ => false,
+                };
+                if !valid {
+                    self.invalid_type(user, span, ty);
+                }
+            }
+            InstKind::Binary { op, lhs, rhs } => {
+                let lhs_ty = self.ty(lhs);
+                let rhs_ty = self.ty(rhs);
+                let kind = self.kind(lhs_ty);
+                let numeric = matches!(kind, TyKind::Int(_) | TyKind::Float(_));
+
+                let (valid, expected) = match op {
+                    BinOp::BitShl | BinOp::BitShr => {
+                        let valid = matches!(kind, TyKind::Int(_))
+                            && matches!(self.kind(rhs_ty), TyKind::Int(_));
+                        (valid, lhs_ty)
+                    }
+                    BinOp::Eq | BinOp::Neq => (true, bool),
+                    BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq => {
+                        let ordered = matches!(kind, TyKind::Bool | TyKind::Char | TyKind::Str);
+                        (numeric || ordered, bool)
+                    }
+                    BinOp::Add => (numeric || kind == TyKind::Str, lhs_ty),
+                    BinOp::Sub | BinOp::Mul | BinOp::Div => (numeric, lhs_ty),
+                    BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => {
+                        (matches!(kind, TyKind::Int(_) | TyKind::Bool), lhs_ty)
+                    }
+                    // Short-circuiting operators are lowered to branches.
+                    BinOp::And | BinOp::Or => (false, bool),
+                };
+
+                if !valid {
+                    self.invalid_type(user, span, lhs_ty);
+                }
+                if !matches!(op, BinOp::BitShl | BinOp::BitShr) {
+                    self.expect_ty(user, span, lhs_ty, rhs_ty);
+                }
+                self.expect_ty(user, span, expected, ty);
+            }
+            InstKind::Unary { value, .. } => {
+                let value_ty = self.ty(value);
+                if !matches!(self.kind(value_ty), TyKind::Int(_) | TyKind::Float(_)) {
+                    self.invalid_type(user, span, value_ty);
+                }
+                self.expect_ty(user, span, value_ty, ty);
+            }
+            InstKind::Call { callee, args } => {
+                let args = body.list(args);
+                match callee {
+                    Callee::Instance(instance) => {
+                        let callee = &self.mir.bodies[instance];
+                        self.expect_tys(user, span, &callee.params(), args);
+                        self.expect_ty(user, span, callee.ret, ty);
+                    }
+                    Callee::Builtin(_) => {
+                        let unit = self.results.tcx.unit();
+                        self.expect_ty(user, span, unit, ty);
+                    }
+                    Callee::Len => {
+                        let [value] = args else {
+                            let kind = ErrorKind::ArgCount {
+                                user: user.to_owned(),
+                                expected: 1,
+                                found: args.len(),
+                            };
+                            self.error(span, kind);
+                            return;
+                        };
+                        let value_ty = self.ty(*value);
+                        if !matches!(self.kind(value_ty), TyKind::Array(_) | TyKind::Str) {
+                            self.invalid_type(user, span, value_ty);
+                        }
+                        if !matches!(self.kind(ty), TyKind::Int(_)) {
+                            self.invalid_type(user, span, ty);
+                        }
+                    }
+                }
+            }
+            InstKind::Load(place) => {
+                if let Some(place_ty) = self.place_ty(place, user, span) {
+                    self.expect_ty(user, span, place_ty, ty);
+                }
+            }
+            InstKind::Store { .. } => {
+                let unit = self.results.tcx.unit();
+                self.expect_ty(user, span, unit, ty);
+            }
+            InstKind::IsVariant { place, variant } => {
+                if let Some(place_ty) = self.place_ty(place, user, span) {
+                    let valid = match self.kind(place_ty) {
+                        TyKind::Adt(item, args) => self.fields(item, args, Some(variant)).is_some(),
+                        _ => false,
+                    };
+                    if !valid {
+                        self.invalid_type(user, span, place_ty);
+                    }
+                }
+                self.expect_ty(user, span, bool, ty);
+            }
+            InstKind::Adt { variant, fields } => {
+                let expected = match self.kind(ty) {
+                    TyKind::Adt(item, args) => self.fields(item, args, variant),
+                    _ => None,
+                };
+                match expected {
+                    Some(expected) => self.expect_tys(user, span, &expected, body.list(fields)),
+                    None => self.invalid_type(user, span, ty),
+                }
+            }
+            InstKind::Array(elements) => {
+                let TyKind::Array(elem) = self.kind(ty) else {
+                    self.invalid_type(user, span, ty);
+                    return;
+                };
+                for &element in body.list(elements) {
+                    self.expect_ty(user, span, elem, self.ty(element));
+                }
+            }
+            InstKind::Repeat { value, count } => {
+                let TyKind::Array(elem) = self.kind(ty) else {
+                    self.invalid_type(user, span, ty);
+                    return;
+                };
+                self.expect_ty(user, span, elem, self.ty(value));
+                let count_ty = self.ty(count);
+                if !matches!(self.kind(count_ty), TyKind::Int(_)) {
+                    self.invalid_type(user, span, count_ty);
+                }
+            }
+        }
+    }
+
+    fn check_terminator(&mut self, block: Block) {
+        let body = self.body;
+        let user = block.to_string();
+        let user = user.as_str();
+        let span = body.span;
+
+        match body.blocks[block].terminator {
+            Terminator::Jump(dest) => self.check_block_call(user, dest),
+            Terminator::Branch {
+                cond,
+                then_dest,
+                else_dest,
+            } => {
+                let bool = self.results.tcx.bool();
+                self.expect_ty(user, span, bool, self.ty(cond));
+                self.check_block_call(user, then_dest);
+                self.check_block_call(user, else_dest);
+            }
+            Terminator::Return(value) => self.expect_ty(user, span, body.ret, self.ty(value)),
+            Terminator::Unreachable => {}
+        }
+    }
+
+    fn check_block_call(&mut self, user: &str, dest: BlockCall) {
+        let body = self.body;
+        let params = body.blocks[dest.block].params.clone();
+        let params = params
+            .iter()
+            .map(|&param| self.ty(param))
+            .collect::<Vec<_>>();
+        self.expect_tys(user, body.span, &params, body.list(dest.args));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ErrorKind, InstKind, Terminator, lower::tests::with_mir};
+
+    const PROGRAM: &str = "enum Shape { Circle(f64), Square { side: f64 } } \
+                           struct V { x: i64 } \
+                           impl Add for V { func add(self, other: V) -> V { V { x: self.x + other.x } } } \
+                           impl Ord for V { func cmp(self, other: V) -> i64 { self.x - other.x } } \
+                           func area(s: Shape) -> f64 { \
+                               match s { Shape::Circle(r) => r * r, Shape::Square { side } => side * side } \
+                           } \
+                           func id<T>(x: T) -> T { x } \
+                           func main() -> i64 { \
+                               let a = [1, 2, 3]; let n = 0; let i = 0; \
+                               while i < a.len() && n >= 0 { n = n + a[i]; i = i + 1; } \
+                               let v = V { x: 1 } + V { x: 2 }; let w = V { x: 0 }; \
+                               if v > w { println(\"gt\"); } \
+                               area(Shape::Square { side: 2.0 }); \
+                               id(n) + [0; 2].len() \
+                           }";
+
+    fn kinds(errors: Vec<crate::Error>) -> Vec<ErrorKind> {
+        errors.iter().map(|error| error.kind().clone()).collect()
+    }
+
+    #[test]
+    fn test_lowered() {
+        let errors = with_mir(PROGRAM, |mir, _, ctx, interner, results| {
+            super::verify(mir, ctx, interner, results)
+        });
+        assert_eq!(kinds(errors), []);
+    }
+
+    #[test]
+    fn test_unstored_load() {
+        let errors = with_mir(
+            "func f(a: i64) -> i64 { a }",
+            |mir, _, ctx, interner, results| {
+                // Drops the store of the parameter, leaving its load reading
+                // nothing.
+                let body = mir.bodies.values_mut().next().unwrap();
+                let entry = body.entry;
+                body.blocks[entry]
+                    .insts
+                    .retain(|&inst| !matches!(body.insts[inst].kind, InstKind::Store { .. }));
+                super::verify(mir, ctx, interner, results)
+            },
+        );
+        assert_eq!(
+            kinds(errors),
+            [ErrorKind::UnstoredLoad {
+                local: "local0".to_owned(),
+                user: "inst1".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_undominated_use() {
+        let errors = with_mir(
+            "func f(a: bool) -> i64 { if a { 1 } else { 2 } }",
+            |mir, _, ctx, interner, results| {
+                // Returns the value of one branch from the merge block.
+                let body = mir.bodies.values_mut().next().unwrap();
+                let (merge, value) = body
+                    .blocks
+                    .values()
+                    .find_map(|data| match data.terminator {
+                        Terminator::Jump(dest) => Some((dest.block, body.list(dest.args)[0])),
+                        _ => None,
+                    })
+                    .unwrap();
+                body.blocks[merge].terminator = Terminator::Return(value);
+                super::verify(mir, ctx, interner, results)
+            },
+        );
+        assert_eq!(
+            kinds(errors),
+            [ErrorKind::UndominatedUse {
+                value: "v3".to_owned(),
+                user: "block3".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let errors = with_mir(
+            "func f(a: i64) -> bool { a > 0 }",
+            |mir, _, ctx, interner, results| {
+                let body = mir.bodies.values_mut().next().unwrap();
+                let inst = body.blocks[body.entry].insts[1];
+                let InstKind::Load(place) = body.insts[inst].kind else {
+                    panic!("expected a load");
+                };
+                let cond = body.blocks[body.entry].params[0];
+                let (then_dest, else_dest) = (
+                    body.block_call(body.entry, &[cond]),
+                    body.block_call(body.entry, &[]),
+                );
+                body.insts[inst].kind = InstKind::Store { place, value: cond };
+                body.blocks[body.entry].terminator = Terminator::Branch {
+                    cond,
+                    then_dest,
+                    else_dest,
+                };
+                super::verify(mir, ctx, interner, results)
+            },
+        );
+        assert_eq!(
+            kinds(errors),
+            [
+                ErrorKind::TypeMismatch {
+                    user: "inst1".to_owned(),
+                    expected: "()".to_owned(),
+                    found: "i64".to_owned(),
+                },
+                ErrorKind::TypeMismatch {
+                    user: "block0".to_owned(),
+                    expected: "bool".to_owned(),
+                    found: "i64".to_owned(),
+                },
+                ErrorKind::ArgCount {
+                    user: "block0".to_owned(),
+                    expected: 1,
+                    found: 0,
+                },
+            ]
+        );
+    }
+}
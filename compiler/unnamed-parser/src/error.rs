@@ -0,0 +1,108 @@
+use std::fmt::{self, Display};
+
+use ariadne::{Label, ReportKind};
+use unnamed_common::{IntoReport, Report, Source, Span, Spanned};
+use unnamed_lexer::TokenKind;
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    span: Span,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(span: Span, kind: ErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    pub fn lexer(error: unnamed_lexer::Error) -> Self {
+        Self::new(error.span(), ErrorKind::Lexer(error))
+    }
+
+    pub fn expected(span: Span, expected: Expected, found: Option<TokenKind>) -> Self {
+        Self::new(span, ErrorKind::Expected { expected, found })
+    }
+
+    pub fn chained_comparison(span: Span) -> Self {
+        Self::new(span, ErrorKind::ChainedComparison)
+    }
+
+    pub fn invalid_assignment_target(span: Span) -> Self {
+        Self::new(span, ErrorKind::InvalidAssignmentTarget)
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ErrorKind {
+    Lexer(unnamed_lexer::Error),
+    Expected {
+        expected: Expected,
+        found: Option<TokenKind>,
+    },
+    ChainedComparison,
+    InvalidAssignmentTarget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Token(TokenKind),
+    Expr,
+    Pattern,
+    Type,
+    Item,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Token(kind) => kind.fmt(f),
+            Expected::Expr => f.write_str("expression"),
+            Expected::Pattern => f.write_str("pattern"),
+            Expected::Type => f.write_str("type"),
+            Expected::Item => f.write_str("item"),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Lexer(error) => error.fmt(f),
+            ErrorKind::Expected { expected, found } => match found {
+                Some(found) => write!(f, "expected {expected}, found {found}"),
+                None => write!(f, "expected {expected}, found end of file"),
+            },
+            ErrorKind::ChainedComparison => f.write_str("comparison operators cannot be chained"),
+            ErrorKind::InvalidAssignmentTarget => {
+                f.write_str("invalid left-hand side of assignment")
+            }
+        }
+    }
+}
+
+impl IntoReport for Error {
+    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
+        let message = self.to_string();
+        let label_message = match self.kind {
+            ErrorKind::Lexer(error) => return error.into_report(source),
+            ErrorKind::Expected { expected, .. } => format!("expected {expected}"),
+            ErrorKind::ChainedComparison => "split this comparison with `&&` or `||`".to_owned(),
+            ErrorKind::InvalidAssignmentTarget => "only variables can be assigned to".to_owned(),
+        };
+
+        Report::build(ReportKind::Error, source.span(self.span))
+            .with_message(message)
+            .with_label(Label::new(source.span(self.span)).with_message(label_message))
+            .finish()
+    }
+}
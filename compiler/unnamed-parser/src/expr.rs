@@ -0,0 +1,1307 @@
+use cranelift_entity::EntityList;
+use unnamed_ast::{
+    ArmEntity, Block, ExprEntity, FieldEntity, FieldPatEntity, PatEntity, PathSegment, TypeEntity,
+    expr::{
+        ArrayExpr, ArrayExprKind, AssignExpr, BinExpr, BinOp, CallExpr, ElseExpr, ElseExprKind,
+        Expr, FieldExpr, IfExpr, IndexExpr, LetExpr, MatchArm, MatchExpr, MethodCallExpr, PathExpr,
+        ReturnExpr, StructExpr, StructFieldExpr, UnaryExpr, UnaryOp, WhileExpr,
+    },
+    pat::{AdtPat, FieldPat, Pat, PatFields},
+};
+use unnamed_common::{Span, Spanned, StrId};
+use unnamed_lexer::TokenKind;
+
+use crate::{Error, Expected, Parser};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StmtKind {
+    Expr,
+    BlockLike,
+    Let,
+}
+
+fn bin_op(kind: TokenKind) -> Option<(BinOp, u8)> {
+    let op = match kind {
+        TokenKind::Or => BinOp::Or,
+        TokenKind::And => BinOp::And,
+
+        TokenKind::Eq => BinOp::Eq,
+        TokenKind::Neq => BinOp::Neq,
+        TokenKind::Lt => BinOp::Lt,
+        TokenKind::LtEq => BinOp::LtEq,
+        TokenKind::Gt => BinOp::Gt,
+        TokenKind::GtEq => BinOp::GtEq,
+
+        TokenKind::BitOr => BinOp::BitOr,
+        TokenKind::BitXor => BinOp::BitXor,
+        TokenKind::BitAnd => BinOp::BitAnd,
+        TokenKind::BitShl => BinOp::BitShl,
+        TokenKind::BitShr => BinOp::BitShr,
+
+        TokenKind::Add => BinOp::Add,
+        TokenKind::Sub => BinOp::Sub,
+        TokenKind::Mul => BinOp::Mul,
+        TokenKind::Div => BinOp::Div,
+
+        _ => return None,
+    };
+
+    Some((op, op.precedence()))
+}
+
+impl<'s> Parser<'s> {
+    pub fn parse_expr(&mut self) -> Result<ExprEntity, Error> {
+        let lhs = self.parse_bin_expr(0)?;
+
+        if self.eat(TokenKind::Asgmt).is_none() {
+            return Ok(lhs);
+        }
+
+        let rhs = self.parse_expr()?;
+        let lhs_span = self.expr_span(lhs);
+        let span = lhs_span + self.expr_span(rhs);
+
+        let Expr::Ident(name, ..) = self.ctx.exprs[lhs] else {
+            self.report(Error::invalid_assignment_target(lhs_span));
+            return Ok(self.ctx.exprs.alloc(Expr::Error(span)));
+        };
+
+        Ok(self.ctx.exprs.alloc(Expr::Assign(AssignExpr {
+            lhs: name,
+            rhs,
+            span,
+        })))
+    }
+
+    fn parse_bin_expr(&mut self, min_precedence: u8) -> Result<ExprEntity, Error> {
+        let mut lhs = self.parse_unary_expr()?;
+        let mut is_comparison = false;
+
+        while let Some((op, precedence)) = self.peek_kind().and_then(bin_op) {
+            if precedence < min_precedence {
+                break;
+            }
+
+            let op_token = self.bump().expect("peeked operator");
+            if op.is_comparison() {
+                if is_comparison {
+                    self.report(Error::chained_comparison(op_token.span));
+                }
+
+                is_comparison = true;
+            }
+
+            let rhs = self.parse_bin_expr(precedence + 1)?;
+            let span = self.expr_span(lhs) + self.expr_span(rhs);
+
+            lhs = self
+                .ctx
+                .exprs
+                .alloc(Expr::Bin(BinExpr { lhs, op, rhs, span }));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_unary_expr(&mut self) -> Result<ExprEntity, Error> {
+        let op = match self.peek_kind() {
+            Some(TokenKind::Add) => UnaryOp::Add,
+            Some(TokenKind::Sub) => UnaryOp::Sub,
+            _ => return self.parse_postfix_expr(),
+        };
+
+        let op_token = self.bump().expect("peeked operator");
+        let value = self.parse_unary_expr()?;
+        let span = op_token.span + self.expr_span(value);
+
+        Ok(self
+            .ctx
+            .exprs
+            .alloc(Expr::Unary(UnaryExpr { op, value, span })))
+    }
+
+    fn parse_postfix_expr(&mut self) -> Result<ExprEntity, Error> {
+        let mut expr = self.parse_primary_expr()?;
+
+        loop {
+            expr = match self.peek_kind() {
+                Some(TokenKind::LeftParen) => self.parse_call_expr(expr, EntityList::new())?,
+                Some(TokenKind::DoubleColon) => {
+                    self.bump();
+                    let (ty_args, ..) = self.parse_type_args()?;
+
+                    self.parse_call_expr(expr, ty_args)?
+                }
+                Some(TokenKind::Dot) => {
+                    self.bump();
+                    self.parse_member_expr(expr)?
+                }
+                Some(TokenKind::LeftSquare) => {
+                    self.bump();
+
+                    let index = self.with_struct_literals(true, |parser| parser.parse_expr())?;
+                    let right_square = self.expect(TokenKind::RightSquare)?;
+                    let span = self.expr_span(expr) + right_square.span;
+
+                    self.ctx.exprs.alloc(Expr::Index(IndexExpr {
+                        base: expr,
+                        index,
+                        span,
+                    }))
+                }
+                _ => break,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_call_expr(
+        &mut self,
+        receiver: ExprEntity,
+        ty_args: EntityList<TypeEntity>,
+    ) -> Result<ExprEntity, Error> {
+        let (args, args_span) = self.parse_args()?;
+        let span = self.expr_span(receiver) + args_span;
+
+        Ok(self.ctx.exprs.alloc(Expr::Call(CallExpr {
+            receiver,
+            ty_args,
+            args,
+            span,
+        })))
+    }
+
+    /// Parses whatever follows a `.`: either a field access or a method call
+    /// with optional `::<T>` type arguments.
+    fn parse_member_expr(&mut self, base: ExprEntity) -> Result<ExprEntity, Error> {
+        let (member, member_span) = self.expect_ident()?;
+
+        let ty_args = if self.eat(TokenKind::DoubleColon).is_some() {
+            let (ty_args, ..) = self.parse_type_args()?;
+            self.expect_token_at(TokenKind::LeftParen)?;
+
+            Some(ty_args)
+        } else if self.at(TokenKind::LeftParen) {
+            Some(EntityList::new())
+        } else {
+            None
+        };
+
+        let Some(ty_args) = ty_args else {
+            let span = self.expr_span(base) + member_span;
+
+            return Ok(self
+                .ctx
+                .exprs
+                .alloc(Expr::Field(FieldExpr { base, member, span })));
+        };
+
+        let (args, args_span) = self.parse_args()?;
+        let span = self.expr_span(base) + args_span;
+
+        Ok(self.ctx.exprs.alloc(Expr::MethodCall(MethodCallExpr {
+            receiver: base,
+            method: member,
+            ty_args,
+            args,
+            span,
+        })))
+    }
+
+    fn parse_args(&mut self) -> Result<(EntityList<ExprEntity>, Span), Error> {
+        let left_paren = self.expect(TokenKind::LeftParen)?;
+        let args = self
+            .with_struct_literals(true, |parser| parser.parse_expr_list(TokenKind::RightParen))?;
+        let right_paren = self.expect(TokenKind::RightParen)?;
+
+        Ok((
+            self.ctx.exprs.alloc_list(&args),
+            left_paren.span + right_paren.span,
+        ))
+    }
+
+    /// Parses comma separated expressions up to (but not including) the
+    /// closing token, allowing a trailing comma.
+    fn parse_expr_list(&mut self, close: TokenKind) -> Result<Vec<ExprEntity>, Error> {
+        let mut exprs = Vec::new();
+        while !self.at(close) {
+            exprs.push(self.parse_expr()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        Ok(exprs)
+    }
+
+    fn parse_primary_expr(&mut self) -> Result<ExprEntity, Error> {
+        let Some(kind) = self.peek_kind() else {
+            return Ok(self.expr_error());
+        };
+
+        let expr = match kind {
+            TokenKind::Int => {
+                let token = self.bump().expect("peeked token");
+                Expr::Int(token.number(), token.int_suffix(), token.span)
+            }
+            TokenKind::Float => {
+                let token = self.bump().expect("peeked token");
+                Expr::Float(token.float(), token.float_suffix(), token.span)
+            }
+            TokenKind::Char => {
+                let token = self.bump().expect("peeked token");
+                Expr::Char(token.char(), token.span)
+            }
+            TokenKind::Str => {
+                let token = self.bump().expect("peeked token");
+                Expr::Str(self.intern(token.string()), token.span)
+            }
+            TokenKind::Error => {
+                let token = self.bump().expect("peeked token");
+                Expr::Error(token.span)
+            }
+            TokenKind::TrueKw | TokenKind::FalseKw => {
+                let token = self.bump().expect("peeked token");
+                Expr::Bool(kind == TokenKind::TrueKw, token.span)
+            }
+            TokenKind::Ident => {
+                let (name, span) = self.expect_ident()?;
+                let is_path =
+                    self.at(TokenKind::DoubleColon) && self.nth_kind(1) == Some(TokenKind::Ident);
+
+                if !is_path {
+                    if self.is_struct_expr_start() {
+                        return self.parse_struct_expr(name, span, None);
+                    }
+
+                    Expr::Ident(name, span)
+                } else {
+                    self.bump();
+                    let (variant, variant_span) = self.expect_ident()?;
+                    let variant = PathSegment {
+                        name: variant,
+                        span: variant_span,
+                    };
+
+                    if self.is_struct_expr_start() {
+                        return self.parse_struct_expr(name, span, Some(variant));
+                    }
+
+                    Expr::Path(PathExpr {
+                        ty: PathSegment { name, span },
+                        variant,
+                        span: span + variant_span,
+                    })
+                }
+            }
+            TokenKind::LeftParen => {
+                let left_paren = self.bump().expect("peeked token");
+                if let Some(right_paren) = self.eat(TokenKind::RightParen) {
+                    Expr::Unit(left_paren.span + right_paren.span)
+                } else {
+                    let expr = self.with_struct_literals(true, |parser| parser.parse_expr())?;
+                    self.expect(TokenKind::RightParen)?;
+
+                    return Ok(expr);
+                }
+            }
+            TokenKind::LeftSquare => return self.parse_array_expr(),
+            TokenKind::LeftBrace => Expr::Block(self.parse_block()?),
+            TokenKind::IfKw => return self.parse_if_expr(),
+            TokenKind::WhileKw => return self.parse_while_expr(),
+            TokenKind::MatchKw => return self.parse_match_expr(),
+            TokenKind::ReturnKw => return self.parse_return_expr(),
+            _ => return Ok(self.expr_error()),
+        };
+
+        Ok(self.ctx.exprs.alloc(expr))
+    }
+
+    /// Reports that an expression was expected and returns an `Error`
+    /// placeholder. The offending token is skipped unless it closes or
+    /// separates something an enclosing rule is waiting for.
+    fn expr_error(&mut self) -> ExprEntity {
+        let error = self.unexpected(Expected::Expr);
+        let span = error.span();
+        self.report(error);
+
+        let is_delimiter = matches!(
+            self.peek_kind(),
+            None | Some(
+                TokenKind::RightParen
+                    | TokenKind::RightBrace
+                    | TokenKind::RightSquare
+                    | TokenKind::Semicolon
+                    | TokenKind::Comma
+            )
+        );
+
+        if !is_delimiter && !self.at_item_start() {
+            self.bump();
+        }
+
+        self.ctx.exprs.alloc(Expr::Error(span))
+    }
+
+    /// Whether the upcoming `{`, right after a name, starts the fields of a
+    /// struct literal rather than a block, like the body of `while x { ... }`.
+    fn is_struct_expr_start(&self) -> bool {
+        if !self.struct_literals || !self.at(TokenKind::LeftBrace) {
+            return false;
+        }
+
+        matches!(
+            (self.nth_kind(1), self.nth_kind(2)),
+            (Some(TokenKind::RightBrace), _)
+                | (
+                    Some(TokenKind::Ident),
+                    Some(TokenKind::Colon | TokenKind::Comma | TokenKind::RightBrace)
+                )
+        )
+    }
+
+    fn parse_struct_expr(
+        &mut self,
+        name: StrId,
+        name_span: Span,
+        variant: Option<PathSegment>,
+    ) -> Result<ExprEntity, Error> {
+        self.expect(TokenKind::LeftBrace)?;
+
+        let mut fields = Vec::new();
+        while !self.at(TokenKind::RightBrace) {
+            fields.push(self.parse_struct_field_expr()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        let right_brace = self.expect(TokenKind::RightBrace)?;
+
+        Ok(self.ctx.exprs.alloc(Expr::Struct(StructExpr {
+            name,
+            variant,
+            fields: self.ctx.field_exprs.alloc_list(&fields),
+            span: name_span + right_brace.span,
+        })))
+    }
+
+    fn parse_struct_field_expr(&mut self) -> Result<FieldEntity, Error> {
+        let (name, name_span) = self.expect_ident()?;
+
+        let value = if self.eat(TokenKind::Colon).is_some() {
+            self.with_struct_literals(true, |parser| parser.parse_expr())?
+        } else {
+            self.ctx.exprs.alloc(Expr::Ident(name, name_span))
+        };
+
+        let span = name_span + self.expr_span(value);
+        Ok(self
+            .ctx
+            .field_exprs
+            .alloc(StructFieldExpr { name, value, span }))
+    }
+
+    fn parse_array_expr(&mut self) -> Result<ExprEntity, Error> {
+        let left_square = self.expect(TokenKind::LeftSquare)?;
+
+        let kind = self.with_struct_literals(true, |parser| {
+            if parser.at(TokenKind::RightSquare) {
+                return Ok(ArrayExprKind::List {
+                    values: EntityList::new(),
+                });
+            }
+
+            let value = parser.parse_expr()?;
+            if parser.eat(TokenKind::Semicolon).is_some() {
+                let repeat = parser.parse_expr()?;
+                return Ok(ArrayExprKind::Repeat { value, repeat });
+            }
+
+            let mut values = vec![value];
+            if parser.eat(TokenKind::Comma).is_some() {
+                values.extend(parser.parse_expr_list(TokenKind::RightSquare)?);
+            }
+
+            Ok(ArrayExprKind::List {
+                values: parser.ctx.exprs.alloc_list(&values),
+            })
+        })?;
+
+        let right_square = self.expect(TokenKind::RightSquare)?;
+
+        Ok(self.ctx.exprs.alloc(Expr::Array(ArrayExpr {
+            kind,
+            span: left_square.span + right_square.span,
+        })))
+    }
+
+    pub fn parse_block(&mut self) -> Result<Block, Error> {
+        let left_brace = self.expect(TokenKind::LeftBrace)?;
+        let (exprs, tail) = self.with_struct_literals(true, |parser| parser.parse_block_stmts());
+
+        let end_span = match self.eat(TokenKind::RightBrace) {
+            Some(right_brace) => right_brace.span,
+            None => {
+                self.report(self.unexpected(Expected::Token(TokenKind::RightBrace)));
+                self.tokens[self.position - 1].span
+            }
+        };
+
+        Ok(Block {
+            exprs: self.ctx.exprs.alloc_list(&exprs),
+            tail,
+            span: left_brace.span + end_span,
+        })
+    }
+
+    /// Parses the statements of a block up to its closing brace. A statement
+    /// that fails to parse is replaced by an `Error` expression covering the
+    /// tokens skipped up to the next `;`, closing brace or item keyword.
+    fn parse_block_stmts(&mut self) -> (Vec<ExprEntity>, Option<ExprEntity>) {
+        let mut exprs = Vec::new();
+
+        while !self.is_eof() && !self.at(TokenKind::RightBrace) && !self.at_item_start() {
+            let errors = self.errors.len();
+
+            let (expr, kind) = match self.parse_stmt() {
+                Ok(stmt) => stmt,
+                Err(error) => {
+                    let span = error.span() + self.synchronize_stmt();
+                    self.report(error);
+
+                    exprs.push(self.ctx.exprs.alloc(Expr::Error(span)));
+                    continue;
+                }
+            };
+
+            // At the end of an unclosed block, the missing `}` is reported
+            // instead of a missing `;`.
+            let has_semicolon = self.eat(TokenKind::Semicolon).is_some();
+            let is_unclosed = self.is_eof() || self.at_item_start();
+
+            if !has_semicolon
+                && kind != StmtKind::Let
+                && (self.at(TokenKind::RightBrace) || is_unclosed)
+            {
+                return (exprs, Some(expr));
+            }
+
+            if !has_semicolon && kind != StmtKind::BlockLike {
+                if self.errors.len() == errors && !is_unclosed && !self.at(TokenKind::Error) {
+                    self.report(self.unexpected(Expected::Token(TokenKind::Semicolon)));
+                } else {
+                    self.synchronize_stmt();
+                }
+            }
+
+            exprs.push(expr);
+        }
+
+        (exprs, None)
+    }
+
+    fn synchronize_stmt(&mut self) -> Span {
+        let span = self
+            .synchronize(|kind| kind == TokenKind::Semicolon || crate::item::is_item_keyword(kind));
+        self.eat(TokenKind::Semicolon);
+
+        span
+    }
+
+    /// Parses an expression in statement position. Block-like expressions
+    /// (`if`, `while`, `match` and blocks) end the statement right after their closing
+    /// brace, so they do not need a trailing `;`.
+    fn parse_stmt(&mut self) -> Result<(ExprEntity, StmtKind), Error> {
+        let expr = match self.peek_kind() {
+            Some(TokenKind::LetKw) => return Ok((self.parse_let_expr()?, StmtKind::Let)),
+            Some(TokenKind::IfKw) => self.parse_if_expr()?,
+            Some(TokenKind::WhileKw) => self.parse_while_expr()?,
+            Some(TokenKind::MatchKw) => self.parse_match_expr()?,
+            Some(TokenKind::LeftBrace) => {
+                let block = self.parse_block()?;
+                self.ctx.exprs.alloc(Expr::Block(block))
+            }
+            _ => return Ok((self.parse_expr()?, StmtKind::Expr)),
+        };
+
+        Ok((expr, StmtKind::BlockLike))
+    }
+
+    /// Parses `let name: Type = value`, where both the type annotation and
+    /// the initializer are optional. The terminating `;` is left to the block.
+    fn parse_let_expr(&mut self) -> Result<ExprEntity, Error> {
+        let let_kw = self.expect(TokenKind::LetKw)?;
+        let (name, name_span) = self.expect_ident()?;
+        let mut span = let_kw.span + name_span;
+
+        let ty = if self.eat(TokenKind::Colon).is_some() {
+            let ty = self.parse_type()?;
+            span += self.ctx.types[ty].span();
+
+            Some(ty)
+        } else {
+            None
+        };
+
+        let value = if self.eat(TokenKind::Asgmt).is_some() {
+            let value = self.parse_expr()?;
+            span += self.expr_span(value);
+
+            Some(value)
+        } else {
+            None
+        };
+
+        Ok(self.ctx.exprs.alloc(Expr::Let(LetExpr {
+            name,
+            name_span,
+            ty,
+            value,
+            span,
+        })))
+    }
+
+    fn parse_if_expr(&mut self) -> Result<ExprEntity, Error> {
+        let if_kw = self.expect(TokenKind::IfKw)?;
+        let cond = self.with_struct_literals(false, |parser| parser.parse_expr())?;
+        let then_branch = self.parse_block()?;
+
+        let else_branch = match self.eat(TokenKind::ElseKw) {
+            Some(else_kw) if self.at(TokenKind::IfKw) => {
+                let else_if = self.parse_if_expr()?;
+
+                Some(ElseExpr {
+                    kind: ElseExprKind::If(else_if),
+                    span: else_kw.span + self.expr_span(else_if),
+                })
+            }
+            Some(else_kw) => {
+                let block = self.parse_block()?;
+
+                Some(ElseExpr {
+                    kind: ElseExprKind::Else(block),
+                    span: else_kw.span + block.span,
+                })
+            }
+            None => None,
+        };
+
+        let end_span = else_branch.map_or(then_branch.span, |else_branch| else_branch.span);
+
+        Ok(self.ctx.exprs.alloc(Expr::If(IfExpr {
+            cond,
+            then_branch,
+            else_branch,
+            span: if_kw.span + end_span,
+        })))
+    }
+
+    fn parse_while_expr(&mut self) -> Result<ExprEntity, Error> {
+        let while_kw = self.expect(TokenKind::WhileKw)?;
+        let cond = self.with_struct_literals(false, |parser| parser.parse_expr())?;
+        let body = self.parse_block()?;
+
+        Ok(self.ctx.exprs.alloc(Expr::While(WhileExpr {
+            cond,
+            body,
+            span: while_kw.span + body.span,
+        })))
+    }
+
+    fn parse_match_expr(&mut self) -> Result<ExprEntity, Error> {
+        let match_kw = self.expect(TokenKind::MatchKw)?;
+        let scrutinee = self.with_struct_literals(false, |parser| parser.parse_expr())?;
+
+        self.expect(TokenKind::LeftBrace)?;
+        let arms = self.with_struct_literals(true, |parser| {
+            let mut arms = Vec::new();
+            while !parser.is_eof() && !parser.at(TokenKind::RightBrace) {
+                let arm = parser.parse_match_arm()?;
+                arms.push(arm);
+
+                // Arms whose body is a block do not need a `,` after them.
+                let is_block =
+                    matches!(parser.ctx.exprs[parser.ctx.arms[arm].body], Expr::Block(_));
+                if parser.eat(TokenKind::Comma).is_none() && !is_block {
+                    break;
+                }
+            }
+
+            Ok(arms)
+        })?;
+        let right_brace = self.expect(TokenKind::RightBrace)?;
+
+        Ok(self.ctx.exprs.alloc(Expr::Match(MatchExpr {
+            scrutinee,
+            arms: self.ctx.arms.alloc_list(&arms),
+            span: match_kw.span + right_brace.span,
+        })))
+    }
+
+    fn parse_match_arm(&mut self) -> Result<ArmEntity, Error> {
+        let pat = self.parse_pat()?;
+        let guard = if self.eat(TokenKind::IfKw).is_some() {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::FatArrow)?;
+        let body = self.parse_expr()?;
+
+        let span = self.ctx.pats[pat].span() + self.expr_span(body);
+        Ok(self.ctx.arms.alloc(MatchArm {
+            pat,
+            guard,
+            body,
+            span,
+        }))
+    }
+
+    /// Parses a pattern: `_`, a binding, a literal, or a struct or variant
+    /// with nested patterns for its fields.
+    fn parse_pat(&mut self) -> Result<PatEntity, Error> {
+        let pat = match self.peek_kind() {
+            Some(TokenKind::Ident) => {
+                let (name, span) = self.expect_ident()?;
+                let name = PathSegment { name, span };
+
+                if self.interner.get(name.name) == "_" {
+                    Pat::Wild(span)
+                } else if self.eat(TokenKind::DoubleColon).is_some() {
+                    let (variant, variant_span) = self.expect_ident()?;
+                    let variant = PathSegment {
+                        name: variant,
+                        span: variant_span,
+                    };
+                    self.parse_adt_pat(name, Some(variant))?
+                } else if self.at(TokenKind::LeftBrace) {
+                    self.parse_adt_pat(name, None)?
+                } else {
+                    Pat::Binding(name.name, span)
+                }
+            }
+            Some(
+                TokenKind::Int
+                | TokenKind::Float
+                | TokenKind::Char
+                | TokenKind::Str
+                | TokenKind::TrueKw
+                | TokenKind::FalseKw,
+            ) => {
+                let lit = self.parse_primary_expr()?;
+                Pat::Lit(lit, self.expr_span(lit))
+            }
+            Some(TokenKind::Sub)
+                if matches!(self.nth_kind(1), Some(TokenKind::Int | TokenKind::Float)) =>
+            {
+                let sub = self.bump().expect("peeked operator");
+                let value = self.parse_primary_expr()?;
+                let span = sub.span + self.expr_span(value);
+                let lit = self.ctx.exprs.alloc(Expr::Unary(UnaryExpr {
+                    op: UnaryOp::Sub,
+                    value,
+                    span,
+                }));
+
+                Pat::Lit(lit, span)
+            }
+            _ => return Err(self.unexpected(Expected::Pattern)),
+        };
+
+        Ok(self.ctx.pats.alloc(pat))
+    }
+
+    /// Parses the fields of a struct or variant pattern after its name.
+    fn parse_adt_pat(
+        &mut self,
+        name: PathSegment,
+        variant: Option<PathSegment>,
+    ) -> Result<Pat, Error> {
+        let mut span = name.span + variant.map_or(name.span, |variant| variant.span);
+
+        let fields = match self.peek_kind() {
+            Some(TokenKind::LeftParen) if variant.is_some() => {
+                self.bump();
+                let mut pats = Vec::new();
+                while !self.at(TokenKind::RightParen) {
+                    pats.push(self.parse_pat()?);
+
+                    if self.eat(TokenKind::Comma).is_none() {
+                        break;
+                    }
+                }
+                span += self.expect(TokenKind::RightParen)?.span;
+
+                PatFields::Tuple(self.ctx.pats.alloc_list(&pats))
+            }
+            Some(TokenKind::LeftBrace) => {
+                self.bump();
+                let mut fields = Vec::new();
+                while !self.at(TokenKind::RightBrace) {
+                    fields.push(self.parse_field_pat()?);
+
+                    if self.eat(TokenKind::Comma).is_none() {
+                        break;
+                    }
+                }
+                span += self.expect(TokenKind::RightBrace)?.span;
+
+                PatFields::Named(self.ctx.field_pats.alloc_list(&fields))
+            }
+            _ => PatFields::Unit,
+        };
+
+        Ok(Pat::Adt(AdtPat {
+            name,
+            variant,
+            fields,
+            span,
+        }))
+    }
+
+    fn parse_field_pat(&mut self) -> Result<FieldPatEntity, Error> {
+        let (name, name_span) = self.expect_ident()?;
+
+        let pat = if self.eat(TokenKind::Colon).is_some() {
+            self.parse_pat()?
+        } else {
+            self.ctx.pats.alloc(Pat::Binding(name, name_span))
+        };
+
+        let span = name_span + self.ctx.pats[pat].span();
+        Ok(self.ctx.field_pats.alloc(FieldPat { name, pat, span }))
+    }
+
+    fn parse_return_expr(&mut self) -> Result<ExprEntity, Error> {
+        let return_kw = self.expect(TokenKind::ReturnKw)?;
+
+        let has_value = !matches!(
+            self.peek_kind(),
+            None | Some(
+                TokenKind::Semicolon
+                    | TokenKind::RightBrace
+                    | TokenKind::RightParen
+                    | TokenKind::RightSquare
+                    | TokenKind::Comma
+            )
+        );
+
+        let value = if has_value {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+
+        let span = value.map_or(return_kw.span, |value| {
+            return_kw.span + self.expr_span(value)
+        });
+
+        Ok(self
+            .ctx
+            .exprs
+            .alloc(Expr::Return(ReturnExpr { value, span })))
+    }
+
+    fn expect_token_at(&self, kind: TokenKind) -> Result<(), Error> {
+        if self.at(kind) {
+            Ok(())
+        } else {
+            Err(self.unexpected(Expected::Token(kind)))
+        }
+    }
+
+    fn expr_span(&self, expr: ExprEntity) -> Span {
+        self.ctx.exprs[expr].span()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_ast::{
+        AstCtx, Block, ExprEntity, PatEntity,
+        expr::{ArrayExprKind, ElseExprKind, Expr},
+        pat::{Pat, PatFields},
+        ty::TypeKind,
+    };
+    use unnamed_common::{Interner, Source};
+    use unnamed_lexer::ErrorKind as LexerErrorKind;
+
+    use crate::{ErrorKind, Parser};
+
+    fn sexpr(ctx: &AstCtx, interner: &Interner, expr: ExprEntity) -> String {
+        let list = |exprs: &[ExprEntity]| {
+            exprs
+                .iter()
+                .map(|&expr| sexpr(ctx, interner, expr))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        match ctx.exprs[expr] {
+            Expr::Ident(name, ..) => interner.get(name).to_owned(),
+            Expr::Str(value, ..) => format!("{:?}", interner.get(value)),
+            Expr::Char(value, ..) => format!("{value:?}"),
+            Expr::Int(value, suffix, ..) => {
+                format!("{value}{}", suffix.map_or("", |suffix| suffix.as_str()))
+            }
+            Expr::Float(value, suffix, ..) => {
+                format!("{value:?}{}", suffix.map_or("", |suffix| suffix.as_str()))
+            }
+            Expr::Bool(value, ..) => value.to_string(),
+            Expr::Unit(..) => "()".to_owned(),
+            Expr::Path(path) => format!(
+                "{}::{}",
+                interner.get(path.ty.name),
+                interner.get(path.variant.name)
+            ),
+            Expr::Block(block) => sblock(ctx, interner, block),
+            Expr::Assign(assign) => format!(
+                "(= {} {})",
+                interner.get(assign.lhs),
+                sexpr(ctx, interner, assign.rhs)
+            ),
+            Expr::Bin(bin) => format!(
+                "({} {} {})",
+                bin.op.as_str(),
+                sexpr(ctx, interner, bin.lhs),
+                sexpr(ctx, interner, bin.rhs)
+            ),
+            Expr::Unary(unary) => format!(
+                "({} {})",
+                unary.op.as_str(),
+                sexpr(ctx, interner, unary.value)
+            ),
+            Expr::Call(call) => format!(
+                "(call {} [{}] {})",
+                sexpr(ctx, interner, call.receiver),
+                call.ty_args.len(&ctx.types.pool),
+                list(ctx.exprs.list(call.args))
+            ),
+            Expr::MethodCall(call) => format!(
+                "(method {} {} [{}] {})",
+                sexpr(ctx, interner, call.receiver),
+                interner.get(call.method),
+                call.ty_args.len(&ctx.types.pool),
+                list(ctx.exprs.list(call.args))
+            ),
+            Expr::If(if_expr) => {
+                let else_branch = match if_expr.else_branch.map(|else_expr| else_expr.kind) {
+                    Some(ElseExprKind::If(expr)) => format!(" {}", sexpr(ctx, interner, expr)),
+                    Some(ElseExprKind::Else(block)) => format!(" {}", sblock(ctx, interner, block)),
+                    None => String::new(),
+                };
+
+                format!(
+                    "(if {} {}{else_branch})",
+                    sexpr(ctx, interner, if_expr.cond),
+                    sblock(ctx, interner, if_expr.then_branch)
+                )
+            }
+            Expr::While(while_expr) => format!(
+                "(while {} {})",
+                sexpr(ctx, interner, while_expr.cond),
+                sblock(ctx, interner, while_expr.body)
+            ),
+            Expr::Match(match_expr) => {
+                let arms = ctx
+                    .arms
+                    .list(match_expr.arms)
+                    .iter()
+                    .map(|&arm| {
+                        let arm = ctx.arms[arm];
+                        let guard = arm
+                            .guard
+                            .map(|guard| format!(" if {}", sexpr(ctx, interner, guard)))
+                            .unwrap_or_default();
+                        format!(
+                            "({}{guard} {})",
+                            spat(ctx, interner, arm.pat),
+                            sexpr(ctx, interner, arm.body)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                format!(
+                    "(match {} {arms})",
+                    sexpr(ctx, interner, match_expr.scrutinee)
+                )
+            }
+            Expr::Let(let_expr) => {
+                let ty = let_expr
+                    .ty
+                    .map(|ty| match ctx.types[ty].kind {
+                        TypeKind::Named { name, .. } => format!(": {}", interner.get(name)),
+                        TypeKind::Unit => ": ()".to_owned(),
+                        TypeKind::Error => ": <error>".to_owned(),
+                    })
+                    .unwrap_or_default();
+                let value = let_expr
+                    .value
+                    .map(|value| format!(" {}", sexpr(ctx, interner, value)))
+                    .unwrap_or_default();
+
+                format!("(let {}{ty}{value})", interner.get(let_expr.name))
+            }
+            Expr::Array(array) => match array.kind {
+                ArrayExprKind::List { values } => format!("[{}]", list(ctx.exprs.list(values))),
+                ArrayExprKind::Repeat { value, repeat } => format!(
+                    "[{}; {}]",
+                    sexpr(ctx, interner, value),
+                    sexpr(ctx, interner, repeat)
+                ),
+            },
+            Expr::Struct(struct_expr) => {
+                let fields = ctx
+                    .field_exprs
+                    .list(struct_expr.fields)
+                    .iter()
+                    .map(|&field| {
+                        let field = ctx.field_exprs[field];
+                        format!(
+                            "{}: {}",
+                            interner.get(field.name),
+                            sexpr(ctx, interner, field.value)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                let variant = struct_expr
+                    .variant
+                    .map(|variant| format!("::{}", interner.get(variant.name)))
+                    .unwrap_or_default();
+
+                format!(
+                    "(struct {}{variant} {fields})",
+                    interner.get(struct_expr.name)
+                )
+            }
+            Expr::Field(field) => format!(
+                "(. {} {})",
+                sexpr(ctx, interner, field.base),
+                interner.get(field.member)
+            ),
+            Expr::Index(index) => format!(
+                "(index {} {})",
+                sexpr(ctx, interner, index.base),
+                sexpr(ctx, interner, index.index)
+            ),
+            Expr::Error(..) => "<error>".to_owned(),
+            Expr::Return(return_expr) => match return_expr.value {
+                Some(value) => format!("(return {})", sexpr(ctx, interner, value)),
+                None => "(return)".to_owned(),
+            },
+        }
+    }
+
+    fn spat(ctx: &AstCtx, interner: &Interner, pat: PatEntity) -> String {
+        match ctx.pats[pat] {
+            Pat::Wild(..) => "_".to_owned(),
+            Pat::Binding(name, ..) => interner.get(name).to_owned(),
+            Pat::Lit(lit, ..) => sexpr(ctx, interner, lit),
+            Pat::Adt(adt) => {
+                let mut name = interner.get(adt.name.name).to_owned();
+                if let Some(variant) = adt.variant {
+                    name = format!("{name}::{}", interner.get(variant.name));
+                }
+
+                match adt.fields {
+                    PatFields::Unit => name,
+                    PatFields::Tuple(pats) => {
+                        let pats = ctx
+                            .pats
+                            .list(pats)
+                            .iter()
+                            .map(|&pat| spat(ctx, interner, pat))
+                            .collect::<Vec<_>>();
+                        format!("{name}({})", pats.join(", "))
+                    }
+                    PatFields::Named(fields) => {
+                        let fields = ctx
+                            .field_pats
+                            .list(fields)
+                            .iter()
+                            .map(|&field| {
+                                let field = ctx.field_pats[field];
+                                format!(
+                                    "{}: {}",
+                                    interner.get(field.name),
+                                    spat(ctx, interner, field.pat)
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        format!("{name} {{{}}}", fields.join(", "))
+                    }
+                }
+            }
+            Pat::Error(..) => "<error>".to_owned(),
+        }
+    }
+
+    fn sblock(ctx: &AstCtx, interner: &Interner, block: Block) -> String {
+        let mut parts = ctx
+            .exprs
+            .list(block.exprs)
+            .iter()
+            .map(|&expr| format!("{};", sexpr(ctx, interner, expr)))
+            .collect::<Vec<_>>();
+        parts.extend(block.tail.map(|tail| sexpr(ctx, interner, tail)));
+
+        format!("{{{}}}", parts.join(" "))
+    }
+
+    fn parse(content: &str) -> String {
+        let interner = Interner::new();
+        let mut parser = Parser::new(Source::new(content, "test_file"), &interner);
+
+        let expr = parser.parse_expr().unwrap();
+        assert!(parser.is_eof(), "unparsed input left in {content:?}");
+        assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+        sexpr(parser.ctx(), &interner, expr)
+    }
+
+    fn parse_error(content: &str) -> ErrorKind {
+        let interner = Interner::new();
+        let mut parser = Parser::new(Source::new(content, "test_file"), &interner);
+
+        if let Err(error) = parser.parse_expr() {
+            return error.kind().clone();
+        }
+
+        let (.., errors) = parser.finish();
+        errors[0].kind().clone()
+    }
+
+    #[test]
+    fn test_arithmetic_precedence() {
+        assert_eq!(parse("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(parse("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(parse("(1 + 2) / 3"), "(/ (+ 1 2) 3)");
+        assert_eq!(parse("-a * +b"), "(* (- a) (+ b))");
+    }
+
+    #[test]
+    fn test_logical_and_bitwise_precedence() {
+        assert_eq!(parse("a || b && c == d"), "(|| a (&& b (== c d)))");
+        assert_eq!(parse("a | b ^ c & d"), "(| a (^ b (& c d)))");
+        assert_eq!(parse("1 << 3 + 2"), "(<< 1 (+ 3 2))");
+        assert_eq!(parse("a >> 1 < b"), "(< (>> a 1) b)");
+        assert_eq!(parse("a <= b && c != d"), "(&& (<= a b) (!= c d))");
+    }
+
+    #[test]
+    fn test_chained_comparison() {
+        assert!(matches!(
+            parse_error("a < b < c"),
+            ErrorKind::ChainedComparison
+        ));
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_eq!(parse("a = b = 1 + 2"), "(= a (= b (+ 1 2)))");
+        assert!(matches!(
+            parse_error("a + b = c"),
+            ErrorKind::InvalidAssignmentTarget
+        ));
+    }
+
+    #[test]
+    fn test_calls_and_postfix() {
+        assert_eq!(parse("foo(1, 2,)"), "(call foo [0] 1 2)");
+        assert_eq!(parse("foo::<i32, Vec<T>>()"), "(call foo [2] )");
+        assert_eq!(
+            parse("a.b.c(1).d[2]"),
+            "(index (. (method (. a b) c [0] 1) d) 2)"
+        );
+        assert_eq!(parse("x.into::<u8>()"), "(method x into [1] )");
+    }
+
+    #[test]
+    fn test_nested_generic_args_split_shift() {
+        assert_eq!(parse("f::<A<B<C>>>(x)"), "(call f [1] x)");
+    }
+
+    #[test]
+    fn test_literals() {
+        assert_eq!(parse("\"hi\""), "\"hi\"");
+        assert_eq!(parse("true"), "true");
+        assert_eq!(parse(r"'\n' == '\u{41}'"), r"(== '\n' 'A')");
+        assert_eq!(parse("()"), "()");
+        assert_eq!(parse("0x10"), "16");
+        assert_eq!(parse("1.5e1 * 2.0"), "(* 15.0 2.0)");
+        assert_eq!(parse("1.max(2)"), "(method 1 max [0] 2)");
+        assert_eq!(parse("10u8 + 0xffi32 * 2f32"), "(+ 10u8 (* 255i32 2.0f32))");
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(parse("[]"), "[]");
+        assert_eq!(parse("[1, 2, 3,]"), "[1 2 3]");
+        assert_eq!(parse("[0; n * 2]"), "[0; (* n 2)]");
+    }
+
+    #[test]
+    fn test_struct_literals() {
+        assert_eq!(parse("Point { x: 1, y }"), "(struct Point x: 1, y: y)");
+        assert_eq!(parse("Empty {}"), "(struct Empty )");
+    }
+
+    #[test]
+    fn test_struct_literal_not_allowed_in_condition() {
+        assert_eq!(parse("if a { b } else { c }"), "(if a {b} {c})");
+        assert_eq!(
+            parse("while x { y = Point { x: 1 }; }"),
+            "(while x {(= y (struct Point x: 1));})"
+        );
+        assert_eq!(parse("if (A {}) {}"), "(if (struct A ) {})");
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(parse("Shape::Empty"), "Shape::Empty");
+        assert_eq!(parse("Shape::Circle(1.0)"), "(call Shape::Circle [0] 1.0)");
+        assert_eq!(
+            parse("Shape::Rect { w: 1, h }"),
+            "(struct Shape::Rect w: 1, h: h)"
+        );
+        assert_eq!(parse("if a == E::A { b }"), "(if (== a E::A) {b})");
+    }
+
+    #[test]
+    fn test_match() {
+        assert_eq!(
+            parse(
+                "match s { Shape::Circle(r) => r, Shape::Rect { w, h: _ } if w > 0 => { w } _ => 0 }"
+            ),
+            "(match s (Shape::Circle(r) r) (Shape::Rect {w: w, h: _} if (> w 0) {w}) (_ 0))"
+        );
+        assert_eq!(
+            parse("match x { 1 => a, -2 => b, \"s\" => c, y => y }"),
+            "(match x (1 a) ((- 2) b) (\"s\" c) (y y))"
+        );
+    }
+
+    #[test]
+    fn test_match_patterns() {
+        assert_eq!(
+            parse(
+                "match p { Point { x: 0, y } => y, Opt::Some(Pair::P(true, -1.5)) => 1, Opt::None => 2, }"
+            ),
+            "(match p (Point {x: 0, y: y} y) (Opt::Some(Pair::P(true, (- 1.5))) 1) (Opt::None 2))"
+        );
+        assert_eq!(parse("{ match a { _ => {} } b }"), "{(match a (_ {})); b}");
+        assert!(matches!(
+            parse_error("match a { + => 1 }"),
+            ErrorKind::Expected { .. }
+        ));
+        assert!(matches!(
+            parse_error("match a { x => 1 y => 2 }"),
+            ErrorKind::Expected { .. }
+        ));
+    }
+
+    #[test]
+    fn test_if_else_chain() {
+        assert_eq!(
+            parse("if a { 1 } else if b { 2 } else { 3 }"),
+            "(if a {1} (if b {2} {3}))"
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            parse("{ a = 1; if a { b } { c } while d {} e }"),
+            "{(= a 1); (if a {b}); {c}; (while d {}); e}"
+        );
+        assert_eq!(
+            parse("{ return; return a + 1 }"),
+            "{(return); (return (+ a 1))}"
+        );
+        assert_eq!(parse("{ a; }"), "{a;}");
+        assert_eq!(parse("{ if a { 1 } else { 2 } }"), "{(if a {1} {2})}");
+        assert_eq!(parse("{ if a { 1 }; }"), "{(if a {1});}");
+    }
+
+    #[test]
+    fn test_let() {
+        assert_eq!(
+            parse("{ let a; let b: i64; let c = 1; let d: u8 = c + 2; d }"),
+            "{(let a); (let b: i64); (let c 1); (let d: u8 (+ c 2)); d}"
+        );
+    }
+
+    #[test]
+    fn test_let_requires_semicolon() {
+        assert!(matches!(
+            parse_error("{ let a = 1 }"),
+            ErrorKind::Expected { .. }
+        ));
+        assert!(matches!(
+            parse_error("let a = 1"),
+            ErrorKind::Expected { .. }
+        ));
+    }
+
+    #[test]
+    fn test_missing_semicolon() {
+        assert!(matches!(parse_error("{ a b }"), ErrorKind::Expected { .. }));
+    }
+
+    #[test]
+    fn test_statement_recovery() {
+        let interner = Interner::new();
+        let mut parser = Parser::new(
+            Source::new("{ let a = ; foo(1 2); b = 3; a + }", "test_file"),
+            &interner,
+        );
+
+        let expr = parser.parse_expr().unwrap();
+        assert_eq!(
+            sexpr(parser.ctx(), &interner, expr),
+            "{(let a <error>); <error>; (= b 3); (+ a <error>)}"
+        );
+        assert_eq!(parser.errors().len(), 3);
+    }
+
+    #[test]
+    fn test_unclosed_block() {
+        let interner = Interner::new();
+        let mut parser = Parser::new(Source::new("{ a = 1; b", "test_file"), &interner);
+
+        let expr = parser.parse_expr().unwrap();
+        assert_eq!(sexpr(parser.ctx(), &interner, expr), "{(= a 1); b}");
+        assert_eq!(parser.errors().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_token_recovery() {
+        let interner = Interner::new();
+        let mut parser = Parser::new(
+            Source::new("{ let a = $; let b = 1 # 2; f(@, 3); a + b }", "test_file"),
+            &interner,
+        );
+
+        // Only the lexer reports the invalid tokens, which become `Error`
+        // expressions or end the statement they appear in.
+        let expr = parser.parse_expr().unwrap();
+        assert_eq!(
+            sexpr(parser.ctx(), &interner, expr),
+            "{(let a <error>); (let b 1); (call f [0] <error> 3); (+ a b)}"
+        );
+
+        let (.., errors) = parser.finish();
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(errors.iter().all(|error| matches!(
+            error.kind(),
+            ErrorKind::Lexer(error) if matches!(error.kind(), LexerErrorKind::InvalidToken)
+        )));
+    }
+}
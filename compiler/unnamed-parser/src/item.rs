@@ -0,0 +1,672 @@
+use cranelift_entity::EntityList;
+use unnamed_ast::{
+    FieldDef, FieldDefEntity, GenericParam, GenericParamEntity, ItemEntity, Module, Param,
+    ParamEntity, Variant, VariantEntity,
+    item::{EnumItem, FuncItem, ImplItem, Item, StructItem, TraitItem, VariantKind},
+};
+use unnamed_common::{Span, Spanned};
+use unnamed_lexer::TokenKind;
+
+use crate::{Error, Expected, Parser};
+
+const SELF_PARAM: &str = "self";
+
+pub(crate) fn is_item_keyword(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::FuncKw
+            | TokenKind::StructKw
+            | TokenKind::EnumKw
+            | TokenKind::TraitKw
+            | TokenKind::ImplKw
+    )
+}
+
+impl<'s> Parser<'s> {
+    pub fn parse_module(&mut self) -> Module {
+        let mut items = Vec::new();
+        while !self.is_eof() {
+            let position = self.position;
+
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize(is_item_keyword);
+
+                    if self.position == position || self.at(TokenKind::RightBrace) {
+                        self.bump();
+                    }
+                }
+            }
+        }
+
+        Module {
+            docs: self.module_docs,
+            items: self.ctx.items.alloc_list(&items),
+            span: Span::new(0, self.source.content.len()),
+        }
+    }
+
+    pub fn parse_item(&mut self) -> Result<ItemEntity, Error> {
+        let item = match self.peek_kind() {
+            Some(TokenKind::FuncKw) => Item::Func(self.parse_func_item(false)?),
+            Some(TokenKind::StructKw) => Item::Struct(self.parse_struct_item()?),
+            Some(TokenKind::EnumKw) => Item::Enum(self.parse_enum_item()?),
+            Some(TokenKind::TraitKw) => Item::Trait(self.parse_trait_item()?),
+            Some(TokenKind::ImplKw) => Item::Impl(self.parse_impl_item()?),
+            _ => return Err(self.unexpected(Expected::Item)),
+        };
+
+        Ok(self.ctx.items.alloc(item))
+    }
+
+    /// Parses `func name<T>(params) -> Ret { ... }`. When `is_signature` is set,
+    /// as inside trait declarations, the body may be replaced by a `;`.
+    fn parse_func_item(&mut self, is_signature: bool) -> Result<FuncItem, Error> {
+        let docs = self.docs();
+        let func_kw = self.expect(TokenKind::FuncKw)?;
+        let (name, name_span) = self.expect_ident()?;
+        let generics = self.parse_generics()?;
+
+        self.expect(TokenKind::LeftParen)?;
+        let mut params = Vec::new();
+        while !self.at(TokenKind::RightParen) {
+            params.push(self.parse_param()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        let right_paren = self.expect(TokenKind::RightParen)?;
+
+        let ret = if self.eat(TokenKind::Arrow).is_some() {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        let (body, end_span) = match self.eat(TokenKind::Semicolon) {
+            Some(semicolon) if is_signature => (None, semicolon.span),
+            Some(semicolon) => {
+                return Err(Error::expected(
+                    semicolon.span,
+                    Expected::Token(TokenKind::LeftBrace),
+                    Some(TokenKind::Semicolon),
+                ));
+            }
+            None => {
+                let body = self.parse_block()?;
+                (Some(body), body.span)
+            }
+        };
+
+        let span = ret.map_or(right_paren.span, |ret| self.ctx.types[ret].span());
+
+        Ok(FuncItem {
+            docs,
+            name,
+            name_span,
+            generics,
+            params: self.ctx.params.alloc_list(&params),
+            ret,
+            body,
+            span: func_kw.span + span + end_span,
+        })
+    }
+
+    /// Parses the optional `<T, U>` list declaring the type parameters of an
+    /// item.
+    fn parse_generics(&mut self) -> Result<EntityList<GenericParamEntity>, Error> {
+        if self.eat(TokenKind::Lt).is_none() {
+            return Ok(EntityList::new());
+        }
+
+        let mut generics = Vec::new();
+        while !self.at(TokenKind::Gt) {
+            let (name, span) = self.expect_ident()?;
+
+            let mut bounds = Vec::new();
+            if self.eat(TokenKind::Colon).is_some() {
+                bounds.push(self.parse_type()?);
+                while self.eat(TokenKind::Add).is_some() {
+                    bounds.push(self.parse_type()?);
+                }
+            }
+            let bounds = self.ctx.types.alloc_list(&bounds);
+
+            let param = GenericParam { name, bounds, span };
+            generics.push(self.ctx.generic_params.alloc(param));
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(TokenKind::Gt)?;
+
+        Ok(self.ctx.generic_params.alloc_list(&generics))
+    }
+
+    fn parse_param(&mut self) -> Result<ParamEntity, Error> {
+        let (name, name_span) = self.expect_ident()?;
+
+        let ty = if self.interner.get(name) == SELF_PARAM && !self.at(TokenKind::Colon) {
+            None
+        } else {
+            self.expect(TokenKind::Colon)?;
+            Some(self.parse_type()?)
+        };
+
+        let span = ty.map_or(name_span, |ty| name_span + self.ctx.types[ty].span());
+
+        Ok(self.ctx.params.alloc(Param { name, ty, span }))
+    }
+
+    fn parse_struct_item(&mut self) -> Result<StructItem, Error> {
+        let docs = self.docs();
+        let struct_kw = self.expect(TokenKind::StructKw)?;
+        let (name, name_span) = self.expect_ident()?;
+        let generics = self.parse_generics()?;
+
+        let (fields, fields_span) = self.parse_field_defs()?;
+
+        Ok(StructItem {
+            docs,
+            name,
+            name_span,
+            generics,
+            fields,
+            span: struct_kw.span + fields_span,
+        })
+    }
+
+    /// Parses the `{ name: Type, ... }` fields of a struct or variant.
+    fn parse_field_defs(&mut self) -> Result<(EntityList<FieldDefEntity>, Span), Error> {
+        let left_brace = self.expect(TokenKind::LeftBrace)?;
+        let mut fields = Vec::new();
+        while !self.at(TokenKind::RightBrace) {
+            fields.push(self.parse_field_def()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        let right_brace = self.expect(TokenKind::RightBrace)?;
+
+        Ok((
+            self.ctx.field_defs.alloc_list(&fields),
+            left_brace.span + right_brace.span,
+        ))
+    }
+
+    fn parse_field_def(&mut self) -> Result<FieldDefEntity, Error> {
+        let (name, name_span) = self.expect_ident()?;
+        self.expect(TokenKind::Colon)?;
+        let ty = self.parse_type()?;
+
+        let span = name_span + self.ctx.types[ty].span();
+        Ok(self.ctx.field_defs.alloc(FieldDef { name, ty, span }))
+    }
+
+    fn parse_enum_item(&mut self) -> Result<EnumItem, Error> {
+        let docs = self.docs();
+        let enum_kw = self.expect(TokenKind::EnumKw)?;
+        let (name, name_span) = self.expect_ident()?;
+        let generics = self.parse_generics()?;
+
+        self.expect(TokenKind::LeftBrace)?;
+        let mut variants = Vec::new();
+        while !self.at(TokenKind::RightBrace) {
+            variants.push(self.parse_variant()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+        let right_brace = self.expect(TokenKind::RightBrace)?;
+
+        Ok(EnumItem {
+            docs,
+            name,
+            name_span,
+            generics,
+            variants: self.ctx.variants.alloc_list(&variants),
+            span: enum_kw.span + right_brace.span,
+        })
+    }
+
+    /// Parses a variant: `Name`, `Name(A, B)` or `Name { a: A }`.
+    fn parse_variant(&mut self) -> Result<VariantEntity, Error> {
+        let (name, mut span) = self.expect_ident()?;
+
+        let kind = match self.peek_kind() {
+            Some(TokenKind::LeftParen) => {
+                self.bump();
+                let mut types = Vec::new();
+                while !self.at(TokenKind::RightParen) {
+                    types.push(self.parse_type()?);
+
+                    if self.eat(TokenKind::Comma).is_none() {
+                        break;
+                    }
+                }
+                span += self.expect(TokenKind::RightParen)?.span;
+
+                VariantKind::Tuple(self.ctx.types.alloc_list(&types))
+            }
+            Some(TokenKind::LeftBrace) => {
+                let (fields, fields_span) = self.parse_field_defs()?;
+                span += fields_span;
+
+                VariantKind::Struct(fields)
+            }
+            _ => VariantKind::Unit,
+        };
+
+        Ok(self.ctx.variants.alloc(Variant { name, kind, span }))
+    }
+
+    fn parse_trait_item(&mut self) -> Result<TraitItem, Error> {
+        let docs = self.docs();
+        let trait_kw = self.expect(TokenKind::TraitKw)?;
+        let (name, name_span) = self.expect_ident()?;
+        let (items, items_span) = self.parse_func_items(true)?;
+
+        Ok(TraitItem {
+            docs,
+            name,
+            name_span,
+            items,
+            span: trait_kw.span + items_span,
+        })
+    }
+
+    fn parse_impl_item(&mut self) -> Result<ImplItem, Error> {
+        let docs = self.docs();
+        let impl_kw = self.expect(TokenKind::ImplKw)?;
+        let generics = self.parse_generics()?;
+
+        let first = self.parse_type()?;
+        let (trait_ref, ty) = if self.eat(TokenKind::ForKw).is_some() {
+            (Some(first), self.parse_type()?)
+        } else {
+            (None, first)
+        };
+
+        let (items, items_span) = self.parse_func_items(false)?;
+
+        Ok(ImplItem {
+            docs,
+            generics,
+            trait_ref,
+            ty,
+            items,
+            span: impl_kw.span + items_span,
+        })
+    }
+
+    /// Parses the `{ func ... }` body of a trait or impl.
+    fn parse_func_items(
+        &mut self,
+        is_signature: bool,
+    ) -> Result<(EntityList<ItemEntity>, Span), Error> {
+        let left_brace = self.expect(TokenKind::LeftBrace)?;
+
+        let mut items = Vec::new();
+        while !self.is_eof() && !self.at(TokenKind::RightBrace) {
+            let func = if self.at(TokenKind::FuncKw) {
+                self.parse_func_item(is_signature)
+            } else {
+                Err(self.unexpected(Expected::Token(TokenKind::FuncKw)))
+            };
+
+            match func {
+                Ok(func) => items.push(self.ctx.items.alloc(Item::Func(func))),
+                Err(error) => {
+                    let position = self.position;
+
+                    self.report(error);
+                    self.synchronize(|kind| kind == TokenKind::FuncKw);
+
+                    if self.position == position && !self.at(TokenKind::RightBrace) {
+                        self.bump();
+                    }
+                }
+            }
+        }
+
+        let right_brace = self.expect(TokenKind::RightBrace)?;
+
+        Ok((
+            self.ctx.items.alloc_list(&items),
+            left_brace.span + right_brace.span,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_ast::{
+        AstCtx, Module,
+        item::{FuncItem, Item, VariantKind},
+        ty::TypeKind,
+    };
+    use unnamed_common::{Interner, Source};
+    use unnamed_lexer::ErrorKind as LexerErrorKind;
+
+    use crate::{ErrorKind, ParsedModule};
+
+    fn parse(content: &str, interner: &Interner) -> (AstCtx, Module) {
+        let ParsedModule {
+            ctx,
+            module,
+            errors,
+        } = crate::parse(Source::new(content, "test_file"), interner);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        (ctx, module)
+    }
+
+    fn func(item: &Item) -> FuncItem {
+        match item {
+            Item::Func(func) => *func,
+            _ => panic!("expected a function, found {item:?}"),
+        }
+    }
+
+    fn type_name(ctx: &AstCtx, interner: &Interner, ty: unnamed_ast::TypeEntity) -> String {
+        match ctx.types[ty].kind {
+            TypeKind::Named { name, .. } => interner.get(name).to_owned(),
+            TypeKind::Unit => "()".to_owned(),
+            TypeKind::Error => "<error>".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_func_item() {
+        let interner = Interner::new();
+        let (ctx, module) = parse(
+            "func add(a: i64, b: i64) -> i64 { a + b } func main() {}",
+            &interner,
+        );
+
+        let items = ctx.items.list(module.items);
+        assert_eq!(items.len(), 2);
+
+        let add = func(&ctx.items[items[0]]);
+        assert_eq!(interner.get(add.name), "add");
+        assert_eq!(add.params.len(&ctx.params.pool), 2);
+        assert_eq!(type_name(&ctx, &interner, add.ret.unwrap()), "i64");
+        assert!(add.body.is_some());
+
+        let main = func(&ctx.items[items[1]]);
+        assert_eq!(interner.get(main.name), "main");
+        assert!(main.ret.is_none());
+    }
+
+    #[test]
+    fn test_struct_item() {
+        let interner = Interner::new();
+        let (ctx, module) = parse("struct Point { x: i64, y: Vec<i64>, }", &interner);
+
+        let Item::Struct(point) = ctx.items[ctx.items.list(module.items)[0]] else {
+            panic!("expected a struct");
+        };
+
+        let fields = ctx.field_defs.list(point.fields);
+        assert_eq!(interner.get(point.name), "Point");
+        assert_eq!(fields.len(), 2);
+        assert_eq!(interner.get(ctx.field_defs[fields[1]].name), "y");
+        assert_eq!(
+            type_name(&ctx, &interner, ctx.field_defs[fields[1]].ty),
+            "Vec"
+        );
+    }
+
+    #[test]
+    fn test_enum_item() {
+        let interner = Interner::new();
+        let content = "enum Shape<T> { Empty, Circle(T), Rect { w: T, h: T }, }";
+        let (ctx, module) = parse(content, &interner);
+
+        let Item::Enum(shape) = ctx.items[ctx.items.list(module.items)[0]] else {
+            panic!("expected an enum");
+        };
+        assert_eq!(interner.get(shape.name), "Shape");
+        assert_eq!(shape.generics.len(&ctx.generic_params.pool), 1);
+
+        let variants: Vec<_> = ctx
+            .variants
+            .list(shape.variants)
+            .iter()
+            .map(|&variant| ctx.variants[variant])
+            .collect();
+        assert_eq!(variants.len(), 3);
+        assert_eq!(interner.get(variants[0].name), "Empty");
+        assert_eq!(variants[0].kind, VariantKind::Unit);
+
+        let VariantKind::Tuple(types) = variants[1].kind else {
+            panic!("expected a tuple variant");
+        };
+        assert_eq!(type_name(&ctx, &interner, ctx.types.list(types)[0]), "T");
+
+        let VariantKind::Struct(fields) = variants[2].kind else {
+            panic!("expected a struct variant");
+        };
+        assert_eq!(ctx.field_defs.list(fields).len(), 2);
+        let span = variants[2].span;
+        assert_eq!(&content[span.start..span.end], "Rect { w: T, h: T }");
+    }
+
+    #[test]
+    fn test_trait_and_impls() {
+        let interner = Interner::new();
+        let (ctx, module) = parse(
+            "trait Show { func show(self) -> i64; func twice(self) -> i64 { self.show() * 2 } }
+             impl Point { func new() -> Point { Point { x: 0 } } }
+             impl Show for Point { func show(self) -> i64 { self.x } }",
+            &interner,
+        );
+
+        let items = ctx.items.list(module.items);
+
+        let Item::Trait(show) = ctx.items[items[0]] else {
+            panic!("expected a trait");
+        };
+        let methods = ctx.items.list(show.items);
+        assert_eq!(methods.len(), 2);
+
+        let show_method = func(&ctx.items[methods[0]]);
+        assert!(show_method.body.is_none());
+        let self_param = ctx.params[ctx.params.list(show_method.params)[0]];
+        assert_eq!(interner.get(self_param.name), "self");
+        assert!(self_param.ty.is_none());
+        assert!(func(&ctx.items[methods[1]]).body.is_some());
+
+        let Item::Impl(inherent) = ctx.items[items[1]] else {
+            panic!("expected an impl");
+        };
+        assert!(inherent.trait_ref.is_none());
+        assert_eq!(type_name(&ctx, &interner, inherent.ty), "Point");
+
+        let Item::Impl(trait_impl) = ctx.items[items[2]] else {
+            panic!("expected an impl");
+        };
+        assert_eq!(
+            type_name(&ctx, &interner, trait_impl.trait_ref.unwrap()),
+            "Show"
+        );
+        assert_eq!(type_name(&ctx, &interner, trait_impl.ty), "Point");
+    }
+
+    #[test]
+    fn test_generics() {
+        let interner = Interner::new();
+        let (ctx, module) = parse(
+            "struct Pair<A, B> { a: A, b: B }
+             impl<T> Pair<T, T> { func swap(self) -> Pair<T, T> { self } }
+             func first<A: Show + Eq, B,>(pair: Pair<A, B>) -> A { pair.a }",
+            &interner,
+        );
+
+        let items = ctx.items.list(module.items);
+        let names = |generics| {
+            ctx.generic_params
+                .list(generics)
+                .iter()
+                .map(|&param| interner.get(ctx.generic_params[param].name))
+                .collect::<Vec<_>>()
+        };
+
+        let Item::Struct(pair) = ctx.items[items[0]] else {
+            panic!("expected a struct");
+        };
+        assert_eq!(names(pair.generics), ["A", "B"]);
+
+        let Item::Impl(pair_impl) = ctx.items[items[1]] else {
+            panic!("expected an impl");
+        };
+        assert_eq!(names(pair_impl.generics), ["T"]);
+        assert_eq!(type_name(&ctx, &interner, pair_impl.ty), "Pair");
+        let swap = func(&ctx.items[ctx.items.list(pair_impl.items)[0]]);
+        assert!(swap.generics.is_empty());
+
+        let first = func(&ctx.items[items[2]]);
+        assert_eq!(names(first.generics), ["A", "B"]);
+
+        let generics = ctx.generic_params.list(first.generics);
+        let bounds: Vec<_> = ctx
+            .types
+            .list(ctx.generic_params[generics[0]].bounds)
+            .iter()
+            .map(|&bound| type_name(&ctx, &interner, bound))
+            .collect();
+        assert_eq!(bounds, ["Show", "Eq"]);
+        assert!(ctx.generic_params[generics[1]].bounds.is_empty());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let interner = Interner::new();
+        let (ctx, module) = parse(
+            "//! The module.
+             //! Second line.
+
+             /// Adds numbers.
+             /// Really.
+             func add() {}
+
+             // Not documented.
+             struct Point {}
+
+             /// The trait.
+             trait Show {
+                 /// Shows it.
+                 func show(self);
+             }
+
+             impl Show for Point {
+                 func show(self) {
+                     /// Not an item, ignored.
+                     1
+                 }
+             }",
+            &interner,
+        );
+
+        let docs = |docs: Option<unnamed_common::StrId>| docs.map(|docs| interner.get(docs));
+        assert_eq!(docs(module.docs), Some("The module.\nSecond line."));
+
+        let items = ctx.items.list(module.items);
+        assert_eq!(
+            docs(func(&ctx.items[items[0]]).docs),
+            Some("Adds numbers.\nReally.")
+        );
+
+        let Item::Struct(point) = ctx.items[items[1]] else {
+            panic!("expected a struct");
+        };
+        assert_eq!(docs(point.docs), None);
+
+        let Item::Trait(show) = ctx.items[items[2]] else {
+            panic!("expected a trait");
+        };
+        assert_eq!(docs(show.docs), Some("The trait."));
+
+        let show_method = func(&ctx.items[ctx.items.list(show.items)[0]]);
+        assert_eq!(docs(show_method.docs), Some("Shows it."));
+
+        let Item::Impl(show_impl) = ctx.items[items[3]] else {
+            panic!("expected an impl");
+        };
+        assert_eq!(docs(show_impl.docs), None);
+    }
+
+    #[test]
+    fn test_func_without_body_outside_trait() {
+        let interner = Interner::new();
+        let parsed = crate::parse(Source::new("func foo();", "test_file"), &interner);
+
+        assert_eq!(parsed.errors.len(), 1);
+        assert!(matches!(
+            parsed.errors[0].kind(),
+            ErrorKind::Expected { .. }
+        ));
+    }
+
+    #[test]
+    fn test_item_recovery() {
+        let interner = Interner::new();
+        let parsed = crate::parse(
+            Source::new(
+                "1 + 2
+                 func broken(a b) { a }
+                 struct Point { x: i64, y: }
+                 impl Point { let x = 1; func ok() {} }
+                 func main() { let p = ; }",
+                "test_file",
+            ),
+            &interner,
+        );
+
+        let names = parsed
+            .ctx
+            .items
+            .list(parsed.module.items)
+            .iter()
+            .map(|&item| match parsed.ctx.items[item] {
+                Item::Func(func) => interner.get(func.name).to_owned(),
+                Item::Struct(struct_item) => interner.get(struct_item.name).to_owned(),
+                Item::Enum(enum_item) => interner.get(enum_item.name).to_owned(),
+                Item::Trait(trait_item) => interner.get(trait_item.name).to_owned(),
+                Item::Impl(..) => "impl".to_owned(),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["Point", "impl", "main"]);
+        assert_eq!(parsed.errors.len(), 5);
+
+        let Item::Impl(impl_item) = parsed.ctx.items[parsed.ctx.items.list(parsed.module.items)[1]]
+        else {
+            panic!("expected an impl");
+        };
+        assert_eq!(impl_item.items.len(&parsed.ctx.items.pool), 1);
+    }
+
+    #[test]
+    fn test_unclosed_raw_string_at_eof() {
+        let interner = Interner::new();
+        let parsed = crate::parse(
+            Source::new("func main() {\n    let s = r#\"abc\n}\n", "test_file"),
+            &interner,
+        );
+
+        // Only the string is reported, not the `}` it swallowed.
+        assert_eq!(parsed.errors.len(), 1, "{:?}", parsed.errors);
+        assert!(matches!(
+            parsed.errors[0].kind(),
+            ErrorKind::Lexer(error)
+                if matches!(error.kind(), LexerErrorKind::UnclosedRawStringLiteral { hashes: 1 })
+        ));
+    }
+}
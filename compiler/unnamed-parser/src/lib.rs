@@ -1 +1,294 @@
+use std::collections::HashMap;
 
+pub mod error;
+mod expr;
+mod item;
+mod ty;
+
+use unnamed_ast::{AstCtx, Module};
+use unnamed_common::{Interner, Source, Span, Spanned, StrId};
+use unnamed_lexer::{ErrorKind as LexerErrorKind, Lexer, Token, TokenKind};
+
+pub use error::{Error, ErrorKind, Expected};
+
+/// The result of parsing a source file. The context is always produced, even
+/// when `errors` is not empty, with `Error` placeholders in place of the parts
+/// that could not be parsed.
+#[derive(Debug)]
+pub struct ParsedModule {
+    pub ctx: AstCtx,
+    pub module: Module,
+    pub errors: Vec<Error>,
+}
+
+/// Parses a whole source file into a module of items.
+pub fn parse<'s>(source: Source<'s>, interner: &'s Interner) -> ParsedModule {
+    let mut parser = Parser::new(source, interner);
+    let module = parser.parse_module();
+    let (ctx, errors) = parser.finish();
+
+    ParsedModule {
+        ctx,
+        module,
+        errors,
+    }
+}
+
+#[derive(Debug)]
+pub struct Parser<'s> {
+    source: Source<'s>,
+    interner: &'s Interner,
+    tokens: Vec<Token<'s>>,
+    position: usize,
+    ctx: AstCtx,
+    errors: Vec<Error>,
+    docs: HashMap<usize, StrId>,
+    module_docs: Option<StrId>,
+    struct_literals: bool,
+    /// Set when the source ends inside a string or comment that is not
+    /// closed, which already explains why the file ends early.
+    unclosed_at_eof: bool,
+}
+
+impl<'s> Parser<'s> {
+    pub fn new(source: Source<'s>, interner: &'s Interner) -> Self {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        let mut docs = HashMap::new();
+        let mut module_docs = Vec::new();
+        let mut pending_docs = Vec::new();
+        let mut unclosed_at_eof = false;
+
+        // Doc comments are taken out of the token stream and remembered by
+        // the index of the token that follows them, so that items can pick
+        // them up. `//!` comments document the module only before its first
+        // token; doc comments anywhere else are treated as plain comments.
+        let mut last_span = None;
+        for token in Lexer::new(source) {
+            if let Ok(token) = &token {
+                last_span = Some(token.span);
+            }
+
+            match token {
+                Ok(token) if token.kind == TokenKind::DocComment => {
+                    pending_docs.push(token.string().to_owned());
+                }
+                Ok(token) if token.kind == TokenKind::InnerDocComment => {
+                    if tokens.is_empty() && pending_docs.is_empty() {
+                        module_docs.push(token.string().to_owned());
+                    }
+                }
+                Ok(token) => {
+                    if !pending_docs.is_empty() {
+                        let text = pending_docs.join("\n");
+                        docs.insert(tokens.len(), interner.intern(&text));
+                        pending_docs.clear();
+                    }
+
+                    tokens.push(token);
+                }
+                Err(error) => {
+                    unclosed_at_eof |= matches!(
+                        error.kind(),
+                        LexerErrorKind::UnclosedStringLiteral
+                            | LexerErrorKind::UnclosedRawStringLiteral { .. }
+                            | LexerErrorKind::UnclosedBlockComment
+                    );
+
+                    // Errors inside the last token, like an invalid escape in
+                    // a string, come with the token itself. Any other error
+                    // stands for source that is not part of a token.
+                    let span = error.span();
+                    let is_in_token = last_span
+                        .is_some_and(|last| last.start <= span.start && span.end <= last.end);
+                    if !is_in_token {
+                        let slice = &source.content[span.start..span.end];
+                        tokens.push(Token::new(span, slice, TokenKind::Error));
+                    }
+
+                    errors.push(Error::lexer(error));
+                }
+            }
+        }
+
+        let module_docs =
+            (!module_docs.is_empty()).then(|| interner.intern(&module_docs.join("\n")));
+
+        Self {
+            source,
+            interner,
+            tokens,
+            position: 0,
+            ctx: AstCtx::new(),
+            errors,
+            docs,
+            module_docs,
+            struct_literals: true,
+            unclosed_at_eof,
+        }
+    }
+
+    pub fn ctx(&self) -> &AstCtx {
+        &self.ctx
+    }
+
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Returns the parsed context together with every error reported so far,
+    /// ordered by their position in the source.
+    pub fn finish(mut self) -> (AstCtx, Vec<Error>) {
+        self.errors.sort_by_key(|error| error.span().start);
+
+        (self.ctx, self.errors)
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    fn at_item_start(&self) -> bool {
+        self.peek_kind().is_some_and(item::is_item_keyword)
+    }
+
+    /// The doc comment written right before the current token.
+    fn docs(&self) -> Option<StrId> {
+        self.docs.get(&self.position).copied()
+    }
+
+    fn peek(&self) -> Option<&Token<'s>> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_kind(&self) -> Option<TokenKind> {
+        self.nth_kind(0)
+    }
+
+    fn nth_kind(&self, n: usize) -> Option<TokenKind> {
+        self.tokens.get(self.position + n).map(|token| token.kind)
+    }
+
+    fn at(&self, kind: TokenKind) -> bool {
+        self.peek_kind() == Some(kind)
+    }
+
+    fn bump(&mut self) -> Option<Token<'s>> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn eat(&mut self, kind: TokenKind) -> Option<Token<'s>> {
+        if self.at(kind) { self.bump() } else { None }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<Token<'s>, Error> {
+        self.eat(kind)
+            .ok_or_else(|| self.unexpected(Expected::Token(kind)))
+    }
+
+    fn expect_ident(&mut self) -> Result<(StrId, Span), Error> {
+        let token = self.expect(TokenKind::Ident)?;
+
+        Ok((self.interner.intern(token.slice), token.span))
+    }
+
+    /// Consumes a `>` closing a type argument list, splitting a `>>` token
+    /// in two so that nested lists like `Foo<Bar<T>>` parse correctly.
+    fn expect_gt(&mut self) -> Result<Span, Error> {
+        let Some(token) = self.tokens.get_mut(self.position) else {
+            return Err(self.unexpected(Expected::Token(TokenKind::Gt)));
+        };
+
+        match token.kind {
+            TokenKind::Gt => {
+                let span = token.span;
+                self.position += 1;
+
+                Ok(span)
+            }
+            TokenKind::BitShr => {
+                let span = Span::new(token.span.start, token.span.start + 1);
+
+                token.kind = TokenKind::Gt;
+                token.slice = &token.slice[1..];
+                token.span.start += 1;
+
+                Ok(span)
+            }
+            _ => Err(self.unexpected(Expected::Token(TokenKind::Gt))),
+        }
+    }
+
+    fn unexpected(&self, expected: Expected) -> Error {
+        match self.peek() {
+            Some(token) => Error::expected(token.span, expected, Some(token.kind)),
+            None => Error::expected(self.eof_span(), expected, None),
+        }
+    }
+
+    fn eof_span(&self) -> Span {
+        let end = self.source.content.len();
+        Span::new(end, end)
+    }
+
+    fn intern(&self, string: &str) -> StrId {
+        self.interner.intern(string)
+    }
+
+    /// Reports an error, unless it is about a token the lexer already
+    /// reported or about the end of a file that ends in an unclosed string or
+    /// comment.
+    fn report(&mut self, error: Error) {
+        if let ErrorKind::Expected { found, .. } = error.kind() {
+            match found {
+                Some(TokenKind::Error) => return,
+                None if self.unclosed_at_eof => return,
+                _ => {}
+            }
+        }
+
+        self.errors.push(error);
+    }
+
+    /// Skips tokens until `stop` matches a token outside of any nested
+    /// brackets, or until an unmatched `}` or the end of file. Unmatched `)`
+    /// and `]` are skipped. Returns the span of the skipped tokens.
+    fn synchronize(&mut self, stop: impl Fn(TokenKind) -> bool) -> Span {
+        let start = self.position;
+        let mut depth = 0usize;
+
+        while let Some(kind) = self.peek_kind() {
+            if depth == 0 && (stop(kind) || kind == TokenKind::RightBrace) {
+                break;
+            }
+
+            match kind {
+                TokenKind::LeftBrace | TokenKind::LeftParen | TokenKind::LeftSquare => depth += 1,
+                TokenKind::RightBrace | TokenKind::RightParen | TokenKind::RightSquare => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+
+            self.position += 1;
+        }
+
+        self.tokens[start..self.position]
+            .iter()
+            .map(|token| token.span)
+            .fold(Span::ZERO, |acc, span| acc + span)
+    }
+
+    fn with_struct_literals<T>(&mut self, allowed: bool, parse: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.struct_literals, allowed);
+        let result = parse(self);
+        self.struct_literals = previous;
+
+        result
+    }
+}
@@ -0,0 +1,85 @@
+use cranelift_entity::EntityList;
+use unnamed_ast::{Type, TypeEntity, ty::TypeKind};
+use unnamed_common::{Span, Spanned};
+use unnamed_lexer::TokenKind;
+
+use crate::{Error, Expected, Parser};
+
+impl<'s> Parser<'s> {
+    pub fn parse_type(&mut self) -> Result<TypeEntity, Error> {
+        match self.peek_kind() {
+            Some(TokenKind::LeftParen) => {
+                let left_paren = self.expect(TokenKind::LeftParen)?;
+                let right_paren = self.expect(TokenKind::RightParen)?;
+
+                Ok(self.ctx.types.alloc(Type {
+                    kind: TypeKind::Unit,
+                    span: left_paren.span + right_paren.span,
+                }))
+            }
+            Some(TokenKind::Ident) => {
+                let (name, mut span) = self.expect_ident()?;
+
+                let args = if self.at(TokenKind::Lt) {
+                    let (args, args_span) = self.parse_type_args()?;
+                    span += args_span;
+
+                    args
+                } else {
+                    EntityList::new()
+                };
+
+                Ok(self.ctx.types.alloc(Type {
+                    kind: TypeKind::Named { name, args },
+                    span,
+                }))
+            }
+            _ => {
+                let error = self.unexpected(Expected::Type);
+                let span = error.span();
+                self.report(error);
+
+                let is_delimiter = matches!(
+                    self.peek_kind(),
+                    None | Some(
+                        TokenKind::RightParen
+                            | TokenKind::RightBrace
+                            | TokenKind::LeftBrace
+                            | TokenKind::Comma
+                            | TokenKind::Semicolon
+                            | TokenKind::Asgmt
+                            | TokenKind::Gt
+                    )
+                );
+
+                if !is_delimiter && !self.at_item_start() {
+                    self.bump();
+                }
+
+                Ok(self.ctx.types.alloc(Type {
+                    kind: TypeKind::Error,
+                    span,
+                }))
+            }
+        }
+    }
+
+    /// Parses a `<T, U>` list, returning the types together with the span of
+    /// the whole list including the angle brackets.
+    pub(crate) fn parse_type_args(&mut self) -> Result<(EntityList<TypeEntity>, Span), Error> {
+        let lt = self.expect(TokenKind::Lt)?;
+
+        let mut args = Vec::new();
+        while !self.at(TokenKind::Gt) && !self.at(TokenKind::BitShr) {
+            args.push(self.parse_type()?);
+
+            if self.eat(TokenKind::Comma).is_none() {
+                break;
+            }
+        }
+
+        let gt_span = self.expect_gt()?;
+
+        Ok((self.ctx.types.alloc_list(&args), lt.span + gt_span))
+    }
+}
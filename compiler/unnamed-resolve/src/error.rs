@@ -0,0 +1,169 @@
+use std::fmt::{self, Display};
+
+use ariadne::{Label, ReportKind};
+use unnamed_common::{IntoReport, Report, Source, Span, Spanned};
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    span: Span,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(span: Span, kind: ErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// What a name was expected to refer to, used in error messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Namespace {
+    Value,
+    /// A local variable, as the target of an assignment.
+    Variable,
+    Type,
+    Struct,
+    Enum,
+}
+
+impl Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Namespace::Value => f.write_str("value"),
+            Namespace::Variable => f.write_str("variable"),
+            Namespace::Type => f.write_str("type"),
+            Namespace::Struct => f.write_str("struct"),
+            Namespace::Enum => f.write_str("enum"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Undefined {
+        namespace: Namespace,
+        name: String,
+        /// A similar name that is in scope.
+        suggestion: Option<String>,
+    },
+    /// `Enum::Variant` where the enum has no such variant.
+    UndefinedVariant {
+        enum_name: String,
+        name: String,
+        /// A variant of the enum with a similar name.
+        suggestion: Option<String>,
+    },
+    /// A name that exists, but refers to something else than expected, like
+    /// a struct used as a value.
+    Mismatched {
+        expected: Namespace,
+        found: &'static str,
+        name: String,
+    },
+    Duplicate {
+        what: &'static str,
+        name: String,
+        previous: Span,
+    },
+    /// A call to a local variable, which can only happen by mistake since
+    /// locals are never functions.
+    CallToLocal {
+        name: String,
+        /// The binding of the local, when it hides a function of the same
+        /// name.
+        shadowed_function: Option<Span>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Undefined {
+                namespace, name, ..
+            } => write!(f, "cannot find {namespace} `{name}` in this scope"),
+            ErrorKind::UndefinedVariant {
+                enum_name, name, ..
+            } => write!(f, "no variant named `{name}` in enum `{enum_name}`"),
+            ErrorKind::Mismatched {
+                expected,
+                found,
+                name,
+            } => write!(f, "expected {expected}, found {found} `{name}`"),
+            ErrorKind::Duplicate { what, name, .. } => {
+                write!(f, "the {what} `{name}` is defined multiple times")
+            }
+            ErrorKind::CallToLocal { name, .. } => {
+                write!(f, "expected function, found local variable `{name}`")
+            }
+        }
+    }
+}
+
+impl IntoReport for Error {
+    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
+        let message = self.to_string();
+        let mut report = Report::build(ReportKind::Error, source.span(self.span));
+
+        let label_message = match self.kind {
+            ErrorKind::Undefined {
+                namespace,
+                suggestion,
+                ..
+            } => {
+                if let Some(suggestion) = suggestion {
+                    report = report.with_help(format!("did you mean `{suggestion}`?"));
+                }
+
+                format!("not found in this scope, expected a {namespace}")
+            }
+            ErrorKind::UndefinedVariant {
+                enum_name,
+                suggestion,
+                ..
+            } => {
+                if let Some(suggestion) = suggestion {
+                    report = report.with_help(format!("did you mean `{suggestion}`?"));
+                }
+
+                format!("variant not found in `{enum_name}`")
+            }
+            ErrorKind::Mismatched { expected, .. } => format!("not a {expected}"),
+            ErrorKind::Duplicate { name, previous, .. } => {
+                report = report.with_label(
+                    Label::new(source.span(previous))
+                        .with_message(format!("previous definition of `{name}` here")),
+                );
+
+                format!("`{name}` redefined here")
+            }
+            ErrorKind::CallToLocal {
+                name,
+                shadowed_function,
+            } => {
+                if let Some(binding) = shadowed_function {
+                    report = report.with_label(
+                        Label::new(source.span(binding))
+                            .with_message(format!("this binding shadows the function `{name}`")),
+                    );
+                }
+
+                "call expression requires a function".to_owned()
+            }
+        };
+
+        report
+            .with_message(message)
+            .with_label(Label::new(source.span(self.span)).with_message(label_message))
+            .finish()
+    }
+}
@@ -0,0 +1,1376 @@
+pub mod error;
+
+use std::collections::HashMap;
+
+use cranelift_entity::{EntityList, PrimaryMap, SecondaryMap, entity_impl};
+use unnamed_ast::{
+    AstCtx, Block, ExprEntity, FieldDefEntity, GenericParam, GenericParamEntity, ItemEntity,
+    Module, Param, ParamEntity, PatEntity, PathSegment, TypeEntity, VariantEntity,
+    expr::{ArrayExprKind, ElseExprKind, Expr, MatchArm},
+    item::{FuncItem, Item, VariantKind},
+    pat::{Pat, PatFields},
+    ty::TypeKind,
+};
+use unnamed_common::{Interner, Span, Spanned, StrId};
+
+pub use error::{Error, ErrorKind, Namespace};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalId(u32);
+entity_impl!(LocalId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalKind {
+    Param(ParamEntity),
+    Let(ExprEntity),
+    /// A binding inside the pattern of a match arm.
+    Pat(PatEntity),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Local {
+    pub name: StrId,
+    /// Span of the name at the binding.
+    pub span: Span,
+    pub kind: LocalKind,
+}
+
+/// Functions provided by the language rather than declared in the module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Print,
+    Println,
+}
+
+impl Builtin {
+    pub const ALL: [Builtin; 2] = [Builtin::Print, Builtin::Println];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+            Builtin::Println => "println",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|builtin| builtin.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimTy {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Bool,
+    Char,
+    Str,
+}
+
+impl PrimTy {
+    pub const ALL: [PrimTy; 13] = [
+        PrimTy::I8,
+        PrimTy::I16,
+        PrimTy::I32,
+        PrimTy::I64,
+        PrimTy::U8,
+        PrimTy::U16,
+        PrimTy::U32,
+        PrimTy::U64,
+        PrimTy::F32,
+        PrimTy::F64,
+        PrimTy::Bool,
+        PrimTy::Char,
+        PrimTy::Str,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PrimTy::I8 => "i8",
+            PrimTy::I16 => "i16",
+            PrimTy::I32 => "i32",
+            PrimTy::I64 => "i64",
+            PrimTy::U8 => "u8",
+            PrimTy::U16 => "u16",
+            PrimTy::U32 => "u32",
+            PrimTy::U64 => "u64",
+            PrimTy::F32 => "f32",
+            PrimTy::F64 => "f64",
+            PrimTy::Bool => "bool",
+            PrimTy::Char => "char",
+            PrimTy::Str => "str",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|ty| ty.name() == name)
+    }
+}
+
+/// A trait built into the language, which operators on types implementing
+/// it desugar to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuiltinTrait {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Neg,
+    Eq,
+    Ord,
+}
+
+impl BuiltinTrait {
+    pub const ALL: [BuiltinTrait; 12] = [
+        BuiltinTrait::Add,
+        BuiltinTrait::Sub,
+        BuiltinTrait::Mul,
+        BuiltinTrait::Div,
+        BuiltinTrait::BitAnd,
+        BuiltinTrait::BitOr,
+        BuiltinTrait::BitXor,
+        BuiltinTrait::Shl,
+        BuiltinTrait::Shr,
+        BuiltinTrait::Neg,
+        BuiltinTrait::Eq,
+        BuiltinTrait::Ord,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BuiltinTrait::Add => "Add",
+            BuiltinTrait::Sub => "Sub",
+            BuiltinTrait::Mul => "Mul",
+            BuiltinTrait::Div => "Div",
+            BuiltinTrait::BitAnd => "BitAnd",
+            BuiltinTrait::BitOr => "BitOr",
+            BuiltinTrait::BitXor => "BitXor",
+            BuiltinTrait::Shl => "Shl",
+            BuiltinTrait::Shr => "Shr",
+            BuiltinTrait::Neg => "Neg",
+            BuiltinTrait::Eq => "Eq",
+            BuiltinTrait::Ord => "Ord",
+        }
+    }
+
+    /// The name of the trait's only method.
+    pub fn method(self) -> &'static str {
+        match self {
+            BuiltinTrait::Add => "add",
+            BuiltinTrait::Sub => "sub",
+            BuiltinTrait::Mul => "mul",
+            BuiltinTrait::Div => "div",
+            BuiltinTrait::BitAnd => "bitand",
+            BuiltinTrait::BitOr => "bitor",
+            BuiltinTrait::BitXor => "bitxor",
+            BuiltinTrait::Shl => "shl",
+            BuiltinTrait::Shr => "shr",
+            BuiltinTrait::Neg => "neg",
+            BuiltinTrait::Eq => "eq",
+            BuiltinTrait::Ord => "cmp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|trait_| trait_.name() == name)
+    }
+}
+
+/// What a name refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Res {
+    Local(LocalId),
+    Func(ItemEntity),
+    Struct(ItemEntity),
+    Enum(ItemEntity),
+    /// A variant of the enum item.
+    Variant(ItemEntity, VariantEntity),
+    Trait(ItemEntity),
+    Builtin(Builtin),
+    PrimTy(PrimTy),
+    BuiltinTrait(BuiltinTrait),
+    /// `Self` inside the trait or impl item.
+    SelfTy(ItemEntity),
+    TyParam(GenericParamEntity),
+}
+
+impl Res {
+    pub fn description(self) -> &'static str {
+        match self {
+            Res::Local(_) => "local variable",
+            Res::Func(_) => "function",
+            Res::Struct(_) => "struct",
+            Res::Enum(_) => "enum",
+            Res::Variant(..) => "enum variant",
+            Res::Trait(_) => "trait",
+            Res::Builtin(_) => "builtin function",
+            Res::PrimTy(_) => "builtin type",
+            Res::BuiltinTrait(_) => "builtin trait",
+            Res::SelfTy(_) => "self type",
+            Res::TyParam(_) => "type parameter",
+        }
+    }
+}
+
+/// The result of name resolution, as side tables over the AST. Entries are
+/// `None` for nodes without a name and for names that failed to resolve.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub locals: PrimaryMap<LocalId, Local>,
+    /// Functions of the module by name.
+    pub functions: HashMap<StrId, ItemEntity>,
+    /// Structs, enums and traits of the module by name.
+    pub type_items: HashMap<StrId, ItemEntity>,
+    /// What each `Expr::Ident`, the target of each `Expr::Assign`, the name
+    /// of each `Expr::Struct` and the variant of each `Expr::Path` refers to.
+    pub exprs: SecondaryMap<ExprEntity, Option<Res>>,
+    /// The local introduced by each `Expr::Let`.
+    pub lets: SecondaryMap<ExprEntity, Option<LocalId>>,
+    pub params: SecondaryMap<ParamEntity, Option<LocalId>>,
+    /// The struct or variant each `Pat::Adt` matches.
+    pub pats: SecondaryMap<PatEntity, Option<Res>>,
+    /// The local introduced by each `Pat::Binding`.
+    pub bindings: SecondaryMap<PatEntity, Option<LocalId>>,
+    /// What the name of each `TypeKind::Named` refers to.
+    pub types: SecondaryMap<TypeEntity, Option<Res>>,
+    /// Errors ordered by their position in the source.
+    pub errors: Vec<Error>,
+}
+
+/// Resolves every name in `module`. Items can be used anywhere in the module,
+/// locals only after their binding and inside the enclosing block.
+pub fn resolve(ctx: &AstCtx, module: &Module, interner: &Interner) -> Resolution {
+    let mut resolver = Resolver {
+        ctx,
+        interner,
+        resolution: Resolution::default(),
+        scopes: Vec::new(),
+        generics: Vec::new(),
+        self_item: None,
+    };
+
+    let items = ctx.items.list(module.items);
+    for &item in items {
+        resolver.declare(item);
+    }
+    for &item in items {
+        resolver.item(item);
+    }
+
+    let mut resolution = resolver.resolution;
+    resolution.errors.sort_by_key(|error| error.span().start);
+
+    resolution
+}
+
+struct Resolver<'a> {
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    resolution: Resolution,
+    /// Locals visible at the current point, innermost block last.
+    scopes: Vec<Vec<LocalId>>,
+    /// Type parameters in scope, those of the innermost item last.
+    generics: Vec<GenericParamEntity>,
+    /// The trait or impl that `Self` refers to.
+    self_item: Option<ItemEntity>,
+}
+
+impl Resolver<'_> {
+    fn declare(&mut self, item: ItemEntity) {
+        let (name, name_span, what) = match self.ctx.items[item] {
+            Item::Func(func) => (func.name, func.name_span, "function"),
+            Item::Struct(struct_item) => {
+                self.check_fields(struct_item.fields);
+                (struct_item.name, struct_item.name_span, "type")
+            }
+            Item::Enum(enum_item) => {
+                let mut variants = HashMap::new();
+                for &variant in self.ctx.variants.list(enum_item.variants) {
+                    let variant = self.ctx.variants[variant];
+                    let span = self.name_span(variant.span.start, variant.name);
+
+                    if let Some(&previous) = variants.get(&variant.name) {
+                        self.duplicate("variant", variant.name, span, previous);
+                    } else {
+                        variants.insert(variant.name, span);
+                    }
+
+                    if let VariantKind::Struct(fields) = variant.kind {
+                        self.check_fields(fields);
+                    }
+                }
+
+                (enum_item.name, enum_item.name_span, "type")
+            }
+            Item::Trait(trait_item) => (trait_item.name, trait_item.name_span, "type"),
+            Item::Impl(_) => return,
+        };
+
+        let namespace = match self.ctx.items[item] {
+            Item::Func(_) => &mut self.resolution.functions,
+            _ => &mut self.resolution.type_items,
+        };
+
+        match namespace.get(&name) {
+            Some(&previous) => {
+                let previous = item_name_span(self.ctx, previous);
+                self.duplicate(what, name, name_span, previous);
+            }
+            None => {
+                namespace.insert(name, item);
+            }
+        }
+    }
+
+    /// Reports fields that are defined more than once.
+    fn check_fields(&mut self, fields: EntityList<FieldDefEntity>) {
+        let mut names = HashMap::new();
+        for &field in self.ctx.field_defs.list(fields) {
+            let field = self.ctx.field_defs[field];
+            let span = self.name_span(field.span.start, field.name);
+
+            if let Some(&previous) = names.get(&field.name) {
+                self.duplicate("field", field.name, span, previous);
+            } else {
+                names.insert(field.name, span);
+            }
+        }
+    }
+
+    fn item(&mut self, item: ItemEntity) {
+        match self.ctx.items[item] {
+            Item::Func(func) => self.func(func),
+            Item::Struct(struct_item) => {
+                let outer = self.push_generics(struct_item.generics);
+                for &field in self.ctx.field_defs.list(struct_item.fields) {
+                    self.ty(self.ctx.field_defs[field].ty);
+                }
+                self.generics.truncate(outer);
+            }
+            Item::Enum(enum_item) => {
+                let outer = self.push_generics(enum_item.generics);
+                for &variant in self.ctx.variants.list(enum_item.variants) {
+                    match self.ctx.variants[variant].kind {
+                        VariantKind::Unit => {}
+                        VariantKind::Tuple(types) => self.types_list(types),
+                        VariantKind::Struct(fields) => {
+                            for &field in self.ctx.field_defs.list(fields) {
+                                self.ty(self.ctx.field_defs[field].ty);
+                            }
+                        }
+                    }
+                }
+                self.generics.truncate(outer);
+            }
+            Item::Trait(trait_item) => {
+                self.self_item = Some(item);
+                self.items(trait_item.items);
+                self.self_item = None;
+            }
+            Item::Impl(impl_item) => {
+                let outer = self.push_generics(impl_item.generics);
+                if let Some(trait_ref) = impl_item.trait_ref {
+                    self.ty(trait_ref);
+                }
+                self.ty(impl_item.ty);
+
+                self.self_item = Some(item);
+                self.items(impl_item.items);
+                self.self_item = None;
+                self.generics.truncate(outer);
+            }
+        }
+    }
+
+    fn items(&mut self, items: EntityList<ItemEntity>) {
+        for &item in self.ctx.items.list(items) {
+            self.item(item);
+        }
+    }
+
+    /// Brings the type parameters of an item into scope, returning the
+    /// length to truncate back to once the item is done.
+    fn push_generics(&mut self, generics: EntityList<GenericParamEntity>) -> usize {
+        let outer = self.generics.len();
+
+        let mut names = HashMap::new();
+        for &param in self.ctx.generic_params.list(generics) {
+            let GenericParam { name, span, .. } = self.ctx.generic_params[param];
+            if let Some(&previous) = names.get(&name) {
+                self.duplicate("type parameter", name, span, previous);
+            } else {
+                names.insert(name, span);
+            }
+
+            self.generics.push(param);
+        }
+
+        for &param in self.ctx.generic_params.list(generics) {
+            for &bound in self.ctx.types.list(self.ctx.generic_params[param].bounds) {
+                self.ty(bound);
+            }
+        }
+
+        outer
+    }
+
+    fn func(&mut self, func: FuncItem) {
+        let outer = self.push_generics(func.generics);
+        self.scopes.push(Vec::new());
+
+        let mut names = HashMap::new();
+        for &param in self.ctx.params.list(func.params) {
+            let Param { name, ty, span } = self.ctx.params[param];
+            if let Some(ty) = ty {
+                self.ty(ty);
+            }
+
+            let span = self.name_span(span.start, name);
+            if let Some(&previous) = names.get(&name) {
+                self.duplicate("parameter", name, span, previous);
+            } else {
+                names.insert(name, span);
+            }
+
+            let local = self.bind(name, span, LocalKind::Param(param));
+            self.resolution.params[param] = Some(local);
+        }
+
+        if let Some(ret) = func.ret {
+            self.ty(ret);
+        }
+        if let Some(body) = func.body {
+            self.block(body);
+        }
+
+        self.scopes.pop();
+        self.generics.truncate(outer);
+    }
+
+    fn ty(&mut self, ty: TypeEntity) {
+        let TypeKind::Named { name, args } = self.ctx.types[ty].kind else {
+            return;
+        };
+
+        let span = self.name_span(self.ctx.types[ty].span.start, name);
+        self.resolution.types[ty] = self.resolve_type(name, span);
+
+        for &arg in self.ctx.types.list(args) {
+            self.ty(arg);
+        }
+    }
+
+    fn block(&mut self, block: Block) {
+        self.scopes.push(Vec::new());
+
+        for &expr in self.ctx.exprs.list(block.exprs) {
+            self.expr(expr);
+        }
+        if let Some(tail) = block.tail {
+            self.expr(tail);
+        }
+
+        self.scopes.pop();
+    }
+
+    fn expr(&mut self, expr: ExprEntity) {
+        match self.ctx.exprs[expr] {
+            Expr::Ident(name, span) => {
+                self.resolution.exprs[expr] = self.resolve_value(name, span);
+            }
+            Expr::Str(..)
+            | Expr::Int(..)
+            | Expr::Float(..)
+            | Expr::Char(..)
+            | Expr::Bool(..)
+            | Expr::Unit(..)
+            | Expr::Error(..) => {}
+            Expr::Path(path) => {
+                self.resolution.exprs[expr] = self.resolve_variant(path.ty, path.variant);
+            }
+            Expr::Block(block) => self.block(block),
+            Expr::Let(let_expr) => {
+                if let Some(ty) = let_expr.ty {
+                    self.ty(ty);
+                }
+                // The value is resolved before the binding exists, so that
+                // `let x = x + 1` refers to an outer `x`.
+                if let Some(value) = let_expr.value {
+                    self.expr(value);
+                }
+
+                let local = self.bind(let_expr.name, let_expr.name_span, LocalKind::Let(expr));
+                self.resolution.lets[expr] = Some(local);
+            }
+            Expr::Assign(assign) => {
+                let span = self.name_span(assign.span.start, assign.lhs);
+                let res = self.resolve_value(assign.lhs, span);
+
+                if let Some(res @ (Res::Func(_) | Res::Builtin(_))) = res {
+                    self.error(
+                        span,
+                        ErrorKind::Mismatched {
+                            expected: Namespace::Variable,
+                            found: res.description(),
+                            name: self.interner.get(assign.lhs).to_owned(),
+                        },
+                    );
+                }
+
+                self.resolution.exprs[expr] = res;
+                self.expr(assign.rhs);
+            }
+            Expr::Bin(bin_expr) => {
+                self.expr(bin_expr.lhs);
+                self.expr(bin_expr.rhs);
+            }
+            Expr::Unary(unary_expr) => self.expr(unary_expr.value),
+            Expr::Call(call) => {
+                self.expr(call.receiver);
+
+                if let Expr::Ident(name, span) = self.ctx.exprs[call.receiver]
+                    && let Some(Res::Local(local)) = self.resolution.exprs[call.receiver]
+                {
+                    let shadowed_function = self
+                        .resolution
+                        .functions
+                        .contains_key(&name)
+                        .then_some(self.resolution.locals[local].span);
+
+                    self.error(
+                        span,
+                        ErrorKind::CallToLocal {
+                            name: self.interner.get(name).to_owned(),
+                            shadowed_function,
+                        },
+                    );
+                }
+
+                self.types_list(call.ty_args);
+                self.exprs(call.args);
+            }
+            Expr::MethodCall(call) => {
+                self.expr(call.receiver);
+                self.types_list(call.ty_args);
+                self.exprs(call.args);
+            }
+            Expr::If(if_expr) => {
+                self.expr(if_expr.cond);
+                self.block(if_expr.then_branch);
+
+                match if_expr.else_branch.map(|else_expr| else_expr.kind) {
+                    Some(ElseExprKind::If(expr)) => self.expr(expr),
+                    Some(ElseExprKind::Else(block)) => self.block(block),
+                    None => {}
+                }
+            }
+            Expr::While(while_expr) => {
+                self.expr(while_expr.cond);
+                self.block(while_expr.body);
+            }
+            Expr::Match(match_expr) => {
+                self.expr(match_expr.scrutinee);
+                for &arm in self.ctx.arms.list(match_expr.arms) {
+                    self.arm(self.ctx.arms[arm]);
+                }
+            }
+            Expr::Array(array_expr) => match array_expr.kind {
+                ArrayExprKind::List { values } => self.exprs(values),
+                ArrayExprKind::Repeat { value, repeat } => {
+                    self.expr(value);
+                    self.expr(repeat);
+                }
+            },
+            Expr::Struct(struct_expr) => {
+                let span = self.name_span(struct_expr.span.start, struct_expr.name);
+                self.resolution.exprs[expr] = match struct_expr.variant {
+                    Some(variant) => {
+                        let name = PathSegment {
+                            name: struct_expr.name,
+                            span,
+                        };
+                        self.resolve_variant(name, variant)
+                    }
+                    None => self.resolve_struct(struct_expr.name, span),
+                };
+
+                for &field in self.ctx.field_exprs.list(struct_expr.fields) {
+                    self.expr(self.ctx.field_exprs[field].value);
+                }
+            }
+            Expr::Field(field_expr) => self.expr(field_expr.base),
+            Expr::Index(index_expr) => {
+                self.expr(index_expr.base);
+                self.expr(index_expr.index);
+            }
+            Expr::Return(return_expr) => {
+                if let Some(value) = return_expr.value {
+                    self.expr(value);
+                }
+            }
+        }
+    }
+
+    /// Resolves a match arm, whose bindings are in scope in its guard and
+    /// body only.
+    fn arm(&mut self, arm: MatchArm) {
+        self.scopes.push(Vec::new());
+
+        let mut names = HashMap::new();
+        self.pat(arm.pat, &mut names);
+        if let Some(guard) = arm.guard {
+            self.expr(guard);
+        }
+        self.expr(arm.body);
+
+        self.scopes.pop();
+    }
+
+    /// Resolves a pattern, binding its names. `names` holds the bindings of
+    /// the whole pattern so far, which must be distinct.
+    fn pat(&mut self, pat: PatEntity, names: &mut HashMap<StrId, Span>) {
+        match self.ctx.pats[pat] {
+            Pat::Wild(_) | Pat::Error(_) => {}
+            Pat::Binding(name, span) => {
+                if let Some(&previous) = names.get(&name) {
+                    self.duplicate("binding", name, span, previous);
+                } else {
+                    names.insert(name, span);
+                }
+
+                let local = self.bind(name, span, LocalKind::Pat(pat));
+                self.resolution.bindings[pat] = Some(local);
+            }
+            Pat::Lit(lit, _) => self.expr(lit),
+            Pat::Adt(adt) => {
+                self.resolution.pats[pat] = match adt.variant {
+                    Some(variant) => self.resolve_variant(adt.name, variant),
+                    None => self.resolve_struct(adt.name.name, adt.name.span),
+                };
+
+                match adt.fields {
+                    PatFields::Unit => {}
+                    PatFields::Tuple(pats) => {
+                        for &pat in self.ctx.pats.list(pats) {
+                            self.pat(pat, names);
+                        }
+                    }
+                    PatFields::Named(fields) => {
+                        for &field in self.ctx.field_pats.list(fields) {
+                            self.pat(self.ctx.field_pats[field].pat, names);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: EntityList<ExprEntity>) {
+        for &expr in self.ctx.exprs.list(exprs) {
+            self.expr(expr);
+        }
+    }
+
+    fn types_list(&mut self, types: EntityList<TypeEntity>) {
+        for &ty in self.ctx.types.list(types) {
+            self.ty(ty);
+        }
+    }
+
+    fn bind(&mut self, name: StrId, span: Span, kind: LocalKind) -> LocalId {
+        let local = self.resolution.locals.push(Local { name, span, kind });
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(local);
+        }
+
+        local
+    }
+
+    fn lookup_local(&self, name: StrId) -> Option<LocalId> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .copied()
+            .find(|&local| self.resolution.locals[local].name == name)
+    }
+
+    fn resolve_value(&mut self, name: StrId, span: Span) -> Option<Res> {
+        if let Some(local) = self.lookup_local(name) {
+            return Some(Res::Local(local));
+        }
+        if let Some(&func) = self.resolution.functions.get(&name) {
+            return Some(Res::Func(func));
+        }
+
+        let string = self.interner.get(name);
+        if let Some(builtin) = Builtin::from_name(string) {
+            return Some(Res::Builtin(builtin));
+        }
+
+        let kind = match self.resolution.type_items.get(&name) {
+            Some(&item) => ErrorKind::Mismatched {
+                expected: Namespace::Value,
+                found: self.type_item_res(item).description(),
+                name: string.to_owned(),
+            },
+            None => {
+                let interner = self.interner;
+                let locals = self.scopes.iter().flatten().map(|&local| {
+                    let name = self.resolution.locals[local].name;
+                    interner.get(name)
+                });
+                let functions = self.resolution.functions.keys().copied();
+                let functions = functions.map(|name| interner.get(name));
+                let builtins = Builtin::ALL.into_iter().map(|builtin| builtin.name());
+
+                ErrorKind::Undefined {
+                    namespace: Namespace::Value,
+                    name: string.to_owned(),
+                    suggestion: suggest(string, locals.chain(functions).chain(builtins)),
+                }
+            }
+        };
+
+        self.error(span, kind);
+        None
+    }
+
+    fn resolve_type(&mut self, name: StrId, span: Span) -> Option<Res> {
+        let generics = &self.ctx.generic_params;
+        if let Some(&param) = self
+            .generics
+            .iter()
+            .rev()
+            .find(|&&param| generics[param].name == name)
+        {
+            return Some(Res::TyParam(param));
+        }
+        if let Some(&item) = self.resolution.type_items.get(&name) {
+            return Some(self.type_item_res(item));
+        }
+
+        let string = self.interner.get(name);
+        if let Some(ty) = PrimTy::from_name(string) {
+            return Some(Res::PrimTy(ty));
+        }
+        if let Some(trait_) = BuiltinTrait::from_name(string) {
+            return Some(Res::BuiltinTrait(trait_));
+        }
+        if string == "Self"
+            && let Some(item) = self.self_item
+        {
+            return Some(Res::SelfTy(item));
+        }
+
+        let kind = if self.resolution.functions.contains_key(&name) {
+            ErrorKind::Mismatched {
+                expected: Namespace::Type,
+                found: "function",
+                name: string.to_owned(),
+            }
+        } else {
+            let interner = self.interner;
+            let items = self.resolution.type_items.keys();
+            let items = items.copied().map(|name| interner.get(name));
+            let prims = PrimTy::ALL.into_iter().map(|ty| ty.name());
+            let traits = BuiltinTrait::ALL.into_iter().map(|trait_| trait_.name());
+            let generics = self.generics.iter().map(|&param| {
+                let name = self.ctx.generic_params[param].name;
+                interner.get(name)
+            });
+
+            ErrorKind::Undefined {
+                namespace: Namespace::Type,
+                name: string.to_owned(),
+                suggestion: suggest(string, generics.chain(items).chain(prims).chain(traits)),
+            }
+        };
+
+        self.error(span, kind);
+        None
+    }
+
+    fn resolve_struct(&mut self, name: StrId, span: Span) -> Option<Res> {
+        let mut res = self.resolve_type(name, span)?;
+
+        // `Self { .. }` in an impl for a struct builds that struct.
+        if let Res::SelfTy(item) = res
+            && let Item::Impl(impl_item) = self.ctx.items[item]
+            && let Some(ty @ Res::Struct(_)) = self.resolution.types[impl_item.ty]
+        {
+            res = ty;
+        }
+
+        if let Res::Struct(_) = res {
+            return Some(res);
+        }
+
+        self.error(
+            span,
+            ErrorKind::Mismatched {
+                expected: Namespace::Struct,
+                found: res.description(),
+                name: self.interner.get(name).to_owned(),
+            },
+        );
+
+        None
+    }
+
+    /// Resolves `Enum::Variant`, which only names variants of enums.
+    fn resolve_variant(&mut self, ty: PathSegment, variant: PathSegment) -> Option<Res> {
+        let mut res = self.resolve_type(ty.name, ty.span)?;
+
+        // `Self::Variant` in an impl for an enum.
+        if let Res::SelfTy(item) = res
+            && let Item::Impl(impl_item) = self.ctx.items[item]
+            && let Some(ty @ Res::Enum(_)) = self.resolution.types[impl_item.ty]
+        {
+            res = ty;
+        }
+
+        let Res::Enum(item) = res else {
+            self.error(
+                ty.span,
+                ErrorKind::Mismatched {
+                    expected: Namespace::Enum,
+                    found: res.description(),
+                    name: self.interner.get(ty.name).to_owned(),
+                },
+            );
+            return None;
+        };
+
+        let Item::Enum(enum_item) = self.ctx.items[item] else {
+            unreachable!("enum resolution to a non-enum item");
+        };
+        let variants = self.ctx.variants.list(enum_item.variants);
+        if let Some(&found) = variants
+            .iter()
+            .find(|&&entity| self.ctx.variants[entity].name == variant.name)
+        {
+            return Some(Res::Variant(item, found));
+        }
+
+        let interner = self.interner;
+        let names = variants
+            .iter()
+            .map(|&entity| interner.get(self.ctx.variants[entity].name));
+        let name = interner.get(variant.name);
+        let kind = ErrorKind::UndefinedVariant {
+            enum_name: interner.get(ty.name).to_owned(),
+            name: name.to_owned(),
+            suggestion: suggest(name, names),
+        };
+
+        self.error(variant.span, kind);
+        None
+    }
+
+    fn type_item_res(&self, item: ItemEntity) -> Res {
+        match self.ctx.items[item] {
+            Item::Enum(_) => Res::Enum(item),
+            Item::Trait(_) => Res::Trait(item),
+            _ => Res::Struct(item),
+        }
+    }
+
+    fn duplicate(&mut self, what: &'static str, name: StrId, span: Span, previous: Span) {
+        let name = self.interner.get(name).to_owned();
+        self.error(
+            span,
+            ErrorKind::Duplicate {
+                what,
+                name,
+                previous,
+            },
+        );
+    }
+
+    fn error(&mut self, span: Span, kind: ErrorKind) {
+        self.resolution.errors.push(Error::new(span, kind));
+    }
+
+    fn name_span(&self, start: usize, name: StrId) -> Span {
+        Span::new(start, start + self.interner.get(name).len())
+    }
+}
+
+fn item_name_span(ctx: &AstCtx, item: ItemEntity) -> Span {
+    match ctx.items[item] {
+        Item::Func(func) => func.name_span,
+        Item::Struct(struct_item) => struct_item.name_span,
+        Item::Enum(enum_item) => enum_item.name_span,
+        Item::Trait(trait_item) => trait_item.name_span,
+        Item::Impl(impl_item) => ctx.types[impl_item.ty].span,
+    }
+}
+
+/// Picks the candidate closest to `name`, if it is close enough to be a
+/// plausible typo.
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    candidates
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate.to_owned())
+}
+
+/// The edit distance between `a` and `b` in characters, where swapping two
+/// adjacent characters counts as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // `distances[i][j]` is the distance between the first `i` characters of
+    // `a` and the first `j` characters of `b`.
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j - 1] + cost)
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_common::Source;
+    use unnamed_parser::ParsedModule;
+
+    use super::*;
+
+    fn resolve(content: &str) -> (ParsedModule, Resolution) {
+        let interner = Interner::new();
+        let parsed = unnamed_parser::parse(Source::new(content, "test_file"), &interner);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+
+        let resolution = super::resolve(&parsed.ctx, &parsed.module, &interner);
+        (parsed, resolution)
+    }
+
+    fn errors(content: &str) -> Vec<(String, Option<String>)> {
+        let (_, resolution) = resolve(content);
+
+        resolution
+            .errors
+            .iter()
+            .map(|error| {
+                let suggestion = match error.kind() {
+                    ErrorKind::Undefined { suggestion, .. }
+                    | ErrorKind::UndefinedVariant { suggestion, .. } => suggestion.clone(),
+                    _ => None,
+                };
+
+                (error.to_string(), suggestion)
+            })
+            .collect()
+    }
+
+    /// Returns the span of the binding each identifier expression refers to,
+    /// in source order.
+    fn bindings(content: &str) -> Vec<(Span, Span)> {
+        let (parsed, resolution) = resolve(content);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        let mut bindings: Vec<_> = parsed
+            .ctx
+            .exprs
+            .map
+            .iter()
+            .filter_map(|(expr, value)| match (value, resolution.exprs[expr]) {
+                (Expr::Ident(_, span), Some(Res::Local(local))) => {
+                    Some((*span, resolution.locals[local].span))
+                }
+                _ => None,
+            })
+            .collect();
+        bindings.sort_by_key(|(span, _)| span.start);
+
+        bindings
+    }
+
+    #[test]
+    fn test_scopes() {
+        let content = "func f(a: i64) { let b = a; { let a = b; a; } let b = b; a + b; }";
+        let span = |text: &str, nth: usize| {
+            let start = content.match_indices(text).nth(nth).unwrap().0;
+            Span::new(start, start + 1)
+        };
+
+        assert_eq!(
+            bindings(content),
+            [
+                (span("a;", 0), span("a:", 0)),
+                (span("b;", 0), span("b =", 0)),
+                (span("a;", 1), span("a =", 0)),
+                (span("b;", 1), span("b =", 0)),
+                (span("a +", 0), span("a:", 0)),
+                (span("b;", 2), span("b =", 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_items() {
+        let (parsed, resolution) = resolve(
+            "struct P { x: i64 } trait T { func t(self) -> bool; } \
+             impl T for P { func t(self) -> bool { true } } \
+             func main() { let p: P = P { x: 1 }; g(p); println(p) } func g(p: P) {}",
+        );
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        let resolved: Vec<_> = parsed
+            .ctx
+            .exprs
+            .map
+            .keys()
+            .filter_map(|expr| resolution.exprs[expr])
+            .map(Res::description)
+            .collect();
+        assert_eq!(
+            resolved,
+            [
+                "struct",
+                "function",
+                "local variable",
+                "builtin function",
+                "local variable"
+            ]
+        );
+
+        let types: Vec<_> = parsed
+            .ctx
+            .types
+            .map
+            .keys()
+            .filter_map(|ty| resolution.types[ty])
+            .map(Res::description)
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "builtin type",
+                "builtin type",
+                "trait",
+                "struct",
+                "builtin type",
+                "struct",
+                "struct"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_self_type() {
+        let (parsed, resolution) = resolve(
+            "struct P {} trait T { func t(self) -> Self; } \
+             impl P { func new() -> Self { Self {} } } func f() -> Self {}",
+        );
+
+        let kinds: Vec<_> = resolution.errors.iter().map(Error::to_string).collect();
+        assert_eq!(kinds, ["cannot find type `Self` in this scope"]);
+
+        let items = parsed.ctx.items.list(parsed.module.items);
+        let types: Vec<_> = parsed
+            .ctx
+            .types
+            .map
+            .keys()
+            .filter_map(|ty| resolution.types[ty])
+            .collect();
+        assert_eq!(
+            types,
+            [
+                Res::SelfTy(items[1]),
+                Res::Struct(items[0]),
+                Res::SelfTy(items[2])
+            ]
+        );
+
+        let literal = parsed
+            .ctx
+            .exprs
+            .map
+            .keys()
+            .find_map(|expr| resolution.exprs[expr]);
+        assert_eq!(literal, Some(Res::Struct(items[0])));
+    }
+
+    #[test]
+    fn test_generics() {
+        let content = "struct Pair<A, B> { a: A, b: B } \
+                       impl<T> Pair<T, T> { func map<U>(self, u: U) -> Pair<T, U> {} } \
+                       func f<T, T: Shw>(t: Tee) -> A {}";
+        let (parsed, resolution) = resolve(content);
+
+        let kinds: Vec<_> = resolution.errors.iter().map(Error::to_string).collect();
+        assert_eq!(
+            kinds,
+            [
+                "the type parameter `T` is defined multiple times",
+                "cannot find type `Shw` in this scope",
+                "cannot find type `Tee` in this scope",
+                "cannot find type `A` in this scope",
+            ]
+        );
+
+        let ctx = &parsed.ctx;
+        let names: Vec<_> = ctx
+            .types
+            .map
+            .keys()
+            .filter_map(|ty| match resolution.types[ty]? {
+                Res::TyParam(param) => {
+                    let span = ctx.generic_params[param].span;
+                    Some(&content[span.start..span.end])
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["A", "B", "T", "T", "U", "T", "U"]);
+
+        // The parameters of the impl and of the method are distinct entities.
+        let params: Vec<_> = ctx
+            .types
+            .map
+            .keys()
+            .filter_map(|ty| resolution.types[ty])
+            .collect();
+        assert_ne!(params[3], params[4]);
+    }
+
+    #[test]
+    fn test_undefined() {
+        assert_eq!(
+            errors("func main() { let count = 1; cout + conut + zzz; mian(); prinln(1) }"),
+            [
+                (
+                    "cannot find value `cout` in this scope".to_owned(),
+                    Some("count".to_owned())
+                ),
+                (
+                    "cannot find value `conut` in this scope".to_owned(),
+                    Some("count".to_owned())
+                ),
+                ("cannot find value `zzz` in this scope".to_owned(), None),
+                (
+                    "cannot find value `mian` in this scope".to_owned(),
+                    Some("main".to_owned())
+                ),
+                (
+                    "cannot find value `prinln` in this scope".to_owned(),
+                    Some("println".to_owned())
+                ),
+            ]
+        );
+
+        assert_eq!(
+            errors("struct Point {} func f(p: Pont, x: i46) { Piont {} }"),
+            [
+                (
+                    "cannot find type `Pont` in this scope".to_owned(),
+                    Some("Point".to_owned())
+                ),
+                (
+                    "cannot find type `i46` in this scope".to_owned(),
+                    Some("i16".to_owned())
+                ),
+                (
+                    "cannot find type `Piont` in this scope".to_owned(),
+                    Some("Point".to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mismatched_and_duplicates() {
+        assert_eq!(
+            errors(
+                "struct S { a: i64, a: bool } struct S {} func f(x: i64, x: i64) {} \
+                 func f() { S; f = 1; let y: f = 0; }"
+            ),
+            [
+                ("the field `a` is defined multiple times".to_owned(), None),
+                ("the type `S` is defined multiple times".to_owned(), None),
+                (
+                    "the parameter `x` is defined multiple times".to_owned(),
+                    None
+                ),
+                (
+                    "the function `f` is defined multiple times".to_owned(),
+                    None
+                ),
+                ("expected value, found struct `S`".to_owned(), None),
+                ("expected variable, found function `f`".to_owned(), None),
+                ("expected type, found function `f`".to_owned(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enums() {
+        let (parsed, resolution) = resolve(
+            "enum Shape<T> { Empty, Circle(T), Rect { w: T, h: T } } \
+             impl Shape<i64> { func empty() -> Self { Self::Empty } } \
+             func f() { Shape::Circle(1); Shape::Rect { w: 1, h: 2 }; }",
+        );
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        let items = parsed.ctx.items.list(parsed.module.items);
+        let variants: Vec<_> = parsed
+            .ctx
+            .exprs
+            .map
+            .keys()
+            .filter_map(|expr| match resolution.exprs[expr]? {
+                Res::Variant(item, variant) => {
+                    assert_eq!(item, items[0]);
+                    Some(parsed.ctx.variants[variant].name)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(variants.len(), 3);
+        assert_eq!(
+            resolution.types.values().flatten().next(),
+            Some(&Res::TyParam(
+                parsed
+                    .ctx
+                    .generic_params
+                    .list(match parsed.ctx.items[items[0]] {
+                        Item::Enum(enum_item) => enum_item.generics,
+                        _ => unreachable!(),
+                    })[0]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_enum_errors() {
+        assert_eq!(
+            errors(
+                "enum E { A, B { x: i64, x: i64 }, A } struct S {} \
+                 func f() { E::C; E::Bb {}; S::A; Q::A; }"
+            ),
+            [
+                ("the field `x` is defined multiple times".to_owned(), None),
+                ("the variant `A` is defined multiple times".to_owned(), None),
+                (
+                    "no variant named `C` in enum `E`".to_owned(),
+                    Some("A".to_owned())
+                ),
+                (
+                    "no variant named `Bb` in enum `E`".to_owned(),
+                    Some("B".to_owned())
+                ),
+                ("expected enum, found struct `S`".to_owned(), None),
+                (
+                    "cannot find type `Q` in this scope".to_owned(),
+                    Some("E".to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_bindings() {
+        let content = "enum E { A(i64), B { x: i64 } } \
+                       func f(e: E, x: i64) { match e { E::A(a) if a > x => a, E::B { x } => x, y => y }; x; }";
+        let span = |text: &str, nth: usize| {
+            let start = content.match_indices(text).nth(nth).unwrap().0;
+            Span::new(start, start + 1)
+        };
+
+        assert_eq!(
+            bindings(content),
+            [
+                (span("e {", 0), span("e:", 0)),
+                (span("a >", 0), span("a)", 0)),
+                (span("x =>", 0), span("x:", 1)),
+                (span("a, E", 0), span("a)", 0)),
+                (span("x, y", 0), span("x }", 0)),
+                (span("y }", 0), span("y =>", 0)),
+                (span("x;", 0), span("x:", 1)),
+            ]
+        );
+
+        assert_eq!(
+            errors(
+                "enum E { A(i64, i64) } func f(e: E) { match e { E::A(a, a) => a, E::Bogus => 0 } }"
+            ),
+            [
+                ("the binding `a` is defined multiple times".to_owned(), None),
+                ("no variant named `Bogus` in enum `E`".to_owned(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_to_local() {
+        let (parsed, resolution) =
+            resolve("func f() {} func main() { let f = 1; f(); let g = 2; g() }");
+
+        let kinds: Vec<_> = resolution.errors.iter().map(Error::kind).collect();
+        let binding = parsed.ctx.exprs.map.values().find_map(|expr| match expr {
+            Expr::Let(let_expr) => Some(let_expr.name_span),
+            _ => None,
+        });
+
+        assert_eq!(
+            kinds,
+            [
+                &ErrorKind::CallToLocal {
+                    name: "f".to_owned(),
+                    shadowed_function: binding,
+                },
+                &ErrorKind::CallToLocal {
+                    name: "g".to_owned(),
+                    shadowed_function: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("é", "e"), 1);
+        assert_eq!(edit_distance("mian", "main"), 1);
+    }
+}
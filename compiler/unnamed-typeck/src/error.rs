@@ -0,0 +1,367 @@
+use std::fmt::{self, Display};
+
+use ariadne::{Label, ReportKind};
+use unnamed_common::{IntoReport, Report, Source, Span, Spanned};
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    span: Span,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new(span: Span, kind: ErrorKind) -> Self {
+        Self { span, kind }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Spanned for Error {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// Types are stored as they are displayed, since errors outlive the type
+/// context they were found with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Mismatched {
+        expected: String,
+        found: String,
+    },
+    InvalidOperands {
+        op: &'static str,
+        lhs: String,
+        rhs: String,
+    },
+    InvalidUnaryOperand {
+        op: &'static str,
+        ty: String,
+    },
+    ArgumentCount {
+        expected: usize,
+        found: usize,
+    },
+    NotCallable(String),
+    /// A function used as a value instead of being called.
+    FunctionAsValue(String),
+    UnknownMethod {
+        ty: String,
+        method: String,
+    },
+    UnknownField {
+        ty: String,
+        field: String,
+    },
+    DuplicateField(String),
+    MissingFields {
+        ty: String,
+        fields: Vec<String>,
+    },
+    /// A variant used with other fields than it was declared with, like a
+    /// tuple variant without arguments.
+    MismatchedVariant {
+        expected: &'static str,
+        found: &'static str,
+        variant: String,
+    },
+    PatternFieldCount {
+        variant: String,
+        expected: usize,
+        found: usize,
+    },
+    /// Values of the matched type that no arm matches, as patterns.
+    NonExhaustive(Vec<String>),
+    /// A match arm that can never be reached, since the arms before it
+    /// already match every value it matches.
+    UnreachablePattern,
+    NotIndexable(String),
+    /// A type that is still unknown once the whole function was checked.
+    CannotInfer(String),
+    LiteralOutOfRange(String),
+    /// A local declared without a value that is not assigned on every path
+    /// to a use.
+    UsedBeforeAssigned(String),
+    UnexpectedTypeArgs,
+    TypeArgCount {
+        expected: usize,
+        found: usize,
+    },
+    /// Type arguments of a call that are still unknown once the whole
+    /// function was checked.
+    CannotInferTypeArgs(String),
+    SelfOutsideImpl,
+    TraitAsType(String),
+    /// A bound or the trait of an impl naming something else than a trait.
+    NotATrait {
+        what: &'static str,
+        name: String,
+    },
+    /// A method defined twice for the same type or trait.
+    DuplicateMethod(String),
+    /// A type parameter of an impl that does not appear in its type.
+    UnconstrainedParam(String),
+    NotTraitMember {
+        method: String,
+        trait_name: String,
+    },
+    MissingTraitMethods(Vec<String>),
+    IncompatibleMethod {
+        method: String,
+        expected: String,
+        found: String,
+    },
+    ConflictingImpls {
+        trait_name: String,
+        ty: String,
+    },
+    AmbiguousMethod {
+        method: String,
+        traits: Vec<String>,
+    },
+    Unimplemented {
+        trait_name: String,
+        ty: String,
+    },
+    /// A call creating the `limit`th nested instance of the same function,
+    /// usually one of a generic function calling itself with ever larger
+    /// types.
+    InstantiationLimit {
+        name: String,
+        limit: usize,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Mismatched { expected, found } => {
+                write!(
+                    f,
+                    "mismatched types: expected `{expected}`, found `{found}`"
+                )
+            }
+            ErrorKind::InvalidOperands { op, lhs, rhs } => {
+                write!(f, "cannot apply `{op}` to `{lhs}` and `{rhs}`")
+            }
+            ErrorKind::InvalidUnaryOperand { op, ty } => {
+                write!(f, "cannot apply unary `{op}` to `{ty}`")
+            }
+            ErrorKind::ArgumentCount { expected, found } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                write!(f, "expected {expected} argument{plural}, found {found}")
+            }
+            ErrorKind::NotCallable(ty) => write!(f, "expected function, found `{ty}`"),
+            ErrorKind::FunctionAsValue(name) => {
+                write!(f, "the function `{name}` can only be called")
+            }
+            ErrorKind::UnknownMethod { ty, method } => {
+                write!(f, "no method named `{method}` found for `{ty}`")
+            }
+            ErrorKind::UnknownField { ty, field } => {
+                write!(f, "no field `{field}` on type `{ty}`")
+            }
+            ErrorKind::DuplicateField(field) => {
+                write!(f, "field `{field}` specified more than once")
+            }
+            ErrorKind::MissingFields { ty, fields } => {
+                let fields: Vec<_> = fields.iter().map(|field| format!("`{field}`")).collect();
+                let plural = if fields.len() == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "missing field{plural} {} in initializer of `{ty}`",
+                    fields.join(", ")
+                )
+            }
+            ErrorKind::MismatchedVariant {
+                expected,
+                found,
+                variant,
+            } => write!(
+                f,
+                "expected {expected} variant, found {found} variant `{variant}`"
+            ),
+            ErrorKind::PatternFieldCount {
+                variant,
+                expected,
+                found,
+            } => {
+                let plural = |count: usize| if count == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "this pattern has {found} field{}, but the variant `{variant}` has {expected} field{}",
+                    plural(*found),
+                    plural(*expected)
+                )
+            }
+            ErrorKind::NonExhaustive(patterns) => {
+                let mut shown: Vec<_> = patterns
+                    .iter()
+                    .take(3)
+                    .map(|pattern| format!("`{pattern}`"))
+                    .collect();
+                let last = match patterns.len() {
+                    0..=1 => None,
+                    2..=3 => shown.pop(),
+                    more => Some(format!("{} more", more - 3)),
+                };
+
+                f.write_str("non-exhaustive patterns: ")?;
+                f.write_str(&shown.join(", "))?;
+                if let Some(last) = last {
+                    write!(f, " and {last}")?;
+                }
+                f.write_str(" not covered")
+            }
+            ErrorKind::UnreachablePattern => f.write_str("unreachable pattern"),
+            ErrorKind::NotIndexable(ty) => write!(f, "cannot index into a value of type `{ty}`"),
+            ErrorKind::CannotInfer(name) => write!(f, "type annotations needed for `{name}`"),
+            ErrorKind::LiteralOutOfRange(ty) => write!(f, "literal out of range for `{ty}`"),
+            ErrorKind::UsedBeforeAssigned(name) => {
+                write!(f, "`{name}` is used before being assigned")
+            }
+            ErrorKind::UnexpectedTypeArgs => f.write_str("unexpected type arguments"),
+            ErrorKind::TypeArgCount { expected, found } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                write!(
+                    f,
+                    "expected {expected} type argument{plural}, found {found}"
+                )
+            }
+            ErrorKind::CannotInferTypeArgs(name) => {
+                write!(f, "cannot infer the type arguments of `{name}`")
+            }
+            ErrorKind::SelfOutsideImpl => {
+                f.write_str("`self` parameter is only allowed in methods")
+            }
+            ErrorKind::TraitAsType(name) => write!(f, "expected type, found trait `{name}`"),
+            ErrorKind::NotATrait { what, name } => {
+                write!(f, "expected trait, found {what} `{name}`")
+            }
+            ErrorKind::DuplicateMethod(name) => {
+                write!(f, "duplicate definitions with name `{name}`")
+            }
+            ErrorKind::UnconstrainedParam(name) => {
+                write!(
+                    f,
+                    "the type parameter `{name}` is not constrained by the impl type"
+                )
+            }
+            ErrorKind::NotTraitMember { method, trait_name } => {
+                write!(
+                    f,
+                    "method `{method}` is not a member of trait `{trait_name}`"
+                )
+            }
+            ErrorKind::MissingTraitMethods(methods) => {
+                let methods: Vec<_> = methods.iter().map(|method| format!("`{method}`")).collect();
+                write!(
+                    f,
+                    "not all trait methods implemented, missing: {}",
+                    methods.join(", ")
+                )
+            }
+            ErrorKind::IncompatibleMethod {
+                method,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "method `{method}` has an incompatible signature for the trait: \
+                     expected `{expected}`, found `{found}`"
+                )
+            }
+            ErrorKind::ConflictingImpls { trait_name, ty } => {
+                write!(
+                    f,
+                    "conflicting implementations of trait `{trait_name}` for type `{ty}`"
+                )
+            }
+            ErrorKind::AmbiguousMethod { method, traits } => {
+                let traits: Vec<_> = traits.iter().map(|name| format!("`{name}`")).collect();
+                write!(
+                    f,
+                    "multiple applicable methods named `{method}` in traits {}",
+                    traits.join(", ")
+                )
+            }
+            ErrorKind::Unimplemented { trait_name, ty } => {
+                write!(f, "the trait `{trait_name}` is not implemented for `{ty}`")
+            }
+            ErrorKind::InstantiationLimit { name, limit } => {
+                write!(
+                    f,
+                    "reached the instantiation limit of {limit} while instantiating `{name}`"
+                )
+            }
+        }
+    }
+}
+
+impl IntoReport for Error {
+    fn into_report<'s>(self, source: Source<'s>) -> Report<'s> {
+        let message = self.to_string();
+
+        let label = match self.kind {
+            ErrorKind::Mismatched { expected, .. } => format!("expected `{expected}`"),
+            ErrorKind::InvalidOperands { .. } | ErrorKind::InvalidUnaryOperand { .. } => {
+                "no implementation for these types".to_owned()
+            }
+            ErrorKind::ArgumentCount { expected, .. } => {
+                let plural = if expected == 1 { "" } else { "s" };
+                format!("expected {expected} argument{plural}")
+            }
+            ErrorKind::NotCallable(_) => "not a function".to_owned(),
+            ErrorKind::FunctionAsValue(_) => "help: add arguments to call it".to_owned(),
+            ErrorKind::UnknownMethod { .. } => "method not found".to_owned(),
+            ErrorKind::UnknownField { .. } => "unknown field".to_owned(),
+            ErrorKind::DuplicateField(_) => "used more than once".to_owned(),
+            ErrorKind::MissingFields { .. } => "missing fields".to_owned(),
+            ErrorKind::MismatchedVariant { expected, .. } => format!("not a {expected} variant"),
+            ErrorKind::PatternFieldCount { expected, .. } => {
+                let plural = if expected == 1 { "" } else { "s" };
+                format!("expected {expected} field{plural}")
+            }
+            ErrorKind::NonExhaustive(_) => "patterns not covered".to_owned(),
+            ErrorKind::UnreachablePattern => {
+                "the arms before already match these values".to_owned()
+            }
+            ErrorKind::NotIndexable(_) => "only arrays can be indexed".to_owned(),
+            ErrorKind::CannotInfer(_) => "consider giving this a type".to_owned(),
+            ErrorKind::LiteralOutOfRange(ty) => format!("does not fit into `{ty}`"),
+            ErrorKind::UsedBeforeAssigned(_) => "not assigned on every path to here".to_owned(),
+            ErrorKind::UnexpectedTypeArgs => "type arguments are not allowed here".to_owned(),
+            ErrorKind::TypeArgCount { expected, .. } => {
+                let plural = if expected == 1 { "" } else { "s" };
+                format!("expected {expected} type argument{plural}")
+            }
+            ErrorKind::CannotInferTypeArgs(_) => "help: specify them with `::<...>`".to_owned(),
+            ErrorKind::SelfOutsideImpl => "not inside an `impl` or trait".to_owned(),
+            ErrorKind::TraitAsType(_) => "not a type".to_owned(),
+            ErrorKind::NotATrait { .. } => "not a trait".to_owned(),
+            ErrorKind::DuplicateMethod(_) => "defined more than once".to_owned(),
+            ErrorKind::UnconstrainedParam(_) => "unconstrained type parameter".to_owned(),
+            ErrorKind::NotTraitMember { trait_name, .. } => {
+                format!("not a member of `{trait_name}`")
+            }
+            ErrorKind::MissingTraitMethods(_) => "missing in this implementation".to_owned(),
+            ErrorKind::IncompatibleMethod { expected, .. } => format!("expected `{expected}`"),
+            ErrorKind::ConflictingImpls { .. } => "conflicting implementation".to_owned(),
+            ErrorKind::AmbiguousMethod { .. } => "multiple methods found".to_owned(),
+            ErrorKind::Unimplemented { trait_name, .. } => {
+                format!("the trait `{trait_name}` is not implemented")
+            }
+            ErrorKind::InstantiationLimit { .. } => "instantiated here".to_owned(),
+        };
+
+        Report::build(ReportKind::Error, source.span(self.span))
+            .with_message(message)
+            .with_label(Label::new(source.span(self.span)).with_message(label))
+            .finish()
+    }
+}
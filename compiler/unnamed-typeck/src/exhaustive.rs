@@ -0,0 +1,389 @@
+//! Exhaustiveness and reachability of match arms, following Maranget's
+//! "Warnings for pattern matching": an arm is unreachable when its pattern
+//! is not useful after the arms before it, and a match is exhaustive when a
+//! wildcard after all arms is not useful. Integers, floats, chars and
+//! strings have too many values to list, so only wildcards cover them.
+
+use std::slice;
+
+use unnamed_ast::{
+    ExprEntity, Item, PatEntity, VariantEntity,
+    expr::{Expr, MatchExpr, UnaryOp},
+    item::VariantKind,
+    pat::{Pat, PatFields},
+};
+use unnamed_common::{Spanned, StrId};
+use unnamed_resolve::Res;
+
+use crate::{
+    Checker,
+    error::ErrorKind,
+    ty::{TyCtx, TyId, TyKind},
+};
+
+/// What a pattern matches at the top, without its fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ctor {
+    /// The only constructor of a struct.
+    Struct,
+    Variant(VariantEntity),
+    Bool(bool),
+    Int(i128),
+    /// Floats compare by their bits, which makes each literal distinct.
+    Float(u64),
+    Char(char),
+    Str(StrId),
+    /// Anything, as matched by `_` and bindings.
+    Wild,
+}
+
+/// A pattern reduced to its constructor and the patterns of the
+/// constructor's fields, in definition order. Also used for the values that
+/// no arm matches.
+#[derive(Debug, Clone)]
+struct DeconPat {
+    ctor: Ctor,
+    fields: Vec<DeconPat>,
+}
+
+impl DeconPat {
+    fn wild() -> Self {
+        Self {
+            ctor: Ctor::Wild,
+            fields: Vec::new(),
+        }
+    }
+}
+
+impl Checker<'_> {
+    /// Reports arms that can never match and values that no arm matches.
+    /// Arms with a guard may not match, so they do not cover anything.
+    pub(crate) fn check_exhaustive(&mut self, match_expr: MatchExpr) {
+        let Some(ty) = self.results.exprs[match_expr.scrutinee] else {
+            return;
+        };
+
+        // Patterns that failed to check would only lead to more errors.
+        let span = match_expr.span;
+        if has_error(&self.results.tcx, ty)
+            || self
+                .results
+                .errors
+                .iter()
+                .any(|error| span.start <= error.span().start && error.span().end <= span.end)
+        {
+            return;
+        }
+
+        let mut rows = Vec::new();
+        for &arm in self.ctx.arms.list(match_expr.arms) {
+            let arm = self.ctx.arms[arm];
+            let pat = self.decon_pat(arm.pat, ty);
+
+            if self.useful(&rows, slice::from_ref(&pat), &[ty]).is_empty() {
+                let span = self.ctx.pats[arm.pat].span();
+                self.error(span, ErrorKind::UnreachablePattern);
+            }
+            if arm.guard.is_none() {
+                rows.push(vec![pat]);
+            }
+        }
+
+        let witnesses = self.useful(&rows, &[DeconPat::wild()], &[ty]);
+        if !witnesses.is_empty() {
+            let patterns = witnesses
+                .iter()
+                .map(|witness| self.fmt_pat(&witness[0], ty))
+                .collect();
+
+            let span = self.ctx.exprs[match_expr.scrutinee].span();
+            self.error(span, ErrorKind::NonExhaustive(patterns));
+        }
+    }
+
+    /// The values matched by `v` that no row of `rows` matches, as patterns.
+    /// `v` is useful after `rows` if there are any. `tys` are the types of
+    /// the columns.
+    fn useful(
+        &mut self,
+        rows: &[Vec<DeconPat>],
+        v: &[DeconPat],
+        tys: &[TyId],
+    ) -> Vec<Vec<DeconPat>> {
+        let Some((head, rest)) = v.split_first() else {
+            return if rows.is_empty() {
+                vec![Vec::new()]
+            } else {
+                Vec::new()
+            };
+        };
+
+        if head.ctor != Ctor::Wild {
+            return self.useful_ctor(rows, head.ctor, &head.fields, rest, tys);
+        }
+
+        // A wildcard is useful for a type with few constructors if it is for
+        // one of them, which also lists which values of each are missing.
+        let all = self.all_ctors(tys[0]);
+        if let Some(all) = all {
+            let mut witnesses = Vec::new();
+            for ctor in all {
+                let fields = vec![DeconPat::wild(); self.ctor_fields(tys[0], ctor).len()];
+                witnesses.extend(self.useful_ctor(rows, ctor, &fields, rest, tys));
+            }
+
+            return witnesses;
+        }
+
+        // Otherwise only the rows starting with a wildcard match the values
+        // of the constructors that are not listed.
+        let default: Vec<_> = rows
+            .iter()
+            .filter(|row| row[0].ctor == Ctor::Wild)
+            .map(|row| row[1..].to_vec())
+            .collect();
+
+        self.useful(&default, rest, &tys[1..])
+            .into_iter()
+            .map(|witness| {
+                let mut values = vec![DeconPat::wild()];
+                values.extend(witness);
+                values
+            })
+            .collect()
+    }
+
+    /// `useful` for a `v` starting with `ctor`, which only the rows starting
+    /// with `ctor` or a wildcard can match.
+    fn useful_ctor(
+        &mut self,
+        rows: &[Vec<DeconPat>],
+        ctor: Ctor,
+        fields: &[DeconPat],
+        rest: &[DeconPat],
+        tys: &[TyId],
+    ) -> Vec<Vec<DeconPat>> {
+        let mut field_tys = self.ctor_fields(tys[0], ctor);
+        let arity = field_tys.len();
+
+        let specialized: Vec<_> = rows
+            .iter()
+            .filter_map(|row| {
+                let mut specialized = match row[0].ctor {
+                    Ctor::Wild => vec![DeconPat::wild(); arity],
+                    head if head == ctor => row[0].fields.clone(),
+                    _ => return None,
+                };
+                specialized.extend(row[1..].iter().cloned());
+
+                Some(specialized)
+            })
+            .collect();
+
+        let mut v = fields.to_vec();
+        v.extend(rest.iter().cloned());
+        field_tys.extend(tys[1..].iter().copied());
+
+        self.useful(&specialized, &v, &field_tys)
+            .into_iter()
+            .map(|mut witness| {
+                let rest = witness.split_off(arity);
+                let mut values = vec![DeconPat {
+                    ctor,
+                    fields: witness,
+                }];
+                values.extend(rest);
+                values
+            })
+            .collect()
+    }
+
+    /// Every constructor of `ty`, or `None` when there are too many to list.
+    fn all_ctors(&self, ty: TyId) -> Option<Vec<Ctor>> {
+        match self.results.tcx[ty] {
+            TyKind::Bool => Some(vec![Ctor::Bool(true), Ctor::Bool(false)]),
+            TyKind::Adt(item, _) => match self.ctx.items[item] {
+                Item::Enum(enum_item) => {
+                    let variants = self.ctx.variants.list(enum_item.variants);
+                    Some(variants.iter().copied().map(Ctor::Variant).collect())
+                }
+                _ => Some(vec![Ctor::Struct]),
+            },
+            TyKind::Never => Some(Vec::new()),
+            _ => None,
+        }
+    }
+
+    /// The types of the fields of `ctor` in a value of type `ty`.
+    fn ctor_fields(&mut self, ty: TyId, ctor: Ctor) -> Vec<TyId> {
+        let TyKind::Adt(item, args) = self.results.tcx[ty] else {
+            return Vec::new();
+        };
+
+        let fields = match ctor {
+            Ctor::Struct => {
+                let fields = self.results.fields[&item].iter();
+                fields.map(|&(_, ty)| ty).collect()
+            }
+            Ctor::Variant(variant) => self.results.variants[&variant].clone(),
+            _ => return Vec::new(),
+        };
+
+        let subst = self.struct_subst(item, args);
+        fields
+            .into_iter()
+            .map(|field| self.results.tcx.subst(field, &subst))
+            .collect()
+    }
+
+    fn decon_pat(&mut self, pat: PatEntity, ty: TyId) -> DeconPat {
+        let adt = match self.ctx.pats[pat] {
+            Pat::Wild(_) | Pat::Binding(..) | Pat::Error(_) => return DeconPat::wild(),
+            Pat::Lit(lit, _) => {
+                return DeconPat {
+                    ctor: self.lit_ctor(lit).unwrap_or(Ctor::Wild),
+                    fields: Vec::new(),
+                };
+            }
+            Pat::Adt(adt) => adt,
+        };
+
+        let (ctor, names): (_, Vec<StrId>) = match self.resolution.pats[pat] {
+            Some(Res::Struct(_)) => {
+                let TyKind::Adt(item, _) = self.results.tcx[ty] else {
+                    return DeconPat::wild();
+                };
+                let names = self.results.fields[&item].iter();
+                (Ctor::Struct, names.map(|&(name, _)| name).collect())
+            }
+            Some(Res::Variant(_, variant)) => {
+                let names = self.variant_fields(variant).into_iter();
+                (
+                    Ctor::Variant(variant),
+                    names.map(|(name, _)| name).collect(),
+                )
+            }
+            _ => return DeconPat::wild(),
+        };
+
+        let tys = self.ctor_fields(ty, ctor);
+        let fields = match adt.fields {
+            PatFields::Unit => Vec::new(),
+            PatFields::Tuple(pats) => {
+                let pats = self.ctx.pats.list(pats).to_vec();
+                pats.into_iter()
+                    .zip(tys)
+                    .map(|(pat, ty)| self.decon_pat(pat, ty))
+                    .collect()
+            }
+            PatFields::Named(field_pats) => {
+                let field_pats = self.ctx.field_pats.list(field_pats).to_vec();
+                names
+                    .iter()
+                    .zip(tys)
+                    .map(|(&name, ty)| {
+                        let field = field_pats
+                            .iter()
+                            .map(|&field| self.ctx.field_pats[field])
+                            .find(|field| field.name == name);
+                        match field {
+                            Some(field) => self.decon_pat(field.pat, ty),
+                            None => DeconPat::wild(),
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        DeconPat { ctor, fields }
+    }
+
+    fn lit_ctor(&self, lit: ExprEntity) -> Option<Ctor> {
+        let ctor = match self.ctx.exprs[lit] {
+            Expr::Bool(value, _) => Ctor::Bool(value),
+            Expr::Int(value, ..) => Ctor::Int(i128::from(value)),
+            Expr::Float(value, ..) => Ctor::Float(value.to_bits()),
+            Expr::Char(value, _) => Ctor::Char(value),
+            Expr::Str(value, _) => Ctor::Str(value),
+            Expr::Unary(unary) if unary.op == UnaryOp::Sub => match self.lit_ctor(unary.value)? {
+                Ctor::Int(value) => Ctor::Int(-value),
+                Ctor::Float(bits) => Ctor::Float((-f64::from_bits(bits)).to_bits()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(ctor)
+    }
+
+    /// Formats a value that no arm matches the way it would be written as a
+    /// pattern.
+    fn fmt_pat(&mut self, pat: &DeconPat, ty: TyId) -> String {
+        let tys = self.ctor_fields(ty, pat.ctor);
+        let mut fields: Vec<_> = pat
+            .fields
+            .iter()
+            .zip(tys)
+            .map(|(field, ty)| self.fmt_pat(field, ty))
+            .collect();
+
+        match pat.ctor {
+            Ctor::Wild => "_".to_owned(),
+            Ctor::Bool(value) => value.to_string(),
+            Ctor::Int(value) => value.to_string(),
+            Ctor::Float(bits) => format!("{:?}", f64::from_bits(bits)),
+            Ctor::Char(value) => format!("{value:?}"),
+            Ctor::Str(value) => format!("{:?}", self.interner.get(value)),
+            Ctor::Struct => {
+                let TyKind::Adt(item, _) = self.results.tcx[ty] else {
+                    return "_".to_owned();
+                };
+                let Item::Struct(struct_item) = self.ctx.items[item] else {
+                    return "_".to_owned();
+                };
+
+                let names = self.results.fields[&item].iter();
+                fields = names
+                    .zip(fields)
+                    .map(|(&(name, _), field)| format!("{}: {field}", self.interner.get(name)))
+                    .collect();
+                fmt_fields(self.interner.get(struct_item.name), &fields)
+            }
+            Ctor::Variant(variant) => {
+                let TyKind::Adt(item, _) = self.results.tcx[ty] else {
+                    return "_".to_owned();
+                };
+
+                let name = self.variant_name(item, variant);
+                match self.ctx.variants[variant].kind {
+                    VariantKind::Unit => name,
+                    VariantKind::Tuple(_) => format!("{name}({})", fields.join(", ")),
+                    VariantKind::Struct(_) => {
+                        fields = self
+                            .variant_fields(variant)
+                            .into_iter()
+                            .zip(fields)
+                            .map(|((name, _), field)| {
+                                format!("{}: {field}", self.interner.get(name))
+                            })
+                            .collect();
+                        fmt_fields(&name, &fields)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `Name { a: _, b: _ }`, or `Name {}` without fields.
+fn fmt_fields(name: &str, fields: &[String]) -> String {
+    if fields.is_empty() {
+        format!("{name} {{}}")
+    } else {
+        format!("{name} {{ {} }}", fields.join(", "))
+    }
+}
+
+fn has_error(tcx: &TyCtx, ty: TyId) -> bool {
+    tcx[ty] == TyKind::Error || tcx.inner(ty).into_iter().any(|ty| has_error(tcx, ty))
+}
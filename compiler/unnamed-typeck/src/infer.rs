@@ -0,0 +1,174 @@
+use cranelift_entity::PrimaryMap;
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+
+use crate::ty::{InferTy, InferVar, TyCtx, TyId, TyKind};
+
+/// Two types that cannot be made equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct UnifyError;
+
+#[derive(Debug, Clone, Copy)]
+struct VarData {
+    /// The type of the variable itself.
+    ty: TyId,
+    /// The type the variable was unified with.
+    value: Option<TyId>,
+}
+
+/// Inference variables of the function being checked.
+#[derive(Debug, Default)]
+pub(crate) struct InferCtx {
+    vars: PrimaryMap<InferVar, VarData>,
+}
+
+impl InferCtx {
+    pub fn new_var(&mut self, tcx: &mut TyCtx) -> TyId {
+        self.push(tcx, InferTy::Var)
+    }
+
+    pub fn new_int_var(&mut self, tcx: &mut TyCtx) -> TyId {
+        self.push(tcx, InferTy::Int)
+    }
+
+    pub fn new_float_var(&mut self, tcx: &mut TyCtx) -> TyId {
+        self.push(tcx, InferTy::Float)
+    }
+
+    fn push(&mut self, tcx: &mut TyCtx, infer: fn(InferVar) -> InferTy) -> TyId {
+        let ty = tcx.intern(TyKind::Infer(infer(self.vars.next_key())));
+        self.vars.push(VarData { ty, value: None });
+
+        ty
+    }
+
+    /// Follows bound variables until reaching a type that is either not a
+    /// variable or an unbound one.
+    pub fn shallow_resolve(&self, tcx: &TyCtx, mut ty: TyId) -> TyId {
+        while let TyKind::Infer(infer) = tcx[ty]
+            && let Some(bound) = self.vars[infer.var()].value
+        {
+            ty = bound;
+        }
+
+        ty
+    }
+
+    /// Replaces every bound variable inside `ty`, leaving unbound ones.
+    pub fn resolve(&self, tcx: &mut TyCtx, ty: TyId) -> TyId {
+        let ty = self.shallow_resolve(tcx, ty);
+        tcx.map_inner(ty, &mut |tcx, inner| self.resolve(tcx, inner))
+    }
+
+    /// Binds unbound integer and float variables to `i64` and `f64`, the
+    /// types of unsuffixed literals that nothing else constrains.
+    pub fn apply_defaults(&mut self, tcx: &mut TyCtx) {
+        let i64 = tcx.intern(TyKind::Int(IntSuffix::I64));
+        let f64 = tcx.intern(TyKind::Float(FloatSuffix::F64));
+
+        for data in self.vars.values_mut() {
+            match (tcx[data.ty], data.value) {
+                (TyKind::Infer(InferTy::Int(_)), None) => data.value = Some(i64),
+                (TyKind::Infer(InferTy::Float(_)), None) => data.value = Some(f64),
+                _ => {}
+            }
+        }
+    }
+
+    /// Makes `a` and `b` the same type by binding variables in them. The
+    /// error and never types unify with everything.
+    pub fn unify(&mut self, tcx: &mut TyCtx, a: TyId, b: TyId) -> Result<(), UnifyError> {
+        let a = self.shallow_resolve(tcx, a);
+        let b = self.shallow_resolve(tcx, b);
+        if a == b {
+            return Ok(());
+        }
+
+        match (tcx[a], tcx[b]) {
+            (TyKind::Error | TyKind::Never, _) | (_, TyKind::Error | TyKind::Never) => Ok(()),
+            (TyKind::Infer(InferTy::Var(var)), _) => self.bind(tcx, var, b),
+            (_, TyKind::Infer(InferTy::Var(var))) => self.bind(tcx, var, a),
+            (TyKind::Infer(InferTy::Int(var)), TyKind::Infer(InferTy::Int(_)) | TyKind::Int(_))
+            | (
+                TyKind::Infer(InferTy::Float(var)),
+                TyKind::Infer(InferTy::Float(_)) | TyKind::Float(_),
+            ) => self.bind(tcx, var, b),
+            (TyKind::Int(_), TyKind::Infer(InferTy::Int(var)))
+            | (TyKind::Float(_), TyKind::Infer(InferTy::Float(var))) => self.bind(tcx, var, a),
+            (TyKind::Array(a), TyKind::Array(b)) => self.unify(tcx, a, b),
+            (TyKind::Adt(a_item, a_args), TyKind::Adt(b_item, b_args)) if a_item == b_item => {
+                let args: Vec<_> = tcx
+                    .list(a_args)
+                    .iter()
+                    .copied()
+                    .zip(tcx.list(b_args).iter().copied())
+                    .collect();
+                for (a, b) in args {
+                    self.unify(tcx, a, b)?;
+                }
+                Ok(())
+            }
+            _ => Err(UnifyError),
+        }
+    }
+
+    fn bind(&mut self, tcx: &mut TyCtx, var: InferVar, ty: TyId) -> Result<(), UnifyError> {
+        // A variable cannot be bound to a type containing itself, as in
+        // `[_] = _`, since that type would be infinitely large.
+        if self.occurs(tcx, var, ty) {
+            return Err(UnifyError);
+        }
+
+        self.vars[var].value = Some(ty);
+        Ok(())
+    }
+
+    fn occurs(&self, tcx: &TyCtx, var: InferVar, ty: TyId) -> bool {
+        let ty = self.shallow_resolve(tcx, ty);
+
+        match tcx[ty] {
+            TyKind::Infer(infer) => infer.var() == var,
+            _ => tcx
+                .inner(ty)
+                .into_iter()
+                .any(|inner| self.occurs(tcx, var, inner)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unify() {
+        let mut tcx = TyCtx::new();
+        let mut infcx = InferCtx::default();
+
+        let i8 = tcx.intern(TyKind::Int(IntSuffix::I8));
+        let bool = tcx.bool();
+        let var = infcx.new_var(&mut tcx);
+        let int = infcx.new_int_var(&mut tcx);
+        let float = infcx.new_float_var(&mut tcx);
+
+        assert_eq!(infcx.unify(&mut tcx, int, bool), Err(UnifyError));
+        assert_eq!(infcx.unify(&mut tcx, int, float), Err(UnifyError));
+
+        // `[_]` and `[{integer}]`, then `{integer}` and `i8`.
+        let array = tcx.intern(TyKind::Array(var));
+        let int_array = tcx.intern(TyKind::Array(int));
+        assert_eq!(infcx.unify(&mut tcx, array, int_array), Ok(()));
+        assert_eq!(infcx.unify(&mut tcx, i8, var), Ok(()));
+
+        assert_eq!(infcx.shallow_resolve(&tcx, int), i8);
+        let i8_array = tcx.intern(TyKind::Array(i8));
+        assert_eq!(infcx.resolve(&mut tcx, array), i8_array);
+
+        let other = infcx.new_var(&mut tcx);
+        let nested = tcx.intern(TyKind::Array(other));
+        assert_eq!(infcx.unify(&mut tcx, other, nested), Err(UnifyError));
+
+        infcx.apply_defaults(&mut tcx);
+        let f64 = tcx.intern(TyKind::Float(FloatSuffix::F64));
+        assert_eq!(infcx.shallow_resolve(&tcx, float), f64);
+    }
+}
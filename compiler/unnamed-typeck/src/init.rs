@@ -0,0 +1,186 @@
+//! Definite initialization: a local declared by a `let` without a value must
+//! be assigned on every path to each of its uses. The body is walked in
+//! evaluation order, tracking the locals that may not be assigned yet, and
+//! the paths of branches are joined after them. Loops may run zero times,
+//! so what their bodies assign does not count after them.
+
+use std::collections::HashSet;
+
+use unnamed_ast::{
+    Block, ExprEntity,
+    expr::{ArrayExprKind, BinOp, ElseExprKind, Expr},
+};
+use unnamed_resolve::{LocalId, Res};
+
+use crate::{Checker, error::ErrorKind, ty::TyKind};
+
+/// The locals that may not be assigned yet, or `None` where the code cannot
+/// be reached.
+type State = Option<HashSet<LocalId>>;
+
+/// The state after two paths meet: a local may be unassigned if it may be on
+/// either path.
+fn join(a: State, b: State) -> State {
+    match (a, b) {
+        (Some(mut a), Some(b)) => {
+            a.extend(b);
+            Some(a)
+        }
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+impl Checker<'_> {
+    /// Reports the uses of locals that are not assigned on every path to
+    /// them, once per local.
+    pub(crate) fn check_init(&mut self, body: Block) {
+        let mut state = Some(HashSet::new());
+        self.init_block(body, &mut state);
+    }
+
+    fn init_block(&mut self, block: Block, state: &mut State) {
+        for &expr in self.ctx.exprs.list(block.exprs) {
+            self.init_expr(expr, state);
+        }
+        if let Some(tail) = block.tail {
+            self.init_expr(tail, state);
+        }
+    }
+
+    fn init_exprs(&mut self, exprs: &[ExprEntity], state: &mut State) {
+        for &expr in exprs {
+            self.init_expr(expr, state);
+        }
+    }
+
+    fn init_expr(&mut self, expr: ExprEntity, state: &mut State) {
+        match self.ctx.exprs[expr] {
+            Expr::Ident(name, span) => {
+                if let Some(Res::Local(local)) = self.resolution.exprs[expr]
+                    && let Some(unassigned) = state
+                    && unassigned.remove(&local)
+                {
+                    let name = self.interner.get(name).to_owned();
+                    self.error(span, ErrorKind::UsedBeforeAssigned(name));
+                }
+            }
+            Expr::Str(..)
+            | Expr::Int(..)
+            | Expr::Float(..)
+            | Expr::Char(..)
+            | Expr::Bool(..)
+            | Expr::Unit(_)
+            | Expr::Path(_)
+            | Expr::Error(_) => {}
+            Expr::Block(block) => self.init_block(block, state),
+            Expr::Let(let_expr) => match let_expr.value {
+                Some(value) => self.init_expr(value, state),
+                None => {
+                    if let Some(local) = self.resolution.lets[expr]
+                        && let Some(unassigned) = state
+                    {
+                        unassigned.insert(local);
+                    }
+                }
+            },
+            Expr::Assign(assign) => {
+                self.init_expr(assign.rhs, state);
+                if let Some(Res::Local(local)) = self.resolution.exprs[expr]
+                    && let Some(unassigned) = state
+                {
+                    unassigned.remove(&local);
+                }
+            }
+            Expr::Bin(bin_expr) if matches!(bin_expr.op, BinOp::And | BinOp::Or) => {
+                self.init_expr(bin_expr.lhs, state);
+
+                // The right operand is not evaluated when the left one
+                // decides the result.
+                let mut rhs = state.clone();
+                self.init_expr(bin_expr.rhs, &mut rhs);
+                *state = join(state.take(), rhs);
+            }
+            Expr::Bin(bin_expr) => {
+                self.init_expr(bin_expr.lhs, state);
+                self.init_expr(bin_expr.rhs, state);
+            }
+            Expr::Unary(unary_expr) => self.init_expr(unary_expr.value, state),
+            Expr::Call(call) => {
+                self.init_expr(call.receiver, state);
+                self.init_exprs(self.ctx.exprs.list(call.args), state);
+            }
+            Expr::MethodCall(method_call) => {
+                self.init_expr(method_call.receiver, state);
+                self.init_exprs(self.ctx.exprs.list(method_call.args), state);
+            }
+            Expr::If(if_expr) => {
+                self.init_expr(if_expr.cond, state);
+
+                let mut else_state = state.clone();
+                self.init_block(if_expr.then_branch, state);
+                match if_expr.else_branch.map(|else_branch| else_branch.kind) {
+                    Some(ElseExprKind::If(expr)) => self.init_expr(expr, &mut else_state),
+                    Some(ElseExprKind::Else(block)) => self.init_block(block, &mut else_state),
+                    None => {}
+                }
+                *state = join(state.take(), else_state);
+            }
+            Expr::While(while_expr) => {
+                self.init_expr(while_expr.cond, state);
+
+                let mut body = state.clone();
+                self.init_block(while_expr.body, &mut body);
+            }
+            Expr::Match(match_expr) => {
+                self.init_expr(match_expr.scrutinee, state);
+
+                // A match without arms never produces a value.
+                let mut after = None;
+                for &arm in self.ctx.arms.list(match_expr.arms) {
+                    let arm = self.ctx.arms[arm];
+                    let mut arm_state = state.clone();
+                    if let Some(guard) = arm.guard {
+                        self.init_expr(guard, &mut arm_state);
+                    }
+                    self.init_expr(arm.body, &mut arm_state);
+                    after = join(after, arm_state);
+                }
+                *state = after;
+            }
+            Expr::Array(array_expr) => match array_expr.kind {
+                ArrayExprKind::List { values } => {
+                    self.init_exprs(self.ctx.exprs.list(values), state);
+                }
+                ArrayExprKind::Repeat { value, repeat } => {
+                    self.init_expr(value, state);
+                    self.init_expr(repeat, state);
+                }
+            },
+            Expr::Struct(struct_expr) => {
+                for &field in self.ctx.field_exprs.list(struct_expr.fields) {
+                    self.init_expr(self.ctx.field_exprs[field].value, state);
+                }
+            }
+            Expr::Field(field_expr) => self.init_expr(field_expr.base, state),
+            Expr::Index(index_expr) => {
+                self.init_expr(index_expr.base, state);
+                self.init_expr(index_expr.index, state);
+            }
+            Expr::Return(return_expr) => {
+                if let Some(value) = return_expr.value {
+                    self.init_expr(value, state);
+                }
+                *state = None;
+            }
+        }
+
+        // Whatever follows an expression that never produces a value, like a
+        // call of a function returning `Never`, cannot be reached.
+        if let Some(ty) = self.results.exprs[expr]
+            && self.results.tcx[ty] == TyKind::Never
+        {
+            *state = None;
+        }
+    }
+}
@@ -0,0 +1,1969 @@
+pub mod error;
+mod exhaustive;
+mod infer;
+mod init;
+pub mod mono;
+mod traits;
+pub mod ty;
+
+use std::{collections::HashMap, mem};
+
+use cranelift_entity::{EntityList, SecondaryMap};
+use unnamed_ast::{
+    AstCtx, BinExpr, Block, ExprEntity, GenericParamEntity, Item, ItemEntity, Module, PatEntity,
+    TypeEntity, VariantEntity,
+    expr::{ArrayExprKind, BinOp, ElseExprKind, Expr, MatchExpr, StructExpr, UnaryOp},
+    item::{FuncItem, VariantKind},
+    pat::{Pat, PatFields},
+    ty::TypeKind,
+};
+use unnamed_common::{Interner, Span, Spanned, StrId};
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+use unnamed_resolve::{BuiltinTrait, LocalId, PrimTy, Res, Resolution};
+
+pub use crate::{
+    error::{Error, ErrorKind},
+    mono::{Instance, InstanceId, Instances, monomorphize},
+    traits::TraitId,
+    ty::{InferTy, Subst, TyCtx, TyId, TyKind, TyList},
+};
+use crate::{infer::InferCtx, traits::Obligation};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnSig {
+    /// Parameter types, starting with the receiver for methods.
+    pub params: Vec<TyId>,
+    pub ret: TyId,
+}
+
+/// The types of a module, as side tables over the AST. Once checking is
+/// done no type contains inference variables; whatever could not be
+/// inferred is the error type.
+#[derive(Debug, Default)]
+pub struct TypeckResults {
+    pub tcx: TyCtx,
+    /// Signatures of all functions, methods included. They refer to the type
+    /// parameters of the function, and for methods to those of the impl.
+    pub signatures: HashMap<ItemEntity, FnSig>,
+    /// Field names and types of every struct, in definition order.
+    pub fields: HashMap<ItemEntity, Vec<(StrId, TyId)>>,
+    /// Field types of every enum variant, in definition order. They refer to
+    /// the type parameters of the enum.
+    pub variants: HashMap<VariantEntity, Vec<TyId>>,
+    /// Type parameters in scope of each function, struct, enum and impl. For
+    /// methods, those of the impl come before the method's own.
+    pub generics: HashMap<ItemEntity, Vec<GenericParamEntity>>,
+    /// The traits each type parameter is bounded by.
+    pub bounds: HashMap<GenericParamEntity, Vec<TraitId>>,
+    /// The trait or impl each method is declared in.
+    pub parents: HashMap<ItemEntity, ItemEntity>,
+    /// What `Self` is inside each trait and impl.
+    pub self_tys: HashMap<ItemEntity, TyId>,
+    /// The trait implemented by each trait impl.
+    pub impl_traits: HashMap<ItemEntity, TraitId>,
+    pub exprs: SecondaryMap<ExprEntity, Option<TyId>>,
+    /// The type of the values each pattern matches.
+    pub pats: SecondaryMap<PatEntity, Option<TyId>>,
+    pub locals: SecondaryMap<LocalId, Option<TyId>>,
+    /// The method called by each `Expr::MethodCall`, `None` for the built-in
+    /// `len`. Calls of trait methods refer to the method declared in the
+    /// trait.
+    pub method_calls: SecondaryMap<ExprEntity, Option<ItemEntity>>,
+    /// The built-in trait whose method each `Expr::Bin` and `Expr::Unary`
+    /// calls, for operands that are not of a primitive type. Instances of
+    /// generic functions whose operands turn out to be primitive use the
+    /// operator of the primitive type instead.
+    pub operators: SecondaryMap<ExprEntity, Option<BuiltinTrait>>,
+    /// The type arguments of the function called by each `Expr::Call` and
+    /// `Expr::MethodCall`, one for each of its `generics`.
+    pub type_args: SecondaryMap<ExprEntity, Option<TyList>>,
+    /// The calls in the body of each function that call a declared function
+    /// or method, overloaded operators included, in the order they were
+    /// checked.
+    pub calls: HashMap<ItemEntity, Vec<ExprEntity>>,
+    /// Methods of each trait and impl by name.
+    methods: HashMap<(ItemEntity, StrId), ItemEntity>,
+    /// Methods of inherent impls by the implementing type, without its type
+    /// arguments, and method name.
+    inherent_methods: HashMap<(TyKind, StrId), ItemEntity>,
+    /// Methods with a `self` parameter of every trait by name.
+    trait_methods: HashMap<StrId, Vec<ItemEntity>>,
+    /// The impls of each trait in source order.
+    trait_impls: HashMap<TraitId, Vec<ItemEntity>>,
+    /// The name of the method of each built-in trait.
+    builtin_methods: HashMap<BuiltinTrait, StrId>,
+    /// Errors ordered by their position in the source.
+    pub errors: Vec<Error>,
+}
+
+/// Checks the types of every function body in `module`, inferring the types
+/// of `let`s without annotation and of unsuffixed literals.
+pub fn check(
+    ctx: &AstCtx,
+    module: &Module,
+    interner: &Interner,
+    resolution: &Resolution,
+) -> TypeckResults {
+    let mut checker = Checker {
+        ctx,
+        interner,
+        resolution,
+        self_name: interner.intern("self"),
+        results: TypeckResults::default(),
+        infcx: InferCtx::default(),
+        ret: None,
+        body_exprs: Vec::new(),
+        body_pats: Vec::new(),
+        body_locals: Vec::new(),
+        body_matches: Vec::new(),
+        obligations: Vec::new(),
+    };
+    for trait_ in BuiltinTrait::ALL {
+        let method = interner.intern(trait_.method());
+        checker.results.builtin_methods.insert(trait_, method);
+    }
+
+    let items = ctx.items.list(module.items);
+    for &item in items {
+        checker.collect(item);
+    }
+    for &item in items {
+        checker.check_item(item);
+    }
+
+    let mut results = checker.results;
+    results.errors.sort_by_key(|error| error.span().start);
+
+    results
+}
+
+struct Checker<'a> {
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    resolution: &'a Resolution,
+    self_name: StrId,
+    results: TypeckResults,
+    infcx: InferCtx,
+    /// Return type of the function being checked.
+    ret: Option<TyId>,
+    /// Expressions, patterns and locals of the function being checked, whose
+    /// types are only final once the whole body was checked.
+    body_exprs: Vec<ExprEntity>,
+    body_pats: Vec<PatEntity>,
+    body_locals: Vec<LocalId>,
+    /// Match expressions of the function being checked, whose patterns are
+    /// checked for exhaustiveness once the types are final.
+    body_matches: Vec<ExprEntity>,
+    /// Traits the types of the function being checked must implement.
+    obligations: Vec<Obligation>,
+}
+
+impl Checker<'_> {
+    fn collect(&mut self, item: ItemEntity) {
+        match self.ctx.items[item] {
+            Item::Func(func) => {
+                let generics = self.ctx.generic_params.list(func.generics).to_vec();
+                self.results.generics.insert(item, generics);
+                self.bounds(func.generics);
+                self.signature(item, func, None);
+            }
+            Item::Struct(struct_item) => {
+                let generics = self.ctx.generic_params.list(struct_item.generics).to_vec();
+                self.results.generics.insert(item, generics);
+                self.bounds(struct_item.generics);
+
+                let fields = self
+                    .ctx
+                    .field_defs
+                    .list(struct_item.fields)
+                    .iter()
+                    .map(|&field| {
+                        let field = self.ctx.field_defs[field];
+                        (field.name, self.lower(field.ty))
+                    })
+                    .collect();
+
+                self.results.fields.insert(item, fields);
+            }
+            Item::Enum(enum_item) => {
+                let generics = self.ctx.generic_params.list(enum_item.generics).to_vec();
+                self.results.generics.insert(item, generics);
+                self.bounds(enum_item.generics);
+
+                for &variant in self.ctx.variants.list(enum_item.variants) {
+                    let fields = match self.ctx.variants[variant].kind {
+                        VariantKind::Unit => Vec::new(),
+                        VariantKind::Tuple(types) => {
+                            let types = self.ctx.types.list(types);
+                            types.iter().map(|&ty| self.lower(ty)).collect()
+                        }
+                        VariantKind::Struct(fields) => {
+                            let fields = self.ctx.field_defs.list(fields);
+                            fields
+                                .iter()
+                                .map(|&field| self.lower(self.ctx.field_defs[field].ty))
+                                .collect()
+                        }
+                    };
+
+                    self.results.variants.insert(variant, fields);
+                }
+            }
+            Item::Trait(trait_item) => {
+                let self_ty = self.results.tcx.intern(TyKind::SelfTy(item));
+                self.results.self_tys.insert(item, self_ty);
+
+                for (method, func) in self.funcs(trait_item.items) {
+                    self.method_signature(item, &[], method, func, self_ty);
+                    if self.define_method(item, method) && self.is_method(func) {
+                        let methods = self.results.trait_methods.entry(func.name);
+                        methods.or_default().push(method);
+                    }
+                }
+            }
+            Item::Impl(impl_item) => {
+                let generics = self.ctx.generic_params.list(impl_item.generics).to_vec();
+                self.results.generics.insert(item, generics.clone());
+                self.bounds(impl_item.generics);
+
+                let self_ty = self.lower(impl_item.ty);
+                self.results.self_tys.insert(item, self_ty);
+
+                let trait_id = impl_item
+                    .trait_ref
+                    .and_then(|trait_ref| self.trait_ref(trait_ref));
+                if let Some(trait_id) = trait_id {
+                    self.results.impl_traits.insert(item, trait_id);
+                    let impls = self.results.trait_impls.entry(trait_id);
+                    impls.or_default().push(item);
+                }
+
+                for (method, func) in self.funcs(impl_item.items) {
+                    self.method_signature(item, &generics, method, func, self_ty);
+                    if self.define_method(item, method)
+                        && impl_item.trait_ref.is_none()
+                        && self.is_method(func)
+                    {
+                        self.define_inherent_method(self_ty, method);
+                    }
+                }
+            }
+        }
+    }
+
+    fn funcs(&self, items: EntityList<ItemEntity>) -> Vec<(ItemEntity, FuncItem)> {
+        self.ctx
+            .items
+            .list(items)
+            .iter()
+            .filter_map(|&item| match self.ctx.items[item] {
+                Item::Func(func) => Some((item, func)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn is_method(&self, func: FuncItem) -> bool {
+        let params = self.ctx.params.list(func.params);
+        params
+            .first()
+            .is_some_and(|&param| self.ctx.params[param].name == self.self_name)
+    }
+
+    fn method_signature(
+        &mut self,
+        parent: ItemEntity,
+        parent_generics: &[GenericParamEntity],
+        method: ItemEntity,
+        func: FuncItem,
+        self_ty: TyId,
+    ) {
+        let own = self.ctx.generic_params.list(func.generics);
+        let generics = [parent_generics, own].concat();
+
+        self.results.generics.insert(method, generics);
+        self.results.parents.insert(method, parent);
+        self.bounds(func.generics);
+        self.signature(method, func, Some(self_ty));
+    }
+
+    fn signature(&mut self, item: ItemEntity, func: FuncItem, self_ty: Option<TyId>) {
+        let mut params = Vec::new();
+        for &param in self.ctx.params.list(func.params) {
+            let param = self.ctx.params[param];
+            let ty = match (param.ty, self_ty) {
+                (Some(ty), _) => self.lower(ty),
+                (None, Some(self_ty)) => self_ty,
+                (None, None) => {
+                    self.error(param.span, ErrorKind::SelfOutsideImpl);
+                    self.results.tcx.error()
+                }
+            };
+
+            params.push(ty);
+        }
+
+        let ret = match func.ret {
+            Some(ret) => self.lower(ret),
+            None => self.results.tcx.unit(),
+        };
+
+        self.results.signatures.insert(item, FnSig { params, ret });
+    }
+
+    /// Converts a type written in the source.
+    fn lower(&mut self, ty: TypeEntity) -> TyId {
+        let span = self.ctx.types[ty].span;
+
+        let kind = match self.ctx.types[ty].kind {
+            TypeKind::Unit => TyKind::Unit,
+            TypeKind::Error => TyKind::Error,
+            TypeKind::Named { name, args } => {
+                let args = self.ctx.types.list(args);
+                let res = self.resolution.types[ty];
+
+                if let Some(Res::Struct(item) | Res::Enum(item)) = res {
+                    return self.lower_adt(item, args, span);
+                }
+                if !args.is_empty() {
+                    self.error(span, ErrorKind::UnexpectedTypeArgs);
+                }
+
+                match res {
+                    Some(Res::PrimTy(prim)) => prim_kind(prim),
+                    Some(Res::TyParam(param)) => TyKind::Param(param),
+                    Some(Res::SelfTy(item)) => match self.results.self_tys.get(&item) {
+                        Some(&self_ty) => return self_ty,
+                        None => TyKind::Error,
+                    },
+                    Some(Res::Trait(_)) => {
+                        let name = self.interner.get(name).to_owned();
+                        self.error(span, ErrorKind::TraitAsType(name));
+                        TyKind::Error
+                    }
+                    _ => TyKind::Error,
+                }
+            }
+        };
+
+        self.results.tcx.intern(kind)
+    }
+
+    /// Converts a struct or enum type with the type arguments written after
+    /// it.
+    fn lower_adt(&mut self, item: ItemEntity, args: &[TypeEntity], span: Span) -> TyId {
+        let expected = match self.ctx.items[item] {
+            Item::Struct(struct_item) => struct_item.generics.len(&self.ctx.generic_params.pool),
+            Item::Enum(enum_item) => enum_item.generics.len(&self.ctx.generic_params.pool),
+            _ => 0,
+        };
+        let mut args: Vec<_> = args.iter().map(|&arg| self.lower(arg)).collect();
+
+        if args.len() != expected {
+            let kind = ErrorKind::TypeArgCount {
+                expected,
+                found: args.len(),
+            };
+            self.error(span, kind);
+            args = vec![self.results.tcx.error(); expected];
+        }
+
+        let args = self.results.tcx.intern_list(&args);
+        self.results.tcx.intern(TyKind::Adt(item, args))
+    }
+
+    fn check_item(&mut self, item: ItemEntity) {
+        match self.ctx.items[item] {
+            Item::Func(func) => self.check_func(item, func),
+            Item::Struct(_) | Item::Enum(_) => {}
+            Item::Trait(trait_item) => {
+                for (method, func) in self.funcs(trait_item.items) {
+                    self.check_func(method, func);
+                }
+            }
+            Item::Impl(impl_item) => {
+                self.check_impl(item, impl_item);
+                for (method, func) in self.funcs(impl_item.items) {
+                    self.check_func(method, func);
+                }
+            }
+        }
+    }
+
+    fn check_func(&mut self, item: ItemEntity, func: FuncItem) {
+        let Some(body) = func.body else {
+            return;
+        };
+
+        let sig = self.results.signatures[&item].clone();
+        for (&param, &ty) in self.ctx.params.list(func.params).iter().zip(&sig.params) {
+            if let Some(local) = self.resolution.params[param] {
+                self.results.locals[local] = Some(ty);
+            }
+        }
+
+        self.ret = Some(sig.ret);
+        let ty = self.block(body);
+        self.coerce(ty, sig.ret, tail_span(self.ctx, body));
+        self.ret = None;
+
+        self.finish_body(item);
+        self.check_init(body);
+    }
+
+    /// Replaces the inferred types of the body of `item` with their final
+    /// types.
+    fn finish_body(&mut self, item: ItemEntity) {
+        self.infcx.apply_defaults(&mut self.results.tcx);
+
+        for local in mem::take(&mut self.body_locals) {
+            let Some(ty) = self.results.locals[local] else {
+                continue;
+            };
+
+            let ty = self.infcx.resolve(&mut self.results.tcx, ty);
+            let (ty, is_complete) = complete(&mut self.results.tcx, ty);
+            if !is_complete {
+                let local = self.resolution.locals[local];
+                let name = self.interner.get(local.name).to_owned();
+                self.error(local.span, ErrorKind::CannotInfer(name));
+            }
+
+            self.results.locals[local] = Some(ty);
+        }
+
+        let mut calls = Vec::new();
+        for expr in mem::take(&mut self.body_exprs) {
+            if let Some(args) = self.results.type_args[expr] {
+                self.finish_type_args(expr, args);
+                calls.push(expr);
+            }
+            if self.results.operators[expr].is_some() {
+                calls.push(expr);
+            }
+
+            let Some(ty) = self.results.exprs[expr] else {
+                continue;
+            };
+
+            let ty = self.infcx.resolve(&mut self.results.tcx, ty);
+            let (ty, _) = complete(&mut self.results.tcx, ty);
+            self.results.exprs[expr] = Some(ty);
+
+            if let Expr::Int(value, _, span) = self.ctx.exprs[expr]
+                && let TyKind::Int(int) = self.results.tcx[ty]
+                && i128::from(value) > int.max()
+            {
+                self.error(span, ErrorKind::LiteralOutOfRange(int.as_str().to_owned()));
+            }
+
+            // The lexer checks suffixed literals, and unsuffixed ones only
+            // against `f64`.
+            if let Expr::Float(value, None, span) = self.ctx.exprs[expr]
+                && let TyKind::Float(FloatSuffix::F32) = self.results.tcx[ty]
+                && (value as f32).is_infinite()
+            {
+                self.error(span, ErrorKind::LiteralOutOfRange("f32".to_owned()));
+            }
+        }
+
+        for pat in mem::take(&mut self.body_pats) {
+            if let Some(ty) = self.results.pats[pat] {
+                let ty = self.infcx.resolve(&mut self.results.tcx, ty);
+                let (ty, _) = complete(&mut self.results.tcx, ty);
+                self.results.pats[pat] = Some(ty);
+            }
+        }
+
+        for expr in mem::take(&mut self.body_matches) {
+            if let Expr::Match(match_expr) = self.ctx.exprs[expr] {
+                self.check_exhaustive(match_expr);
+            }
+        }
+
+        self.check_obligations();
+        self.results.calls.insert(item, calls);
+    }
+
+    /// Resolves the type arguments of a call, which must all be known.
+    fn finish_type_args(&mut self, expr: ExprEntity, args: TyList) {
+        let mut is_complete = true;
+        let args: Vec<_> = self.results.tcx.list(args).to_vec();
+        let args: Vec<_> = args
+            .into_iter()
+            .map(|arg| {
+                let arg = self.infcx.resolve(&mut self.results.tcx, arg);
+                let (arg, complete) = complete(&mut self.results.tcx, arg);
+                is_complete &= complete;
+                arg
+            })
+            .collect();
+        self.results.type_args[expr] = Some(self.results.tcx.intern_list(&args));
+
+        if !is_complete {
+            let (name, span) = match self.ctx.exprs[expr] {
+                Expr::Call(call) => {
+                    let Expr::Ident(name, span) = self.ctx.exprs[call.receiver] else {
+                        return;
+                    };
+                    (name, span)
+                }
+                Expr::MethodCall(call) => (call.method, call.span),
+                _ => return,
+            };
+
+            let name = self.interner.get(name).to_owned();
+            self.error(span, ErrorKind::CannotInferTypeArgs(name));
+        }
+    }
+
+    fn block(&mut self, block: Block) -> TyId {
+        let mut diverges = false;
+        for &expr in self.ctx.exprs.list(block.exprs) {
+            let ty = self.expr(expr);
+            diverges |= self.kind(ty) == TyKind::Never;
+        }
+
+        match block.tail {
+            Some(tail) => self.expr(tail),
+            None if diverges => self.results.tcx.never(),
+            None => self.results.tcx.unit(),
+        }
+    }
+
+    fn expr(&mut self, expr: ExprEntity) -> TyId {
+        let ty = self.expr_kind(expr);
+        self.results.exprs[expr] = Some(ty);
+        self.body_exprs.push(expr);
+
+        ty
+    }
+
+    fn expr_kind(&mut self, expr: ExprEntity) -> TyId {
+        let kind = match self.ctx.exprs[expr] {
+            Expr::Ident(name, span) => match self.resolution.exprs[expr] {
+                Some(Res::Local(local)) => return self.local_ty(local),
+                Some(Res::Func(_) | Res::Builtin(_)) => {
+                    let name = self.interner.get(name).to_owned();
+                    self.error(span, ErrorKind::FunctionAsValue(name));
+                    TyKind::Error
+                }
+                _ => TyKind::Error,
+            },
+            Expr::Str(..) => TyKind::Str,
+            Expr::Int(_, Some(suffix), _) => TyKind::Int(suffix),
+            Expr::Int(_, None, _) => return self.infcx.new_int_var(&mut self.results.tcx),
+            Expr::Float(_, Some(suffix), _) => TyKind::Float(suffix),
+            Expr::Float(_, None, _) => return self.infcx.new_float_var(&mut self.results.tcx),
+            Expr::Char(..) => TyKind::Char,
+            Expr::Bool(..) => TyKind::Bool,
+            Expr::Unit(..) => TyKind::Unit,
+            Expr::Error(..) => TyKind::Error,
+            Expr::Path(path) => match self.resolution.exprs[expr] {
+                Some(Res::Variant(item, variant)) => {
+                    let (ty, _) = self.fresh_adt(item, path.span);
+                    self.expect_variant(item, variant, "unit", path.span);
+                    return ty;
+                }
+                _ => TyKind::Error,
+            },
+            Expr::Block(block) => return self.block(block),
+            Expr::Let(let_expr) => {
+                let declared = let_expr.ty.map(|ty| self.lower(ty));
+                let value = let_expr
+                    .value
+                    .map(|value| (self.expr(value), self.ctx.exprs[value].span()));
+
+                let ty = match (declared, value) {
+                    (Some(declared), Some((value, span))) => {
+                        self.coerce(value, declared, span);
+                        declared
+                    }
+                    (Some(declared), None) => declared,
+                    (None, Some((value, _))) => value,
+                    (None, None) => self.infcx.new_var(&mut self.results.tcx),
+                };
+
+                if let Some(local) = self.resolution.lets[expr] {
+                    self.results.locals[local] = Some(ty);
+                    self.body_locals.push(local);
+                }
+
+                TyKind::Unit
+            }
+            Expr::Assign(assign) => {
+                let value = self.expr(assign.rhs);
+                if let Some(Res::Local(local)) = self.resolution.exprs[expr] {
+                    let ty = self.local_ty(local);
+                    self.coerce(value, ty, self.ctx.exprs[assign.rhs].span());
+                }
+
+                TyKind::Unit
+            }
+            Expr::Bin(bin_expr) => return self.bin(expr, bin_expr),
+            Expr::Unary(unary_expr) => {
+                let ty = self.expr(unary_expr.value);
+                if unary_expr.op == UnaryOp::Sub && self.is_overloadable(ty) {
+                    let trait_ = BuiltinTrait::Neg;
+                    return self.overloaded(expr, trait_, ty, None, unary_expr.span);
+                }
+
+                if !self.is_numeric(ty) {
+                    let ty = self.display(ty);
+                    let op = unary_expr.op.as_str();
+                    self.error(unary_expr.span, ErrorKind::InvalidUnaryOperand { op, ty });
+                    TyKind::Error
+                } else {
+                    return ty;
+                }
+            }
+            Expr::Call(call) => {
+                let args = self.ctx.exprs.list(call.args);
+
+                match (
+                    self.ctx.exprs[call.receiver],
+                    self.resolution.exprs[call.receiver],
+                ) {
+                    (Expr::Ident(..), Some(Res::Func(item))) => {
+                        let subst = self.instantiate(expr, item, call.ty_args, None);
+                        let sig = self.results.signatures[&item].clone();
+                        let params: Vec<_> = sig
+                            .params
+                            .iter()
+                            .map(|&param| self.results.tcx.subst(param, &subst))
+                            .collect();
+
+                        self.args(&params, args, call.span);
+                        return self.results.tcx.subst(sig.ret, &subst);
+                    }
+                    (Expr::Path(path), Some(Res::Variant(item, variant))) => {
+                        self.no_type_args(call.ty_args);
+                        let (ty, subst) = self.fresh_adt(item, call.span);
+
+                        if self.expect_variant(item, variant, "tuple", path.span) {
+                            let params: Vec<_> = self.results.variants[&variant]
+                                .clone()
+                                .into_iter()
+                                .map(|param| self.results.tcx.subst(param, &subst))
+                                .collect();
+                            self.args(&params, args, call.span);
+                        } else {
+                            for &arg in args {
+                                self.expr(arg);
+                            }
+                        }
+
+                        return ty;
+                    }
+                    (Expr::Ident(..), Some(Res::Builtin(_))) => {
+                        self.no_type_args(call.ty_args);
+                        for &arg in args {
+                            self.expr(arg);
+                        }
+                        TyKind::Unit
+                    }
+                    _ => {
+                        self.no_type_args(call.ty_args);
+                        let receiver = self.expr(call.receiver);
+                        for &arg in args {
+                            self.expr(arg);
+                        }
+
+                        if self.kind(receiver) != TyKind::Error {
+                            let span = self.ctx.exprs[call.receiver].span();
+                            let ty = self.display(receiver);
+                            self.error(span, ErrorKind::NotCallable(ty));
+                        }
+                        TyKind::Error
+                    }
+                }
+            }
+            Expr::MethodCall(call) => {
+                let receiver = self.expr(call.receiver);
+                let receiver = self.infcx.shallow_resolve(&self.results.tcx, receiver);
+                let args = self.ctx.exprs.list(call.args);
+                let method = self.lookup_method(receiver, call);
+
+                match (method, self.kind(receiver)) {
+                    (Some(method), _) => {
+                        self.results.method_calls[expr] = Some(method);
+
+                        // The `Self` of trait methods is the receiver, while
+                        // the type parameters of an impl are inferred from
+                        // it.
+                        let self_ty = match self.ctx.items[self.results.parents[&method]] {
+                            Item::Trait(_) => Some(receiver),
+                            _ => None,
+                        };
+                        let subst = self.instantiate(expr, method, call.ty_args, self_ty);
+                        let sig = self.results.signatures[&method].clone();
+                        let params: Vec<_> = sig
+                            .params
+                            .iter()
+                            .map(|&param| self.results.tcx.subst(param, &subst))
+                            .collect();
+
+                        let span = self.ctx.exprs[call.receiver].span();
+                        self.coerce(receiver, params[0], span);
+                        self.args(&params[1..], args, call.span);
+                        return self.results.tcx.subst(sig.ret, &subst);
+                    }
+                    (None, TyKind::Array(_) | TyKind::Str)
+                        if self.interner.get(call.method) == "len" =>
+                    {
+                        self.no_type_args(call.ty_args);
+                        self.args(&[], args, call.span);
+                        TyKind::Int(IntSuffix::I64)
+                    }
+                    (None, kind) => {
+                        self.no_type_args(call.ty_args);
+                        for &arg in args {
+                            self.expr(arg);
+                        }
+
+                        if !matches!(kind, TyKind::Error | TyKind::Never) {
+                            let ty = self.display(receiver);
+                            let method = self.interner.get(call.method).to_owned();
+                            self.error(call.span, ErrorKind::UnknownMethod { ty, method });
+                        }
+                        TyKind::Error
+                    }
+                }
+            }
+            Expr::If(if_expr) => {
+                self.condition(if_expr.cond);
+                let then_ty = self.block(if_expr.then_branch);
+
+                let Some(else_expr) = if_expr.else_branch else {
+                    let unit = self.results.tcx.unit();
+                    self.coerce(then_ty, unit, tail_span(self.ctx, if_expr.then_branch));
+                    return unit;
+                };
+
+                let (else_ty, span) = match else_expr.kind {
+                    ElseExprKind::If(expr) => (self.expr(expr), self.ctx.exprs[expr].span()),
+                    ElseExprKind::Else(block) => (self.block(block), tail_span(self.ctx, block)),
+                };
+
+                self.coerce(else_ty, then_ty, span);
+                if self.kind(then_ty) == TyKind::Never {
+                    return else_ty;
+                }
+                return then_ty;
+            }
+            Expr::While(while_expr) => {
+                self.condition(while_expr.cond);
+
+                let body = self.block(while_expr.body);
+                let unit = self.results.tcx.unit();
+                self.coerce(body, unit, tail_span(self.ctx, while_expr.body));
+
+                TyKind::Unit
+            }
+            Expr::Match(match_expr) => return self.match_expr(expr, match_expr),
+            Expr::Array(array_expr) => match array_expr.kind {
+                ArrayExprKind::List { values } => {
+                    let elem = self.infcx.new_var(&mut self.results.tcx);
+                    for &value in self.ctx.exprs.list(values) {
+                        let ty = self.expr(value);
+                        self.coerce(ty, elem, self.ctx.exprs[value].span());
+                    }
+
+                    TyKind::Array(elem)
+                }
+                ArrayExprKind::Repeat { value, repeat } => {
+                    let elem = self.expr(value);
+                    self.index(repeat);
+
+                    TyKind::Array(elem)
+                }
+            },
+            Expr::Struct(struct_expr) => return self.struct_expr(expr, struct_expr),
+            Expr::Field(field_expr) => {
+                let base = self.expr(field_expr.base);
+                let base = self.infcx.shallow_resolve(&self.results.tcx, base);
+
+                let field = match self.kind(base) {
+                    TyKind::Adt(item, args) => self.results.fields[&item]
+                        .iter()
+                        .find(|(name, _)| *name == field_expr.member)
+                        .map(|&(_, ty)| (ty, self.struct_subst(item, args))),
+                    _ => None,
+                };
+
+                match (field, self.kind(base)) {
+                    (Some((ty, subst)), _) => return self.results.tcx.subst(ty, &subst),
+                    (None, TyKind::Error | TyKind::Never) => TyKind::Error,
+                    (None, _) => {
+                        let field = self.interner.get(field_expr.member);
+                        let span =
+                            Span::new(field_expr.span.end - field.len(), field_expr.span.end);
+                        let kind = ErrorKind::UnknownField {
+                            ty: self.display(base),
+                            field: field.to_owned(),
+                        };
+
+                        self.error(span, kind);
+                        TyKind::Error
+                    }
+                }
+            }
+            Expr::Index(index_expr) => {
+                let base = self.expr(index_expr.base);
+                self.index(index_expr.index);
+
+                let base = self.infcx.shallow_resolve(&self.results.tcx, base);
+                match self.kind(base) {
+                    TyKind::Array(elem) => return elem,
+                    TyKind::Error | TyKind::Never => TyKind::Error,
+                    _ => {
+                        let ty = self.display(base);
+                        let span = self.ctx.exprs[index_expr.base].span();
+                        self.error(span, ErrorKind::NotIndexable(ty));
+                        TyKind::Error
+                    }
+                }
+            }
+            Expr::Return(return_expr) => {
+                let (ty, span) = match return_expr.value {
+                    Some(value) => (self.expr(value), self.ctx.exprs[value].span()),
+                    None => (self.results.tcx.unit(), return_expr.span),
+                };
+
+                if let Some(ret) = self.ret {
+                    self.coerce(ty, ret, span);
+                }
+
+                TyKind::Never
+            }
+        };
+
+        self.results.tcx.intern(kind)
+    }
+
+    fn bin(&mut self, expr: ExprEntity, bin_expr: BinExpr) -> TyId {
+        let lhs = self.expr(bin_expr.lhs);
+        let rhs = self.expr(bin_expr.rhs);
+        let op = bin_expr.op;
+
+        let trait_ = match op {
+            BinOp::And | BinOp::Or => {
+                let bool = self.results.tcx.bool();
+                self.coerce(lhs, bool, self.ctx.exprs[bin_expr.lhs].span());
+                self.coerce(rhs, bool, self.ctx.exprs[bin_expr.rhs].span());
+                return bool;
+            }
+            BinOp::Add => BuiltinTrait::Add,
+            BinOp::Sub => BuiltinTrait::Sub,
+            BinOp::Mul => BuiltinTrait::Mul,
+            BinOp::Div => BuiltinTrait::Div,
+            BinOp::BitAnd => BuiltinTrait::BitAnd,
+            BinOp::BitOr => BuiltinTrait::BitOr,
+            BinOp::BitXor => BuiltinTrait::BitXor,
+            BinOp::BitShl => BuiltinTrait::Shl,
+            BinOp::BitShr => BuiltinTrait::Shr,
+            BinOp::Eq | BinOp::Neq => BuiltinTrait::Eq,
+            BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq => BuiltinTrait::Ord,
+        };
+
+        // Operands whose type is only known from the other one, like a
+        // `let` without initializer, take it before picking the operator.
+        if let TyKind::Infer(InferTy::Var(_)) = self.kind(lhs)
+            && !matches!(trait_, BuiltinTrait::Shl | BuiltinTrait::Shr)
+        {
+            let _ = self.infcx.unify(&mut self.results.tcx, lhs, rhs);
+        }
+
+        if self.is_overloadable(lhs) {
+            let rhs = (rhs, self.ctx.exprs[bin_expr.rhs].span());
+            let ty = self.overloaded(expr, trait_, lhs, Some(rhs), bin_expr.span);
+            return if op.is_comparison() {
+                self.results.tcx.bool()
+            } else {
+                ty
+            };
+        }
+
+        let supported = match op {
+            // The amount to shift by can have any integer type.
+            BinOp::BitShl | BinOp::BitShr => self.is_int(lhs) && self.is_int(rhs),
+            _ => {
+                let unified = self.infcx.unify(&mut self.results.tcx, lhs, rhs).is_ok();
+                let kind = self.kind(lhs);
+
+                unified
+                    && match op {
+                        BinOp::Eq | BinOp::Neq => !matches!(kind, TyKind::Infer(InferTy::Var(_))),
+                        BinOp::Lt | BinOp::LtEq | BinOp::Gt | BinOp::GtEq => {
+                            self.is_numeric(lhs)
+                                || matches!(kind, TyKind::Bool | TyKind::Char | TyKind::Str)
+                        }
+                        BinOp::Add => self.is_numeric(lhs) || kind == TyKind::Str,
+                        BinOp::Sub | BinOp::Mul | BinOp::Div => self.is_numeric(lhs),
+                        _ => self.is_int(lhs) || kind == TyKind::Bool,
+                    }
+            }
+        };
+
+        // Arrays are compared element by element, so their elements must be
+        // comparable too, which is only known once their type is.
+        if supported
+            && matches!(op, BinOp::Eq | BinOp::Neq)
+            && let TyKind::Array(elem) = self.kind(lhs)
+        {
+            self.obligations.push(Obligation {
+                ty: elem,
+                trait_id: TraitId::Builtin(BuiltinTrait::Eq),
+                span: bin_expr.span,
+            });
+        }
+
+        if !supported {
+            let kind = ErrorKind::InvalidOperands {
+                op: op.as_str(),
+                lhs: self.display(lhs),
+                rhs: self.display(rhs),
+            };
+            self.error(bin_expr.span, kind);
+        }
+
+        if op.is_comparison() {
+            self.results.tcx.bool()
+        } else if supported {
+            lhs
+        } else {
+            self.results.tcx.error()
+        }
+    }
+
+    /// Checks an operator applied to a type that is not primitive as a call
+    /// of the method of the built-in `trait_`.
+    fn overloaded(
+        &mut self,
+        expr: ExprEntity,
+        trait_: BuiltinTrait,
+        operand: TyId,
+        rhs: Option<(TyId, Span)>,
+        span: Span,
+    ) -> TyId {
+        self.results.operators[expr] = Some(trait_);
+        self.obligations.push(Obligation {
+            ty: operand,
+            trait_id: TraitId::Builtin(trait_),
+            span,
+        });
+
+        let sig = self.builtin_sig(trait_, operand);
+        if let (Some((rhs, span)), Some(&param)) = (rhs, sig.params.get(1)) {
+            self.coerce(rhs, param, span);
+        }
+
+        sig.ret
+    }
+
+    /// Whether operators on `ty` are calls of the methods of the built-in
+    /// traits instead of built into the language.
+    fn is_overloadable(&self, ty: TyId) -> bool {
+        matches!(
+            self.kind(ty),
+            TyKind::Adt(..) | TyKind::Param(_) | TyKind::SelfTy(_)
+        )
+    }
+
+    fn struct_expr(&mut self, expr: ExprEntity, struct_expr: StructExpr) -> TyId {
+        let field_exprs = self.ctx.field_exprs.list(struct_expr.fields);
+
+        let fields = match self.resolution.exprs[expr] {
+            Some(Res::Struct(item)) => Some((item, self.results.fields[&item].clone())),
+            Some(Res::Variant(item, variant)) => {
+                let span = struct_expr
+                    .variant
+                    .map_or(struct_expr.span, |variant| variant.span);
+                self.expect_variant(item, variant, "struct", span)
+                    .then(|| (item, self.variant_fields(variant)))
+            }
+            _ => None,
+        };
+        let Some((item, fields)) = fields else {
+            for &field in field_exprs {
+                self.expr(self.ctx.field_exprs[field].value);
+            }
+            return self.results.tcx.error();
+        };
+
+        let (ty, subst) = self.fresh_adt(item, struct_expr.span);
+
+        let mut seen = Vec::new();
+        for &field in field_exprs {
+            let field = self.ctx.field_exprs[field];
+            let value = self.expr(field.value);
+            let name = self.interner.get(field.name);
+            let span = Span::new(field.span.start, field.span.start + name.len());
+
+            if seen.contains(&field.name) {
+                self.error(span, ErrorKind::DuplicateField(name.to_owned()));
+                continue;
+            }
+            seen.push(field.name);
+
+            match fields.iter().find(|(def, _)| *def == field.name) {
+                Some(&(_, def_ty)) => {
+                    let def_ty = self.results.tcx.subst(def_ty, &subst);
+                    self.coerce(value, def_ty, self.ctx.exprs[field.value].span())
+                }
+                None => {
+                    let kind = ErrorKind::UnknownField {
+                        ty: self.display(ty),
+                        field: name.to_owned(),
+                    };
+                    self.error(span, kind);
+                }
+            }
+        }
+
+        let missing: Vec<_> = fields
+            .iter()
+            .filter(|(name, _)| !seen.contains(name))
+            .map(|&(name, _)| self.interner.get(name).to_owned())
+            .collect();
+        if !missing.is_empty() {
+            let mut name = self.interner.get(struct_expr.name).to_owned();
+            let mut span = Span::new(struct_expr.span.start, struct_expr.span.start + name.len());
+            if let Some(variant) = struct_expr.variant {
+                name = format!("{name}::{}", self.interner.get(variant.name));
+                span += variant.span;
+            }
+
+            let kind = ErrorKind::MissingFields {
+                ty: name,
+                fields: missing,
+            };
+            self.error(span, kind);
+        }
+
+        ty
+    }
+
+    /// The type of a struct or enum with new inference variables as its type
+    /// arguments, and what its type parameters stand for.
+    fn fresh_adt(&mut self, item: ItemEntity, span: Span) -> (TyId, Subst) {
+        let params = self.results.generics[&item].clone();
+        let args: Vec<_> = params
+            .iter()
+            .map(|_| self.infcx.new_var(&mut self.results.tcx))
+            .collect();
+        let subst = Subst::new(&params, &args, None);
+        self.require_bounds(&params, &args, span);
+
+        let args = self.results.tcx.intern_list(&args);
+        (self.results.tcx.intern(TyKind::Adt(item, args)), subst)
+    }
+
+    /// Checks that `variant` is used with the fields it was declared with,
+    /// which is `expected` of `unit`, `tuple` or `struct`.
+    fn expect_variant(
+        &mut self,
+        item: ItemEntity,
+        variant: VariantEntity,
+        expected: &'static str,
+        span: Span,
+    ) -> bool {
+        let found = variant_shape(self.ctx.variants[variant].kind);
+        if found == expected {
+            return true;
+        }
+
+        let kind = ErrorKind::MismatchedVariant {
+            expected,
+            found,
+            variant: self.variant_name(item, variant),
+        };
+        self.error(span, kind);
+
+        false
+    }
+
+    /// The names and types of the fields of a struct variant.
+    fn variant_fields(&self, variant: VariantEntity) -> Vec<(StrId, TyId)> {
+        let VariantKind::Struct(fields) = self.ctx.variants[variant].kind else {
+            return Vec::new();
+        };
+
+        let names = self.ctx.field_defs.list(fields).iter();
+        let names = names.map(|&field| self.ctx.field_defs[field].name);
+        names
+            .zip(self.results.variants[&variant].iter().copied())
+            .collect()
+    }
+
+    /// `Enum::Variant`, as it is written in the source.
+    fn variant_name(&self, item: ItemEntity, variant: VariantEntity) -> String {
+        let Item::Enum(enum_item) = self.ctx.items[item] else {
+            unreachable!("variant of a non-enum item");
+        };
+
+        format!(
+            "{}::{}",
+            self.interner.get(enum_item.name),
+            self.interner.get(self.ctx.variants[variant].name)
+        )
+    }
+
+    fn match_expr(&mut self, expr: ExprEntity, match_expr: MatchExpr) -> TyId {
+        let scrutinee = self.expr(match_expr.scrutinee);
+
+        // The first arm that does not diverge decides the type of the match,
+        // like the branches of an `if`.
+        let mut ty = None;
+        for &arm in self.ctx.arms.list(match_expr.arms) {
+            let arm = self.ctx.arms[arm];
+            self.pat(arm.pat, scrutinee);
+            if let Some(guard) = arm.guard {
+                self.condition(guard);
+            }
+
+            let body = self.expr(arm.body);
+            match ty {
+                Some(ty) => self.coerce(body, ty, self.ctx.exprs[arm.body].span()),
+                None if self.kind(body) != TyKind::Never => ty = Some(body),
+                None => {}
+            }
+        }
+
+        self.body_matches.push(expr);
+        ty.unwrap_or_else(|| self.results.tcx.never())
+    }
+
+    /// Checks that `pat` can match values of type `expected`, giving its
+    /// bindings their types.
+    fn pat(&mut self, pat: PatEntity, expected: TyId) {
+        self.results.pats[pat] = Some(expected);
+        self.body_pats.push(pat);
+
+        match self.ctx.pats[pat] {
+            Pat::Wild(_) | Pat::Error(_) => {}
+            Pat::Binding(..) => {
+                if let Some(local) = self.resolution.bindings[pat] {
+                    self.results.locals[local] = Some(expected);
+                    self.body_locals.push(local);
+                }
+            }
+            Pat::Lit(lit, span) => {
+                let ty = self.expr(lit);
+                self.coerce(ty, expected, span);
+            }
+            Pat::Adt(adt) => {
+                let (item, fields) = match self.resolution.pats[pat] {
+                    Some(Res::Struct(item)) => {
+                        let fields = self.results.fields[&item].clone();
+                        (item, Some(fields))
+                    }
+                    Some(Res::Variant(item, variant)) => {
+                        let span = adt.variant.map_or(adt.span, |variant| variant.span);
+
+                        let fields = match adt.fields {
+                            PatFields::Unit => self.expect_variant(item, variant, "unit", span),
+                            PatFields::Tuple(_) => {
+                                self.expect_variant(item, variant, "tuple", span)
+                            }
+                            PatFields::Named(_) => {
+                                self.expect_variant(item, variant, "struct", span)
+                            }
+                        };
+
+                        (item, fields.then(|| self.variant_fields(variant)))
+                    }
+                    _ => {
+                        self.error_pats(adt.fields);
+                        return;
+                    }
+                };
+
+                let (ty, subst) = self.fresh_adt(item, adt.span);
+                self.coerce(ty, expected, adt.span);
+
+                let Some(fields) = fields else {
+                    self.error_pats(adt.fields);
+                    return;
+                };
+                let fields: Vec<_> = fields
+                    .into_iter()
+                    .map(|(name, ty)| (name, self.results.tcx.subst(ty, &subst)))
+                    .collect();
+
+                match (self.resolution.pats[pat], adt.fields) {
+                    (_, PatFields::Unit) => {}
+                    (Some(Res::Variant(item, variant)), PatFields::Tuple(pats)) => {
+                        let tys: Vec<_> = self.results.variants[&variant]
+                            .clone()
+                            .into_iter()
+                            .map(|ty| self.results.tcx.subst(ty, &subst))
+                            .collect();
+                        let pats = self.ctx.pats.list(pats);
+
+                        if pats.len() != tys.len() {
+                            let kind = ErrorKind::PatternFieldCount {
+                                variant: self.variant_name(item, variant),
+                                expected: tys.len(),
+                                found: pats.len(),
+                            };
+                            self.error(adt.span, kind);
+                        }
+
+                        for (i, &sub) in pats.iter().enumerate() {
+                            let ty = match tys.get(i) {
+                                Some(&ty) => ty,
+                                None => self.results.tcx.error(),
+                            };
+                            self.pat(sub, ty);
+                        }
+                    }
+                    (_, PatFields::Tuple(pats)) => {
+                        for &sub in self.ctx.pats.list(pats) {
+                            let error = self.results.tcx.error();
+                            self.pat(sub, error);
+                        }
+                    }
+                    (_, PatFields::Named(field_pats)) => {
+                        let mut seen = Vec::new();
+                        for &field in self.ctx.field_pats.list(field_pats) {
+                            let field = self.ctx.field_pats[field];
+                            let name = self.interner.get(field.name);
+                            let span = Span::new(field.span.start, field.span.start + name.len());
+
+                            let ty = if seen.contains(&field.name) {
+                                self.error(span, ErrorKind::DuplicateField(name.to_owned()));
+                                self.results.tcx.error()
+                            } else {
+                                seen.push(field.name);
+                                match fields.iter().find(|(def, _)| *def == field.name) {
+                                    Some(&(_, ty)) => ty,
+                                    None => {
+                                        let kind = ErrorKind::UnknownField {
+                                            ty: self.display(ty),
+                                            field: name.to_owned(),
+                                        };
+                                        self.error(span, kind);
+                                        self.results.tcx.error()
+                                    }
+                                }
+                            };
+
+                            self.pat(field.pat, ty);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the nested patterns of a pattern whose type is unknown.
+    fn error_pats(&mut self, fields: PatFields) {
+        let pats: Vec<_> = match fields {
+            PatFields::Unit => Vec::new(),
+            PatFields::Tuple(pats) => self.ctx.pats.list(pats).to_vec(),
+            PatFields::Named(fields) => self
+                .ctx
+                .field_pats
+                .list(fields)
+                .iter()
+                .map(|&field| self.ctx.field_pats[field].pat)
+                .collect(),
+        };
+
+        for pat in pats {
+            let error = self.results.tcx.error();
+            self.pat(pat, error);
+        }
+    }
+
+    /// Checks the arguments of a call against the parameter types.
+    fn args(&mut self, params: &[TyId], args: &[ExprEntity], span: Span) {
+        if params.len() != args.len() {
+            let kind = ErrorKind::ArgumentCount {
+                expected: params.len(),
+                found: args.len(),
+            };
+            self.error(span, kind);
+        }
+
+        for (i, &arg) in args.iter().enumerate() {
+            let ty = self.expr(arg);
+            if let Some(&param) = params.get(i) {
+                self.coerce(ty, param, self.ctx.exprs[arg].span());
+            }
+        }
+    }
+
+    fn condition(&mut self, cond: ExprEntity) {
+        let ty = self.expr(cond);
+        let bool = self.results.tcx.bool();
+        self.coerce(ty, bool, self.ctx.exprs[cond].span());
+    }
+
+    /// Checks an array index or length, which can have any integer type.
+    fn index(&mut self, index: ExprEntity) {
+        let ty = self.expr(index);
+        let int = self.infcx.new_int_var(&mut self.results.tcx);
+        self.coerce(ty, int, self.ctx.exprs[index].span());
+    }
+
+    fn no_type_args(&mut self, ty_args: EntityList<TypeEntity>) {
+        let ty_args = self.ctx.types.list(ty_args);
+        if let (Some(&first), Some(&last)) = (ty_args.first(), ty_args.last()) {
+            let span = self.ctx.types[first].span + self.ctx.types[last].span;
+            self.error(span, ErrorKind::UnexpectedTypeArgs);
+        }
+    }
+
+    fn local_ty(&mut self, local: LocalId) -> TyId {
+        match self.results.locals[local] {
+            Some(ty) => ty,
+            None => self.results.tcx.error(),
+        }
+    }
+
+    /// Creates inference variables for the type parameters of the function
+    /// called by `expr`, binding the function's own parameters to the
+    /// explicit `ty_args` if there are any.
+    fn instantiate(
+        &mut self,
+        expr: ExprEntity,
+        item: ItemEntity,
+        ty_args: EntityList<TypeEntity>,
+        self_ty: Option<TyId>,
+    ) -> Subst {
+        let params = self.results.generics[&item].clone();
+        let args: Vec<_> = params
+            .iter()
+            .map(|_| self.infcx.new_var(&mut self.results.tcx))
+            .collect();
+
+        let Item::Func(func) = self.ctx.items[item] else {
+            unreachable!("only functions are called");
+        };
+        let own = func.generics.len(&self.ctx.generic_params.pool);
+        let explicit = self.ctx.types.list(ty_args);
+
+        if own == 0 {
+            self.no_type_args(ty_args);
+        } else if let (Some(&first), Some(&last)) = (explicit.first(), explicit.last()) {
+            let span = self.ctx.types[first].span + self.ctx.types[last].span;
+            let explicit: Vec<_> = explicit.iter().map(|&ty| self.lower(ty)).collect();
+
+            if explicit.len() == own {
+                let own_args = &args[args.len() - own..];
+                for (&arg, &ty) in own_args.iter().zip(&explicit) {
+                    self.coerce(ty, arg, span);
+                }
+            } else {
+                let kind = ErrorKind::TypeArgCount {
+                    expected: own,
+                    found: explicit.len(),
+                };
+                self.error(span, kind);
+            }
+        }
+
+        self.require_bounds(&params, &args, self.ctx.exprs[expr].span());
+        self.results.type_args[expr] = Some(self.results.tcx.intern_list(&args));
+        Subst::new(&params, &args, self_ty)
+    }
+
+    /// What the type parameters of a struct stand for in `Struct(item, args)`.
+    fn struct_subst(&self, item: ItemEntity, args: TyList) -> Subst {
+        let params = &self.results.generics[&item];
+        Subst::new(params, self.results.tcx.list(args), None)
+    }
+
+    /// Checks that a value of type `found` can be used where `expected` is
+    /// required.
+    fn coerce(&mut self, found: TyId, expected: TyId, span: Span) {
+        if self
+            .infcx
+            .unify(&mut self.results.tcx, found, expected)
+            .is_err()
+        {
+            let kind = ErrorKind::Mismatched {
+                expected: self.display(expected),
+                found: self.display(found),
+            };
+            self.error(span, kind);
+        }
+    }
+
+    /// The kind of `ty` after following bound inference variables.
+    fn kind(&self, ty: TyId) -> TyKind {
+        let tcx = &self.results.tcx;
+        tcx[self.infcx.shallow_resolve(tcx, ty)]
+    }
+
+    fn is_int(&self, ty: TyId) -> bool {
+        matches!(
+            self.kind(ty),
+            TyKind::Int(_) | TyKind::Infer(InferTy::Int(_)) | TyKind::Error | TyKind::Never
+        )
+    }
+
+    fn is_numeric(&self, ty: TyId) -> bool {
+        self.is_int(ty)
+            || matches!(
+                self.kind(ty),
+                TyKind::Float(_) | TyKind::Infer(InferTy::Float(_))
+            )
+    }
+
+    fn display(&mut self, ty: TyId) -> String {
+        let ty = self.infcx.resolve(&mut self.results.tcx, ty);
+        self.results
+            .tcx
+            .display(ty, self.ctx, self.interner)
+            .to_string()
+    }
+
+    fn error(&mut self, span: Span, kind: ErrorKind) {
+        self.results.errors.push(Error::new(span, kind));
+    }
+}
+
+/// Replaces the unbound variables left in a resolved type by the error type,
+/// returning whether there were none.
+fn complete(tcx: &mut TyCtx, ty: TyId) -> (TyId, bool) {
+    if let TyKind::Infer(_) = tcx[ty] {
+        return (tcx.error(), false);
+    }
+
+    let mut is_complete = true;
+    let ty = tcx.map_inner(ty, &mut |tcx, inner| {
+        let (inner, complete) = complete(tcx, inner);
+        is_complete &= complete;
+        inner
+    });
+
+    (ty, is_complete)
+}
+
+/// How the fields of a variant are declared, as used in error messages.
+fn variant_shape(kind: VariantKind) -> &'static str {
+    match kind {
+        VariantKind::Unit => "unit",
+        VariantKind::Tuple(_) => "tuple",
+        VariantKind::Struct(_) => "struct",
+    }
+}
+
+fn prim_kind(prim: PrimTy) -> TyKind {
+    match prim {
+        PrimTy::I8 => TyKind::Int(IntSuffix::I8),
+        PrimTy::I16 => TyKind::Int(IntSuffix::I16),
+        PrimTy::I32 => TyKind::Int(IntSuffix::I32),
+        PrimTy::I64 => TyKind::Int(IntSuffix::I64),
+        PrimTy::U8 => TyKind::Int(IntSuffix::U8),
+        PrimTy::U16 => TyKind::Int(IntSuffix::U16),
+        PrimTy::U32 => TyKind::Int(IntSuffix::U32),
+        PrimTy::U64 => TyKind::Int(IntSuffix::U64),
+        PrimTy::F32 => TyKind::Float(FloatSuffix::F32),
+        PrimTy::F64 => TyKind::Float(FloatSuffix::F64),
+        PrimTy::Bool => TyKind::Bool,
+        PrimTy::Char => TyKind::Char,
+        PrimTy::Str => TyKind::Str,
+    }
+}
+
+/// The span to blame when the value of a block has the wrong type: its tail
+/// expression, or the whole block when it has none.
+fn tail_span(ctx: &AstCtx, block: Block) -> Span {
+    match block.tail {
+        Some(tail) => ctx.exprs[tail].span(),
+        None => block.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_common::Source;
+
+    use super::*;
+
+    fn check(content: &str) -> (Vec<String>, HashMap<String, String>) {
+        let interner = Interner::new();
+        let parsed = unnamed_parser::parse(Source::new(content, "test_file"), &interner);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        let results = super::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+        let errors = results.errors.iter().map(Error::to_string).collect();
+
+        // Types of the locals by name, with later bindings overriding
+        // earlier ones.
+        let locals = resolution
+            .locals
+            .iter()
+            .filter_map(|(local, data)| {
+                let ty = results.locals[local]?;
+                let ty = results.tcx.display(ty, &parsed.ctx, &interner);
+                Some((interner.get(data.name).to_owned(), ty.to_string()))
+            })
+            .collect();
+
+        (errors, locals)
+    }
+
+    fn errors(content: &str) -> Vec<String> {
+        check(content).0
+    }
+
+    #[test]
+    fn test_inference() {
+        let (errors, locals) = check(
+            "struct P { x: u8 } \
+             func f(p: P) -> u8 { \
+                 let a = 1; let b = a; let c: u8 = b; \
+                 let d; d = 2.5; let e = 1 << 2u32; \
+                 let g = [[1i16], []]; let h = g[0][0]; \
+                 let i = p.x + 1; let j = [0; 4].len(); \
+                 let k = if c > 1 { \"a\" } else { return 0; }; \
+                 i \
+             }",
+        );
+
+        assert_eq!(errors, Vec::<String>::new());
+        let expected = [
+            ("p", "P"),
+            ("a", "u8"),
+            ("b", "u8"),
+            ("c", "u8"),
+            ("d", "f64"),
+            ("e", "i64"),
+            ("g", "[[i16]]"),
+            ("h", "i16"),
+            ("i", "u8"),
+            ("j", "i64"),
+            ("k", "str"),
+        ];
+        for (name, ty) in expected {
+            assert_eq!(locals[name], ty, "type of `{name}`");
+        }
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(
+            errors(
+                "func f() { 1 + true; \"a\" - \"b\"; 1 && 2; 1.5 << 1; 'a' < 'b'; \
+                 [1] == [2]; -true; 1i8 + 1u8; }"
+            ),
+            [
+                "cannot apply `+` to `{integer}` and `bool`",
+                "cannot apply `-` to `str` and `str`",
+                "mismatched types: expected `bool`, found `{integer}`",
+                "mismatched types: expected `bool`, found `{integer}`",
+                "cannot apply `<<` to `{float}` and `{integer}`",
+                "cannot apply unary `-` to `bool`",
+                "cannot apply `+` to `i8` and `u8`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(
+            errors(
+                "func f(x: i64) -> bool { \
+                     if x { 1 } else { true }; \
+                     if true { 1 } \
+                     while 1 {} \
+                     if x > 0 { return 1; } \
+                     x \
+                 } \
+                 func g() -> i64 { return; } \
+                 func h() -> i64 { while true {} }"
+            ),
+            [
+                "mismatched types: expected `bool`, found `i64`",
+                "mismatched types: expected `{integer}`, found `bool`",
+                "mismatched types: expected `()`, found `{integer}`",
+                "mismatched types: expected `bool`, found `{integer}`",
+                "mismatched types: expected `bool`, found `{integer}`",
+                "mismatched types: expected `bool`, found `i64`",
+                "mismatched types: expected `i64`, found `()`",
+                "mismatched types: expected `i64`, found `()`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_calls() {
+        assert_eq!(
+            errors(
+                "struct P {} \
+                 impl P { func get(self, x: i64) -> i64 { x } } \
+                 trait T { func t(self) -> Self; func d(self) -> bool { true } } \
+                 impl T for P { func t(self) -> P { self } } \
+                 func f(a: i64, b: bool) {} \
+                 func main() { \
+                     f(1); f(1, 2); println(1, true); \
+                     let p = P {}; let x: i64 = p.get(1); p.get(); \
+                     let q: P = p.t(); let d: bool = q.d(); \
+                     p.missing(); [1][0](); f; \
+                 }"
+            ),
+            [
+                "expected 2 arguments, found 1",
+                "mismatched types: expected `bool`, found `{integer}`",
+                "expected 1 argument, found 0",
+                "no method named `missing` found for `P`",
+                "expected function, found `{integer}`",
+                "the function `f` can only be called",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structs_and_arrays() {
+        assert_eq!(
+            errors(
+                "struct P { x: i64, y: i64 } \
+                 func f(p: P) { \
+                     P { x: 1, x: 2, z: 3 }; \
+                     P { x: true, y: 1 }; \
+                     p.z; p.x.y; \
+                     [1, true]; p[0]; [1][true]; \
+                 }"
+            ),
+            [
+                "missing field `y` in initializer of `P`",
+                "field `x` specified more than once",
+                "no field `z` on type `P`",
+                "mismatched types: expected `i64`, found `bool`",
+                "no field `z` on type `P`",
+                "no field `y` on type `i64`",
+                "mismatched types: expected `{integer}`, found `bool`",
+                "cannot index into a value of type `P`",
+                "mismatched types: expected `{integer}`, found `bool`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generics() {
+        let (errors, locals) = check(
+            "struct Pair<A, B> { a: A, b: B } \
+             impl<T> Pair<T, T> { func swap(self) -> Pair<T, T> { Pair { a: self.b, b: self.a } } } \
+             impl<A, B> Pair<A, B> { func map_b<C>(self, c: C) -> Pair<A, C> { Pair { a: self.a, b: c } } } \
+             func id<T>(t: T) -> T { t } \
+             func second<A, B>(x: A, y: B) -> B { y } \
+             func f() -> Later<i8> { \
+                 let a = id(1u8); let b = id::<bool>(true); let c = second([1], 1.5); \
+                 let p = Pair { a: 1, b: 2 }; let d = p.swap().a; \
+                 let q = p.map_b(\"x\"); let e: Pair<str, i16> = Pair { a: \"y\", b: 3 }; \
+                 let g = e.map_b::<char>('c'); \
+                 Later { x: 1 } \
+             } \
+             struct Later<T> { x: T }",
+        );
+
+        assert_eq!(errors, Vec::<String>::new());
+        let expected = [
+            ("a", "u8"),
+            ("b", "bool"),
+            ("c", "f64"),
+            ("p", "Pair<i64, i64>"),
+            ("d", "i64"),
+            ("q", "Pair<i64, str>"),
+            ("g", "Pair<str, char>"),
+            ("t", "T"),
+            ("y", "B"),
+        ];
+        for (name, ty) in expected {
+            assert_eq!(locals[name], ty, "type of `{name}`");
+        }
+    }
+
+    #[test]
+    fn test_generic_errors() {
+        assert_eq!(
+            errors(
+                "struct Pair<A, B> { a: A, b: B } \
+                 struct Wrap<T> { t: T } \
+                 impl<T> Pair<T, T> { func same(self) {} } \
+                 func id<T>(t: T) -> T { t + t } \
+                 func none<T>() {} \
+                 func f(p: Pair<i64>, w: Wrap<bool, bool>, x: i64<u8>) { \
+                     id::<u8>(true); id::<u8, u8>(1); f::<u8>(p, w, 1); none(); \
+                     let q = Pair { a: 1, b: true }; q.same(); \
+                     let r: Wrap<i64> = Wrap { t: true }; \
+                 }"
+            ),
+            [
+                "the trait `Add` is not implemented for `T`",
+                "expected 2 type arguments, found 1",
+                "expected 1 type argument, found 2",
+                "unexpected type arguments",
+                "mismatched types: expected `u8`, found `bool`",
+                "expected 1 type argument, found 2",
+                "unexpected type arguments",
+                "cannot infer the type arguments of `none`",
+                "mismatched types: expected `Pair<{integer}, {integer}>`, found `Pair<{integer}, bool>`",
+                "mismatched types: expected `Wrap<i64>`, found `Wrap<bool>`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_traits() {
+        let (errors, locals) = check(
+            "trait Show { func show(self) -> i64; func twice(self) -> i64 { self.show() * 2 } } \
+             struct P { x: i64 } \
+             struct Wrap<T> { t: T } \
+             impl P { func show(self) -> bool { true } } \
+             impl Show for P { func show(self) -> i64 { self.x } } \
+             impl Show for i64 { func show(self) -> i64 { self } } \
+             impl<T: Show> Show for Wrap<T> { func show(self) -> i64 { self.t.show() } } \
+             func total<T: Show>(t: T) -> i64 { t.twice() + t.show() } \
+             func f() { \
+                 let a = P { x: 1 }.show(); let b = Wrap { t: 1 }.twice(); \
+                 let c = total(Wrap { t: Wrap { t: P { x: 2 } } }); let d = 5.show(); \
+             }",
+        );
+
+        assert_eq!(errors, Vec::<String>::new());
+        let expected = [("a", "bool"), ("b", "i64"), ("c", "i64"), ("d", "i64")];
+        for (name, ty) in expected {
+            assert_eq!(locals[name], ty, "type of `{name}`");
+        }
+    }
+
+    #[test]
+    fn test_trait_errors() {
+        assert_eq!(
+            errors(
+                "trait Show { func show(self) -> i64; func other(self) {} } \
+                 trait Print { func show(self) -> i64; } \
+                 struct P {} struct Q {} struct W<T> { t: T } \
+                 impl Show for P { func show(self) -> bool { true } func extra(self) {} } \
+                 impl Show for Q {} \
+                 impl Print for Q { func show(self) -> i64 { 1 } } \
+                 impl Show for P { func show(self) -> i64 { 1 } } \
+                 impl<T> Show for W<T> { func show(self) -> i64 { 1 } } \
+                 impl Show for W<bool> { func show(self) -> i64 { 2 } } \
+                 impl<T> P { func f(self) {} func f(self) {} } \
+                 impl Q for P {} \
+                 func need<T: Show>(t: T) {} \
+                 func g<T: P>(t: T) { t.show(); } \
+                 func h(q: Q) { need(1); need(W { t: q }); q.show(); }"
+            ),
+            [
+                "method `show` has an incompatible signature for the trait: \
+                 expected `fn(P) -> i64`, found `fn(P) -> bool`",
+                "method `extra` is not a member of trait `Show`",
+                "not all trait methods implemented, missing: `show`",
+                "conflicting implementations of trait `Show` for type `P`",
+                "conflicting implementations of trait `Show` for type `W<bool>`",
+                "the type parameter `T` is not constrained by the impl type",
+                "duplicate definitions with name `f`",
+                "expected trait, found struct `Q`",
+                "expected trait, found struct `P`",
+                "no method named `show` found for `T`",
+                "the trait `Show` is not implemented for `i64`",
+                "multiple applicable methods named `show` in traits `Show`, `Print`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operator_overloading() {
+        let (errors, locals) = check(
+            "struct V { x: i64 } \
+             impl Add for V { func add(self, o: V) -> V { V { x: self.x + o.x } } } \
+             impl Neg for V { func neg(self) -> V { V { x: -self.x } } } \
+             impl Eq for V { func eq(self, o: V) -> bool { self.x == o.x } } \
+             impl Ord for V { func cmp(self, o: V) -> i64 { self.x - o.x } } \
+             func sum<T: Add>(a: T, b: T) -> T { a + b } \
+             func f() { let a = V { x: 1 } + V { x: 2 }; let b = -a; let c = a == b; \
+             let d = a < b; let e = sum(a, b); let g = sum(1, 2); let h = 1 < 2; }",
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(locals["a"], "V");
+        assert_eq!(locals["b"], "V");
+        assert_eq!(locals["c"], "bool");
+        assert_eq!(locals["d"], "bool");
+        assert_eq!(locals["e"], "V");
+        assert_eq!(locals["g"], "i64");
+        assert_eq!(locals["h"], "bool");
+    }
+
+    #[test]
+    fn test_operator_overloading_errors() {
+        assert_eq!(
+            errors(
+                "struct P {} \
+                 impl Add for P { func add(self, o: i64) -> P { self } } \
+                 impl Add for i64 { func add(self, o: i64) -> i64 { self } } \
+                 func sum<T>(a: T, b: T) -> T { a + b } \
+                 func f(p: P) { p - p; }"
+            ),
+            [
+                "method `add` has an incompatible signature for the trait: \
+                 expected `fn(P, P) -> P`, found `fn(P, i64) -> P`",
+                "conflicting implementations of trait `Add` for type `i64`",
+                "the trait `Add` is not implemented for `T`",
+                "the trait `Sub` is not implemented for `P`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_definite_initialization() {
+        assert_eq!(
+            errors(
+                "func f(c: bool) -> i64 { \
+                     let a: i64; a; \
+                     let b; if c { b = 1; } b; \
+                     let d; while c { d = 1; } d; \
+                     let e; c && { e = true; e }; e; \
+                     let g; match c { true => g = 1, false => {} } g + g; \
+                     0 \
+                 } \
+                 func ok(c: bool) -> i64 { \
+                     let a; if c { a = 1; } else { a = 2; } \
+                     let b; if c { b = 1; } else { return 0; } \
+                     let d; match c { true => d = 1, false => return 0 } \
+                     let e; e = a; \
+                     let x = 1; let x; x = 2; \
+                     a + b + d + e + x \
+                 }"
+            ),
+            [
+                "`a` is used before being assigned",
+                "`b` is used before being assigned",
+                "`d` is used before being assigned",
+                "`e` is used before being assigned",
+                "`g` is used before being assigned",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_comparison_errors() {
+        assert_eq!(
+            errors(
+                "struct P {} \
+                 struct Q {} \
+                 impl Eq for Q { func eq(self, o: Q) -> bool { true } } \
+                 func f<T: Eq, U>(p: P, q: Q, t: T, u: U) { \
+                     [p, p] == [p, p]; [q] != [q]; [[t]] == [[t]]; [[u]] == [[u]]; \
+                     let a = []; a == [p]; [1, 2] == [1, 2]; \
+                 }"
+            ),
+            [
+                "the trait `Eq` is not implemented for `P`",
+                "the trait `Eq` is not implemented for `[U]`",
+                "the trait `Eq` is not implemented for `P`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enums_and_match() {
+        let (errors, locals) = check(
+            "enum Opt<T> { None, Some(T) } \
+             enum Shape { Circle(f64), Rect { w: f64, h: f64 } } \
+             func area(s: Shape) -> f64 { \
+                 match s { Shape::Circle(r) => 3.14 * r * r, Shape::Rect { w, h } => w * h } \
+             } \
+             func f(o: Opt<i64>) -> i64 { \
+                 let a = Opt::Some(true); let b = Opt::None; let c: Opt<u8> = b; \
+                 let d = Shape::Rect { w: 1.0, h: 2.0 }; \
+                 let e = match o { Opt::Some(x) if x > 0 => x, Opt::Some(0) => return 0, _ => 1 }; \
+                 let g = match a { Opt::Some(true) => \"yes\", _ => \"no\" }; \
+                 e \
+             }",
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(locals["r"], "f64");
+        assert_eq!(locals["w"], "f64");
+        assert_eq!(locals["a"], "Opt<bool>");
+        assert_eq!(locals["b"], "Opt<u8>");
+        assert_eq!(locals["c"], "Opt<u8>");
+        assert_eq!(locals["d"], "Shape");
+        assert_eq!(locals["x"], "i64");
+        assert_eq!(locals["e"], "i64");
+        assert_eq!(locals["g"], "str");
+    }
+
+    #[test]
+    fn test_enum_errors() {
+        assert_eq!(
+            errors(
+                "enum E { A, B(i64), C { x: bool } } \
+                 func f() { E::B; E::A(1); E::C { x: 1 }; E::B { x: true }; E::C {}; } \
+                 func g(e: E) { match e { E::B(a, b) => {} E::C(x) => {} E::A => {} 1 => {} } }"
+            ),
+            [
+                "expected unit variant, found tuple variant `E::B`",
+                "expected tuple variant, found unit variant `E::A`",
+                "mismatched types: expected `bool`, found `{integer}`",
+                "expected struct variant, found tuple variant `E::B`",
+                "missing field `x` in initializer of `E::C`",
+                "this pattern has 2 fields, but the variant `E::B` has 1 field",
+                "expected tuple variant, found struct variant `E::C`",
+                "mismatched types: expected `E`, found `{integer}`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exhaustiveness() {
+        assert_eq!(
+            errors(
+                "enum E { A, B(bool), C { x: i64 } } struct P { a: bool, b: bool } \
+                 func f(e: E, p: P, n: i64, s: str) { \
+                     match e { E::A => 0, E::B(true) => 1 }; \
+                     match e { E::B(true) => 0, E::A => 1, E::C { x: 0 } => 2, _ => 3 }; \
+                     match e { E::A => 0, E::B(x) if x => 1, E::B(false) => 2, E::C {} => 3 }; \
+                     match p { P { a: true, b: false } => 0, P { a: false } => 1 }; \
+                     match n { 0 => 0, -1 => 1 }; \
+                     match s { \"a\" => 0, x => 1, \"b\" => 2 }; \
+                     match e { _ => 0, E::A => 1 }; \
+                 }"
+            ),
+            [
+                "non-exhaustive patterns: `E::B(false)` and `E::C { x: _ }` not covered",
+                "non-exhaustive patterns: `E::B(true)` not covered",
+                "non-exhaustive patterns: `P { a: true, b: true }` not covered",
+                "non-exhaustive patterns: `_` not covered",
+                "unreachable pattern",
+                "unreachable pattern",
+            ]
+        );
+        assert_eq!(
+            errors(
+                "enum Never {} enum O<T> { N, S(T) } \
+                 func f(n: Never, o: O<O<bool>>) -> i64 { \
+                     match o { O::N => 0, O::S(O::N) => 1, O::S(O::S(true)) => 2 }; \
+                     match n {} \
+                 }"
+            ),
+            ["non-exhaustive patterns: `O::S(O::S(false))` not covered"]
+        );
+    }
+
+    #[test]
+    fn test_inference_errors() {
+        assert_eq!(
+            errors(
+                "func f() { let a = []; let b; let c: u8 = 256; let d = 255u8; \
+                 let e: i8 = 127; let f: f32 = 1e39; let g: f32 = 3e38; }"
+            ),
+            [
+                "type annotations needed for `a`",
+                "type annotations needed for `b`",
+                "literal out of range for `u8`",
+                "literal out of range for `f32`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,528 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use cranelift_entity::{PrimaryMap, SecondaryMap, entity_impl, packed_option::PackedOption};
+use unnamed_ast::{AstCtx, ExprEntity, Item, ItemEntity, Module, expr::Expr};
+use unnamed_common::{Interner, Spanned};
+use unnamed_resolve::{BuiltinTrait, Res, Resolution};
+
+use crate::{
+    TraitId, TypeckResults,
+    error::{Error, ErrorKind},
+    ty::{Subst, TyCtx, TyId, TyList},
+};
+
+/// How many instances of a function may be found, one within the body of the
+/// other, along a chain of calls. Generic functions calling themselves with
+/// ever larger types would otherwise be instantiated forever, while chains of
+/// different functions are finite and can be of any length.
+pub const INSTANTIATION_LIMIT: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceId(u32);
+entity_impl!(InstanceId);
+
+/// A function together with the concrete types its type parameters stand
+/// for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instance {
+    pub item: ItemEntity,
+    /// One type for each of the item's `TypeckResults::generics`.
+    pub args: TyList,
+    /// The implementing type, for default methods of traits.
+    pub self_ty: Option<TyId>,
+}
+
+/// The functions the backend has to generate code for, one per set of type
+/// arguments each function is used with.
+#[derive(Debug, Default)]
+pub struct Instances {
+    pub instances: PrimaryMap<InstanceId, Instance>,
+    /// The instance called by each call in the body of each instance.
+    pub calls: HashMap<(InstanceId, ExprEntity), InstanceId>,
+    /// Calls past the `INSTANTIATION_LIMIT`, after which no more instances
+    /// are collected.
+    pub errors: Vec<Error>,
+    interned: HashMap<Instance, InstanceId>,
+}
+
+impl Instances {
+    /// What the type parameters of the item stand for in `instance`.
+    pub fn subst(&self, results: &TypeckResults, instance: InstanceId) -> Subst {
+        let Instance {
+            item,
+            args,
+            self_ty,
+        } = self.instances[instance];
+
+        Subst::new(&results.generics[&item], results.tcx.list(args), self_ty)
+    }
+
+    /// The concrete type of `expr` in the body of `instance`.
+    pub fn expr_ty(
+        &self,
+        results: &mut TypeckResults,
+        instance: InstanceId,
+        expr: ExprEntity,
+    ) -> Option<TyId> {
+        let subst = self.subst(results, instance);
+        let ty = results.exprs[expr]?;
+
+        Some(results.tcx.subst(ty, &subst))
+    }
+
+    /// Returns a wrapper that formats an instance as `name<args>`, prefixed
+    /// by the implementing type for methods.
+    pub fn display<'a>(
+        &'a self,
+        instance: InstanceId,
+        ctx: &'a AstCtx,
+        interner: &'a Interner,
+        results: &'a mut TypeckResults,
+    ) -> DisplayInstance<'a> {
+        let Instance {
+            item,
+            args,
+            self_ty,
+        } = self.instances[instance];
+
+        // The arguments of an impl come first, and are shown as part of its
+        // type instead.
+        let own = match ctx.items[item] {
+            Item::Func(func) => func.generics.len(&ctx.generic_params.pool),
+            _ => 0,
+        };
+        let args = results.tcx.list(args).to_vec();
+        let (parent_args, own_args) = args.split_at(args.len() - own);
+
+        let owner = results
+            .parents
+            .get(&item)
+            .and_then(|parent| match ctx.items[*parent] {
+                Item::Impl(_) => {
+                    let subst = Subst::new(&results.generics[&item], parent_args, None);
+                    let impl_ty = results.self_tys[parent];
+                    Some(results.tcx.subst(impl_ty, &subst))
+                }
+                _ => self_ty,
+            });
+
+        DisplayInstance {
+            tcx: &results.tcx,
+            ctx,
+            interner,
+            item,
+            owner,
+            args: own_args.to_vec(),
+        }
+    }
+}
+
+pub struct DisplayInstance<'a> {
+    tcx: &'a TyCtx,
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    item: ItemEntity,
+    owner: Option<TyId>,
+    args: Vec<TyId>,
+}
+
+impl Display for DisplayInstance<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Item::Func(func) = self.ctx.items[self.item] else {
+            return Ok(());
+        };
+
+        if let Some(owner) = self.owner {
+            write!(f, "{}::", self.tcx.display(owner, self.ctx, self.interner))?;
+        }
+        f.write_str(self.interner.get(func.name))?;
+        self.tcx.fmt_list(&self.args, self.ctx, self.interner, f)
+    }
+}
+
+/// Finds every instance reachable from the non-generic functions of
+/// `module`, which must have been checked without errors.
+pub fn monomorphize(
+    ctx: &AstCtx,
+    module: &Module,
+    interner: &Interner,
+    resolution: &Resolution,
+    results: &mut TypeckResults,
+) -> Instances {
+    let mut collector = Collector {
+        ctx,
+        interner,
+        resolution,
+        results,
+        instances: Instances::default(),
+        callers: SecondaryMap::new(),
+        depths: SecondaryMap::new(),
+        queue: Vec::new(),
+    };
+
+    for &item in ctx.items.list(module.items) {
+        match ctx.items[item] {
+            Item::Func(func) if func.generics.is_empty() => collector.root(item),
+            Item::Impl(impl_item) if impl_item.generics.is_empty() => {
+                for &method in ctx.items.list(impl_item.items) {
+                    if let Item::Func(func) = ctx.items[method]
+                        && func.generics.is_empty()
+                    {
+                        collector.root(method);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    while let Some(instance) = collector.queue.pop() {
+        collector.visit(instance);
+    }
+
+    collector.instances
+}
+
+struct Collector<'a> {
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    resolution: &'a Resolution,
+    results: &'a mut TypeckResults,
+    instances: Instances,
+    /// The instance each instance was first found to be called by, none for
+    /// the non-generic functions.
+    callers: SecondaryMap<InstanceId, PackedOption<InstanceId>>,
+    /// How many instances of the same function each instance was found
+    /// through, following `callers`.
+    depths: SecondaryMap<InstanceId, u32>,
+    /// Instances whose calls were not visited yet.
+    queue: Vec<InstanceId>,
+}
+
+impl Collector<'_> {
+    fn root(&mut self, item: ItemEntity) {
+        self.intern(Instance {
+            item,
+            args: TyList::EMPTY,
+            self_ty: None,
+        });
+    }
+
+    fn intern(&mut self, instance: Instance) -> InstanceId {
+        if let Some(&id) = self.instances.interned.get(&instance) {
+            return id;
+        }
+
+        let id = self.instances.instances.push(instance);
+        self.instances.interned.insert(instance, id);
+        self.queue.push(id);
+
+        id
+    }
+
+    /// Records that `expr` in the body of `caller` calls `instance`, unless
+    /// that would create an instance past the instantiation limit. Nothing
+    /// more is collected after that.
+    fn call(&mut self, caller: InstanceId, expr: ExprEntity, instance: Instance) {
+        if !self.instances.errors.is_empty() {
+            return;
+        }
+
+        if let Some(&callee) = self.instances.interned.get(&instance) {
+            self.instances.calls.insert((caller, expr), callee);
+            return;
+        }
+
+        let mut ancestor = Some(caller);
+        let depth = loop {
+            match ancestor {
+                Some(id) if self.instances.instances[id].item == instance.item => {
+                    break self.depths[id] + 1;
+                }
+                Some(id) => ancestor = self.callers[id].expand(),
+                None => break 0,
+            }
+        };
+
+        if depth > INSTANTIATION_LIMIT {
+            let name = match self.ctx.items[instance.item] {
+                Item::Func(func) => self.interner.get(func.name).to_owned(),
+                _ => String::new(),
+            };
+            self.instances.errors.push(Error::new(
+                self.ctx.exprs[expr].span(),
+                ErrorKind::InstantiationLimit {
+                    name,
+                    limit: INSTANTIATION_LIMIT as usize,
+                },
+            ));
+            self.queue.clear();
+            return;
+        }
+
+        let callee = self.intern(instance);
+        self.callers[callee] = caller.into();
+        self.depths[callee] = depth;
+        self.instances.calls.insert((caller, expr), callee);
+    }
+
+    fn visit(&mut self, id: InstanceId) {
+        let item = self.instances.instances[id].item;
+        let subst = self.instances.subst(self.results, id);
+        let calls = self.results.calls.get(&item).cloned().unwrap_or_default();
+
+        for call in calls {
+            if let Some(trait_) = self.results.operators[call] {
+                self.operator(id, call, trait_);
+                continue;
+            }
+
+            let (callee, receiver) = match self.ctx.exprs[call] {
+                Expr::Call(call) => match self.resolution.exprs[call.receiver] {
+                    Some(Res::Func(callee)) => (callee, None),
+                    _ => continue,
+                },
+                Expr::MethodCall(method_call) => match self.results.method_calls[call] {
+                    Some(callee) => (callee, Some(method_call.receiver)),
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            let Some(args) = self.results.type_args[call] else {
+                continue;
+            };
+            let args: Vec<_> = self.results.tcx.list(args).to_vec();
+            let args: Vec<_> = args
+                .into_iter()
+                .map(|arg| self.results.tcx.subst(arg, &subst))
+                .collect();
+
+            let instance = match self.results.parents.get(&callee) {
+                Some(&parent) if matches!(self.ctx.items[parent], Item::Trait(_)) => {
+                    let self_ty = receiver
+                        .and_then(|receiver| self.results.exprs[receiver])
+                        .map(|ty| self.results.tcx.subst(ty, &subst));
+                    self.trait_method(callee, &args, self_ty)
+                }
+                _ => Instance {
+                    item: callee,
+                    args: self.results.tcx.intern_list(&args),
+                    self_ty: None,
+                },
+            };
+
+            self.call(id, call, instance);
+        }
+    }
+
+    /// Records the method called by an overloaded operator, unless its
+    /// operand is of a primitive type in this instance.
+    fn operator(&mut self, id: InstanceId, expr: ExprEntity, trait_: BuiltinTrait) {
+        let operand = match self.ctx.exprs[expr] {
+            Expr::Bin(bin_expr) => bin_expr.lhs,
+            Expr::Unary(unary_expr) => unary_expr.value,
+            _ => return,
+        };
+        let Some(ty) = self.instances.expr_ty(self.results, id, operand) else {
+            return;
+        };
+        let Some((method, args)) = self.results.operator_method(trait_, ty) else {
+            return;
+        };
+
+        let args = self.results.tcx.intern_list(&args);
+        let instance = Instance {
+            item: method,
+            args,
+            self_ty: None,
+        };
+        self.call(id, expr, instance);
+    }
+
+    /// Picks the implementation of a trait method for the concrete type
+    /// `self_ty`: the method of the impl if it has one, and the default
+    /// method of the trait otherwise.
+    fn trait_method(
+        &mut self,
+        method: ItemEntity,
+        args: &[TyId],
+        self_ty: Option<TyId>,
+    ) -> Instance {
+        let default = |tcx: &mut TyCtx| Instance {
+            item: method,
+            args: tcx.intern_list(args),
+            self_ty,
+        };
+
+        let Some(self_ty) = self_ty else {
+            return default(&mut self.results.tcx);
+        };
+        let Item::Func(func) = self.ctx.items[method] else {
+            return default(&mut self.results.tcx);
+        };
+
+        // The type parameters of the impl are those matching its type against
+        // `self_ty`, those of the method are the same as in the call.
+        let trait_item = self.results.parents[&method];
+        let trait_id = TraitId::Item(trait_item);
+        let Some((impl_item, mut impl_args)) = self.results.find_impl(trait_id, self_ty) else {
+            return default(&mut self.results.tcx);
+        };
+        let Some(implementation) = self.results.method(impl_item, func.name) else {
+            return default(&mut self.results.tcx);
+        };
+        impl_args.extend_from_slice(args);
+
+        Instance {
+            item: implementation,
+            args: self.results.tcx.intern_list(&impl_args),
+            self_ty: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_common::Source;
+
+    use super::*;
+
+    fn monomorphize_errors(content: &str) -> Vec<Error> {
+        let interner = Interner::new();
+        let parsed = unnamed_parser::parse(Source::new(content, "test_file"), &interner);
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        let mut results = crate::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+        assert!(results.errors.is_empty(), "{:?}", results.errors);
+
+        monomorphize(
+            &parsed.ctx,
+            &parsed.module,
+            &interner,
+            &resolution,
+            &mut results,
+        )
+        .errors
+    }
+
+    fn instances(content: &str) -> Vec<String> {
+        let interner = Interner::new();
+        let parsed = unnamed_parser::parse(Source::new(content, "test_file"), &interner);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let mut results = crate::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+        assert!(results.errors.is_empty(), "{:?}", results.errors);
+
+        let instances = monomorphize(
+            &parsed.ctx,
+            &parsed.module,
+            &interner,
+            &resolution,
+            &mut results,
+        );
+        instances
+            .instances
+            .keys()
+            .map(|instance| {
+                instances
+                    .display(instance, &parsed.ctx, &interner, &mut results)
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_monomorphize() {
+        assert_eq!(
+            instances(
+                "struct Pair<A, B> { a: A, b: B } \
+                 impl<A, B> Pair<A, B> { func first(self) -> A { id(self.a) } } \
+                 trait Show { func show(self) -> i64; func twice(self) -> i64 { self.show() * 2 } } \
+                 impl<T> Show for Pair<T, bool> { func show(self) -> i64 { 1 } } \
+                 func id<T>(t: T) -> T { t } \
+                 func unused<T>(t: T) {} \
+                 func total<T: Show>(t: T) -> i64 { t.show() } \
+                 func main() { \
+                     id(1); id(true); id(2); \
+                     let p = Pair { a: 'c', b: true }; p.first(); p.twice(); \
+                     Pair { a: 1u8, b: 2 }.first(); total(Pair { a: 1, b: false }); \
+                 }"
+            ),
+            [
+                "main",
+                "id<i64>",
+                "id<bool>",
+                "Pair<char, bool>::first",
+                "Pair<char, bool>::twice",
+                "Pair<u8, i64>::first",
+                "total<Pair<i64, bool>>",
+                "Pair<i64, bool>::show",
+                "id<u8>",
+                "Pair<char, bool>::show",
+                "id<char>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(
+            instances(
+                "struct V<T> { x: T } \
+                 impl<T> Add for V<T> { func add(self, o: V<T>) -> V<T> { self } } \
+                 impl Neg for V<bool> { func neg(self) -> V<bool> { self } } \
+                 func sum<T: Add>(a: T, b: T) -> T { a + b } \
+                 func main() { \
+                     sum(1, 2); sum(V { x: 1 }, V { x: 2 }); \
+                     -V { x: true }; V { x: 'a' } + V { x: 'b' }; \
+                 }"
+            ),
+            [
+                "V<bool>::neg",
+                "main",
+                "sum<i64>",
+                "sum<V<i64>>",
+                "V<char>::add",
+                "V<i64>::add",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_instantiation_limit() {
+        let content = "struct W<T> { v: T } \
+                       func g<T>(x: T, n: i64) -> i64 { if n == 0 { 0 } else { g(W { v: x }, n - 1) } } \
+                       func main() -> i64 { g(1, 3) }";
+        let errors = monomorphize_errors(content);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(
+            errors[0].to_string(),
+            "reached the instantiation limit of 64 while instantiating `g`"
+        );
+        assert_eq!(
+            &content[errors[0].span().start..errors[0].span().end],
+            "g(W { v: x }, n - 1)"
+        );
+
+        // Deep but finite nesting is fine.
+        let content = "struct W<T> { v: T } \
+                       func a<T>(x: T) -> i64 { b(W { v: x }) } \
+                       func b<T>(x: T) -> i64 { 0 } \
+                       func main() -> i64 { a(1) }";
+        assert!(monomorphize_errors(content).is_empty());
+
+        // So is a chain of calls longer than the limit, as long as no
+        // function is instantiated from its own instances.
+        let mut content = "func main() -> i64 { f0(1) }".to_owned();
+        for i in 0..100 {
+            content += &format!(" func f{i}<T>(x: T) -> i64 {{ f{}(x) }}", i + 1);
+        }
+        content += " func f100<T>(x: T) -> i64 { 0 }";
+        assert!(monomorphize_errors(&content).is_empty());
+        assert_eq!(instances(&content).len(), 102);
+    }
+}
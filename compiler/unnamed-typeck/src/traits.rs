@@ -0,0 +1,605 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    mem,
+};
+
+use cranelift_entity::EntityList;
+use unnamed_ast::{
+    GenericParamEntity, Item, ItemEntity, TypeEntity,
+    expr::MethodCallExpr,
+    item::{FuncItem, ImplItem},
+    ty::TypeKind,
+};
+use unnamed_common::{Span, StrId};
+use unnamed_lexer::IntSuffix;
+use unnamed_resolve::{BuiltinTrait, Res};
+
+use crate::{
+    Checker, FnSig, TypeckResults,
+    error::ErrorKind,
+    ty::{InferTy, Subst, TyCtx, TyId, TyKind, TyList},
+};
+
+/// A trait declared in the module or built into the language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TraitId {
+    Item(ItemEntity),
+    Builtin(BuiltinTrait),
+}
+
+/// A trait that a type must implement, checked once the type is inferred.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Obligation {
+    pub ty: TyId,
+    pub trait_id: TraitId,
+    pub span: Span,
+}
+
+impl TypeckResults {
+    /// The methods called `name` that can be called on `ty`: its inherent
+    /// method if it has one, and otherwise the methods of every trait it
+    /// implements with that name. Methods of built-in traits are those of
+    /// the impl for `ty`.
+    pub fn lookup_method(&self, ty: TyId, name: StrId) -> Vec<ItemEntity> {
+        let kind = self.tcx[ty];
+        if let TyKind::Infer(InferTy::Var(_)) | TyKind::Error | TyKind::Never = kind {
+            return Vec::new();
+        }
+
+        if let Some(&method) = self.inherent_methods.get(&(method_key(kind), name)) {
+            return vec![method];
+        }
+
+        let methods = self.trait_methods.get(&name).map_or(&[][..], Vec::as_slice);
+        let methods = methods
+            .iter()
+            .copied()
+            .filter(|method| self.implements(ty, TraitId::Item(self.parents[method])));
+
+        let builtins = self.builtin_methods.iter();
+        let builtins = builtins
+            .filter(|&(_, &method)| method == name)
+            .filter_map(|(&trait_, _)| Some(self.operator_method(trait_, ty)?.0));
+
+        methods.chain(builtins).collect()
+    }
+
+    /// The method of the impl of the built-in `trait_` for `ty`, together
+    /// with the types the impl's type parameters stand for. Primitive types
+    /// implement the built-in traits without an impl.
+    pub fn operator_method(
+        &self,
+        trait_: BuiltinTrait,
+        ty: TyId,
+    ) -> Option<(ItemEntity, Vec<TyId>)> {
+        let (impl_item, args) = self.find_impl(TraitId::Builtin(trait_), ty)?;
+        let method = self.method(impl_item, self.builtin_methods[&trait_])?;
+
+        Some((method, args))
+    }
+
+    /// The method called `name` declared in the trait or impl `parent`.
+    pub fn method(&self, parent: ItemEntity, name: StrId) -> Option<ItemEntity> {
+        self.methods.get(&(parent, name)).copied()
+    }
+
+    /// Whether `ty` implements `trait_id`. Types that are not fully known
+    /// yet implement a trait if any of its impls could apply to them.
+    pub fn implements(&self, ty: TyId, trait_id: TraitId) -> bool {
+        match self.tcx[ty] {
+            TyKind::Param(param) => self
+                .bounds
+                .get(&param)
+                .is_some_and(|bounds| bounds.contains(&trait_id)),
+            TyKind::SelfTy(item) => trait_id == TraitId::Item(item),
+            TyKind::Infer(InferTy::Var(_)) | TyKind::Error | TyKind::Never => true,
+            _ => {
+                let primitive = match trait_id {
+                    TraitId::Builtin(trait_) => self.is_primitive_impl(ty, trait_),
+                    TraitId::Item(_) => false,
+                };
+                primitive || self.find_impl(trait_id, ty).is_some()
+            }
+        }
+    }
+
+    /// Whether `ty` is a primitive type supporting the operators of the
+    /// built-in `trait_`.
+    pub fn is_primitive_impl(&self, ty: TyId, trait_: BuiltinTrait) -> bool {
+        let kind = self.tcx[ty];
+        let is_int = matches!(kind, TyKind::Int(_) | TyKind::Infer(InferTy::Int(_)));
+        let is_float = matches!(kind, TyKind::Float(_) | TyKind::Infer(InferTy::Float(_)));
+
+        match trait_ {
+            BuiltinTrait::Add => is_int || is_float || kind == TyKind::Str,
+            BuiltinTrait::Sub | BuiltinTrait::Mul | BuiltinTrait::Div | BuiltinTrait::Neg => {
+                is_int || is_float
+            }
+            BuiltinTrait::BitAnd | BuiltinTrait::BitOr | BuiltinTrait::BitXor => {
+                is_int || kind == TyKind::Bool
+            }
+            BuiltinTrait::Shl | BuiltinTrait::Shr => is_int,
+            BuiltinTrait::Eq => match kind {
+                TyKind::Array(elem) => self.implements(elem, TraitId::Builtin(trait_)),
+                TyKind::Unit | TyKind::Bool | TyKind::Char | TyKind::Str => true,
+                _ => is_int || is_float,
+            },
+            BuiltinTrait::Ord => {
+                is_int || is_float || matches!(kind, TyKind::Bool | TyKind::Char | TyKind::Str)
+            }
+        }
+    }
+
+    /// Finds the impl of `trait_id` for `ty`, together with the types its
+    /// type parameters stand for.
+    pub fn find_impl(&self, trait_id: TraitId, ty: TyId) -> Option<(ItemEntity, Vec<TyId>)> {
+        let impls = self.trait_impls.get(&trait_id)?;
+
+        impls.iter().find_map(|&impl_item| {
+            let mut params = HashMap::new();
+            if !match_ty(&self.tcx, self.self_tys[&impl_item], ty, &mut params) {
+                return None;
+            }
+
+            let args = self.generics[&impl_item]
+                .iter()
+                .map(|param| params.get(param).copied())
+                .collect::<Option<Vec<_>>>()?;
+
+            let generics = self.generics[&impl_item].iter().zip(&args);
+            let bounds_hold = generics.into_iter().all(|(param, &arg)| {
+                let bounds = self.bounds.get(param).map_or(&[][..], Vec::as_slice);
+                bounds.iter().all(|&bound| self.implements(arg, bound))
+            });
+
+            bounds_hold.then_some((impl_item, args))
+        })
+    }
+}
+
+impl Checker<'_> {
+    /// Records the traits bounding each of `generics`.
+    pub(crate) fn bounds(&mut self, generics: EntityList<GenericParamEntity>) {
+        for &param in self.ctx.generic_params.list(generics) {
+            let bounds = self.ctx.types.list(self.ctx.generic_params[param].bounds);
+            let bounds = bounds
+                .iter()
+                .filter_map(|&bound| self.trait_ref(bound))
+                .collect();
+
+            self.results.bounds.insert(param, bounds);
+        }
+    }
+
+    /// Finds the trait named by a bound or by the trait of an impl.
+    pub(crate) fn trait_ref(&mut self, ty: TypeEntity) -> Option<TraitId> {
+        let span = self.ctx.types[ty].span;
+        let TypeKind::Named { name, args } = self.ctx.types[ty].kind else {
+            return None;
+        };
+
+        if !args.is_empty() {
+            self.error(span, ErrorKind::UnexpectedTypeArgs);
+        }
+
+        match self.resolution.types[ty]? {
+            Res::Trait(item) => Some(TraitId::Item(item)),
+            Res::BuiltinTrait(trait_) => Some(TraitId::Builtin(trait_)),
+            res => {
+                let kind = ErrorKind::NotATrait {
+                    what: res.description(),
+                    name: self.interner.get(name).to_owned(),
+                };
+                self.error(span, kind);
+                None
+            }
+        }
+    }
+
+    /// Adds a method to the methods of its trait or impl, returning whether
+    /// its name is still free.
+    pub(crate) fn define_method(&mut self, parent: ItemEntity, method: ItemEntity) -> bool {
+        let Item::Func(func) = self.ctx.items[method] else {
+            return false;
+        };
+
+        match self.results.methods.entry((parent, func.name)) {
+            Entry::Occupied(_) => {
+                let name = self.interner.get(func.name).to_owned();
+                self.error(func.name_span, ErrorKind::DuplicateMethod(name));
+                false
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(method);
+                true
+            }
+        }
+    }
+
+    /// Adds a method of an inherent impl to the methods of its type.
+    pub(crate) fn define_inherent_method(&mut self, self_ty: TyId, method: ItemEntity) {
+        let Item::Func(func) = self.ctx.items[method] else {
+            return;
+        };
+
+        let key = (method_key(self.results.tcx[self_ty]), func.name);
+        match self.results.inherent_methods.entry(key) {
+            Entry::Occupied(_) => {
+                let name = self.interner.get(func.name).to_owned();
+                self.error(func.name_span, ErrorKind::DuplicateMethod(name));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(method);
+            }
+        }
+    }
+
+    /// Checks that an impl is well-formed, and for trait impls that it
+    /// implements exactly the methods of the trait without overlapping
+    /// another impl.
+    pub(crate) fn check_impl(&mut self, item: ItemEntity, impl_item: ImplItem) {
+        let self_ty = self.results.self_tys[&item];
+
+        for &param in self.ctx.generic_params.list(impl_item.generics) {
+            if !contains_param(&self.results.tcx, self_ty, param) {
+                let param = self.ctx.generic_params[param];
+                let name = self.interner.get(param.name).to_owned();
+                self.error(param.span, ErrorKind::UnconstrainedParam(name));
+            }
+        }
+
+        let Some(&trait_id) = self.results.impl_traits.get(&item) else {
+            return;
+        };
+        let trait_name = self.trait_name(trait_id);
+
+        // The methods without a default body.
+        let required: Vec<_> = match trait_id {
+            TraitId::Item(trait_item) => {
+                let Item::Trait(trait_def) = self.ctx.items[trait_item] else {
+                    return;
+                };
+                let funcs = self.funcs(trait_def.items).into_iter();
+                let funcs = funcs.filter(|(_, func)| func.body.is_none());
+                funcs.map(|(_, func)| func.name).collect()
+            }
+            TraitId::Builtin(trait_) => vec![self.results.builtin_methods[&trait_]],
+        };
+
+        for (method, func) in self.funcs(impl_item.items) {
+            let expected = match trait_id {
+                TraitId::Item(trait_item) => self
+                    .results
+                    .method(trait_item, func.name)
+                    .map(|trait_method| self.trait_method_sig(trait_method, func, self_ty)),
+                TraitId::Builtin(trait_) => (func.name == required[0])
+                    .then(|| (Vec::new(), self.builtin_sig(trait_, self_ty))),
+            };
+
+            match expected {
+                Some((generics, expected)) => {
+                    self.compare_method(method, func, &generics, expected)
+                }
+                None => {
+                    let kind = ErrorKind::NotTraitMember {
+                        method: self.interner.get(func.name).to_owned(),
+                        trait_name: trait_name.clone(),
+                    };
+                    self.error(func.name_span, kind);
+                }
+            }
+        }
+
+        let header = Span::new(impl_item.span.start, self.ctx.types[impl_item.ty].span.end);
+        let missing: Vec<_> = required
+            .into_iter()
+            .filter(|&name| self.results.method(item, name).is_none())
+            .map(|name| self.interner.get(name).to_owned())
+            .collect();
+        if !missing.is_empty() {
+            self.error(header, ErrorKind::MissingTraitMethods(missing));
+        }
+
+        let earlier = self.results.trait_impls[&trait_id]
+            .iter()
+            .take_while(|&&other| other != item);
+        let mut conflicts = earlier.into_iter().any(|other| {
+            let other = self.results.self_tys[other];
+            overlap(&self.results.tcx, self_ty, other, &mut HashMap::new())
+        });
+        if let TraitId::Builtin(trait_) = trait_id {
+            conflicts |= self.results.is_primitive_impl(self_ty, trait_);
+        }
+        if conflicts {
+            let kind = ErrorKind::ConflictingImpls {
+                trait_name,
+                ty: self.display(self_ty),
+            };
+            self.error(header, kind);
+        }
+    }
+
+    /// The signature a method implementing `trait_method` must have, with
+    /// `Self` being the implementing type and the type parameters of the
+    /// trait method renamed to those of `func`. Also returns the type
+    /// parameters it must have.
+    fn trait_method_sig(
+        &mut self,
+        trait_method: ItemEntity,
+        func: FuncItem,
+        self_ty: TyId,
+    ) -> (Vec<GenericParamEntity>, FnSig) {
+        let own = self.ctx.generic_params.list(func.generics);
+        let trait_own = self.results.generics[&trait_method].clone();
+
+        let own_tys: Vec<_> = own
+            .iter()
+            .map(|&param| self.results.tcx.intern(TyKind::Param(param)))
+            .collect();
+        let subst = Subst::new(&trait_own, &own_tys, Some(self_ty));
+
+        let trait_sig = &self.results.signatures[&trait_method];
+        let (params, ret) = (trait_sig.params.clone(), trait_sig.ret);
+        let sig = FnSig {
+            params: params
+                .into_iter()
+                .map(|param| self.results.tcx.subst(param, &subst))
+                .collect(),
+            ret: self.results.tcx.subst(ret, &subst),
+        };
+
+        // Parameters the method has in excess are reported as a mismatch,
+        // while missing ones are shown with their name in the trait.
+        let mut generics: Vec<_> = own.iter().copied().take(trait_own.len()).collect();
+        generics.extend(trait_own.iter().skip(own.len()));
+
+        (generics, sig)
+    }
+
+    /// The signature of the method of a built-in trait implemented for
+    /// `self_ty`.
+    pub(crate) fn builtin_sig(&mut self, trait_: BuiltinTrait, self_ty: TyId) -> FnSig {
+        let tcx = &mut self.results.tcx;
+        let (params, ret) = match trait_ {
+            BuiltinTrait::Neg => (vec![self_ty], self_ty),
+            BuiltinTrait::Shl | BuiltinTrait::Shr => {
+                let amount = tcx.intern(TyKind::Int(IntSuffix::I64));
+                (vec![self_ty, amount], self_ty)
+            }
+            BuiltinTrait::Eq => (vec![self_ty, self_ty], tcx.bool()),
+            BuiltinTrait::Ord => {
+                let ordering = tcx.intern(TyKind::Int(IntSuffix::I64));
+                (vec![self_ty, self_ty], ordering)
+            }
+            _ => (vec![self_ty, self_ty], self_ty),
+        };
+
+        FnSig { params, ret }
+    }
+
+    /// Checks that a method of a trait impl has the signature and type
+    /// parameters of the trait method it implements.
+    fn compare_method(
+        &mut self,
+        method: ItemEntity,
+        func: FuncItem,
+        generics: &[GenericParamEntity],
+        expected: FnSig,
+    ) {
+        let own: Vec<_> = self.ctx.generic_params.list(func.generics).to_vec();
+        let found = self.results.signatures[&method].clone();
+
+        if own.len() != generics.len() || expected != found {
+            let kind = ErrorKind::IncompatibleMethod {
+                method: self.interner.get(func.name).to_owned(),
+                expected: self.fmt_sig(generics, &expected),
+                found: self.fmt_sig(&own, &found),
+            };
+            self.error(func.name_span, kind);
+        }
+    }
+
+    pub(crate) fn trait_name(&self, trait_id: TraitId) -> String {
+        match trait_id {
+            TraitId::Item(item) => match self.ctx.items[item] {
+                Item::Trait(trait_item) => self.interner.get(trait_item.name).to_owned(),
+                _ => String::new(),
+            },
+            TraitId::Builtin(trait_) => trait_.name().to_owned(),
+        }
+    }
+
+    /// Formats a signature as `fn<T>(A, B) -> C`.
+    fn fmt_sig(&mut self, generics: &[GenericParamEntity], sig: &FnSig) -> String {
+        let generics: Vec<_> = generics
+            .iter()
+            .map(|&param| self.interner.get(self.ctx.generic_params[param].name))
+            .collect();
+        let generics = if generics.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", generics.join(", "))
+        };
+
+        let params: Vec<_> = sig
+            .params
+            .iter()
+            .map(|&param| self.display(param))
+            .collect();
+        let ret = match self.results.tcx[sig.ret] {
+            TyKind::Unit => String::new(),
+            _ => format!(" -> {}", self.display(sig.ret)),
+        };
+
+        format!("fn{generics}({}){ret}", params.join(", "))
+    }
+
+    /// Looks up the method called by `call`, reporting when several traits
+    /// provide it. Calls of trait methods require the receiver to implement
+    /// the trait.
+    pub(crate) fn lookup_method(
+        &mut self,
+        receiver: TyId,
+        call: MethodCallExpr,
+    ) -> Option<ItemEntity> {
+        let methods = self.results.lookup_method(receiver, call.method);
+        let &method = methods.first()?;
+
+        if methods.len() > 1 {
+            let traits = methods
+                .iter()
+                .filter_map(|method| self.method_trait(*method))
+                .map(|trait_id| self.trait_name(trait_id))
+                .collect();
+            let kind = ErrorKind::AmbiguousMethod {
+                method: self.interner.get(call.method).to_owned(),
+                traits,
+            };
+            self.error(call.span, kind);
+        }
+
+        let parent = self.results.parents[&method];
+        if let Item::Trait(_) = self.ctx.items[parent] {
+            self.obligations.push(Obligation {
+                ty: receiver,
+                trait_id: TraitId::Item(parent),
+                span: call.span,
+            });
+        }
+
+        Some(method)
+    }
+
+    /// The trait a method is declared in or implements.
+    fn method_trait(&self, method: ItemEntity) -> Option<TraitId> {
+        let parent = self.results.parents[&method];
+        match self.ctx.items[parent] {
+            Item::Trait(_) => Some(TraitId::Item(parent)),
+            _ => self.results.impl_traits.get(&parent).copied(),
+        }
+    }
+
+    /// Requires the types standing for `params` to implement their bounds.
+    pub(crate) fn require_bounds(
+        &mut self,
+        params: &[GenericParamEntity],
+        args: &[TyId],
+        span: Span,
+    ) {
+        for (param, &ty) in params.iter().zip(args) {
+            let bounds = self.results.bounds.get(param).cloned().unwrap_or_default();
+            for trait_id in bounds {
+                self.obligations.push(Obligation { ty, trait_id, span });
+            }
+        }
+    }
+
+    /// Checks the obligations of the body being finished, whose types are
+    /// final.
+    pub(crate) fn check_obligations(&mut self) {
+        for obligation in mem::take(&mut self.obligations) {
+            let ty = self.infcx.resolve(&mut self.results.tcx, obligation.ty);
+            let (ty, _) = crate::complete(&mut self.results.tcx, ty);
+
+            if !self.results.implements(ty, obligation.trait_id) {
+                let kind = ErrorKind::Unimplemented {
+                    trait_name: self.trait_name(obligation.trait_id),
+                    ty: self.display(ty),
+                };
+                self.error(obligation.span, kind);
+            }
+        }
+    }
+}
+
+/// The key of the types in method tables, which ignores type arguments, so
+/// that the methods of `impl<T> Pair<T>` are found on every `Pair`.
+fn method_key(kind: TyKind) -> TyKind {
+    match kind {
+        TyKind::Adt(item, _) => TyKind::Adt(item, TyList::EMPTY),
+        kind => kind,
+    }
+}
+
+/// Binds the type parameters in `pattern` so that it becomes `ty`, returning
+/// whether that is possible. Unknown parts of `ty` match anything.
+fn match_ty(
+    tcx: &TyCtx,
+    pattern: TyId,
+    ty: TyId,
+    params: &mut HashMap<GenericParamEntity, TyId>,
+) -> bool {
+    let is_unknown = |ty: TyId| matches!(tcx[ty], TyKind::Infer(_) | TyKind::Error);
+
+    match (tcx[pattern], tcx[ty]) {
+        (TyKind::Param(param), _) => match params.get(&param) {
+            Some(&bound) => bound == ty || is_unknown(bound) || is_unknown(ty),
+            None => {
+                params.insert(param, ty);
+                true
+            }
+        },
+        (_, TyKind::Infer(InferTy::Var(_)) | TyKind::Error) => true,
+        (TyKind::Int(_), TyKind::Infer(InferTy::Int(_)))
+        | (TyKind::Float(_), TyKind::Infer(InferTy::Float(_))) => true,
+        (TyKind::Array(pattern), TyKind::Array(ty)) => match_ty(tcx, pattern, ty, params),
+        (TyKind::Adt(pattern_item, patterns), TyKind::Adt(item, tys)) => {
+            pattern_item == item
+                && tcx
+                    .list(patterns)
+                    .iter()
+                    .zip(tcx.list(tys))
+                    .all(|(&pattern, &ty)| match_ty(tcx, pattern, ty, params))
+        }
+        (pattern, ty) => pattern == ty,
+    }
+}
+
+/// Whether some type is an instance of both `a` and `b`, whose type
+/// parameters can stand for anything.
+fn overlap(tcx: &TyCtx, a: TyId, b: TyId, params: &mut HashMap<GenericParamEntity, TyId>) -> bool {
+    let resolve = |params: &HashMap<_, _>, mut ty: TyId| {
+        while let TyKind::Param(param) = tcx[ty]
+            && let Some(&bound) = params.get(&param)
+        {
+            ty = bound;
+        }
+        ty
+    };
+
+    let (a, b) = (resolve(params, a), resolve(params, b));
+    if a == b {
+        return true;
+    }
+
+    match (tcx[a], tcx[b]) {
+        (TyKind::Param(param), _) => {
+            params.insert(param, b);
+            true
+        }
+        (_, TyKind::Param(param)) => {
+            params.insert(param, a);
+            true
+        }
+        (TyKind::Array(a), TyKind::Array(b)) => overlap(tcx, a, b, params),
+        (TyKind::Adt(a_item, a_args), TyKind::Adt(b_item, b_args)) => {
+            a_item == b_item
+                && tcx
+                    .list(a_args)
+                    .iter()
+                    .zip(tcx.list(b_args))
+                    .all(|(&a, &b)| overlap(tcx, a, b, params))
+        }
+        _ => false,
+    }
+}
+
+fn contains_param(tcx: &TyCtx, ty: TyId, param: GenericParamEntity) -> bool {
+    match tcx[ty] {
+        TyKind::Param(other) => other == param,
+        _ => tcx
+            .inner(ty)
+            .into_iter()
+            .any(|inner| contains_param(tcx, inner, param)),
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    ops::Index,
+};
+
+use cranelift_entity::{PrimaryMap, entity_impl};
+use unnamed_ast::{AstCtx, GenericParamEntity, Item, ItemEntity};
+use unnamed_common::Interner;
+use unnamed_lexer::{FloatSuffix, IntSuffix};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TyId(u32);
+entity_impl!(TyId);
+
+/// An interned list of types, like the type arguments of a struct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TyList(u32);
+entity_impl!(TyList);
+
+impl TyList {
+    /// The empty list, which every context interns first.
+    pub const EMPTY: TyList = TyList(0);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InferVar(u32);
+entity_impl!(InferVar);
+
+/// A type that is not known yet. Integer and float variables can only be
+/// bound to integer and float types respectively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InferTy {
+    Var(InferVar),
+    Int(InferVar),
+    Float(InferVar),
+}
+
+impl InferTy {
+    pub fn var(self) -> InferVar {
+        match self {
+            InferTy::Var(var) | InferTy::Int(var) | InferTy::Float(var) => var,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TyKind {
+    Unit,
+    Bool,
+    Int(IntSuffix),
+    Float(FloatSuffix),
+    Char,
+    Str,
+    Array(TyId),
+    /// A struct or enum together with its type arguments.
+    Adt(ItemEntity, TyList),
+    /// A type parameter, inside the item declaring it.
+    Param(GenericParamEntity),
+    /// The implementing type inside the declaration of a trait.
+    SelfTy(ItemEntity),
+    /// The type of expressions that never produce a value, like `return`.
+    Never,
+    Infer(InferTy),
+    /// The type of an expression that failed to check. It is compatible with
+    /// every other type, so that each mistake is reported once.
+    Error,
+}
+
+/// What the type parameters of an item, and the `Self` of a trait, stand for
+/// in one use of it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subst {
+    pub params: HashMap<GenericParamEntity, TyId>,
+    pub self_ty: Option<TyId>,
+}
+
+impl Subst {
+    pub fn new(params: &[GenericParamEntity], args: &[TyId], self_ty: Option<TyId>) -> Self {
+        Self {
+            params: params.iter().copied().zip(args.iter().copied()).collect(),
+            self_ty,
+        }
+    }
+}
+
+/// Interns types, so that equal types have the same id.
+#[derive(Debug)]
+pub struct TyCtx {
+    kinds: PrimaryMap<TyId, TyKind>,
+    interned: HashMap<TyKind, TyId>,
+    lists: PrimaryMap<TyList, Vec<TyId>>,
+    interned_lists: HashMap<Vec<TyId>, TyList>,
+}
+
+impl Default for TyCtx {
+    fn default() -> Self {
+        let mut tcx = Self {
+            kinds: PrimaryMap::new(),
+            interned: HashMap::new(),
+            lists: PrimaryMap::new(),
+            interned_lists: HashMap::new(),
+        };
+        tcx.intern_list(&[]);
+
+        tcx
+    }
+}
+
+impl TyCtx {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&mut self, kind: TyKind) -> TyId {
+        if let Some(&ty) = self.interned.get(&kind) {
+            return ty;
+        }
+
+        let ty = self.kinds.push(kind);
+        self.interned.insert(kind, ty);
+
+        ty
+    }
+
+    pub fn intern_list(&mut self, tys: &[TyId]) -> TyList {
+        if let Some(&list) = self.interned_lists.get(tys) {
+            return list;
+        }
+
+        let list = self.lists.push(tys.to_vec());
+        self.interned_lists.insert(tys.to_vec(), list);
+
+        list
+    }
+
+    pub fn list(&self, list: TyList) -> &[TyId] {
+        &self.lists[list]
+    }
+
+    pub fn unit(&mut self) -> TyId {
+        self.intern(TyKind::Unit)
+    }
+
+    pub fn bool(&mut self) -> TyId {
+        self.intern(TyKind::Bool)
+    }
+
+    pub fn never(&mut self) -> TyId {
+        self.intern(TyKind::Never)
+    }
+
+    pub fn error(&mut self) -> TyId {
+        self.intern(TyKind::Error)
+    }
+
+    /// Rebuilds `ty` with `f` applied to each type directly inside it.
+    pub fn map_inner(&mut self, ty: TyId, f: &mut dyn FnMut(&mut Self, TyId) -> TyId) -> TyId {
+        match self[ty] {
+            TyKind::Array(elem) => {
+                let elem = f(self, elem);
+                self.intern(TyKind::Array(elem))
+            }
+            TyKind::Adt(item, args) => {
+                let args: Vec<_> = self.list(args).to_vec();
+                let args: Vec<_> = args.into_iter().map(|arg| f(self, arg)).collect();
+                let args = self.intern_list(&args);
+                self.intern(TyKind::Adt(item, args))
+            }
+            _ => ty,
+        }
+    }
+
+    /// The types directly inside `ty`.
+    pub fn inner(&self, ty: TyId) -> Vec<TyId> {
+        match self[ty] {
+            TyKind::Array(elem) => vec![elem],
+            TyKind::Adt(_, args) => self.list(args).to_vec(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Replaces the type parameters and `Self` in `ty` by what they stand
+    /// for in `subst`.
+    pub fn subst(&mut self, ty: TyId, subst: &Subst) -> TyId {
+        match self[ty] {
+            TyKind::Param(param) => subst.params.get(&param).copied().unwrap_or(ty),
+            TyKind::SelfTy(_) => subst.self_ty.unwrap_or(ty),
+            _ => self.map_inner(ty, &mut |tcx, ty| tcx.subst(ty, subst)),
+        }
+    }
+
+    /// Returns a wrapper that formats the type the way it is written in the
+    /// source.
+    pub fn display<'a>(
+        &'a self,
+        ty: TyId,
+        ctx: &'a AstCtx,
+        interner: &'a Interner,
+    ) -> DisplayTy<'a> {
+        DisplayTy {
+            tcx: self,
+            ty,
+            ctx,
+            interner,
+        }
+    }
+}
+
+impl TyCtx {
+    /// Formats `<A, B>`, or nothing for an empty list.
+    pub fn fmt_list(
+        &self,
+        tys: &[TyId],
+        ctx: &AstCtx,
+        interner: &Interner,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        if tys.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str("<")?;
+        for (i, &ty) in tys.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", self.display(ty, ctx, interner))?;
+        }
+        f.write_str(">")
+    }
+}
+
+impl Index<TyId> for TyCtx {
+    type Output = TyKind;
+
+    fn index(&self, ty: TyId) -> &TyKind {
+        &self.kinds[ty]
+    }
+}
+
+pub struct DisplayTy<'a> {
+    tcx: &'a TyCtx,
+    ty: TyId,
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+}
+
+impl Display for DisplayTy<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |item: ItemEntity| match self.ctx.items[item] {
+            Item::Struct(struct_item) => self.interner.get(struct_item.name),
+            Item::Enum(enum_item) => self.interner.get(enum_item.name),
+            Item::Trait(trait_item) => self.interner.get(trait_item.name),
+            Item::Func(func) => self.interner.get(func.name),
+            Item::Impl(_) => "impl",
+        };
+
+        match self.tcx[self.ty] {
+            TyKind::Unit => f.write_str("()"),
+            TyKind::Bool => f.write_str("bool"),
+            TyKind::Int(ty) => f.write_str(ty.as_str()),
+            TyKind::Float(ty) => f.write_str(ty.as_str()),
+            TyKind::Char => f.write_str("char"),
+            TyKind::Str => f.write_str("str"),
+            TyKind::Array(elem) => {
+                let elem = self.tcx.display(elem, self.ctx, self.interner);
+                write!(f, "[{elem}]")
+            }
+            TyKind::Adt(item, args) => {
+                f.write_str(name(item))?;
+                self.tcx
+                    .fmt_list(self.tcx.list(args), self.ctx, self.interner, f)
+            }
+            TyKind::Param(param) => {
+                f.write_str(self.interner.get(self.ctx.generic_params[param].name))
+            }
+            TyKind::SelfTy(_) => f.write_str("Self"),
+            TyKind::Never => f.write_str("!"),
+            TyKind::Infer(InferTy::Var(_)) => f.write_str("_"),
+            TyKind::Infer(InferTy::Int(_)) => f.write_str("{integer}"),
+            TyKind::Infer(InferTy::Float(_)) => f.write_str("{float}"),
+            TyKind::Error => f.write_str("{error}"),
+        }
+    }
+}
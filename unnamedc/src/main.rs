@@ -1 +1,407 @@
-fn main() {}
+mod session;
+
+use std::{
+    fs,
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use unnamed_ast::printer;
+use unnamed_codegen::Program;
+use unnamed_common::{Interner, Source, Spanned};
+use unnamed_interpreter::{Interpreter, Value};
+use unnamed_lexer::Lexer;
+use unnamed_parser::ParsedModule;
+use unnamed_typeck::{Instances, TypeckResults};
+
+use crate::session::Session;
+
+#[derive(Parser, Debug)]
+#[command(name = "unnamedc", version, about = "The unnamed language compiler")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Analyze a file and report errors without producing any output.
+    Check(CompileArgs),
+    /// Compile a file to a native executable.
+    Build {
+        #[command(flatten)]
+        args: CompileArgs,
+        /// Where to write the executable. Defaults to the file name without
+        /// its extension.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Compile a file and run its `main` function.
+    Run {
+        #[command(flatten)]
+        args: CompileArgs,
+        /// Compile the program into memory and run the generated code
+        /// instead of interpreting it.
+        #[arg(long)]
+        jit: bool,
+    },
+}
+
+impl Command {
+    fn args(&self) -> &CompileArgs {
+        match self {
+            Command::Check(args) | Command::Build { args, .. } | Command::Run { args, .. } => args,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+struct CompileArgs {
+    /// The source file to compile.
+    file: PathBuf,
+    /// Print intermediate representations to stdout.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    emit: Vec<Emit>,
+    /// How much to optimize the mid-level IR, which native code is
+    /// generated from.
+    #[arg(short = 'O', value_enum, default_value = "1")]
+    opt_level: OptLevel,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OptLevel {
+    /// No optimizations.
+    #[value(name = "0")]
+    O0,
+    /// Constant and branch folding, and removal of dead code.
+    #[value(name = "1")]
+    O1,
+    /// Also removal of dead stores, repeated until nothing changes.
+    #[value(name = "2")]
+    O2,
+}
+
+impl From<OptLevel> for unnamed_mir::OptLevel {
+    fn from(level: OptLevel) -> Self {
+        match level {
+            OptLevel::O0 => unnamed_mir::OptLevel::O0,
+            OptLevel::O1 => unnamed_mir::OptLevel::O1,
+            OptLevel::O2 => unnamed_mir::OptLevel::O2,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Emit {
+    /// The tokens produced by the lexer.
+    Tokens,
+    /// The syntax tree produced by the parser.
+    Ast,
+    /// The typed high-level IR lowered from the syntax tree.
+    Hir,
+    /// The concrete functions produced by monomorphization.
+    Instances,
+    /// The control-flow graph of every concrete function.
+    Mir,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let args = cli.command.args();
+
+    let path = args.file.to_string_lossy();
+    let content = match fs::read_to_string(&args.file) {
+        Ok(content) => content,
+        Err(error) => {
+            eprintln!("error: cannot read `{path}`: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let source = Source::new(&content, &path);
+    let interner = Interner::new();
+    let mut session = Session::new(source);
+
+    let code = compile(&cli.command, &mut session, &interner);
+    if session.has_errors() {
+        let count = session.error_count();
+        let plural = if count == 1 { "" } else { "s" };
+        eprintln!("error: could not compile `{path}` due to {count} previous error{plural}");
+
+        return ExitCode::FAILURE;
+    }
+
+    if session.failed_at_run_time() {
+        eprintln!("error: `{path}` failed at run time");
+    }
+
+    code
+}
+
+fn compile(command: &Command, session: &mut Session, interner: &Interner) -> ExitCode {
+    let args = command.args();
+
+    if args.emit.contains(&Emit::Tokens) {
+        emit_tokens(session.source);
+    }
+
+    let parsed = unnamed_parser::parse(session.source, interner);
+    session.report_all(parsed.errors.iter().cloned());
+
+    if args.emit.contains(&Emit::Ast) {
+        emit_ast(&parsed, interner);
+    }
+
+    if session.has_errors() {
+        return ExitCode::FAILURE;
+    }
+
+    let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, interner);
+    session.report_all(resolution.errors.iter().cloned());
+
+    if session.has_errors() {
+        return ExitCode::FAILURE;
+    }
+
+    let mut typeck = unnamed_typeck::check(&parsed.ctx, &parsed.module, interner, &resolution);
+    session.report_all(mem::take(&mut typeck.errors));
+
+    if session.has_errors() {
+        return ExitCode::FAILURE;
+    }
+
+    // Native code is generated from the optimized MIR.
+    let generates_code = matches!(
+        command,
+        Command::Build { .. } | Command::Run { jit: true, .. }
+    );
+    let needs_mir = generates_code || args.emit.contains(&Emit::Mir);
+
+    // The interpreter evaluates the HIR too.
+    let needs_hir =
+        needs_mir || args.emit.contains(&Emit::Hir) || matches!(command, Command::Run { .. });
+    let hir = needs_hir
+        .then(|| unnamed_hir::lower(&parsed.ctx, &parsed.module, &resolution, &mut typeck));
+
+    if args.emit.contains(&Emit::Hir)
+        && let Some(hir) = &hir
+    {
+        print!(
+            "{}",
+            unnamed_hir::printer::print_hir(hir, &parsed.ctx, interner, &typeck)
+        );
+    }
+
+    // Monomorphization can fail on programs that type-check, so it runs for
+    // every command for `check` to report everything `build` would.
+    let mut instances = unnamed_typeck::monomorphize(
+        &parsed.ctx,
+        &parsed.module,
+        interner,
+        &resolution,
+        &mut typeck,
+    );
+    session.report_all(mem::take(&mut instances.errors));
+
+    if session.has_errors() {
+        return ExitCode::FAILURE;
+    }
+
+    if args.emit.contains(&Emit::Instances) {
+        emit_instances(&instances, &parsed, interner, &mut typeck);
+    }
+
+    let mir = match &hir {
+        Some(hir) if needs_mir => {
+            let mut mir = unnamed_mir::lower(hir, &instances, &mut typeck);
+            unnamed_mir::optimize(&mut mir, args.opt_level.into(), interner, &typeck);
+
+            // Debug builds of the compiler check every MIR they generate
+            // code from, so that a broken pass is caught before codegen.
+            if cfg!(debug_assertions) || args.emit.contains(&Emit::Mir) {
+                session.report_all(unnamed_mir::verify(
+                    &mir,
+                    &parsed.ctx,
+                    interner,
+                    &mut typeck,
+                ));
+            }
+
+            if args.emit.contains(&Emit::Mir) {
+                print!(
+                    "{}",
+                    unnamed_mir::printer::print_mir(
+                        &mir,
+                        &instances,
+                        &parsed.ctx,
+                        interner,
+                        &mut typeck
+                    )
+                );
+            }
+
+            Some(mir)
+        }
+        _ => None,
+    };
+
+    if session.has_errors() {
+        return ExitCode::FAILURE;
+    }
+
+    match command {
+        Command::Check(_) => ExitCode::SUCCESS,
+        Command::Build { args, output } => {
+            let program = Program {
+                ctx: &parsed.ctx,
+                module: &parsed.module,
+                interner,
+                source: session.source,
+                resolution: &resolution,
+                results: &mut typeck,
+                instances: &instances,
+                mir: mir.as_ref().expect("the MIR is lowered for builds"),
+            };
+            let output = output
+                .clone()
+                .unwrap_or_else(|| args.file.with_extension(""));
+            build(session, program, &output)
+        }
+        Command::Run { jit: true, .. } => {
+            let program = Program {
+                ctx: &parsed.ctx,
+                module: &parsed.module,
+                interner,
+                source: session.source,
+                resolution: &resolution,
+                results: &mut typeck,
+                instances: &instances,
+                mir: mir.as_ref().expect("the MIR is lowered for the JIT"),
+            };
+            run_jit(session, program)
+        }
+        Command::Run { .. } => {
+            let hir = hir
+                .as_ref()
+                .expect("the HIR is lowered for the interpreter");
+            let mut interpreter = Interpreter::new(
+                &parsed.ctx,
+                &parsed.module,
+                hir,
+                &instances,
+                &mut typeck,
+                interner,
+                io::stdout(),
+            );
+            run(session, &mut interpreter)
+        }
+    }
+}
+
+/// Compiles the module to an object file next to `output` and links it into
+/// an executable with the system C compiler.
+fn build(session: &mut Session, program: Program, output: &Path) -> ExitCode {
+    let object = match unnamed_codegen::emit_object(program) {
+        Ok(object) => object,
+        Err(error) => {
+            session.report(error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let object_path = output.with_extension("o");
+    if let Err(error) = fs::write(&object_path, object) {
+        eprintln!("error: cannot write `{}`: {error}", object_path.display());
+        return ExitCode::FAILURE;
+    }
+
+    let linked = unnamed_codegen::link::link(&object_path, output);
+    let _ = fs::remove_file(&object_path);
+
+    match linked {
+        Ok(linked) if linked.status.success() => ExitCode::SUCCESS,
+        Ok(linked) => {
+            eprintln!("error: linking `{}` failed", output.display());
+            eprint!("{}", String::from_utf8_lossy(&linked.stderr));
+            ExitCode::FAILURE
+        }
+        Err(error) => {
+            eprintln!("error: cannot run the C compiler to link: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Compiles the module into memory and calls its `main` function, exiting
+/// with the same code as the executable built from it.
+fn run_jit(session: &mut Session, program: Program) -> ExitCode {
+    let result = unnamed_codegen::jit(program).and_then(|program| program.run_main());
+
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(error) => {
+            session.report(error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Interprets the module, exiting with the value returned by `main` if it is
+/// an integer.
+fn run(session: &mut Session, interpreter: &mut Interpreter<impl Write>) -> ExitCode {
+    match interpreter.run_main() {
+        Ok(Value::Int(int)) => ExitCode::from(int.value as u8),
+        Ok(_) => ExitCode::SUCCESS,
+        Err(error) => {
+            session.report_runtime(error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints one line per token with its position, kind and text. Invalid
+/// tokens are listed with their error, which the parser reports as well.
+fn emit_tokens(source: Source) {
+    for token in Lexer::new(source) {
+        let (span, kind, text) = match token {
+            Ok(token) => (
+                token.span,
+                format!("{:?}", token.kind),
+                format!("{:?}", token.slice),
+            ),
+            Err(error) => (
+                error.span(),
+                "Error".to_owned(),
+                format!("{:?}", error.kind()),
+            ),
+        };
+
+        let position = source.position(span);
+        let position = format!("{}:{}", position.line, position.column);
+        println!("{position:<8} {kind:<16} {text}");
+    }
+}
+
+fn emit_ast(parsed: &ParsedModule, interner: &Interner) {
+    print!(
+        "{}",
+        printer::print_module(&parsed.ctx, interner, &parsed.module)
+    );
+}
+
+fn emit_instances(
+    instances: &Instances,
+    parsed: &ParsedModule,
+    interner: &Interner,
+    typeck: &mut TypeckResults,
+) {
+    for instance in instances.instances.keys() {
+        println!(
+            "{}",
+            instances.display(instance, &parsed.ctx, interner, typeck)
+        );
+    }
+}
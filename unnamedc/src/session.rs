@@ -0,0 +1,58 @@
+use unnamed_common::{IntoReport, Source, SourceCache};
+
+/// The source file being compiled, together with the diagnostics reported
+/// for it so far.
+pub struct Session<'s> {
+    pub source: Source<'s>,
+    cache: SourceCache<'s>,
+    error_count: usize,
+    failed_at_run_time: bool,
+}
+
+impl<'s> Session<'s> {
+    pub fn new(source: Source<'s>) -> Self {
+        let mut cache = SourceCache::new();
+        cache.insert(source);
+
+        Self {
+            source,
+            cache,
+            error_count: 0,
+            failed_at_run_time: false,
+        }
+    }
+
+    /// Prints `error` to stderr and records that compilation failed.
+    pub fn report(&mut self, error: impl IntoReport) {
+        self.error_count += 1;
+
+        // There is nowhere left to report a failure to write to stderr.
+        let _ = error.into_report(self.source).eprint(&self.cache);
+    }
+
+    /// Prints `error` to stderr and records that the program stopped with
+    /// it while running, after compiling successfully.
+    pub fn report_runtime(&mut self, error: impl IntoReport) {
+        self.failed_at_run_time = true;
+
+        let _ = error.into_report(self.source).eprint(&self.cache);
+    }
+
+    pub fn report_all(&mut self, errors: impl IntoIterator<Item = impl IntoReport>) {
+        for error in errors {
+            self.report(error);
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count > 0
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.error_count
+    }
+
+    pub fn failed_at_run_time(&self) -> bool {
+        self.failed_at_run_time
+    }
+}
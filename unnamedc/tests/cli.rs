@@ -0,0 +1,132 @@
+//! Runs the compiler binary on small programs and checks how it exits.
+
+use std::{
+    fs,
+    process::{Command, Output},
+};
+
+/// Writes `content` to a file named after `name` and runs the compiler with
+/// `args` followed by its path.
+fn unnamedc(name: &str, content: &str, args: &[&str]) -> Output {
+    let path = std::env::temp_dir().join(format!("unnamedc-{}-{name}.un", std::process::id()));
+    fs::write(&path, content).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_unnamedc"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .unwrap();
+
+    let _ = fs::remove_file(&path);
+    output
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_check_valid() {
+    let output = unnamedc("check-valid", "func main() -> i64 { 1 + 2 }", &["check"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn test_check_invalid() {
+    let output = unnamedc("check-invalid", "func main() -> i64 { true }", &["check"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("could not compile"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn test_check_instantiation_limit() {
+    let content = "struct W<T> { v: T } \
+                   func g<T>(x: T, n: i64) -> i64 { if n == 0 { 0 } else { g(W { v: x }, n - 1) } } \
+                   func main() -> i64 { g(1, 3) }";
+    let output = unnamedc("check-instantiation-limit", content, &["check"]);
+    let stderr = stderr(&output);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("reached the instantiation limit of 64 while instantiating `g`"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn test_run_exit_code() {
+    let output = unnamedc("run-exit-code", "func main() -> i64 { 40 + 2 }", &["run"]);
+    assert_eq!(output.status.code(), Some(42), "{}", stderr(&output));
+}
+
+#[test]
+fn test_run_runtime_error() {
+    let content = "func div(a: i64, b: i64) -> i64 { a / b } func main() -> i64 { div(1, 0) }";
+    let output = unnamedc("run-runtime-error", content, &["run"]);
+    let stderr = stderr(&output);
+    assert_eq!(output.status.code(), Some(1), "{stderr}");
+    assert!(stderr.contains("failed at run time"), "{stderr}");
+    assert!(!stderr.contains("could not compile"), "{stderr}");
+}
+
+#[test]
+fn test_run_matches_jit() {
+    let content = "func add<T: Add>(a: T, b: T) -> T { a + b }
+        func main() -> i64 {
+            let x: u64 = 18446744073709551615;
+            let f: f32 = 0.1;
+            println(x, f + 0.2, add(16777216.0f32, 1.0), add(1.0, 1e-8));
+            let y: u8 = 200;
+            println(add(y, 55));
+            7
+        }";
+    let interpreted = unnamedc("run-matches-jit", content, &["run"]);
+    let compiled = unnamedc("run-matches-jit", content, &["run", "--jit"]);
+    assert_eq!(
+        interpreted.status.code(),
+        Some(7),
+        "{}",
+        stderr(&interpreted)
+    );
+    assert_eq!(compiled.status.code(), Some(7), "{}", stderr(&compiled));
+    assert_eq!(
+        String::from_utf8_lossy(&interpreted.stdout),
+        "18446744073709551615 0.3 16777216.0 1.00000001\n255\n"
+    );
+    assert_eq!(interpreted.stdout, compiled.stdout);
+
+    let content = "func main() -> i64 { let x: u8 = 200; println(x + 100); 0 }";
+    for args in [&["run"][..], &["run", "--jit"]] {
+        let output = unnamedc("run-overflow", content, args);
+        let stderr = stderr(&output);
+        assert!(!output.status.success(), "{stderr}");
+        assert!(
+            stderr.contains("attempt to compute a value that overflows its type"),
+            "{stderr}"
+        );
+    }
+}
+
+#[test]
+fn test_run_jit_opt_levels() {
+    let content =
+        "func sum(n: i64) -> i64 { let s = 0; let i = 0; while i < n { i = i + 1; s = s + i; } s }
+        func main() -> i64 { sum(8) }";
+    for level in ["-O0", "-O1", "-O2"] {
+        let output = unnamedc("run-jit", content, &["run", "--jit", level]);
+        assert_eq!(output.status.code(), Some(36), "{}", stderr(&output));
+    }
+}
+
+#[test]
+fn test_run_jit_stack_overflow() {
+    let content = "func f(n: i64) -> i64 { f(n + 1) + 1 } func main() -> i64 { f(0) }";
+    let output = unnamedc("run-jit-stack-overflow", content, &["run", "--jit"]);
+    let stderr = stderr(&output);
+    assert_eq!(output.status.code(), Some(101), "{stderr}");
+    assert!(stderr.starts_with("error: stack overflow"), "{stderr}");
+}
@@ -0,0 +1,1395 @@
+//! The canonical formatter for unnamed source files.
+//!
+//! The file is parsed as usual and re-printed from its syntax tree. Comments
+//! are not part of the tree, so they are taken from a lexer that keeps them
+//! as tokens and written back before the first statement, item, member or
+//! list element that follows them, or after the one they follow on its
+//! line. Block comments within a type, a parameter, a `let` initializer or
+//! a `return` stay inline before the token that follows them. Blank lines
+//! between statements and members are kept, collapsed to a single one.
+
+use cranelift_entity::EntityList;
+use unnamed_ast::{
+    AstCtx, Block, ExprEntity, FieldDefEntity, FieldEntity, GenericParamEntity, Item, ItemEntity,
+    Module, ParamEntity, PatEntity, TypeEntity,
+    expr::{ArrayExprKind, ElseExprKind, Expr, MatchExpr},
+    item::VariantKind,
+    pat::{Pat, PatFields},
+    ty::TypeKind,
+};
+use unnamed_common::{Interner, Source, Span, Spanned};
+use unnamed_lexer::{Lexer, TokenKind};
+
+/// The maximum width of a line, which the formatter only exceeds when a
+/// single element does not fit on its own.
+pub const MAX_WIDTH: usize = 100;
+
+const INDENT: usize = 4;
+
+/// Formats a whole source file. Files with syntax errors are not formatted,
+/// and the errors are returned instead.
+pub fn format(source: Source) -> Result<String, Vec<unnamed_parser::Error>> {
+    let interner = Interner::new();
+    let parsed = unnamed_parser::parse(source, &interner);
+    if !parsed.errors.is_empty() {
+        return Err(parsed.errors);
+    }
+
+    let mut formatter = Formatter::new(source, &parsed.ctx, &interner);
+    Ok(formatter.module(&parsed.module))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Comment<'s> {
+    span: Span,
+    text: &'s str,
+}
+
+/// One element of a list that is broken over several lines.
+#[derive(Debug, Clone, Copy)]
+enum ListItem {
+    Expr(ExprEntity),
+    Field(FieldEntity),
+    Param(ParamEntity),
+}
+
+struct Formatter<'a> {
+    source: Source<'a>,
+    ctx: &'a AstCtx,
+    interner: &'a Interner,
+    comments: Vec<Comment<'a>>,
+    /// Index of the first comment that has not been written yet.
+    next_comment: usize,
+    /// Set while formatting the condition of an `if` or `while`, where
+    /// struct literals must be parenthesized.
+    in_condition: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: Source<'a>, ctx: &'a AstCtx, interner: &'a Interner) -> Self {
+        let comments = Lexer::with_comments(source)
+            .flatten()
+            .filter(|token| {
+                matches!(
+                    token.kind,
+                    TokenKind::LineComment
+                        | TokenKind::BlockComment
+                        | TokenKind::DocComment
+                        | TokenKind::InnerDocComment
+                )
+            })
+            .map(|token| Comment {
+                span: token.span,
+                text: token.slice.trim_end(),
+            })
+            .collect();
+
+        Self {
+            source,
+            ctx,
+            interner,
+            comments,
+            next_comment: 0,
+            in_condition: false,
+        }
+    }
+
+    fn module(&mut self, module: &Module) -> String {
+        let mut out = String::new();
+        let mut prev_end = None;
+
+        for (i, &item) in self.ctx.items.list(module.items).iter().enumerate() {
+            let span = self.ctx.items[item].span();
+            self.element_start(&mut out, span.start, 0, &mut prev_end, i > 0);
+
+            let text = self.item(item);
+            out.push_str(&text);
+            self.element_end(&mut out, span.end, &mut prev_end);
+        }
+
+        self.comments_before(&mut out, usize::MAX, 0, &mut prev_end);
+        out
+    }
+
+    /// Writes the comments before an element starting at `start`, and the
+    /// indentation of the element itself. Blank lines from the source are
+    /// kept, and one is always added when `separate` is set.
+    fn element_start(
+        &mut self,
+        out: &mut String,
+        start: usize,
+        indent: usize,
+        prev_end: &mut Option<usize>,
+        separate: bool,
+    ) {
+        if separate {
+            out.push('\n');
+            *prev_end = None;
+        }
+
+        self.comments_before(out, start, indent, prev_end);
+        if prev_end.is_some_and(|end| self.has_blank_line(end, start)) {
+            out.push('\n');
+        }
+
+        push_indent(out, indent);
+    }
+
+    /// Ends the line of an element ending at `end`, moving a comment that
+    /// follows it on the same source line to the end of the line.
+    fn element_end(&mut self, out: &mut String, end: usize, prev_end: &mut Option<usize>) {
+        *prev_end = Some(self.trailing_comment(out, end).unwrap_or(end));
+        out.push('\n');
+    }
+
+    /// Appends the comment that follows `end` on the same source line, if
+    /// any, returning where it ends. Only a separator may come between them,
+    /// and nothing may follow the comment on its line, as a comment before
+    /// more code belongs to that code.
+    fn trailing_comment(&mut self, out: &mut String, end: usize) -> Option<usize> {
+        let comment = self.comments.get(self.next_comment).copied()?;
+        if comment.span.start < end {
+            return None;
+        }
+
+        let content = self.source.content;
+        let between = &content[end..comment.span.start];
+        let after = content[comment.span.end..].split('\n').next().unwrap_or("");
+        if between.contains('\n')
+            || !matches!(between.trim(), "" | "," | ";")
+            || !after.trim().is_empty()
+        {
+            return None;
+        }
+
+        out.push(' ');
+        out.push_str(comment.text);
+        self.next_comment += 1;
+
+        Some(comment.span.end)
+    }
+
+    /// Takes the remaining block comments between the previous token and
+    /// `pos`, to write them inline next to the token at `pos`. A line
+    /// comment ends its line, so it stops the run and is left to the element
+    /// it is in.
+    fn inline_comments(&mut self, pos: usize) -> Vec<&'a str> {
+        let start = self.trivia_start(pos);
+        let mut texts = Vec::new();
+        while let Some(comment) = self.comments.get(self.next_comment).copied()
+            && comment.span.start < pos
+            && comment.span.start >= start
+            && comment.text.starts_with("/*")
+        {
+            texts.push(comment.text);
+            self.next_comment += 1;
+        }
+
+        texts
+    }
+
+    /// The start of the whitespace and comments that come right before
+    /// `pos`.
+    fn trivia_start(&self, pos: usize) -> usize {
+        let mut start = pos;
+        loop {
+            start = self.source.content[..start].trim_end().len();
+            let index = self
+                .comments
+                .partition_point(|comment| comment.span.end < start);
+            match self.comments.get(index) {
+                Some(comment) if comment.span.end == start => start = comment.span.start,
+                _ => return start,
+            }
+        }
+    }
+
+    /// The inline comments before the token at `pos`, each followed by a
+    /// space, as in `/* c */ i64`.
+    fn leading_comments(&mut self, pos: usize) -> String {
+        self.inline_comments(pos)
+            .iter()
+            .map(|text| format!("{text} "))
+            .collect()
+    }
+
+    /// The inline comments before the closing delimiter at `pos`, each
+    /// preceded by a space, as in `i64 /* c */>`.
+    fn closing_comments(&mut self, pos: usize) -> String {
+        self.inline_comments(pos)
+            .iter()
+            .map(|text| format!(" {text}"))
+            .collect()
+    }
+
+    /// Starts the members of a `{` ending at `open_end`, keeping a comment
+    /// right after the brace on its line.
+    fn open_brace(&mut self, out: &mut String, open_end: usize) {
+        out.push_str(" {");
+        self.trailing_comment(out, open_end);
+        out.push('\n');
+    }
+
+    /// Writes every remaining comment that starts before `pos`, one per
+    /// line.
+    fn comments_before(
+        &mut self,
+        out: &mut String,
+        pos: usize,
+        indent: usize,
+        prev_end: &mut Option<usize>,
+    ) {
+        while let Some(comment) = self.comments.get(self.next_comment).copied()
+            && comment.span.start < pos
+        {
+            if prev_end.is_some_and(|end| self.has_blank_line(end, comment.span.start)) {
+                out.push('\n');
+            }
+
+            push_indent(out, indent);
+            out.push_str(comment.text);
+            out.push('\n');
+
+            self.next_comment += 1;
+            *prev_end = Some(comment.span.end);
+        }
+    }
+
+    fn is_in_comment(&self, pos: usize) -> bool {
+        let index = self
+            .comments
+            .partition_point(|comment| comment.span.end <= pos);
+        self.comments
+            .get(index)
+            .is_some_and(|comment| comment.span.start <= pos)
+    }
+
+    /// The offset of the first `token` at or after `start` that is not part
+    /// of a comment.
+    fn find_token(&self, start: usize, token: &str) -> Option<usize> {
+        self.source.content[start..]
+            .match_indices(token)
+            .map(|(offset, _)| start + offset)
+            .find(|&pos| !self.is_in_comment(pos))
+    }
+
+    /// The offset of the last `token` before `end` that is not part of a
+    /// comment.
+    fn rfind_token(&self, end: usize, token: &str) -> Option<usize> {
+        self.source.content[..end]
+            .rmatch_indices(token)
+            .map(|(offset, _)| offset)
+            .find(|&pos| !self.is_in_comment(pos))
+    }
+
+    fn has_comments_in(&self, span: Span) -> bool {
+        self.comments[self.next_comment..]
+            .iter()
+            .take_while(|comment| comment.span.start < span.end)
+            .any(|comment| comment.span.start >= span.start)
+    }
+
+    /// The end of the first `{` within `span`.
+    fn open_brace_end(&self, span: Span) -> usize {
+        let offset = self.slice(span).find('{').map_or(0, |offset| offset + 1);
+        span.start + offset
+    }
+
+    fn has_blank_line(&self, start: usize, end: usize) -> bool {
+        start < end && self.source.content[start..end].matches('\n').count() >= 2
+    }
+
+    fn slice(&self, span: Span) -> &'a str {
+        &self.source.content[span.start..span.end]
+    }
+
+    fn name(&self, name: unnamed_common::StrId) -> &'a str {
+        self.interner.get(name)
+    }
+
+    fn item(&mut self, item: ItemEntity) -> String {
+        let indent = 0;
+
+        match self.ctx.items[item] {
+            Item::Func(func) => self.func(func, indent),
+            Item::Struct(struct_item) => {
+                let header = format!(
+                    "struct {}{}",
+                    self.name(struct_item.name),
+                    self.generics(struct_item.generics)
+                );
+                let fields = self.ctx.field_defs.list(struct_item.fields);
+
+                self.members(header, fields.len(), struct_item.span, indent, |this, i| {
+                    let field = this.ctx.field_defs[fields[i]];
+                    let text = format!("{}: {},", this.name(field.name), this.ty(field.ty));
+
+                    (field.span, text)
+                })
+            }
+            Item::Enum(enum_item) => {
+                let header = format!(
+                    "enum {}{}",
+                    self.name(enum_item.name),
+                    self.generics(enum_item.generics)
+                );
+                let variants = self.ctx.variants.list(enum_item.variants);
+
+                self.members(header, variants.len(), enum_item.span, indent, |this, i| {
+                    let variant = this.ctx.variants[variants[i]];
+                    let name = this.name(variant.name);
+                    let text = match variant.kind {
+                        VariantKind::Unit => format!("{name},"),
+                        VariantKind::Tuple(types) => {
+                            let types = this.ctx.types.list(types).iter();
+                            let types: Vec<_> = types.map(|&ty| this.ty(ty)).collect();
+                            format!("{name}({}),", types.join(", "))
+                        }
+                        VariantKind::Struct(fields) => {
+                            format!("{name} {},", this.field_defs(fields))
+                        }
+                    };
+
+                    (variant.span, text)
+                })
+            }
+            Item::Trait(trait_item) => {
+                let header = format!("trait {}", self.name(trait_item.name));
+                self.member_items(header, trait_item.items, trait_item.span, indent)
+            }
+            Item::Impl(impl_item) => {
+                let generics = self.generics(impl_item.generics);
+                let header = match impl_item.trait_ref {
+                    Some(trait_ref) => format!(
+                        "impl{generics} {} for {}",
+                        self.ty(trait_ref),
+                        self.ty(impl_item.ty)
+                    ),
+                    None => format!("impl{generics} {}", self.ty(impl_item.ty)),
+                };
+
+                self.member_items(header, impl_item.items, impl_item.span, indent)
+            }
+        }
+    }
+
+    fn func(&mut self, func: unnamed_ast::item::FuncItem, indent: usize) -> String {
+        let mut out = format!(
+            "func {}{}",
+            self.name(func.name),
+            self.generics(func.generics)
+        );
+
+        let params = self
+            .ctx
+            .params
+            .list(func.params)
+            .iter()
+            .map(|&param| ListItem::Param(param))
+            .collect::<Vec<_>>();
+        let used = indent * INDENT + out.len();
+        out += &self.list("(", ")", &params, indent, used, false);
+
+        if let Some(ret) = func.ret {
+            out += " -> ";
+            out += &self.ty(ret);
+        }
+
+        match func.body {
+            Some(body) => {
+                out.push(' ');
+                out += &self.block(body, indent);
+            }
+            None => out.push(';'),
+        }
+
+        out
+    }
+
+    /// Formats the functions of a trait or impl, separated by blank lines.
+    fn member_items(
+        &mut self,
+        header: String,
+        items: EntityList<ItemEntity>,
+        span: Span,
+        indent: usize,
+    ) -> String {
+        let items = self.ctx.items.list(items);
+
+        let mut out = header;
+        if items.is_empty() && !self.has_comments_in(span) {
+            return out + " {}";
+        }
+
+        self.open_brace(&mut out, self.open_brace_end(span));
+        let mut prev_end = None;
+        for (i, &item) in items.iter().enumerate() {
+            let Item::Func(func) = self.ctx.items[item] else {
+                continue;
+            };
+
+            self.element_start(&mut out, func.span.start, indent + 1, &mut prev_end, i > 0);
+            let text = self.func(func, indent + 1);
+            out += &text;
+            self.element_end(&mut out, func.span.end, &mut prev_end);
+        }
+
+        self.comments_before(&mut out, span.end, indent + 1, &mut prev_end);
+        push_indent(&mut out, indent);
+        out + "}"
+    }
+
+    /// Formats `count` members between braces, one per line. `member`
+    /// returns the source span and text of a member.
+    fn members(
+        &mut self,
+        header: String,
+        count: usize,
+        span: Span,
+        indent: usize,
+        mut member: impl FnMut(&mut Self, usize) -> (Span, String),
+    ) -> String {
+        let mut out = header;
+        if count == 0 && !self.has_comments_in(span) {
+            return out + " {}";
+        }
+
+        self.open_brace(&mut out, self.open_brace_end(span));
+        let mut prev_end = None;
+        for i in 0..count {
+            let (member_span, text) = member(self, i);
+
+            self.element_start(
+                &mut out,
+                member_span.start,
+                indent + 1,
+                &mut prev_end,
+                false,
+            );
+            out += &text;
+            self.element_end(&mut out, member_span.end, &mut prev_end);
+        }
+
+        self.comments_before(&mut out, span.end, indent + 1, &mut prev_end);
+        push_indent(&mut out, indent);
+        out + "}"
+    }
+
+    /// Formats the fields of a struct variant on one line, as in
+    /// `{ w: f64, h: f64 }`.
+    fn field_defs(&mut self, fields: EntityList<FieldDefEntity>) -> String {
+        let fields = self.ctx.field_defs.list(fields);
+        if fields.is_empty() {
+            return "{}".to_owned();
+        }
+
+        let fields: Vec<_> = fields
+            .iter()
+            .map(|&field| {
+                let field = self.ctx.field_defs[field];
+                format!("{}: {}", self.name(field.name), self.ty(field.ty))
+            })
+            .collect();
+        format!("{{ {} }}", fields.join(", "))
+    }
+
+    /// Formats a type, keeping the block comments before it and inside its
+    /// arguments inline.
+    fn ty(&mut self, ty: TypeEntity) -> String {
+        let ty = self.ctx.types[ty];
+        let comments = self.leading_comments(ty.span.start);
+
+        let ty = match ty.kind {
+            TypeKind::Named { name, args } => {
+                let args = self.ctx.types.list(args);
+                if args.is_empty() {
+                    self.name(name).to_owned()
+                } else {
+                    let args = args
+                        .iter()
+                        .map(|&arg| self.ty(arg))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let close = self.rfind_token(ty.span.end, ">").unwrap_or(ty.span.end);
+                    let closing = self.closing_comments(close);
+                    format!("{}<{args}{closing}>", self.name(name))
+                }
+            }
+            TypeKind::Unit => "()".to_owned(),
+            TypeKind::Error => self.slice(ty.span).to_owned(),
+        };
+
+        comments + &ty
+    }
+
+    fn generics(&mut self, generics: EntityList<GenericParamEntity>) -> String {
+        let generics = self.ctx.generic_params.list(generics);
+        if generics.is_empty() {
+            return String::new();
+        }
+
+        let names = generics
+            .iter()
+            .map(|&param| {
+                let param = self.ctx.generic_params[param];
+                let bounds = self.ctx.types.list(param.bounds);
+                if bounds.is_empty() {
+                    return self.name(param.name).to_owned();
+                }
+
+                let bounds: Vec<_> = bounds.iter().map(|&bound| self.ty(bound)).collect();
+                format!("{}: {}", self.name(param.name), bounds.join(" + "))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("<{names}>")
+    }
+
+    fn type_args(&mut self, args: EntityList<TypeEntity>) -> String {
+        let args = self.ctx.types.list(args);
+        let Some(&last) = args.last() else {
+            return String::new();
+        };
+
+        let last_end = self.ctx.types[last].span.end;
+        let close = self.find_token(last_end, ">").unwrap_or(last_end);
+        let args = args
+            .iter()
+            .map(|&arg| self.ty(arg))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let closing = self.closing_comments(close);
+        format!("::<{args}{closing}>")
+    }
+
+    fn block(&mut self, block: Block, indent: usize) -> String {
+        let stmts = self.ctx.exprs.list(block.exprs);
+        if stmts.is_empty() && block.tail.is_none() && !self.has_comments_in(block.span) {
+            return "{}".to_owned();
+        }
+
+        let in_condition = std::mem::replace(&mut self.in_condition, false);
+
+        let mut out = "{".to_owned();
+        self.trailing_comment(&mut out, block.span.start + 1);
+        out.push('\n');
+        let mut prev_end = None;
+        for &stmt in stmts.iter().chain(&block.tail) {
+            let span = self.ctx.exprs[stmt].span();
+            self.element_start(&mut out, span.start, indent + 1, &mut prev_end, false);
+
+            let text = self.expr(stmt, indent + 1, (indent + 1) * INDENT);
+            out += &text;
+            if Some(stmt) != block.tail && !self.is_block_like(stmt) {
+                out.push(';');
+            }
+
+            self.element_end(&mut out, span.end, &mut prev_end);
+        }
+
+        self.comments_before(&mut out, block.span.end, indent + 1, &mut prev_end);
+        push_indent(&mut out, indent);
+        out.push('}');
+
+        self.in_condition = in_condition;
+        out
+    }
+
+    fn is_block_like(&self, expr: ExprEntity) -> bool {
+        matches!(
+            self.ctx.exprs[expr],
+            Expr::Block(..) | Expr::If(..) | Expr::While(..) | Expr::Match(..)
+        )
+    }
+
+    /// Formats an expression starting `used` columns into its first line.
+    /// Lines after the first are indented absolutely, `indent` being the
+    /// level of the line the expression starts on.
+    fn expr(&mut self, expr: ExprEntity, indent: usize, used: usize) -> String {
+        let ctx = self.ctx;
+
+        match ctx.exprs[expr] {
+            Expr::Ident(name, ..) => self.name(name).to_owned(),
+            Expr::Str(.., span)
+            | Expr::Int(.., span)
+            | Expr::Float(.., span)
+            | Expr::Char(.., span)
+            | Expr::Error(span) => self.slice(span).to_owned(),
+            Expr::Bool(value, ..) => value.to_string(),
+            Expr::Unit(..) => "()".to_owned(),
+            Expr::Path(path) => {
+                format!(
+                    "{}::{}",
+                    self.name(path.ty.name),
+                    self.name(path.variant.name)
+                )
+            }
+            Expr::Block(block) => self.block(block, indent),
+            Expr::Let(let_expr) => {
+                let mut out = format!("let {}", self.name(let_expr.name));
+                if let Some(ty) = let_expr.ty {
+                    out += ": ";
+                    out += &self.ty(ty);
+                }
+                if let Some(value) = let_expr.value {
+                    out += " = ";
+                    out += &self.leading_comments(self.ctx.exprs[value].span().start);
+                    out += &self.expr(value, indent, used + out.len());
+                }
+
+                out
+            }
+            Expr::Assign(assign) => {
+                let out = format!("{} = ", self.name(assign.lhs));
+                let rhs = self.expr(assign.rhs, indent, used + out.len());
+
+                out + &rhs
+            }
+            Expr::Bin(bin) => {
+                let precedence = bin.op.precedence();
+                let lhs = self.operand(bin.lhs, precedence, false, indent, used);
+                let op = bin.op.as_str();
+
+                // Comments around the operator break the line after the
+                // left operand, keeping them on their own line or after it.
+                let lhs_end = ctx.exprs[bin.lhs].span().end;
+                let rhs_start = ctx.exprs[bin.rhs].span().start;
+                let has_comments = self.has_comments_in(Span::new(lhs_end, rhs_start));
+
+                let checkpoint = self.next_comment;
+                if !has_comments {
+                    let rhs_used = last_line_width(&lhs, used) + op.len() + 2;
+                    let rhs = self.operand(bin.rhs, precedence, true, indent, rhs_used);
+                    let flat = format!("{lhs} {op} {rhs}");
+                    if lines_fit(&flat, used) {
+                        return flat;
+                    }
+                }
+
+                self.next_comment = checkpoint;
+                let mut out = lhs;
+                let op_end = self
+                    .find_token(lhs_end, op)
+                    .map_or(lhs_end, |pos| pos + op.len());
+                self.trailing_comment(&mut out, lhs_end)
+                    .or_else(|| self.trailing_comment(&mut out, op_end));
+                out.push('\n');
+                self.comments_before(&mut out, rhs_start, indent + 1, &mut None);
+
+                let rhs_used = (indent + 1) * INDENT + op.len() + 1;
+                let rhs = self.operand(bin.rhs, precedence, true, indent + 1, rhs_used);
+                push_indent(&mut out, indent + 1);
+                out + op + " " + &rhs
+            }
+            Expr::Unary(unary) => {
+                let op = unary.op.as_str();
+                let value = self.expr(unary.value, indent, used + op.len());
+
+                if self.needs_parens_as_operand(unary.value) {
+                    format!("{op}({value})")
+                } else {
+                    format!("{op}{value}")
+                }
+            }
+            Expr::Call(call) => {
+                let mut out = self.postfix_base(call.receiver, indent, used);
+                out += &self.type_args(call.ty_args);
+
+                let args = self.expr_items(call.args);
+                let list = self.list("(", ")", &args, indent, last_line_width(&out, used), false);
+                out + &list
+            }
+            Expr::MethodCall(method_call) => {
+                let mut out = self.postfix_base(method_call.receiver, indent, used);
+                out += ".";
+                out += self.name(method_call.method);
+                out += &self.type_args(method_call.ty_args);
+
+                let args = self.expr_items(method_call.args);
+                let list = self.list("(", ")", &args, indent, last_line_width(&out, used), false);
+                out + &list
+            }
+            Expr::Field(field) => {
+                let out = self.postfix_base(field.base, indent, used);
+                out + "." + self.name(field.member)
+            }
+            Expr::Index(index) => {
+                let out = self.postfix_base(index.base, indent, used);
+                let index = self.expr(index.index, indent, last_line_width(&out, used) + 1);
+
+                format!("{out}[{index}]")
+            }
+            Expr::If(if_expr) => {
+                let mut out = "if ".to_owned();
+                out += &self.condition(if_expr.cond, indent, used + out.len());
+                out.push(' ');
+                out += &self.block(if_expr.then_branch, indent);
+
+                if let Some(else_branch) = if_expr.else_branch {
+                    out += " else ";
+                    match else_branch.kind {
+                        ElseExprKind::If(expr) => {
+                            let used = last_line_width(&out, used);
+                            out += &self.expr(expr, indent, used);
+                        }
+                        ElseExprKind::Else(block) => out += &self.block(block, indent),
+                    }
+                }
+
+                out
+            }
+            Expr::While(while_expr) => {
+                let mut out = "while ".to_owned();
+                out += &self.condition(while_expr.cond, indent, used + out.len());
+                out.push(' ');
+                out + &self.block(while_expr.body, indent)
+            }
+            Expr::Match(match_expr) => self.match_expr(match_expr, indent, used),
+            Expr::Array(array) => match array.kind {
+                ArrayExprKind::List { values } => {
+                    let values = self.expr_items(values);
+                    self.list("[", "]", &values, indent, used, false)
+                }
+                ArrayExprKind::Repeat { value, repeat } => {
+                    let value = self.expr(value, indent, used + 1);
+                    let repeat = self.expr(repeat, indent, last_line_width(&value, used) + 3);
+
+                    format!("[{value}; {repeat}]")
+                }
+            },
+            Expr::Struct(struct_expr) => {
+                let mut name = self.name(struct_expr.name).to_owned();
+                if let Some(variant) = struct_expr.variant {
+                    name = format!("{name}::{}", self.name(variant.name));
+                }
+
+                let fields = ctx
+                    .field_exprs
+                    .list(struct_expr.fields)
+                    .iter()
+                    .map(|&field| ListItem::Field(field))
+                    .collect::<Vec<_>>();
+
+                let used = used + name.len() + 1 + usize::from(self.in_condition);
+                let out = if fields.is_empty() {
+                    format!("{name} {{}}")
+                } else {
+                    let fields = self.list("{", "}", &fields, indent, used, true);
+                    format!("{name} {fields}")
+                };
+
+                if self.in_condition {
+                    format!("({out})")
+                } else {
+                    out
+                }
+            }
+            Expr::Return(return_expr) => match return_expr.value {
+                Some(value) => {
+                    let mut out = "return ".to_owned();
+                    out += &self.leading_comments(self.ctx.exprs[value].span().start);
+                    out += &self.expr(value, indent, used + out.len());
+                    out
+                }
+                None => "return".to_owned(),
+            },
+        }
+    }
+
+    /// Formats a match with one arm per line. Arms end with a comma unless
+    /// their body is a block.
+    fn match_expr(&mut self, match_expr: MatchExpr, indent: usize, used: usize) -> String {
+        let mut out = "match ".to_owned();
+        out += &self.condition(match_expr.scrutinee, indent, used + out.len());
+
+        let arms = self.ctx.arms.list(match_expr.arms);
+        if arms.is_empty() && !self.has_comments_in(match_expr.span) {
+            return out + " {}";
+        }
+
+        let scrutinee_end = self.ctx.exprs[match_expr.scrutinee].span().end;
+        let open_end = self.open_brace_end(Span::new(scrutinee_end, match_expr.span.end));
+        self.open_brace(&mut out, open_end);
+
+        let mut prev_end = None;
+        for &arm in arms {
+            let arm = self.ctx.arms[arm];
+            self.element_start(&mut out, arm.span.start, indent + 1, &mut prev_end, false);
+
+            let mut text = self.pat(arm.pat);
+            if let Some(guard) = arm.guard {
+                text += " if ";
+                let used = (indent + 1) * INDENT + text.len();
+                text += &self.expr(guard, indent + 1, used);
+            }
+            text += " => ";
+
+            let used = last_line_width(&text, (indent + 1) * INDENT);
+            text += &self.expr(arm.body, indent + 1, used);
+            if !matches!(self.ctx.exprs[arm.body], Expr::Block(..)) {
+                text.push(',');
+            }
+
+            out += &text;
+            self.element_end(&mut out, arm.span.end, &mut prev_end);
+        }
+
+        self.comments_before(&mut out, match_expr.span.end, indent + 1, &mut prev_end);
+        push_indent(&mut out, indent);
+        out + "}"
+    }
+
+    fn pat(&mut self, pat: PatEntity) -> String {
+        match self.ctx.pats[pat] {
+            Pat::Wild(_) => "_".to_owned(),
+            Pat::Binding(name, _) => self.name(name).to_owned(),
+            Pat::Lit(lit, _) => self.expr(lit, 0, 0),
+            Pat::Error(span) => self.slice(span).to_owned(),
+            Pat::Adt(adt) => {
+                let mut out = self.name(adt.name.name).to_owned();
+                if let Some(variant) = adt.variant {
+                    out += "::";
+                    out += self.name(variant.name);
+                }
+
+                match adt.fields {
+                    PatFields::Unit => out,
+                    PatFields::Tuple(pats) => {
+                        let pats = self.ctx.pats.list(pats);
+                        let pats: Vec<_> = pats.iter().map(|&pat| self.pat(pat)).collect();
+                        format!("{out}({})", pats.join(", "))
+                    }
+                    PatFields::Named(fields) => {
+                        let fields = self.ctx.field_pats.list(fields);
+                        if fields.is_empty() {
+                            return out + " {}";
+                        }
+
+                        let fields: Vec<_> = fields
+                            .iter()
+                            .map(|&field| {
+                                let field = self.ctx.field_pats[field];
+                                let name = self.name(field.name);
+                                match self.ctx.pats[field.pat] {
+                                    Pat::Binding(binding, _) if binding == field.name => {
+                                        name.to_owned()
+                                    }
+                                    _ => format!("{name}: {}", self.pat(field.pat)),
+                                }
+                            })
+                            .collect();
+                        format!("{out} {{ {} }}", fields.join(", "))
+                    }
+                }
+            }
+        }
+    }
+
+    fn condition(&mut self, cond: ExprEntity, indent: usize, used: usize) -> String {
+        let in_condition = std::mem::replace(&mut self.in_condition, true);
+        let out = self.expr(cond, indent, used);
+        self.in_condition = in_condition;
+
+        out
+    }
+
+    /// Formats an operand of a binary operator with the given precedence,
+    /// adding the parentheses the tree no longer records.
+    fn operand(
+        &mut self,
+        expr: ExprEntity,
+        precedence: u8,
+        is_rhs: bool,
+        indent: usize,
+        used: usize,
+    ) -> String {
+        let needs_parens = match self.ctx.exprs[expr] {
+            Expr::Bin(bin) => {
+                let inner = bin.op.precedence();
+                inner < precedence || (inner == precedence && (is_rhs || bin.op.is_comparison()))
+            }
+            Expr::Assign(..) | Expr::Let(..) | Expr::Return(..) => true,
+            _ => false,
+        };
+
+        if needs_parens {
+            format!("({})", self.expr(expr, indent, used + 1))
+        } else {
+            self.expr(expr, indent, used)
+        }
+    }
+
+    fn needs_parens_as_operand(&self, expr: ExprEntity) -> bool {
+        matches!(
+            self.ctx.exprs[expr],
+            Expr::Bin(..) | Expr::Assign(..) | Expr::Let(..) | Expr::Return(..)
+        )
+    }
+
+    /// Formats the expression a call, field access or index applies to.
+    fn postfix_base(&mut self, expr: ExprEntity, indent: usize, used: usize) -> String {
+        if self.needs_parens_as_operand(expr) || matches!(self.ctx.exprs[expr], Expr::Unary(..)) {
+            format!("({})", self.expr(expr, indent, used + 1))
+        } else {
+            self.expr(expr, indent, used)
+        }
+    }
+
+    fn expr_items(&self, exprs: EntityList<ExprEntity>) -> Vec<ListItem> {
+        self.ctx
+            .exprs
+            .list(exprs)
+            .iter()
+            .map(|&expr| ListItem::Expr(expr))
+            .collect()
+    }
+
+    fn list_item_span(&self, item: ListItem) -> Span {
+        match item {
+            ListItem::Expr(expr) => self.ctx.exprs[expr].span(),
+            ListItem::Field(field) => self.ctx.field_exprs[field].span,
+            ListItem::Param(param) => self.ctx.params[param].span,
+        }
+    }
+
+    fn list_item(&mut self, item: ListItem, indent: usize, used: usize) -> String {
+        match item {
+            ListItem::Expr(expr) => self.expr(expr, indent, used),
+            ListItem::Field(field) => {
+                let field = self.ctx.field_exprs[field];
+                let name = self.name(field.name);
+
+                let is_shorthand = matches!(
+                    self.ctx.exprs[field.value],
+                    Expr::Ident(value, span) if value == field.name && span == field.span
+                );
+                if is_shorthand {
+                    return name.to_owned();
+                }
+
+                let in_condition = std::mem::replace(&mut self.in_condition, false);
+                let value = self.expr(field.value, indent, used + name.len() + 2);
+                self.in_condition = in_condition;
+
+                format!("{name}: {value}")
+            }
+            ListItem::Param(param) => {
+                let param = self.ctx.params[param];
+                match param.ty {
+                    Some(ty) => format!("{}: {}", self.name(param.name), self.ty(ty)),
+                    None => self.name(param.name).to_owned(),
+                }
+            }
+        }
+    }
+
+    /// Formats a delimited, comma separated list on one line if it fits and
+    /// has no comments, and otherwise with one item per line and a trailing
+    /// comma. Comments then go on the line of the item they follow or on
+    /// their own line before the next one. `padded` lists have spaces inside
+    /// their delimiters on one line, as in `P { x: 1 }`.
+    fn list(
+        &mut self,
+        open: &str,
+        close: &str,
+        items: &[ListItem],
+        indent: usize,
+        used: usize,
+        padded: bool,
+    ) -> String {
+        // The comments between the delimiters, including those before the
+        // first item and after the last one.
+        let bounds = items.first().zip(items.last()).map(|(&first, &last)| {
+            let (first, last) = (self.list_item_span(first), self.list_item_span(last));
+            let start = self.rfind_token(first.start, open).unwrap_or(first.start);
+            let end = self.find_token(last.end, close).unwrap_or(last.end);
+            Span::new(start, end)
+        });
+        let checkpoint = self.next_comment;
+        let in_condition = std::mem::replace(&mut self.in_condition, false);
+
+        let mut flat = open.to_owned();
+        if padded && !items.is_empty() {
+            flat.push(' ');
+        }
+        for (i, &item) in items.iter().enumerate() {
+            if i > 0 {
+                flat += ", ";
+            }
+            let used = used + flat.len();
+            flat += &self.list_item(item, indent, used);
+        }
+        if padded && !items.is_empty() {
+            flat.push(' ');
+        }
+        flat += close;
+
+        // Comments the items wrote inline do not break the list.
+        let has_comments = bounds.is_some_and(|bounds| self.has_comments_in(bounds));
+        if items.is_empty() || (fits(&flat, used) && !has_comments) {
+            self.in_condition = in_condition;
+            return flat;
+        }
+
+        self.next_comment = checkpoint;
+        let mut out = open.to_owned();
+        out.push('\n');
+        for &item in items {
+            let span = self.list_item_span(item);
+            self.comments_before(&mut out, span.start, indent + 1, &mut None);
+
+            push_indent(&mut out, indent + 1);
+            out += &self.list_item(item, indent + 1, (indent + 1) * INDENT);
+            out.push(',');
+            self.trailing_comment(&mut out, span.end);
+            out.push('\n');
+        }
+        if let Some(bounds) = bounds {
+            self.comments_before(&mut out, bounds.end, indent + 1, &mut None);
+        }
+        push_indent(&mut out, indent);
+        out += close;
+
+        self.in_condition = in_condition;
+        out
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    out.extend(std::iter::repeat_n(' ', indent * INDENT));
+}
+
+/// Whether `text` is a single line that fits after `used` columns.
+fn fits(text: &str, used: usize) -> bool {
+    !text.contains('\n') && used + text.chars().count() <= MAX_WIDTH
+}
+
+/// Whether every line of `text` fits, when it starts `used` columns into
+/// its first line.
+fn lines_fit(text: &str, used: usize) -> bool {
+    text.lines().enumerate().all(|(i, line)| {
+        let used = if i == 0 { used } else { 0 };
+        used + line.chars().count() <= MAX_WIDTH
+    })
+}
+
+/// The column at the end of `text` when it starts `used` columns into a
+/// line.
+fn last_line_width(text: &str, used: usize) -> usize {
+    match text.rsplit_once('\n') {
+        Some((_, last)) => last.chars().count(),
+        None => used + text.chars().count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unnamed_common::Source;
+
+    use crate::format;
+
+    fn fmt(content: &str) -> String {
+        format(Source::new(content, "test_file")).unwrap()
+    }
+
+    #[test]
+    fn test_items() {
+        assert_eq!(
+            fmt("struct Point{x:i64,y:Vec<i64>}
+                 func add(a:i64,b:i64)->i64{a+b}
+                 trait Show{func show(self)->i64;}
+                 impl Show for Point{func show(self)->i64{self.x}}
+                 struct Pair< A,B >{a:A,b:B}
+                 impl <T:Show+Eq>Pair<T,T>{func first< U >(self,u:U)->T{self.a}}"),
+            "struct Point {
+    x: i64,
+    y: Vec<i64>,
+}
+
+func add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+trait Show {
+    func show(self) -> i64;
+}
+
+impl Show for Point {
+    func show(self) -> i64 {
+        self.x
+    }
+}
+
+struct Pair<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<T: Show + Eq> Pair<T, T> {
+    func first<U>(self, u: U) -> T {
+        self.a
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(
+            fmt(
+                "func main(){let x:i64=-(1+2)*3;x=x-(4-5);if x<=1&&(P{y}).y==2{while x>0{x=x-1}}else if f::<T>(){}else{return}a.b.c(1,[0;2])[0]}"
+            ),
+            "func main() {
+    let x: i64 = -(1 + 2) * 3;
+    x = x - (4 - 5);
+    if x <= 1 && (P { y }).y == 2 {
+        while x > 0 {
+            x = x - 1
+        }
+    } else if f::<T>() {} else {
+        return
+    }
+    a.b.c(1, [0; 2])[0]
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_enums_and_match() {
+        assert_eq!(
+            fmt("enum Shape<T>{Empty,Circle(T),Rect{w:T,h:T}}
+                 func area(s:Shape<f64>)->f64{match s{Shape::Empty=>0.0,Shape::Circle(r)if r>0.0=>{3.14*r*r}
+                 // Both sides.
+                 Shape::Rect{w,h:-1.0}=>w,_=>{let a=Shape::Rect{w:1.0,h:2.0};match a{}}}}"),
+            "enum Shape<T> {
+    Empty,
+    Circle(T),
+    Rect { w: T, h: T },
+}
+
+func area(s: Shape<f64>) -> f64 {
+    match s {
+        Shape::Empty => 0.0,
+        Shape::Circle(r) if r > 0.0 => {
+            3.14 * r * r
+        }
+        // Both sides.
+        Shape::Rect { w, h: -1.0 } => w,
+        _ => {
+            let a = Shape::Rect { w: 1.0, h: 2.0 };
+            match a {}
+        }
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let source = "//! Module docs.
+
+/// Adds.
+func add() {   // trailing
+
+
+    let a = 1;  /* block */
+    // before b
+    let b = 2;
+
+    // at the end
+}
+// last
+";
+
+        assert_eq!(
+            fmt(source),
+            "//! Module docs.
+
+/// Adds.
+func add() { // trailing
+    let a = 1; /* block */
+    // before b
+    let b = 2;
+
+    // at the end
+}
+// last
+"
+        );
+    }
+
+    #[test]
+    fn test_list_comments() {
+        let source = "struct S { a: i64, // a
+    b: i64 }
+
+func f(a: i64, // first
+    // before b
+    b: i64) -> i64 {
+    match a { 0 => g(1, /* two */ 2), // zero
+        _ => a + // why
+            b
+    }
+}
+";
+        let formatted = "struct S {
+    a: i64, // a
+    b: i64,
+}
+
+func f(
+    a: i64, // first
+    // before b
+    b: i64,
+) -> i64 {
+    match a {
+        0 => g(
+            1,
+            /* two */
+            2,
+        ), // zero
+        _ => a // why
+            + b,
+    }
+}
+";
+
+        assert_eq!(fmt(source), formatted);
+        assert_eq!(fmt(formatted), formatted);
+    }
+
+    #[test]
+    fn test_operand_comments() {
+        let source = "func main() {
+    let x = a
+        // because
+        + b;
+    let p = P { x: 1, // x
+    };
+}
+";
+
+        let formatted = "func main() {
+    let x = a
+        // because
+        + b;
+    let p = P {
+        x: 1, // x
+    };
+}
+";
+
+        assert_eq!(fmt(source), formatted);
+        assert_eq!(fmt(formatted), formatted);
+    }
+
+    #[test]
+    fn test_param_comments() {
+        let source = "func f(a:/* first */i64, b: /* second */ i64) {}\n";
+        let formatted = "func f(a: /* first */ i64, b: /* second */ i64) {}\n";
+
+        assert_eq!(fmt(source), formatted);
+        assert_eq!(fmt(formatted), formatted);
+    }
+
+    #[test]
+    fn test_type_arg_comments() {
+        let source = "struct S { a: Map</* key */ str, Vec<i64 /* last */>> }\n";
+        let formatted = "struct S {
+    a: Map</* key */ str, Vec<i64 /* last */>>,
+}
+";
+
+        assert_eq!(fmt(source), formatted);
+        assert_eq!(fmt(formatted), formatted);
+    }
+
+    #[test]
+    fn test_initializer_comments() {
+        let source = "func main() { let x: i64 = /* start */ 1 + 2; }";
+        let formatted = "func main() {
+    let x: i64 = /* start */ 1 + 2;
+}
+";
+
+        assert_eq!(fmt(source), formatted);
+        assert_eq!(fmt(formatted), formatted);
+    }
+
+    #[test]
+    fn test_return_comments() {
+        let source = "func f(a: i64) -> i64 { return /* unchanged */ a; }";
+        let formatted = "func f(a: i64) -> i64 {
+    return /* unchanged */ a;
+}
+";
+
+        assert_eq!(fmt(source), formatted);
+        assert_eq!(fmt(formatted), formatted);
+    }
+
+    #[test]
+    fn test_turbofish_comments() {
+        let source = "func main() { x.foo::</* ta */ i64>(); id::<i64 /* tb */>(1); }";
+        let formatted = "func main() {
+    x.foo::</* ta */ i64>();
+    id::<i64 /* tb */>(1);
+}
+";
+
+        assert_eq!(fmt(source), formatted);
+        assert_eq!(fmt(formatted), formatted);
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let source = "func main() { call(first_argument_value, second_argument_value, third_argument_value, fourth_argument_value, fifth) }";
+
+        assert_eq!(
+            fmt(source),
+            "func main() {
+    call(
+        first_argument_value,
+        second_argument_value,
+        third_argument_value,
+        fourth_argument_value,
+        fifth,
+    )
+}
+"
+        );
+
+        let source = "func main() { let total = first_operand_value_name * 2 + second_operand_value_name * 4 + third_operand_value_name * 8; }";
+        assert_eq!(
+            fmt(source),
+            "func main() {
+    let total = first_operand_value_name * 2 + second_operand_value_name * 4
+        + third_operand_value_name * 8;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let source = "struct P { x: i64 }
+
+// Comment.
+func main() -> i64 {
+    let p = P { x: 1 };
+    if p.x == 1 { p.x } else { 0 }
+}
+";
+
+        let once = fmt(source);
+        assert_eq!(fmt(&once), once);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert!(format(Source::new("func main( {", "test_file")).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use similar::TextDiff;
+use unnamed_common::{IntoReport, Source, SourceCache};
+
+#[derive(Parser, Debug)]
+#[command(name = "unnamedfmt", version, about = "Format unnamed source files")]
+struct Cli {
+    /// Do not write the files, but print a diff and exit with a non-zero
+    /// status if any of them is not formatted.
+    #[arg(long)]
+    check: bool,
+    /// The files to format in place.
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+enum Outcome {
+    Unchanged,
+    Changed,
+    Failed,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let mut failed = false;
+    let mut unformatted = false;
+    for path in &cli.files {
+        match format_file(path, cli.check) {
+            Outcome::Unchanged => {}
+            Outcome::Changed => unformatted = true,
+            Outcome::Failed => failed = true,
+        }
+    }
+
+    if failed || (cli.check && unformatted) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn format_file(path: &PathBuf, check: bool) -> Outcome {
+    let name = path.to_string_lossy();
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) => {
+            eprintln!("error: cannot read `{name}`: {error}");
+            return Outcome::Failed;
+        }
+    };
+
+    let source = Source::new(&content, &name);
+    let formatted = match unnamedfmt::format(source) {
+        Ok(formatted) => formatted,
+        Err(errors) => {
+            let mut cache = SourceCache::new();
+            cache.insert(source);
+
+            for error in errors {
+                let _ = error.into_report(source).eprint(&cache);
+            }
+            eprintln!("error: cannot format `{name}` because it has syntax errors");
+
+            return Outcome::Failed;
+        }
+    };
+
+    if formatted == content {
+        return Outcome::Unchanged;
+    }
+
+    if check {
+        let diff = TextDiff::from_lines(&content, &formatted);
+        print!(
+            "{}",
+            diff.unified_diff().header(
+                &format!("{name} (original)"),
+                &format!("{name} (formatted)")
+            )
+        );
+    } else if let Err(error) = fs::write(path, formatted) {
+        eprintln!("error: cannot write `{name}`: {error}");
+        return Outcome::Failed;
+    }
+
+    Outcome::Changed
+}
@@ -0,0 +1,840 @@
+//! Parses a document and indexes the names in it, so that requests can find
+//! what is under the cursor without walking the AST again.
+
+use std::collections::HashMap;
+
+use cranelift_entity::EntityList;
+use unnamed_ast::{
+    AstCtx, Block, ExprEntity, FieldDefEntity, GenericParamEntity, ItemEntity, Module, PatEntity,
+    PathSegment, TypeEntity, VariantEntity,
+    expr::{ArrayExprKind, ElseExprKind, Expr},
+    item::{FuncItem, Item, VariantKind},
+    pat::{Pat, PatFields},
+    ty::TypeKind,
+};
+use unnamed_common::{Interner, Source, Span, Spanned, StrId};
+use unnamed_lexer::{Lexer, TokenKind};
+use unnamed_parser::ParsedModule;
+use unnamed_resolve::{LocalId, Res, Resolution};
+use unnamed_typeck::TypeckResults;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Variant,
+    Trait,
+    Field,
+    Param,
+    Local,
+    TypeParam,
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub kind: DefinitionKind,
+    /// Span of the name at the definition.
+    pub span: Span,
+    /// The definition as written in the source, without its body.
+    pub signature: String,
+    pub docs: Option<String>,
+}
+
+/// A name in the document that refers to a definition. The names at the
+/// definitions themselves are references too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    pub span: Span,
+    pub definition: usize,
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub content: String,
+    pub interner: Interner,
+    pub parsed: ParsedModule,
+    pub resolution: Resolution,
+    pub typeck: TypeckResults,
+    /// Every token of the document, comments included, in source order.
+    pub tokens: Vec<(Span, TokenKind)>,
+    pub definitions: Vec<Definition>,
+    /// References ordered by position.
+    pub references: Vec<Reference>,
+}
+
+impl Analysis {
+    pub fn new(content: String) -> Self {
+        let interner = Interner::new();
+        let source = Source::new(&content, "");
+
+        let parsed = unnamed_parser::parse(source, &interner);
+        let tokens: Vec<_> = Lexer::with_comments(source)
+            .filter_map(Result::ok)
+            .map(|token| (token.span, token.kind))
+            .collect();
+
+        let resolution = unnamed_resolve::resolve(&parsed.ctx, &parsed.module, &interner);
+        let typeck = unnamed_typeck::check(&parsed.ctx, &parsed.module, &interner, &resolution);
+
+        let mut indexer = Indexer::new(&parsed.ctx, &resolution, &interner, &content, &tokens);
+        indexer.module(parsed.module);
+
+        let definitions = indexer.definitions;
+        let mut references = indexer.references;
+        references.sort_by_key(|reference| reference.span.start);
+
+        Self {
+            content,
+            interner,
+            parsed,
+            resolution,
+            typeck,
+            tokens,
+            definitions,
+            references,
+        }
+    }
+
+    pub fn source(&self) -> Source<'_> {
+        Source::new(&self.content, "")
+    }
+
+    /// Returns the reference touching `offset`, also matching a cursor
+    /// placed right after the name.
+    pub fn reference_at(&self, offset: usize) -> Option<Reference> {
+        let index = self
+            .references
+            .partition_point(|reference| reference.span.end < offset);
+
+        self.references
+            .get(index)
+            .filter(|reference| reference.span.start <= offset)
+            .copied()
+    }
+}
+
+struct Indexer<'a> {
+    ctx: &'a AstCtx,
+    resolution: &'a Resolution,
+    interner: &'a Interner,
+    content: &'a str,
+    tokens: &'a [(Span, TokenKind)],
+    definitions: Vec<Definition>,
+    references: Vec<Reference>,
+    items: HashMap<ItemEntity, usize>,
+    locals: HashMap<LocalId, usize>,
+    variants: HashMap<VariantEntity, usize>,
+    generics: HashMap<GenericParamEntity, usize>,
+    methods: HashMap<StrId, Vec<usize>>,
+    /// Fields of every struct and struct variant, keyed by the definition
+    /// of the struct or variant and then by the field name.
+    fields: HashMap<(usize, StrId), usize>,
+    field_names: HashMap<StrId, Vec<usize>>,
+}
+
+impl<'a> Indexer<'a> {
+    fn new(
+        ctx: &'a AstCtx,
+        resolution: &'a Resolution,
+        interner: &'a Interner,
+        content: &'a str,
+        tokens: &'a [(Span, TokenKind)],
+    ) -> Self {
+        Self {
+            ctx,
+            resolution,
+            interner,
+            content,
+            tokens,
+            definitions: Vec::new(),
+            references: Vec::new(),
+            items: HashMap::new(),
+            locals: HashMap::new(),
+            variants: HashMap::new(),
+            generics: HashMap::new(),
+            methods: HashMap::new(),
+            fields: HashMap::new(),
+            field_names: HashMap::new(),
+        }
+    }
+
+    fn module(&mut self, module: Module) {
+        let items = self.ctx.items.list(module.items);
+
+        // Items can be used before they are defined, so they are all declared
+        // before any body is visited.
+        for &item in items {
+            self.declare(item);
+        }
+
+        for &item in items {
+            self.item(self.ctx.items[item]);
+        }
+    }
+
+    fn declare(&mut self, item: ItemEntity) {
+        match self.ctx.items[item] {
+            Item::Func(func) => {
+                let definition = self.func_definition(func, DefinitionKind::Function);
+                self.items.insert(item, definition);
+            }
+            Item::Struct(struct_item) => {
+                let definition = self.define(
+                    DefinitionKind::Struct,
+                    struct_item.name_span,
+                    self.text(Span::new(struct_item.span.start, struct_item.name_span.end))
+                        + &self.generics_text(struct_item.generics),
+                    struct_item.docs,
+                );
+                self.items.insert(item, definition);
+
+                self.define_fields(definition, struct_item.fields);
+            }
+            Item::Enum(enum_item) => {
+                let definition = self.define(
+                    DefinitionKind::Enum,
+                    enum_item.name_span,
+                    self.text(Span::new(enum_item.span.start, enum_item.name_span.end))
+                        + &self.generics_text(enum_item.generics),
+                    enum_item.docs,
+                );
+                self.items.insert(item, definition);
+
+                let enum_name = self.interner.get(enum_item.name);
+                for &entity in self.ctx.variants.list(enum_item.variants) {
+                    let variant = self.ctx.variants[entity];
+                    let definition = self.define(
+                        DefinitionKind::Variant,
+                        self.name_span(variant.span.start, variant.name),
+                        format!("{enum_name}::{}", self.text(variant.span)),
+                        None,
+                    );
+                    self.variants.insert(entity, definition);
+
+                    if let VariantKind::Struct(fields) = variant.kind {
+                        self.define_fields(definition, fields);
+                    }
+                }
+            }
+            Item::Trait(trait_item) => {
+                let definition = self.define(
+                    DefinitionKind::Trait,
+                    trait_item.name_span,
+                    format!("trait {}", self.interner.get(trait_item.name)),
+                    trait_item.docs,
+                );
+                self.items.insert(item, definition);
+
+                self.declare_methods(trait_item.items);
+            }
+            Item::Impl(impl_item) => self.declare_methods(impl_item.items),
+        }
+    }
+
+    /// Defines the fields of the struct or variant defined by `owner`.
+    fn define_fields(&mut self, owner: usize, fields: EntityList<FieldDefEntity>) {
+        for &field in self.ctx.field_defs.list(fields) {
+            let field = self.ctx.field_defs[field];
+            let definition = self.define(
+                DefinitionKind::Field,
+                self.name_span(field.span.start, field.name),
+                self.text(field.span),
+                None,
+            );
+
+            self.fields.insert((owner, field.name), definition);
+            self.field_names
+                .entry(field.name)
+                .or_default()
+                .push(definition);
+        }
+    }
+
+    fn declare_methods(&mut self, items: EntityList<ItemEntity>) {
+        for &item in self.ctx.items.list(items) {
+            if let Item::Func(func) = self.ctx.items[item] {
+                let definition = self.func_definition(func, DefinitionKind::Method);
+                self.methods.entry(func.name).or_default().push(definition);
+            }
+        }
+    }
+
+    fn generics_text(&self, generics: EntityList<GenericParamEntity>) -> String {
+        let names: Vec<_> = self
+            .ctx
+            .generic_params
+            .list(generics)
+            .iter()
+            .map(|&param| self.param_text(param))
+            .collect();
+
+        if names.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", names.join(", "))
+        }
+    }
+
+    /// A type parameter with its bounds, like `T: Show + Eq`.
+    fn param_text(&self, param: GenericParamEntity) -> String {
+        let param = self.ctx.generic_params[param];
+        let name = self.interner.get(param.name).to_owned();
+        let bounds: Vec<_> = self
+            .ctx
+            .types
+            .list(param.bounds)
+            .iter()
+            .map(|&bound| self.text(self.ctx.types[bound].span))
+            .collect();
+
+        if bounds.is_empty() {
+            name
+        } else {
+            format!("{name}: {}", bounds.join(" + "))
+        }
+    }
+
+    fn func_definition(&mut self, func: FuncItem, kind: DefinitionKind) -> usize {
+        // Signatures in traits end with a `;` instead of a body.
+        let end = func.body.map_or(func.span.end, |body| body.span.start);
+        let signature = self.text(Span::new(func.span.start, end));
+        let signature = signature.trim_end_matches(';').to_owned();
+
+        self.define(kind, func.name_span, signature, func.docs)
+    }
+
+    fn item(&mut self, item: Item) {
+        match item {
+            Item::Func(func) => self.func(func),
+            Item::Struct(struct_item) => {
+                self.generics(struct_item.generics);
+                self.field_types(struct_item.fields);
+            }
+            Item::Enum(enum_item) => {
+                self.generics(enum_item.generics);
+                for &variant in self.ctx.variants.list(enum_item.variants) {
+                    match self.ctx.variants[variant].kind {
+                        VariantKind::Unit => {}
+                        VariantKind::Tuple(types) => self.types_list(types),
+                        VariantKind::Struct(fields) => self.field_types(fields),
+                    }
+                }
+            }
+            Item::Trait(trait_item) => {
+                for &item in self.ctx.items.list(trait_item.items) {
+                    self.item(self.ctx.items[item]);
+                }
+            }
+            Item::Impl(impl_item) => {
+                self.generics(impl_item.generics);
+                if let Some(trait_ref) = impl_item.trait_ref {
+                    self.ty(trait_ref);
+                }
+                self.ty(impl_item.ty);
+
+                for &item in self.ctx.items.list(impl_item.items) {
+                    self.item(self.ctx.items[item]);
+                }
+            }
+        }
+    }
+
+    fn field_types(&mut self, fields: EntityList<FieldDefEntity>) {
+        for &field in self.ctx.field_defs.list(fields) {
+            self.ty(self.ctx.field_defs[field].ty);
+        }
+    }
+
+    fn generics(&mut self, generics: EntityList<GenericParamEntity>) {
+        for &param in self.ctx.generic_params.list(generics) {
+            let span = self.ctx.generic_params[param].span;
+            let signature = self.param_text(param);
+            let definition = self.define(DefinitionKind::TypeParam, span, signature, None);
+            self.generics.insert(param, definition);
+        }
+
+        for &param in self.ctx.generic_params.list(generics) {
+            for &bound in self.ctx.types.list(self.ctx.generic_params[param].bounds) {
+                self.ty(bound);
+            }
+        }
+    }
+
+    fn func(&mut self, func: FuncItem) {
+        self.generics(func.generics);
+        for &param in self.ctx.params.list(func.params) {
+            let local = self.resolution.params[param];
+            let param = self.ctx.params[param];
+            if let Some(ty) = param.ty {
+                self.ty(ty);
+            }
+
+            let definition = self.define(
+                DefinitionKind::Param,
+                self.name_span(param.span.start, param.name),
+                self.text(param.span),
+                None,
+            );
+            self.bind(local, definition);
+        }
+
+        if let Some(ret) = func.ret {
+            self.ty(ret);
+        }
+
+        if let Some(body) = func.body {
+            self.block(body);
+        }
+    }
+
+    fn ty(&mut self, ty: TypeEntity) {
+        let res = self.resolution.types[ty];
+        let ty = self.ctx.types[ty];
+        if let TypeKind::Named { name, args } = ty.kind {
+            if let Some(definition) = self.lookup(res) {
+                self.refer(self.name_span(ty.span.start, name), definition);
+            }
+
+            for &arg in self.ctx.types.list(args) {
+                self.ty(arg);
+            }
+        }
+    }
+
+    fn block(&mut self, block: Block) {
+        for &expr in self.ctx.exprs.list(block.exprs) {
+            self.expr(expr);
+        }
+        if let Some(tail) = block.tail {
+            self.expr(tail);
+        }
+    }
+
+    fn expr(&mut self, expr: ExprEntity) {
+        match self.ctx.exprs[expr] {
+            Expr::Ident(_, span) => {
+                if let Some(definition) = self.lookup(self.resolution.exprs[expr]) {
+                    self.refer(span, definition);
+                }
+            }
+            Expr::Str(..)
+            | Expr::Int(..)
+            | Expr::Float(..)
+            | Expr::Char(..)
+            | Expr::Bool(..)
+            | Expr::Unit(..)
+            | Expr::Error(..) => {}
+            Expr::Path(path) => {
+                self.path(self.resolution.exprs[expr], path.ty, Some(path.variant));
+            }
+            Expr::Block(block) => self.block(block),
+            Expr::Let(let_expr) => {
+                if let Some(ty) = let_expr.ty {
+                    self.ty(ty);
+                }
+                if let Some(value) = let_expr.value {
+                    self.expr(value);
+                }
+
+                let end = let_expr
+                    .ty
+                    .map_or(let_expr.name_span, |ty| self.ctx.types[ty].span);
+                let definition = self.define(
+                    DefinitionKind::Local,
+                    let_expr.name_span,
+                    self.text(Span::new(let_expr.span.start, end.end)),
+                    None,
+                );
+                self.bind(self.resolution.lets[expr], definition);
+            }
+            Expr::Assign(assign) => {
+                if let Some(definition) = self.lookup(self.resolution.exprs[expr]) {
+                    self.refer(self.name_span(assign.span.start, assign.lhs), definition);
+                }
+                self.expr(assign.rhs);
+            }
+            Expr::Bin(bin_expr) => {
+                self.expr(bin_expr.lhs);
+                self.expr(bin_expr.rhs);
+            }
+            Expr::Unary(unary_expr) => self.expr(unary_expr.value),
+            Expr::Call(call) => {
+                self.expr(call.receiver);
+                self.types_list(call.ty_args);
+                self.exprs(call.args);
+            }
+            Expr::MethodCall(call) => {
+                self.expr(call.receiver);
+
+                let receiver_end = self.ctx.exprs[call.receiver].span().end;
+                if let (Some(span), Some(definition)) = (
+                    self.ident_after(receiver_end, call.method),
+                    self.unique(self.methods.get(&call.method)),
+                ) {
+                    self.refer(span, definition);
+                }
+
+                self.types_list(call.ty_args);
+                self.exprs(call.args);
+            }
+            Expr::If(if_expr) => {
+                self.expr(if_expr.cond);
+                self.block(if_expr.then_branch);
+
+                match if_expr.else_branch.map(|else_expr| else_expr.kind) {
+                    Some(ElseExprKind::If(expr)) => self.expr(expr),
+                    Some(ElseExprKind::Else(block)) => self.block(block),
+                    None => {}
+                }
+            }
+            Expr::While(while_expr) => {
+                self.expr(while_expr.cond);
+                self.block(while_expr.body);
+            }
+            Expr::Match(match_expr) => {
+                self.expr(match_expr.scrutinee);
+                for &arm in self.ctx.arms.list(match_expr.arms) {
+                    let arm = self.ctx.arms[arm];
+                    self.pat(arm.pat);
+                    if let Some(guard) = arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(arm.body);
+                }
+            }
+            Expr::Array(array_expr) => match array_expr.kind {
+                ArrayExprKind::List { values } => self.exprs(values),
+                ArrayExprKind::Repeat { value, repeat } => {
+                    self.expr(value);
+                    self.expr(repeat);
+                }
+            },
+            Expr::Struct(struct_expr) => {
+                let name = PathSegment {
+                    name: struct_expr.name,
+                    span: self.name_span(struct_expr.span.start, struct_expr.name),
+                };
+                let owner = self.path(self.resolution.exprs[expr], name, struct_expr.variant);
+
+                for &field in self.ctx.field_exprs.list(struct_expr.fields) {
+                    let field = self.ctx.field_exprs[field];
+
+                    // In the `Point { x }` shorthand the name is the variable
+                    // as much as the field, and the variable is more useful.
+                    let value_span = self.ctx.exprs[field.value].span();
+                    let is_shorthand = value_span.start == field.span.start;
+
+                    if !is_shorthand
+                        && let Some(owner) = owner
+                        && let Some(&definition) = self.fields.get(&(owner, field.name))
+                    {
+                        self.refer(self.name_span(field.span.start, field.name), definition);
+                    }
+
+                    self.expr(field.value);
+                }
+            }
+            Expr::Field(field_expr) => {
+                self.expr(field_expr.base);
+
+                if let Some(definition) = self.unique(self.field_names.get(&field_expr.member)) {
+                    let len = self.interner.get(field_expr.member).len();
+                    let span = Span::new(field_expr.span.end - len, field_expr.span.end);
+                    self.refer(span, definition);
+                }
+            }
+            Expr::Index(index_expr) => {
+                self.expr(index_expr.base);
+                self.expr(index_expr.index);
+            }
+            Expr::Return(return_expr) => {
+                if let Some(value) = return_expr.value {
+                    self.expr(value);
+                }
+            }
+        }
+    }
+
+    /// Refers the names of a struct or `Enum::Variant` to their definitions,
+    /// and returns the definition of the struct or variant.
+    fn path(
+        &mut self,
+        res: Option<Res>,
+        name: PathSegment,
+        variant: Option<PathSegment>,
+    ) -> Option<usize> {
+        if let Some(Res::Variant(item, _)) = res
+            && let Some(&definition) = self.items.get(&item)
+        {
+            self.refer(name.span, definition);
+        }
+
+        let definition = self.lookup(res)?;
+        let span = variant.map_or(name.span, |variant| variant.span);
+        self.refer(span, definition);
+
+        Some(definition)
+    }
+
+    fn pat(&mut self, pat: PatEntity) {
+        match self.ctx.pats[pat] {
+            Pat::Wild(_) | Pat::Error(_) => {}
+            Pat::Binding(_, span) => {
+                let definition = self.define(DefinitionKind::Local, span, self.text(span), None);
+                self.bind(self.resolution.bindings[pat], definition);
+            }
+            Pat::Lit(lit, _) => self.expr(lit),
+            Pat::Adt(adt) => {
+                let owner = self.path(self.resolution.pats[pat], adt.name, adt.variant);
+
+                match adt.fields {
+                    PatFields::Unit => {}
+                    PatFields::Tuple(pats) => {
+                        for &pat in self.ctx.pats.list(pats) {
+                            self.pat(pat);
+                        }
+                    }
+                    PatFields::Named(fields) => {
+                        for &field in self.ctx.field_pats.list(fields) {
+                            let field = self.ctx.field_pats[field];
+
+                            // Like in struct literals, the `{ x }` shorthand
+                            // refers to the binding rather than the field.
+                            let is_shorthand =
+                                self.ctx.pats[field.pat].span().start == field.span.start;
+                            if !is_shorthand
+                                && let Some(owner) = owner
+                                && let Some(&definition) = self.fields.get(&(owner, field.name))
+                            {
+                                self.refer(
+                                    self.name_span(field.span.start, field.name),
+                                    definition,
+                                );
+                            }
+
+                            self.pat(field.pat);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: EntityList<ExprEntity>) {
+        for &expr in self.ctx.exprs.list(exprs) {
+            self.expr(expr);
+        }
+    }
+
+    fn types_list(&mut self, types: EntityList<TypeEntity>) {
+        for &ty in self.ctx.types.list(types) {
+            self.ty(ty);
+        }
+    }
+
+    fn define(
+        &mut self,
+        kind: DefinitionKind,
+        span: Span,
+        signature: String,
+        docs: Option<StrId>,
+    ) -> usize {
+        let definition = self.definitions.len();
+        self.definitions.push(Definition {
+            kind,
+            span,
+            signature,
+            docs: docs.map(|docs| self.interner.get(docs).to_owned()),
+        });
+        self.refer(span, definition);
+
+        definition
+    }
+
+    fn refer(&mut self, span: Span, definition: usize) {
+        self.references.push(Reference { span, definition });
+    }
+
+    fn bind(&mut self, local: Option<LocalId>, definition: usize) {
+        if let Some(local) = local {
+            self.locals.insert(local, definition);
+        }
+    }
+
+    /// The definition a resolved name refers to, if it is in the document.
+    fn lookup(&self, res: Option<Res>) -> Option<usize> {
+        match res? {
+            Res::Local(local) => self.locals.get(&local).copied(),
+            Res::Func(item) | Res::Struct(item) | Res::Enum(item) | Res::Trait(item) => {
+                self.items.get(&item).copied()
+            }
+            Res::Variant(_, variant) => self.variants.get(&variant).copied(),
+            Res::TyParam(param) => self.generics.get(&param).copied(),
+            Res::Builtin(_) | Res::PrimTy(_) | Res::BuiltinTrait(_) | Res::SelfTy(_) => None,
+        }
+    }
+
+    /// Without types, methods and fields are only resolved when a single
+    /// definition has that name.
+    fn unique(&self, definitions: Option<&Vec<usize>>) -> Option<usize> {
+        match definitions.map(Vec::as_slice) {
+            Some(&[definition]) => Some(definition),
+            _ => None,
+        }
+    }
+
+    /// Finds the span of the identifier `name` in the first identifier token
+    /// after `offset`, for names the AST keeps no span for.
+    fn ident_after(&self, offset: usize, name: StrId) -> Option<Span> {
+        let index = self.tokens.partition_point(|(span, _)| span.start < offset);
+
+        self.tokens[index..]
+            .iter()
+            .find(|(_, kind)| *kind == TokenKind::Ident)
+            .map(|&(span, _)| span)
+            .filter(|&span| &self.content[span.start..span.end] == self.interner.get(name))
+    }
+
+    fn name_span(&self, start: usize, name: StrId) -> Span {
+        Span::new(start, start + self.interner.get(name).len())
+    }
+
+    /// The source text of `span` with runs of whitespace collapsed into a
+    /// single space.
+    fn text(&self, span: Span) -> String {
+        let text = &self.content[span.start..span.end];
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition_at<'a>(analysis: &'a Analysis, text: &str, nth: usize) -> Option<&'a Definition> {
+        let offset = analysis
+            .content
+            .match_indices(text)
+            .nth(nth)
+            .map(|(offset, _)| offset)
+            .unwrap();
+
+        analysis
+            .reference_at(offset)
+            .map(|reference| &analysis.definitions[reference.definition])
+    }
+
+    #[test]
+    fn test_resolves_names() {
+        let analysis = Analysis::new(
+            "/// A point.\n\
+             struct Point { x: i64 }\n\
+             impl Point { func get(self) -> i64 { self.x } }\n\
+             func make(x: i64) -> Point { let p = Point { x }; p.get(); Point { x: x } }\n\
+             func main() { let x = 1; { let x = 2; x; } x = make(x).x; }\n"
+                .to_owned(),
+        );
+        assert!(analysis.parsed.errors.is_empty());
+
+        let point = definition_at(&analysis, "Point", 2).unwrap();
+        assert_eq!(point.kind, DefinitionKind::Struct);
+        assert_eq!(point.signature, "struct Point");
+        assert_eq!(point.docs.as_deref(), Some("A point."));
+
+        let field = definition_at(&analysis, "x", 1).unwrap();
+        assert_eq!(
+            (field.kind, field.signature.as_str()),
+            (DefinitionKind::Field, "x: i64")
+        );
+
+        let get = definition_at(&analysis, "get", 1).unwrap();
+        assert_eq!(get.signature, "func get(self) -> i64");
+
+        // The shorthand field refers to the parameter, the explicit one to
+        // the field.
+        let shorthand = definition_at(&analysis, "x", 3).unwrap();
+        assert_eq!(shorthand.kind, DefinitionKind::Param);
+        let explicit = definition_at(&analysis, "x: x", 0).unwrap();
+        assert_eq!(explicit.kind, DefinitionKind::Field);
+
+        let inner = definition_at(&analysis, "x;", 0).unwrap();
+        assert_eq!(inner.signature, "let x");
+        assert_eq!(inner.span.start, analysis.content.find("x = 2").unwrap(),);
+
+        let assigned = definition_at(&analysis, "x = make", 0).unwrap();
+        assert_eq!(assigned.span.start, analysis.content.find("x = 1").unwrap());
+
+        let make = definition_at(&analysis, "make(x)", 0).unwrap();
+        assert_eq!(make.kind, DefinitionKind::Function);
+        assert_eq!(make.signature, "func make(x: i64) -> Point");
+    }
+
+    #[test]
+    fn test_enums_and_patterns() {
+        let analysis = Analysis::new(
+            "/// A shape.
+             enum Shape<T> { Empty, Circle(T), Rect { w: T, h: T } }
+             func area(s: Shape<f64>) -> f64 {
+                 let r = Shape::Rect { w: 1.0, h: 2.0 };
+                 match s { Shape::Circle(r) => r, Shape::Rect { w, h: height } => w * height, _ => 0.0 }
+             }
+"
+            .to_owned(),
+        );
+        assert!(analysis.parsed.errors.is_empty());
+
+        let shape = definition_at(&analysis, "Shape", 2).unwrap();
+        assert_eq!(shape.kind, DefinitionKind::Enum);
+        assert_eq!(shape.signature, "enum Shape<T>");
+        assert_eq!(shape.docs.as_deref(), Some("A shape."));
+
+        let circle = definition_at(&analysis, "Circle(r)", 0).unwrap();
+        assert_eq!(circle.kind, DefinitionKind::Variant);
+        assert_eq!(circle.signature, "Shape::Circle(T)");
+
+        let w = definition_at(&analysis, "w: 1.0", 0).unwrap();
+        assert_eq!(w.kind, DefinitionKind::Field);
+        assert_eq!(w.span.start, analysis.content.find("w: T").unwrap());
+        let h = definition_at(&analysis, "h: height", 0).unwrap();
+        assert_eq!(h.span.start, analysis.content.find("h: T").unwrap());
+
+        // Bindings in arms shadow the locals outside of the match.
+        let r = definition_at(&analysis, "r,", 0).unwrap();
+        assert_eq!(r.kind, DefinitionKind::Local);
+        assert_eq!(r.span.start, analysis.content.find("r)").unwrap());
+
+        let shorthand = definition_at(&analysis, "w *", 0).unwrap();
+        assert_eq!(shorthand.kind, DefinitionKind::Local);
+        assert_eq!(shorthand.span.start, analysis.content.find("w, h").unwrap());
+    }
+
+    #[test]
+    fn test_type_params() {
+        let analysis = Analysis::new(
+            "trait Show {}
+             struct Pair<A: Show, B> { a: A, b: B }
+             impl<T> Pair<T, T> { func get<U>(self, u: U) -> T { self.a } }
+"
+            .to_owned(),
+        );
+        assert!(analysis.parsed.errors.is_empty());
+
+        let pair = definition_at(&analysis, "Pair", 0).unwrap();
+        assert_eq!(pair.signature, "struct Pair<A: Show, B>");
+
+        let a = definition_at(&analysis, "A, b", 0).unwrap();
+        assert_eq!(a.kind, DefinitionKind::TypeParam);
+        assert_eq!(a.signature, "A: Show");
+        assert_eq!(a.span.start, analysis.content.find("A:").unwrap());
+
+        let show = definition_at(&analysis, "Show, B", 0).unwrap();
+        assert_eq!(show.kind, DefinitionKind::Trait);
+
+        let t = definition_at(&analysis, "T {", 0).unwrap();
+        assert_eq!(t.span.start, analysis.content.find("T>").unwrap());
+        let u = definition_at(&analysis, "U)", 0).unwrap();
+        assert_eq!(u.span.start, analysis.content.find("U>").unwrap());
+    }
+}
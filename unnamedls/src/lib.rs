@@ -0,0 +1,403 @@
+mod analysis;
+mod position;
+mod semantic_tokens;
+
+use std::{collections::HashMap, error::Error};
+
+use cranelift_entity::EntityList;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams,
+    SemanticTokens, SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams,
+    SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities, SymbolKind,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{
+        DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _,
+        SemanticTokensFullRequest,
+    },
+};
+use unnamed_ast::{
+    FieldDefEntity, Item, ItemEntity,
+    item::{FuncItem, VariantKind},
+};
+use unnamed_common::{Span, Spanned};
+
+use crate::analysis::{Analysis, DefinitionKind};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: semantic_tokens::legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..Default::default()
+            },
+        )),
+        ..Default::default()
+    }
+}
+
+/// Runs the server on `connection` until the client asks it to shut down.
+pub fn run(connection: &Connection) -> Result<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    Server::new(connection).main_loop()
+}
+
+struct Server<'c> {
+    connection: &'c Connection,
+    documents: HashMap<Uri, Analysis>,
+}
+
+impl<'c> Server<'c> {
+    fn new(connection: &'c Connection) -> Self {
+        Self {
+            connection,
+            documents: HashMap::new(),
+        }
+    }
+
+    fn main_loop(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    let response = self.handle_request(request);
+                    self.connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, Self::hover),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, Self::definition),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, Self::symbols)
+            }
+            SemanticTokensFullRequest::METHOD => {
+                self.respond::<SemanticTokensFullRequest>(request, Self::semantic_tokens)
+            }
+            _ => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request `{}`", request.method),
+            ),
+        }
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(error) => Response::new_err(
+                request.id,
+                ErrorCode::InvalidParams as i32,
+                error.to_string(),
+            ),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: lsp_types::DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+
+                self.update(document.uri, document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: lsp_types::DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+
+                // With full synchronization the last change holds the
+                // whole document.
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update(params.text_document.uri, change.text),
+                    None => Ok(()),
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: lsp_types::DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+
+                self.documents.remove(&uri);
+                self.publish(uri, Vec::new())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn update(&mut self, uri: Uri, text: String) -> Result<()> {
+        let analysis = Analysis::new(text);
+        let source = analysis.source();
+
+        let diagnostic = |span: Span, message: String| Diagnostic {
+            range: position::range(source, span),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("unnamed".to_owned()),
+            message,
+            ..Default::default()
+        };
+
+        // Like the compiler, each pass only reports errors once the previous
+        // ones succeeded, since a broken input mostly produces follow-up
+        // errors.
+        let diagnostics = if !analysis.parsed.errors.is_empty() {
+            let errors = analysis.parsed.errors.iter();
+            errors
+                .map(|error| diagnostic(error.span(), error.to_string()))
+                .collect()
+        } else if !analysis.resolution.errors.is_empty() {
+            let errors = analysis.resolution.errors.iter();
+            errors
+                .map(|error| diagnostic(error.span(), error.to_string()))
+                .collect()
+        } else {
+            let errors = analysis.typeck.errors.iter();
+            errors
+                .map(|error| diagnostic(error.span(), error.to_string()))
+                .collect()
+        };
+
+        self.documents.insert(uri.clone(), analysis);
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&self, uri: Uri, diagnostics: Vec<Diagnostic>) -> Result<()> {
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_owned(), params);
+
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+
+        Ok(())
+    }
+
+    /// Looks up the document and the reference under the cursor.
+    fn at(&self, params: &TextDocumentPositionParams) -> Option<(&Analysis, analysis::Reference)> {
+        let analysis = self.documents.get(&params.text_document.uri)?;
+        let offset = position::offset(&analysis.content, params.position);
+
+        Some((analysis, analysis.reference_at(offset)?))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (analysis, reference) = self.at(&params.text_document_position_params)?;
+        let definition = &analysis.definitions[reference.definition];
+
+        let mut value = format!("```unnamed\n{}\n```", definition.signature);
+        if let Some(docs) = &definition.docs {
+            value.push_str("\n\n---\n\n");
+            value.push_str(docs.trim());
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(position::range(analysis.source(), reference.span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let (analysis, reference) = self.at(&params.text_document_position_params)?;
+        let definition = &analysis.definitions[reference.definition];
+
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: params.text_document_position_params.text_document.uri,
+            range: position::range(analysis.source(), definition.span),
+        }))
+    }
+
+    fn symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let analysis = self.documents.get(&params.text_document.uri)?;
+        let symbols = func_symbols(analysis, analysis.parsed.module.items);
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    fn semantic_tokens(&self, params: SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let analysis = self.documents.get(&params.text_document.uri)?;
+
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: semantic_tokens::tokens(analysis),
+        }))
+    }
+}
+
+fn item_symbol(analysis: &Analysis, item: ItemEntity) -> DocumentSymbol {
+    let ctx = &analysis.parsed.ctx;
+    let interner = &analysis.interner;
+    let text = |span: Span| &analysis.content[span.start..span.end];
+
+    let (mut symbol, children) = match ctx.items[item] {
+        Item::Func(func) => return func_symbol(analysis, func),
+        Item::Struct(struct_item) => {
+            let name = interner.get(struct_item.name);
+            let kind = SymbolKind::STRUCT;
+            let symbol = symbol(
+                analysis,
+                name,
+                kind,
+                struct_item.span,
+                struct_item.name_span,
+            );
+
+            (symbol, field_symbols(analysis, struct_item.fields))
+        }
+        Item::Enum(enum_item) => {
+            let variants = ctx
+                .variants
+                .list(enum_item.variants)
+                .iter()
+                .map(|&variant| {
+                    let variant = ctx.variants[variant];
+                    let name = interner.get(variant.name);
+                    let name_span = Span::new(variant.span.start, variant.span.start + name.len());
+
+                    let kind = SymbolKind::ENUM_MEMBER;
+                    let mut symbol = symbol(analysis, name, kind, variant.span, name_span);
+                    match variant.kind {
+                        VariantKind::Unit => {}
+                        VariantKind::Tuple(_) => {
+                            let fields = Span::new(name_span.end, variant.span.end);
+                            symbol.detail = Some(text(fields).to_owned());
+                        }
+                        VariantKind::Struct(fields) => {
+                            symbol.children = Some(field_symbols(analysis, fields));
+                        }
+                    }
+
+                    symbol
+                });
+
+            let name = interner.get(enum_item.name);
+            let kind = SymbolKind::ENUM;
+            let symbol = symbol(analysis, name, kind, enum_item.span, enum_item.name_span);
+
+            (symbol, variants.collect())
+        }
+        Item::Trait(trait_item) => {
+            let name = interner.get(trait_item.name);
+            let kind = SymbolKind::INTERFACE;
+            let symbol = symbol(analysis, name, kind, trait_item.span, trait_item.name_span);
+
+            (symbol, func_symbols(analysis, trait_item.items))
+        }
+        Item::Impl(impl_item) => {
+            let ty = ctx.types[impl_item.ty].span;
+            let name = match impl_item.trait_ref {
+                Some(trait_ref) => {
+                    let trait_ref = ctx.types[trait_ref].span;
+                    format!("impl {} for {}", text(trait_ref), text(ty))
+                }
+                None => format!("impl {}", text(ty)),
+            };
+
+            let symbol = symbol(analysis, &name, SymbolKind::OBJECT, impl_item.span, ty);
+            (symbol, func_symbols(analysis, impl_item.items))
+        }
+    };
+
+    symbol.children = Some(children);
+    symbol
+}
+
+fn field_symbols(analysis: &Analysis, fields: EntityList<FieldDefEntity>) -> Vec<DocumentSymbol> {
+    let ctx = &analysis.parsed.ctx;
+
+    ctx.field_defs
+        .list(fields)
+        .iter()
+        .map(|&field| {
+            let field = ctx.field_defs[field];
+            let name = analysis.interner.get(field.name);
+            let name_span = Span::new(field.span.start, field.span.start + name.len());
+
+            let mut symbol = symbol(analysis, name, SymbolKind::FIELD, field.span, name_span);
+            let ty = ctx.types[field.ty].span;
+            symbol.detail = Some(analysis.content[ty.start..ty.end].to_owned());
+
+            symbol
+        })
+        .collect()
+}
+
+fn func_symbols(analysis: &Analysis, items: EntityList<ItemEntity>) -> Vec<DocumentSymbol> {
+    analysis
+        .parsed
+        .ctx
+        .items
+        .list(items)
+        .iter()
+        .map(|&item| item_symbol(analysis, item))
+        .collect()
+}
+
+fn func_symbol(analysis: &Analysis, func: FuncItem) -> DocumentSymbol {
+    let definition = analysis
+        .reference_at(func.name_span.start)
+        .map(|reference| &analysis.definitions[reference.definition]);
+
+    let kind = match definition.map(|definition| definition.kind) {
+        Some(DefinitionKind::Method) => SymbolKind::METHOD,
+        _ => SymbolKind::FUNCTION,
+    };
+
+    let name = analysis.interner.get(func.name);
+    let mut symbol = symbol(analysis, name, kind, func.span, func.name_span);
+    symbol.detail = definition.map(|definition| definition.signature.clone());
+
+    symbol
+}
+
+#[allow(deprecated)]
+fn symbol(
+    analysis: &Analysis,
+    name: &str,
+    kind: SymbolKind,
+    span: Span,
+    name_span: Span,
+) -> DocumentSymbol {
+    let source = analysis.source();
+
+    DocumentSymbol {
+        name: name.to_owned(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: position::range(source, span),
+        selection_range: position::range(source, name_span),
+        children: None,
+    }
+}
@@ -0,0 +1,102 @@
+//! Conversions between byte offsets in a source and LSP positions, which count
+//! lines from zero and columns in UTF-16 code units.
+
+use lsp_types::{Position, Range};
+use unnamed_common::{Source, Span};
+
+pub fn position(source: Source, offset: usize) -> Position {
+    let position = source.position(Span::new(offset, offset));
+
+    // `Source::position` reports an offset pointing at a newline as the start
+    // of the next line, while editors expect it at the end of the current one.
+    let (line, line_start) = if position.line_start > offset {
+        let line_start = source.content[..offset].rfind('\n').map_or(0, |i| i + 1);
+        (position.line - 2, line_start)
+    } else {
+        (position.line - 1, position.line_start)
+    };
+
+    Position::new(line as u32, utf16_len(&source.content[line_start..offset]))
+}
+
+pub fn range(source: Source, span: Span) -> Range {
+    Range::new(position(source, span.start), position(source, span.end))
+}
+
+/// Returns the byte offset of `position`, clamped to the end of its line and
+/// to the end of the content.
+pub fn offset(content: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match content[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return content.len(),
+        }
+    }
+
+    let line_end = content[line_start..]
+        .find('\n')
+        .map_or(content.len(), |newline| line_start + newline);
+
+    let mut column = 0;
+    for (index, char) in content[line_start..line_end].char_indices() {
+        if column >= position.character {
+            return line_start + index;
+        }
+        column += char.len_utf16() as u32;
+    }
+
+    line_end
+}
+
+pub fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|char| char.len_utf16() as u32).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_utf16() {
+        let content = "let a = 1;\nlet 🚀 = \"é\";\n";
+        let source = Source::new(content, "test_file");
+
+        assert_eq!(position(source, 0), Position::new(0, 0));
+        assert_eq!(position(source, 4), Position::new(0, 4));
+        assert_eq!(position(source, 11), Position::new(1, 0));
+
+        // The rocket is 4 bytes in UTF-8 but 2 code units in UTF-16.
+        let equals = content.find('=').unwrap();
+        let second_equals = content[equals + 1..].find('=').unwrap() + equals + 1;
+        assert_eq!(position(source, second_equals), Position::new(1, 7));
+        assert_eq!(position(source, content.len() - 3), Position::new(1, 11));
+    }
+
+    #[test]
+    fn test_position_at_newline() {
+        let content = "ab\ncd\n";
+        let source = Source::new(content, "test_file");
+
+        assert_eq!(position(source, 2), Position::new(0, 2));
+        assert_eq!(position(source, 5), Position::new(1, 2));
+        assert_eq!(position(source, 6), Position::new(2, 0));
+        assert_eq!(
+            range(source, Span::new(1, 4)),
+            Range::new(Position::new(0, 1), Position::new(1, 1))
+        );
+    }
+
+    #[test]
+    fn test_offset_round_trip() {
+        let content = "func 🚀() {}\n  x\nlast";
+        let source = Source::new(content, "test_file");
+
+        for offset in content.char_indices().map(|(index, _)| index) {
+            assert_eq!(super::offset(content, position(source, offset)), offset);
+        }
+
+        assert_eq!(offset(content, Position::new(1, 99)), 18);
+        assert_eq!(offset(content, Position::new(9, 0)), content.len());
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use lsp_types::{SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokensLegend};
+use unnamed_common::Span;
+use unnamed_lexer::TokenKind;
+
+use crate::{
+    analysis::{Analysis, DefinitionKind},
+    position::utf16_len,
+};
+
+/// Token types in the order of their index in the encoded tokens.
+const TOKEN_TYPES: [SemanticTokenType; 16] = [
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::METHOD,
+    SemanticTokenType::STRUCT,
+    SemanticTokenType::INTERFACE,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::STRING,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::TYPE_PARAMETER,
+    SemanticTokenType::ENUM,
+    SemanticTokenType::ENUM_MEMBER,
+];
+
+const TOKEN_MODIFIERS: [SemanticTokenModifier; 1] = [SemanticTokenModifier::DECLARATION];
+
+const DECLARATION: u32 = 1;
+
+const BUILTIN_TYPES: [&str; 13] = [
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64", "bool", "char", "str",
+];
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+fn token_type(ty: SemanticTokenType) -> u32 {
+    TOKEN_TYPES.iter().position(|known| *known == ty).unwrap() as u32
+}
+
+fn definition_type(kind: DefinitionKind) -> SemanticTokenType {
+    match kind {
+        DefinitionKind::Function => SemanticTokenType::FUNCTION,
+        DefinitionKind::Method => SemanticTokenType::METHOD,
+        DefinitionKind::Struct => SemanticTokenType::STRUCT,
+        DefinitionKind::Enum => SemanticTokenType::ENUM,
+        DefinitionKind::Variant => SemanticTokenType::ENUM_MEMBER,
+        DefinitionKind::Trait => SemanticTokenType::INTERFACE,
+        DefinitionKind::Field => SemanticTokenType::PROPERTY,
+        DefinitionKind::Param => SemanticTokenType::PARAMETER,
+        DefinitionKind::Local => SemanticTokenType::VARIABLE,
+        DefinitionKind::TypeParam => SemanticTokenType::TYPE_PARAMETER,
+    }
+}
+
+fn kind_type(kind: TokenKind) -> Option<SemanticTokenType> {
+    let ty = match kind {
+        TokenKind::Int | TokenKind::Float => SemanticTokenType::NUMBER,
+        TokenKind::Str | TokenKind::Char => SemanticTokenType::STRING,
+        TokenKind::DocComment
+        | TokenKind::InnerDocComment
+        | TokenKind::LineComment
+        | TokenKind::BlockComment => SemanticTokenType::COMMENT,
+        TokenKind::Add
+        | TokenKind::Sub
+        | TokenKind::Mul
+        | TokenKind::Div
+        | TokenKind::Eq
+        | TokenKind::Asgmt
+        | TokenKind::Neq
+        | TokenKind::Lt
+        | TokenKind::LtEq
+        | TokenKind::Gt
+        | TokenKind::GtEq
+        | TokenKind::And
+        | TokenKind::Or
+        | TokenKind::BitOr
+        | TokenKind::BitAnd
+        | TokenKind::BitXor
+        | TokenKind::BitShr
+        | TokenKind::BitShl
+        | TokenKind::Arrow
+        | TokenKind::FatArrow => SemanticTokenType::OPERATOR,
+        TokenKind::TrueKw
+        | TokenKind::FalseKw
+        | TokenKind::FuncKw
+        | TokenKind::TraitKw
+        | TokenKind::ImplKw
+        | TokenKind::StructKw
+        | TokenKind::EnumKw
+        | TokenKind::MatchKw
+        | TokenKind::WhileKw
+        | TokenKind::LetKw
+        | TokenKind::ForKw
+        | TokenKind::IfKw
+        | TokenKind::ElseKw
+        | TokenKind::ReturnKw => SemanticTokenType::KEYWORD,
+        TokenKind::Ident
+        | TokenKind::Comma
+        | TokenKind::Dot
+        | TokenKind::LeftParen
+        | TokenKind::RightParen
+        | TokenKind::LeftBrace
+        | TokenKind::RightBrace
+        | TokenKind::LeftSquare
+        | TokenKind::RightSquare
+        | TokenKind::Semicolon
+        | TokenKind::Colon
+        | TokenKind::DoubleColon
+        | TokenKind::Error => return None,
+    };
+
+    Some(ty)
+}
+
+/// Classifies every token of the document and encodes them relative to each
+/// other, as the protocol requires.
+pub fn tokens(analysis: &Analysis) -> Vec<SemanticToken> {
+    let references: HashMap<usize, _> = analysis
+        .references
+        .iter()
+        .map(|reference| (reference.span.start, reference))
+        .collect();
+
+    let mut encoder = Encoder::new(&analysis.content);
+    for &(span, kind) in &analysis.tokens {
+        let text = &analysis.content[span.start..span.end];
+
+        let (ty, modifiers) = if kind == TokenKind::Ident {
+            match references.get(&span.start) {
+                Some(reference) => {
+                    let definition = &analysis.definitions[reference.definition];
+                    let modifiers = if definition.span == span {
+                        DECLARATION
+                    } else {
+                        0
+                    };
+
+                    (definition_type(definition.kind), modifiers)
+                }
+                None if BUILTIN_TYPES.contains(&text) => (SemanticTokenType::TYPE, 0),
+                None => continue,
+            }
+        } else {
+            match kind_type(kind) {
+                Some(ty) => (ty, 0),
+                None => continue,
+            }
+        };
+
+        encoder.push(span, token_type(ty), modifiers);
+    }
+
+    encoder.tokens
+}
+
+struct Encoder<'a> {
+    content: &'a str,
+    tokens: Vec<SemanticToken>,
+    /// Byte offset the current line and column were computed for.
+    offset: usize,
+    line: u32,
+    column: u32,
+    previous_line: u32,
+    previous_column: u32,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(content: &'a str) -> Self {
+        Self {
+            content,
+            tokens: Vec::new(),
+            offset: 0,
+            line: 0,
+            column: 0,
+            previous_line: 0,
+            previous_column: 0,
+        }
+    }
+
+    /// Moves the current position forward to `offset`.
+    fn advance(&mut self, offset: usize) {
+        let text = &self.content[self.offset..offset];
+
+        match text.rfind('\n') {
+            Some(newline) => {
+                self.line += text.matches('\n').count() as u32;
+                self.column = utf16_len(&text[newline + 1..]);
+            }
+            None => self.column += utf16_len(text),
+        }
+
+        self.offset = offset;
+    }
+
+    /// Pushes a token, split into one token per line since clients are not
+    /// required to support tokens spanning multiple lines.
+    fn push(&mut self, span: Span, token_type: u32, modifiers: u32) {
+        let mut start = span.start;
+        for line in self.content[span.start..span.end].split_inclusive('\n') {
+            let text = line.trim_end_matches(['\n', '\r']);
+            self.advance(start);
+
+            if !text.is_empty() {
+                let delta_line = self.line - self.previous_line;
+                let delta_start = if delta_line == 0 {
+                    self.column - self.previous_column
+                } else {
+                    self.column
+                };
+
+                self.tokens.push(SemanticToken {
+                    delta_line,
+                    delta_start,
+                    length: utf16_len(text),
+                    token_type,
+                    token_modifiers_bitset: modifiers,
+                });
+
+                self.previous_line = self.line;
+                self.previous_column = self.column;
+            }
+
+            start += line.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(tokens: &[SemanticToken]) -> Vec<(u32, u32, u32, SemanticTokenType, u32)> {
+        let mut line = 0;
+        let mut column = 0;
+
+        tokens
+            .iter()
+            .map(|token| {
+                if token.delta_line == 0 {
+                    column += token.delta_start;
+                } else {
+                    line += token.delta_line;
+                    column = token.delta_start;
+                }
+
+                let ty = TOKEN_TYPES[token.token_type as usize].clone();
+                (line, column, token.length, ty, token.token_modifiers_bitset)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        let analysis = Analysis::new(
+            "/* a\n   b */ func f(a: i64) -> i64 {\n    let s = \"🚀\"; a + 1\n}\n".to_owned(),
+        );
+
+        assert_eq!(
+            decode(&tokens(&analysis)),
+            [
+                (0, 0, 4, SemanticTokenType::COMMENT, 0),
+                (1, 0, 7, SemanticTokenType::COMMENT, 0),
+                (1, 8, 4, SemanticTokenType::KEYWORD, 0),
+                (1, 13, 1, SemanticTokenType::FUNCTION, DECLARATION),
+                (1, 15, 1, SemanticTokenType::PARAMETER, DECLARATION),
+                (1, 18, 3, SemanticTokenType::TYPE, 0),
+                (1, 23, 2, SemanticTokenType::OPERATOR, 0),
+                (1, 26, 3, SemanticTokenType::TYPE, 0),
+                (2, 4, 3, SemanticTokenType::KEYWORD, 0),
+                (2, 8, 1, SemanticTokenType::VARIABLE, DECLARATION),
+                (2, 10, 1, SemanticTokenType::OPERATOR, 0),
+                (2, 12, 4, SemanticTokenType::STRING, 0),
+                (2, 18, 1, SemanticTokenType::PARAMETER, 0),
+                (2, 20, 1, SemanticTokenType::OPERATOR, 0),
+                (2, 22, 1, SemanticTokenType::NUMBER, 0),
+            ]
+        );
+    }
+}
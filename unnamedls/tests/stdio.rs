@@ -0,0 +1,184 @@
+//! Drives the server binary with a scripted JSON-RPC session over stdin.
+
+use std::{
+    io::{BufReader, Write},
+    process::{Command, Stdio},
+};
+
+use lsp_server::{ErrorCode, Message, Notification, Request, RequestId, ResponseError};
+use serde_json::{Value, json};
+
+const URI: &str = "file:///test.un";
+
+const SOURCE: &str = "\
+/// A point on the 🚀 plane.
+struct Point { x: i64, y: i64 }
+
+func norm(p: Point) -> i64 {
+    p.x * p.x + p.y * p.y
+}
+
+func main() -> i64 {
+    let s = \"é\"; let p = Point { x: 1, y: 2 };
+    norm(p) +
+}
+";
+
+fn request(id: i32, method: &str, params: Value) -> Message {
+    Message::Request(Request::new(RequestId::from(id), method.to_owned(), params))
+}
+
+fn notification(method: &str, params: Value) -> Message {
+    Message::Notification(Notification::new(method.to_owned(), params))
+}
+
+fn position(line: u32, character: u32) -> Value {
+    json!({ "textDocument": { "uri": URI }, "position": { "line": line, "character": character } })
+}
+
+/// Sends `messages` to a fresh server and returns everything it answered.
+fn session(messages: &[Message]) -> Vec<Message> {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_unnamedls"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut input = Vec::new();
+    for message in messages {
+        message.clone().write(&mut input).unwrap();
+    }
+    server.stdin.take().unwrap().write_all(&input).unwrap();
+
+    let output = server.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let mut reader = BufReader::new(output.stdout.as_slice());
+    let mut responses = Vec::new();
+    while let Some(message) = Message::read(&mut reader).unwrap() {
+        responses.push(message);
+    }
+
+    responses
+}
+
+fn response(responses: &[Message], id: i32) -> Result<Value, ResponseError> {
+    responses
+        .iter()
+        .find_map(|message| match message {
+            Message::Response(response) if response.id == RequestId::from(id) => {
+                Some(response.response_result.clone())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no response to request {id}"))
+}
+
+fn result(responses: &[Message], id: i32) -> Value {
+    response(responses, id).unwrap()
+}
+
+#[test]
+fn test_session() {
+    let responses = session(&[
+        request(0, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "unnamed", "version": 1, "text": SOURCE }
+            }),
+        ),
+        request(1, "textDocument/hover", position(8, 27)),
+        request(2, "textDocument/definition", position(9, 5)),
+        request(
+            3,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+        request(
+            4,
+            "textDocument/semanticTokens/full",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+        request(5, "textDocument/unknown", json!({})),
+        notification(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+        request(6, "shutdown", json!(null)),
+        notification("exit", json!(null)),
+    ]);
+
+    let capabilities = &result(&responses, 0)["capabilities"];
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["textDocumentSync"], 1);
+
+    let diagnostics: Vec<_> = responses
+        .iter()
+        .filter_map(|message| match message {
+            Message::Notification(notification)
+                if notification.method == "textDocument/publishDiagnostics" =>
+            {
+                Some(notification.params["diagnostics"].clone())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            json!([{
+                "range": {
+                    "start": { "line": 10, "character": 0 },
+                    "end": { "line": 10, "character": 1 }
+                },
+                "severity": 1,
+                "source": "unnamed",
+                "message": "expected expression, found `}`"
+            }]),
+            json!([]),
+        ]
+    );
+
+    // Hovering `Point` in the struct literal shows its declaration and docs.
+    let hover = result(&responses, 1);
+    assert_eq!(
+        hover["contents"]["value"],
+        "```unnamed\nstruct Point\n```\n\n---\n\nA point on the 🚀 plane."
+    );
+    assert_eq!(
+        hover["range"],
+        json!({ "start": { "line": 8, "character": 25 }, "end": { "line": 8, "character": 30 } })
+    );
+
+    assert_eq!(
+        result(&responses, 2),
+        json!({
+            "uri": URI,
+            "range": {
+                "start": { "line": 3, "character": 5 },
+                "end": { "line": 3, "character": 9 }
+            }
+        })
+    );
+
+    let symbols = result(&responses, 3);
+    let names: Vec<_> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Point", "norm", "main"]);
+    assert_eq!(symbols[0]["children"][1]["name"], "y");
+    assert_eq!(symbols[0]["children"][1]["detail"], "i64");
+    assert_eq!(symbols[1]["detail"], "func norm(p: Point) -> i64");
+
+    // The first token is the doc comment, which spans 28 UTF-16 units.
+    let tokens = result(&responses, 4)["data"].clone();
+    assert_eq!(tokens.as_array().unwrap()[..5], [0, 0, 28, 11, 0]);
+
+    let unknown = response(&responses, 5).unwrap_err();
+    assert_eq!(unknown.code, ErrorCode::MethodNotFound as i32);
+}